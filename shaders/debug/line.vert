@@ -0,0 +1,18 @@
+#version 450
+
+layout(location = 0) in vec3 vertPosition;
+layout(location = 2) in vec3 vertColor;
+
+layout(push_constant) uniform ViewProjection {
+    mat4 view;
+    mat4 projection;
+};
+
+layout(location = 0) out vec3 fragColor;
+
+// Debug line vertices are given in world space
+void main() {
+    fragColor = vertColor;
+
+    gl_Position = projection * view * vec4(vertPosition, 1.0);
+}
@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec3 fragPosition;
+layout(location = 1) in vec3 fragNormal;
+layout(location = 2) in vec3 fragColor;
+
+// World space position, world space normal and albedo. An albedo alpha of 0 marks pixels no
+// geometry was drawn to
+layout(location = 0) out vec4 outPosition;
+layout(location = 1) out vec4 outNormal;
+layout(location = 2) out vec4 outAlbedo;
+
+void main() {
+    outPosition = vec4(fragPosition, 1.0);
+    outNormal = vec4(normalize(fragNormal), 0.0);
+    outAlbedo = vec4(fragColor, 1.0);
+}
@@ -0,0 +1,77 @@
+#version 450
+
+#include "../shadow/shadows.glsl"
+
+layout(input_attachment_index = 0, set = 0, binding = 0) uniform subpassInput gPosition;
+layout(input_attachment_index = 1, set = 0, binding = 1) uniform subpassInput gNormal;
+layout(input_attachment_index = 2, set = 0, binding = 2) uniform subpassInput gAlbedo;
+
+layout(push_constant) uniform ViewProjection {
+    mat4 view;
+    mat4 projection;
+};
+
+layout(location = 0) out vec4 outColor;
+
+// Lights the scene when it has no shadow casting lights
+const vec3 DEFAULT_LIGHT_DIRECTION = normalize(vec3(-0.4, -1.0, -0.3));
+const vec3 DEFAULT_LIGHT_COLOR = vec3(1.0);
+const float AMBIENT = 0.1;
+const float SHININESS = 32.0;
+
+// Blinn-Phong diffuse and specular light arriving from a direction
+vec3 shade(vec3 albedo, vec3 normal, vec3 toLight, vec3 toCamera, vec3 lightColor) {
+    vec3 halfway = normalize(toLight + toCamera);
+
+    float diffuse = max(dot(normal, toLight), 0.0);
+    float specular = diffuse > 0.0 ? pow(max(dot(normal, halfway), 0.0), SHININESS) : 0.0;
+
+    return (albedo * diffuse + specular) * lightColor;
+}
+
+void main() {
+    vec4 albedo = subpassLoad(gAlbedo);
+
+    if (albedo.a == 0.0) {
+        outColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec3 position = subpassLoad(gPosition).xyz;
+    vec3 normal = normalize(subpassLoad(gNormal).xyz);
+    vec3 cameraPosition = inverse(view)[3].xyz;
+    vec3 toCamera = normalize(cameraPosition - position);
+    float viewDepth = -(view * vec4(position, 1.0)).z;
+
+    vec3 color = albedo.rgb * AMBIENT;
+
+    if (shadowedLightCount == 0) {
+        color += shade(albedo.rgb, normal, -DEFAULT_LIGHT_DIRECTION, toCamera, DEFAULT_LIGHT_COLOR);
+    }
+
+    for (uint light = 0; light < shadowedLightCount; light++) {
+        vec3 toLight = -shadowedLights[light].direction.xyz;
+        float attenuation = 1.0;
+
+        if (int(shadowedLights[light].position.w) == LIGHT_SPOT) {
+            vec3 offset = shadowedLights[light].position.xyz - position;
+            toLight = normalize(offset);
+
+            float cone = dot(-toLight, shadowedLights[light].direction.xyz);
+            float range = shadowedLights[light].color.a;
+            float halfAngle = shadowedLights[light].direction.w;
+
+            attenuation = smoothstep(halfAngle, mix(halfAngle, 1.0, 0.1), cone)
+                * clamp(1.0 - length(offset) / range, 0.0, 1.0);
+        }
+
+        if (attenuation > 0.0) {
+            attenuation *= shadowFactor(light, position, viewDepth);
+        }
+
+        color += attenuation
+            * shade(albedo.rgb, normal, toLight, toCamera, shadowedLights[light].color.rgb);
+    }
+
+    outColor = vec4(color, 1.0);
+}
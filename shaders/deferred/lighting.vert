@@ -0,0 +1,8 @@
+#version 450
+
+// Covers the screen with a single triangle generated from the vertex index
+void main() {
+    vec2 position = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
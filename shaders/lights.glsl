@@ -0,0 +1,135 @@
+// Lights of the scene, bound by the renderer to descriptor set 1 of any pipeline declaring these
+// bindings, together with the lists of lights affecting each cluster of the first camera's view
+// frustum. Clusters split the frustum into a grid of tiles across the screen and slices in depth
+
+#ifndef LIGHT_DIRECTIONAL
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_SPOT 1
+#define LIGHT_POINT 2
+#endif
+
+// Only the light culling shader writes the cluster light lists
+#ifdef CLUSTER_CULLING
+#define CLUSTER_ACCESS writeonly
+#else
+#define CLUSTER_ACCESS readonly
+#endif
+
+struct Light {
+    // xyz: world space position, w: LIGHT_DIRECTIONAL, LIGHT_SPOT or LIGHT_POINT
+    vec4 position;
+    // xyz: world space direction the light shines in, w: range, 0 for unlimited
+    vec4 direction;
+    // rgb: color, a: intensity
+    vec4 color;
+    // x: constant, y: linear, z: quadratic distance attenuation
+    vec4 attenuation;
+    // x: cosine of half the fully lit inner cone angle, y: cosine of half the outer cone angle
+    vec4 cone;
+};
+
+layout(set = 1, binding = 2) readonly buffer Lights {
+    uint lightCount;
+    Light lights[];
+};
+
+layout(set = 1, binding = 3) uniform Clusters {
+    // view and inverse projection of the camera the clusters were culled for
+    mat4 clusterView;
+    mat4 clusterInverseProjection;
+    // x, y: window space offset, z, w: size of the camera's viewport
+    vec4 clusterViewport;
+    // xyz: number of clusters across, down and in depth, 0 if lights were not culled.
+    // w: the maximum number of lights per cluster
+    uvec4 clusterGrid;
+    // x: near and y: far distance of the camera, between which depth slices are spaced
+    // exponentially
+    vec4 clusterDepth;
+};
+
+// The number of lights affecting each cluster, indexed by x, then y, then depth slice
+layout(set = 1, binding = 4) CLUSTER_ACCESS buffer ClusterLightCounts {
+    uint clusterLightCounts[];
+};
+
+// clusterGrid.w light indices for each cluster, of which the cluster's count are used
+layout(set = 1, binding = 5) CLUSTER_ACCESS buffer ClusterLightIndices {
+    uint clusterLightIndices[];
+};
+
+// Returns the view space distance in front of the camera at which a depth slice begins
+float clusterSliceDepth(uint slice) {
+    return clusterDepth.x * pow(clusterDepth.y / clusterDepth.x, float(slice) / float(clusterGrid.z));
+}
+
+#ifndef CLUSTER_CULLING
+
+// Returns true if lights were culled for a view, so that the lights of a fragment can be looked
+// up by its cluster. Otherwise every light must be considered
+bool clustersCulledFor(mat4 view) {
+    return clusterGrid.x > 0u && view == clusterView;
+}
+
+// Returns the cluster containing a fragment, from its window coordinates and its view space
+// distance in front of the camera
+uint getCluster(vec2 fragCoord, float viewDepth) {
+    vec2 uv = clamp((fragCoord - clusterViewport.xy) / clusterViewport.zw, 0.0, 1.0);
+    uvec2 tile = min(uvec2(uv * vec2(clusterGrid.xy)), clusterGrid.xy - 1u);
+    float depth = log(max(viewDepth, clusterDepth.x) / clusterDepth.x)
+        / log(clusterDepth.y / clusterDepth.x);
+    uint slice = min(uint(max(depth, 0.0) * float(clusterGrid.z)), clusterGrid.z - 1u);
+
+    return tile.x + clusterGrid.x * (tile.y + clusterGrid.y * slice);
+}
+
+// Returns the number of lights affecting a cluster
+uint getClusterLightCount(uint cluster) {
+    return min(clusterLightCounts[cluster], clusterGrid.w);
+}
+
+// Returns the index into lights of one of the lights affecting a cluster
+uint getClusterLight(uint cluster, uint index) {
+    return clusterLightIndices[cluster * clusterGrid.w + index];
+}
+
+// Returns the light arriving at a world space position from a light, and the direction towards
+// the light
+vec3 lightRadiance(uint light, vec3 worldPosition, out vec3 toLight) {
+    vec3 radiance = lights[light].color.rgb * lights[light].color.a;
+    int lightType = int(lights[light].position.w);
+
+    toLight = -normalize(lights[light].direction.xyz);
+
+    if (lightType == LIGHT_DIRECTIONAL) {
+        return radiance;
+    }
+
+    vec3 offset = lights[light].position.xyz - worldPosition;
+    float lightDistance = max(length(offset), 1e-4);
+    vec3 attenuation = lights[light].attenuation.xyz;
+    float range = lights[light].direction.w;
+
+    toLight = offset / lightDistance;
+    radiance /= max(attenuation.x + attenuation.y * lightDistance
+                    + attenuation.z * lightDistance * lightDistance, 1e-4);
+
+    // Fade out smoothly towards the range rather than cutting off
+    if (range > 0.0) {
+        float ratio = lightDistance / range;
+        float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+        radiance *= window * window;
+    }
+
+    if (lightType == LIGHT_SPOT) {
+        float cosAngle = dot(-toLight, normalize(lights[light].direction.xyz));
+        float cosInner = lights[light].cone.x;
+        float cosOuter = lights[light].cone.y;
+        float falloff = clamp((cosAngle - cosOuter) / max(cosInner - cosOuter, 1e-4), 0.0, 1.0);
+
+        radiance *= falloff * falloff;
+    }
+
+    return radiance;
+}
+
+#endif
@@ -0,0 +1,8 @@
+#version 450
+
+layout(location = 0) out vec4 color;
+
+// Proxies only count the fragments passing the depth test, their color is never written
+void main() {
+    color = vec4(0.0);
+}
@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) in vec3 vertPosition;
+
+layout(push_constant) uniform ViewProjection {
+    mat4 view;
+    mat4 projection;
+};
+
+// Proxy box vertices are given in world space
+void main() {
+    gl_Position = projection * view * vec4(vertPosition, 1.0);
+}
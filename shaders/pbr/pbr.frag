@@ -0,0 +1,205 @@
+#version 450
+
+#include "../lights.glsl"
+
+layout(location = 0) in vec3 fragPosition;
+layout(location = 1) in vec3 fragNormal;
+layout(location = 2) in vec3 fragColor;
+layout(location = 3) in vec2 fragUV;
+
+layout(push_constant) uniform ViewProjection {
+    mat4 view;
+    mat4 projection;
+};
+
+// Factors of the glTF metallic-roughness material model, each scaling the texture of the same name
+layout(set = 2, binding = 0) uniform PbrMaterial {
+    vec4 baseColorFactor;
+    vec3 emissiveFactor;
+    float metallicFactor;
+    float roughnessFactor;
+    float normalScale;
+    float occlusionStrength;
+    // Fragments whose base color alpha is below the cutoff are discarded
+    float alphaCutoff;
+    // Scales the light reflected from the environment, which replaces the constant ambient light
+    // when above 0
+    float environmentStrength;
+} material;
+
+layout(set = 2, binding = 1) uniform sampler2D baseColorTexture;
+// g: roughness, b: metallic
+layout(set = 2, binding = 2) uniform sampler2D metallicRoughnessTexture;
+// Tangent space normals
+layout(set = 2, binding = 3) uniform sampler2D normalTexture;
+// r: ambient occlusion
+layout(set = 2, binding = 4) uniform sampler2D occlusionTexture;
+layout(set = 2, binding = 5) uniform sampler2D emissiveTexture;
+// Surrounding radiance, usually the scene's environment, with mip levels standing in for rougher
+// reflections
+layout(set = 2, binding = 6) uniform samplerCube environmentTexture;
+
+layout(location = 0) out vec4 outColor;
+
+const float PI = 3.14159265359;
+const float AMBIENT = 0.03;
+// Lights the scene when it has no lights, bright enough that a white surface facing it is white
+const vec3 DEFAULT_LIGHT_DIRECTION = normalize(vec3(-0.4, -1.0, -0.3));
+const vec3 DEFAULT_LIGHT_RADIANCE = vec3(PI);
+// Reflectance of dielectrics at normal incidence
+const vec3 DIELECTRIC_F0 = vec3(0.04);
+
+// Applies the normal map to a world space normal. Vertices carry no tangents, so the tangent
+// frame is built from the screen space derivatives of position and texture coordinates
+vec3 perturbNormal(vec3 normal, vec3 position, vec2 uv) {
+    vec3 mapped = texture(normalTexture, uv).xyz * 2.0 - 1.0;
+    mapped.xy *= material.normalScale;
+
+    vec3 dPositionX = dFdx(position);
+    vec3 dPositionY = dFdy(position);
+    vec2 dUVX = dFdx(uv);
+    vec2 dUVY = dFdy(uv);
+
+    vec3 perpendicularY = cross(dPositionY, normal);
+    vec3 perpendicularX = cross(normal, dPositionX);
+    vec3 tangent = perpendicularY * dUVX.x + perpendicularX * dUVY.x;
+    vec3 bitangent = perpendicularY * dUVX.y + perpendicularX * dUVY.y;
+
+    float lengthSquared = max(dot(tangent, tangent), dot(bitangent, bitangent));
+
+    // Without varying texture coordinates there is no tangent frame to map normals in
+    if (lengthSquared == 0.0) {
+        return normal;
+    }
+
+    float scale = inversesqrt(lengthSquared);
+
+    return normalize(mat3(tangent * scale, bitangent * scale, normal) * mapped);
+}
+
+// GGX / Trowbridge-Reitz normal distribution
+float distributionGGX(float normalDotHalfway, float alpha) {
+    float alphaSquared = alpha * alpha;
+    float denominator = normalDotHalfway * normalDotHalfway * (alphaSquared - 1.0) + 1.0;
+
+    return alphaSquared / (PI * denominator * denominator);
+}
+
+// Height correlated Smith visibility for GGX, including the 1 / (4 n.l n.v) of the specular BRDF
+float visibilitySmithGGX(float normalDotLight, float normalDotView, float alpha) {
+    float alphaSquared = alpha * alpha;
+    float viewTerm = normalDotLight
+        * sqrt(normalDotView * normalDotView * (1.0 - alphaSquared) + alphaSquared);
+    float lightTerm = normalDotView
+        * sqrt(normalDotLight * normalDotLight * (1.0 - alphaSquared) + alphaSquared);
+
+    return 0.5 / max(viewTerm + lightTerm, 1e-5);
+}
+
+vec3 fresnelSchlick(float viewDotHalfway, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(1.0 - viewDotHalfway, 5.0);
+}
+
+// Lambert diffuse and GGX specular light reflected towards the camera from light arriving from a
+// direction with the given radiance
+vec3 shade(vec3 baseColor, float metallic, float roughness, vec3 normal, vec3 toCamera,
+           vec3 toLight, vec3 radiance) {
+    float normalDotLight = dot(normal, toLight);
+
+    if (normalDotLight <= 0.0) {
+        return vec3(0.0);
+    }
+
+    vec3 halfway = normalize(toLight + toCamera);
+    float normalDotView = max(dot(normal, toCamera), 1e-4);
+    float normalDotHalfway = max(dot(normal, halfway), 0.0);
+    float viewDotHalfway = max(dot(toCamera, halfway), 0.0);
+    float alpha = roughness * roughness;
+
+    vec3 fresnel = fresnelSchlick(viewDotHalfway, mix(DIELECTRIC_F0, baseColor, metallic));
+    vec3 specular = fresnel * distributionGGX(normalDotHalfway, alpha)
+        * visibilitySmithGGX(normalDotLight, normalDotView, alpha);
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * baseColor / PI;
+
+    return (diffuse + specular) * radiance * normalDotLight;
+}
+
+// Diffuse and specular light reflected towards the camera from the environment. Blurrier mip
+// levels approximate the spread of rough reflections and the irradiance of diffuse surfaces
+vec3 shadeEnvironment(vec3 baseColor, float metallic, float roughness, vec3 normal,
+                      vec3 toCamera) {
+    float maxLevel = float(textureQueryLevels(environmentTexture) - 1);
+    float normalDotView = max(dot(normal, toCamera), 0.0);
+
+    vec3 f0 = mix(DIELECTRIC_F0, baseColor, metallic);
+    // Rough surfaces reflect less at grazing angles, as much of their reflection is shadowed
+    vec3 fresnel = f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(1.0 - normalDotView, 5.0);
+
+    vec3 reflection = textureLod(environmentTexture, reflect(-toCamera, normal),
+                                 roughness * maxLevel).rgb;
+    vec3 irradiance = textureLod(environmentTexture, normal, maxLevel).rgb;
+
+    return (1.0 - fresnel) * (1.0 - metallic) * baseColor * irradiance + fresnel * reflection;
+}
+
+// The light reflected towards the camera from one of the scene's lights
+vec3 shadeLight(uint light, vec3 baseColor, float metallic, float roughness, vec3 normal,
+                vec3 toCamera) {
+    vec3 toLight;
+    vec3 radiance = lightRadiance(light, fragPosition, toLight);
+
+    return shade(baseColor, metallic, roughness, normal, toCamera, toLight, radiance);
+}
+
+void main() {
+    vec4 baseColor = texture(baseColorTexture, fragUV) * material.baseColorFactor
+        * vec4(fragColor, 1.0);
+
+    if (baseColor.a < material.alphaCutoff) {
+        discard;
+    }
+
+    vec2 metallicRoughness = texture(metallicRoughnessTexture, fragUV).bg;
+    float metallic = clamp(metallicRoughness.x * material.metallicFactor, 0.0, 1.0);
+    // Perfectly smooth surfaces would reflect point lights as single bright pixels
+    float roughness = clamp(metallicRoughness.y * material.roughnessFactor, 0.04, 1.0);
+    float occlusion = mix(1.0, texture(occlusionTexture, fragUV).r, material.occlusionStrength);
+    vec3 emissive = texture(emissiveTexture, fragUV).rgb * material.emissiveFactor;
+
+    vec3 normal = perturbNormal(normalize(fragNormal), fragPosition, fragUV);
+    vec3 cameraPosition = inverse(view)[3].xyz;
+    vec3 toCamera = normalize(cameraPosition - fragPosition);
+
+    vec3 color = vec3(0.0);
+
+    if (lightCount == 0u) {
+        color += shade(baseColor.rgb, metallic, roughness, normal, toCamera,
+                       -DEFAULT_LIGHT_DIRECTION, DEFAULT_LIGHT_RADIANCE);
+    }
+
+    if (clustersCulledFor(view)) {
+        float viewDepth = -(view * vec4(fragPosition, 1.0)).z;
+        uint cluster = getCluster(gl_FragCoord.xy, viewDepth);
+
+        for (uint index = 0u; index < getClusterLightCount(cluster); index++) {
+            color += shadeLight(getClusterLight(cluster, index), baseColor.rgb, metallic,
+                                roughness, normal, toCamera);
+        }
+    } else {
+        for (uint light = 0u; light < lightCount; light++) {
+            color += shadeLight(light, baseColor.rgb, metallic, roughness, normal, toCamera);
+        }
+    }
+
+    vec3 ambient = AMBIENT * baseColor.rgb;
+
+    if (material.environmentStrength > 0.0) {
+        ambient = shadeEnvironment(baseColor.rgb, metallic, roughness, normal, toCamera)
+            * material.environmentStrength;
+    }
+
+    // Occlusion only darkens the ambient light, which stands in for indirect lighting
+    color += ambient * occlusion + emissive;
+
+    outColor = vec4(color, baseColor.a);
+}
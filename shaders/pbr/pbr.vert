@@ -0,0 +1,31 @@
+#version 450
+
+layout(location = 0) in vec3 vertPosition;
+layout(location = 1) in vec3 vertNormal;
+layout(location = 2) in vec3 vertColor;
+layout(location = 3) in vec2 vertUV;
+
+layout(set = 0, binding = 0) uniform UBO {
+    mat4 transform;
+};
+
+layout(push_constant) uniform ViewProjection {
+    mat4 view;
+    mat4 projection;
+};
+
+layout(location = 0) out vec3 fragPosition;
+layout(location = 1) out vec3 fragNormal;
+layout(location = 2) out vec3 fragColor;
+layout(location = 3) out vec2 fragUV;
+
+void main() {
+    vec4 worldPosition = transform * vec4(vertPosition, 1.0);
+
+    fragPosition = worldPosition.xyz;
+    fragNormal = mat3(transpose(inverse(transform))) * vertNormal;
+    fragColor = vertColor;
+    fragUV = vertUV;
+
+    gl_Position = projection * view * worldPosition;
+}
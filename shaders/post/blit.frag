@@ -0,0 +1,10 @@
+#version 450
+
+#include "post.glsl"
+
+layout(location = 0) out vec4 color;
+
+// Copies the final image of the chain into the swapchain image
+void main() {
+    color = vec4(texture(source, uv).rgb, 1.0);
+}
@@ -0,0 +1,12 @@
+#version 450
+
+#include "post.glsl"
+
+layout(set = 0, binding = 1) uniform sampler2D bloom;
+
+layout(location = 0) out vec4 color;
+
+// Adds the blurred bloom image, scaled by parameters.x, to the source
+void main() {
+    color = vec4(texture(source, uv).rgb + texture(bloom, uv).rgb * post.parameters.x, 1.0);
+}
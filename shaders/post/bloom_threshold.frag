@@ -0,0 +1,21 @@
+#version 450
+
+#include "post.glsl"
+
+layout(location = 0) out vec4 color;
+
+// Downsamples the source to the half resolution bloom image, keeping only the light brighter than
+// the threshold in parameters.x. Four bilinear taps average a 4x4 block of source texels
+void main() {
+    vec2 offset = post.texelSize;
+
+    vec3 average = 0.25 * (texture(source, uv + vec2(-offset.x, -offset.y)).rgb +
+                           texture(source, uv + vec2(offset.x, -offset.y)).rgb +
+                           texture(source, uv + vec2(-offset.x, offset.y)).rgb +
+                           texture(source, uv + vec2(offset.x, offset.y)).rgb);
+
+    float brightness = luminance(average);
+    float contribution = max(brightness - post.parameters.x, 0.0) / max(brightness, 0.0001);
+
+    color = vec4(average * contribution, 1.0);
+}
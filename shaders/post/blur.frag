@@ -0,0 +1,23 @@
+#version 450
+
+#include "post.glsl"
+
+layout(location = 0) out vec4 color;
+
+// Gaussian weights of a 9 tap kernel, folded into 5 bilinear taps
+const float offsets[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float weights[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
+// Blurs the source along the direction in parameters.xy, one texel apart
+void main() {
+    vec2 direction = post.parameters.xy * post.texelSize;
+
+    vec3 sum = texture(source, uv).rgb * weights[0];
+
+    for (int i = 1; i < 3; i++) {
+        sum += texture(source, uv + direction * offsets[i]).rgb * weights[i];
+        sum += texture(source, uv - direction * offsets[i]).rgb * weights[i];
+    }
+
+    color = vec4(sum, 1.0);
+}
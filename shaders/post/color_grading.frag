@@ -0,0 +1,16 @@
+#version 450
+
+#include "post.glsl"
+
+layout(set = 0, binding = 1) uniform sampler3D lut;
+
+layout(location = 0) out vec4 color;
+
+// Maps the source's color through a 3D lookup table with parameters.x texels along each axis.
+// Colors are scaled onto texel centers so the table's first and last entries are hit exactly
+void main() {
+    vec3 original = clamp(texture(source, uv).rgb, 0.0, 1.0);
+    float size = post.parameters.x;
+
+    color = vec4(texture(lut, original * (size - 1.0) / size + 0.5 / size).rgb, 1.0);
+}
@@ -0,0 +1,50 @@
+#version 450
+
+#include "post.glsl"
+
+#define FXAA_EDGE_THRESHOLD 0.125
+#define FXAA_EDGE_THRESHOLD_MIN 0.0312
+#define FXAA_SPAN_MAX 8.0
+#define FXAA_REDUCE_MUL (1.0 / 8.0)
+#define FXAA_REDUCE_MIN (1.0 / 128.0)
+
+layout(location = 0) out vec4 color;
+
+// Fast approximate antialiasing. Blurs along edges found from the luminance of the source's
+// neighbouring texels, so it should run after tonemapping
+void main() {
+    vec3 center = texture(source, uv).rgb;
+
+    float lumaCenter = luminance(center);
+    float lumaNW = luminance(texture(source, uv + vec2(-1.0, -1.0) * post.texelSize).rgb);
+    float lumaNE = luminance(texture(source, uv + vec2(1.0, -1.0) * post.texelSize).rgb);
+    float lumaSW = luminance(texture(source, uv + vec2(-1.0, 1.0) * post.texelSize).rgb);
+    float lumaSE = luminance(texture(source, uv + vec2(1.0, 1.0) * post.texelSize).rgb);
+
+    float lumaMin = min(lumaCenter, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaCenter, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    if (lumaMax - lumaMin < max(FXAA_EDGE_THRESHOLD_MIN, lumaMax * FXAA_EDGE_THRESHOLD)) {
+        color = vec4(center, 1.0);
+        return;
+    }
+
+    vec2 direction = vec2(-((lumaNW + lumaNE) - (lumaSW + lumaSE)),
+                          (lumaNW + lumaSW) - (lumaNE + lumaSE));
+
+    float directionReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * FXAA_REDUCE_MUL,
+                                FXAA_REDUCE_MIN);
+    float inverseDirectionMin = 1.0 / (min(abs(direction.x), abs(direction.y)) + directionReduce);
+
+    direction = clamp(direction * inverseDirectionMin, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX)) *
+                post.texelSize;
+
+    vec3 nearSamples = 0.5 * (texture(source, uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+                              texture(source, uv + direction * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 farSamples = nearSamples * 0.5 + 0.25 * (texture(source, uv + direction * -0.5).rgb +
+                                                  texture(source, uv + direction * 0.5).rgb);
+
+    float lumaFar = luminance(farSamples);
+
+    color = vec4(lumaFar < lumaMin || lumaFar > lumaMax ? nearSamples : farSamples, 1.0);
+}
@@ -0,0 +1,14 @@
+// Inputs shared by every post-processing pass. The image written by the previous pass is bound
+// as source, and texelSize is the size of one of its texels in texture coordinates
+layout(location = 0) in vec2 uv;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+layout(push_constant) uniform PostParameters {
+    vec4 parameters;
+    vec2 texelSize;
+} post;
+
+float luminance(vec3 color) {
+    return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
@@ -0,0 +1,22 @@
+#version 450
+
+#include "post.glsl"
+
+#define TONEMAP_REINHARD 0
+#define TONEMAP_ACES 1
+
+layout(location = 0) out vec4 color;
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+// parameters.x is the exposure and parameters.y the tonemapping operator
+void main() {
+    vec3 hdr = texture(source, uv).rgb * post.parameters.x;
+
+    vec3 mapped = int(post.parameters.y) == TONEMAP_ACES ? aces(hdr) : hdr / (1.0 + hdr);
+
+    color = vec4(mapped, 1.0);
+}
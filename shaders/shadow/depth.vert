@@ -0,0 +1,16 @@
+#version 450
+
+layout(location = 0) in vec3 vertPosition;
+
+layout(set = 0, binding = 0) uniform UBO {
+    mat4 transform;
+};
+
+layout(push_constant) uniform LightViewProjection {
+    mat4 viewProjection;
+};
+
+// Renders only the depth of each instance as seen from a light
+void main() {
+    gl_Position = viewProjection * transform * vec4(vertPosition, 1.0);
+}
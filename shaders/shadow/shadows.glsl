@@ -0,0 +1,89 @@
+// Shadow maps of the scene's shadow casting lights, bound by the renderer to descriptor set 1 of
+// any pipeline declaring these bindings
+
+#define MAX_SHADOWED_LIGHTS 4
+#define MAX_CASCADES 4
+
+#ifndef LIGHT_DIRECTIONAL
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_SPOT 1
+#define LIGHT_POINT 2
+#endif
+
+struct ShadowedLight {
+    // world space to shadow map clip space, one per cascade
+    mat4 viewProjections[MAX_CASCADES];
+    // view space distance from the camera at which each cascade ends
+    vec4 cascadeSplits;
+    // xyz: world space position, w: LIGHT_DIRECTIONAL or LIGHT_SPOT
+    vec4 position;
+    // xyz: world space direction, w: cosine of half the spot cone angle
+    vec4 direction;
+    // rgb: color scaled by intensity, a: spot light range
+    vec4 color;
+    // x: cascade count, y: PCF radius in texels, z: size of a texel in texture coordinates
+    vec4 params;
+};
+
+layout(set = 1, binding = 0) uniform Shadows {
+    ShadowedLight shadowedLights[MAX_SHADOWED_LIGHTS];
+    uint shadowedLightCount;
+};
+
+layout(set = 1, binding = 1) uniform sampler2DArrayShadow shadowMaps[MAX_SHADOWED_LIGHTS];
+
+// Compares a depth against a shadow map. Indexing the sampler array with a variable needs the
+// shaderSampledImageArrayDynamicIndexing feature, so each map is indexed with a constant instead,
+// with a case for each of the MAX_SHADOWED_LIGHTS maps
+float sampleShadowMap(uint light, vec4 coord) {
+    switch (light) {
+    case 0:
+        return texture(shadowMaps[0], coord);
+    case 1:
+        return texture(shadowMaps[1], coord);
+    case 2:
+        return texture(shadowMaps[2], coord);
+    default:
+        return texture(shadowMaps[3], coord);
+    }
+}
+
+// Returns the fraction of a shadowed light reaching a world space position, from 0 in full shadow
+// to 1 fully lit, filtering the shadow map with percentage closer filtering. viewDepth is the
+// distance of the position in front of the camera, which selects the cascade
+float shadowFactor(uint light, vec3 worldPosition, float viewDepth) {
+    int cascadeCount = int(shadowedLights[light].params.x);
+    int cascade = 0;
+
+    if (int(shadowedLights[light].position.w) == LIGHT_DIRECTIONAL) {
+        if (viewDepth > shadowedLights[light].cascadeSplits[cascadeCount - 1]) {
+            return 1.0;
+        }
+
+        while (cascade < cascadeCount - 1 && viewDepth > shadowedLights[light].cascadeSplits[cascade]) {
+            cascade++;
+        }
+    }
+
+    vec4 clip = shadowedLights[light].viewProjections[cascade] * vec4(worldPosition, 1.0);
+    vec3 ndc = clip.xyz / clip.w;
+
+    if (any(greaterThan(abs(ndc.xy), vec2(1.0))) || ndc.z < 0.0 || ndc.z > 1.0) {
+        return 1.0;
+    }
+
+    vec2 uv = ndc.xy * 0.5 + 0.5;
+    int radius = int(shadowedLights[light].params.y);
+    float texel = shadowedLights[light].params.z;
+
+    float lit = 0.0;
+
+    for (int x = -radius; x <= radius; x++) {
+        for (int y = -radius; y <= radius; y++) {
+            vec2 offset = vec2(x, y) * texel;
+            lit += sampleShadowMap(light, vec4(uv + offset, cascade, ndc.z));
+        }
+    }
+
+    return lit / float((2 * radius + 1) * (2 * radius + 1));
+}
@@ -0,0 +1,20 @@
+#version 450
+
+layout(push_constant) uniform ViewProjection {
+    mat4 view;
+    mat4 projection;
+};
+
+layout(location = 0) out vec3 fragDirection;
+
+// Covers the screen with a single triangle at the far plane, generated from the vertex index,
+// passing on the world space direction through each corner
+void main() {
+    vec2 ndc = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2) * 2.0 - 1.0;
+
+    // The direction is unprojected from the near plane, which stays finite for infinite far planes
+    vec4 viewPoint = inverse(projection) * vec4(ndc, 0.0, 1.0);
+    fragDirection = inverse(mat3(view)) * (viewPoint.xyz / viewPoint.w);
+
+    gl_Position = vec4(ndc, 1.0, 1.0);
+}
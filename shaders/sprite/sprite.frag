@@ -0,0 +1,21 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler2D spriteTexture;
+
+layout(location = 0) in vec4 fragTint;
+layout(location = 1) in vec2 fragUV;
+layout(location = 2) in float fragDistanceField;
+
+layout(location = 0) out vec4 color;
+
+void main() {
+    vec4 texel = texture(spriteTexture, fragUV);
+
+    // Distance fields store the outline at 0.5, which is antialiased over about a pixel
+    if (fragDistanceField > 0.5) {
+        float width = max(fwidth(texel.a), 1e-4);
+        texel.a = smoothstep(0.5 - width, 0.5 + width, texel.a);
+    }
+
+    color = texel * fragTint;
+}
@@ -0,0 +1,25 @@
+#version 450
+
+layout(location = 0) in vec3 vertPosition;
+// Sprite vertices carry the alpha of their tint in the x component of the normal, and whether
+// their texture holds a distance field in the y component
+layout(location = 1) in vec3 vertNormal;
+layout(location = 2) in vec3 vertColor;
+layout(location = 3) in vec2 vertUV;
+
+layout(push_constant) uniform ViewProjection {
+    mat4 view;
+    mat4 projection;
+};
+
+layout(location = 0) out vec4 fragTint;
+layout(location = 1) out vec2 fragUV;
+layout(location = 2) out float fragDistanceField;
+
+void main() {
+    fragTint = vec4(vertColor, vertNormal.x);
+    fragUV = vertUV;
+    fragDistanceField = vertNormal.y;
+
+    gl_Position = projection * view * vec4(vertPosition, 1.0);
+}
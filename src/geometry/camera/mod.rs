@@ -0,0 +1,40 @@
+use cgmath::{Matrix4, SquareMatrix};
+
+use super::viewport::Viewport;
+
+/// Layer mask matching every instance layer
+pub const ALL_LAYERS: u32 = !0;
+
+/// A view of a scene drawn into a rectangle of the render target. Several cameras can draw the same
+/// scene in one frame, e.g. for split-screen or picture-in-picture
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub view_: Matrix4<f32>,
+    pub projection_: Matrix4<f32>,
+    /// The rectangle this camera draws to, as fractions of the renderer's viewport
+    pub viewport_: Viewport,
+    /// Only instances whose layer mask shares a bit with this mask are drawn by this camera
+    pub layer_mask_: u32,
+}
+
+impl Camera {
+    pub fn new(view: Matrix4<f32>, projection: Matrix4<f32>) -> Camera {
+        Camera {
+            view_: view,
+            projection_: projection,
+            viewport_: Viewport::full(),
+            layer_mask_: ALL_LAYERS,
+        }
+    }
+
+    /// Returns whether this camera draws instances with the given layer mask
+    pub fn sees_layers(&self, layer_mask: u32) -> bool {
+        self.layer_mask_ & layer_mask != 0
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera::new(Matrix4::identity(), Matrix4::identity())
+    }
+}
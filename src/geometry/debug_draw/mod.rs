@@ -0,0 +1,228 @@
+use std::f32::consts::PI;
+
+use cgmath::{conv, EuclideanSpace, Matrix4, Point3, SquareMatrix, Transform, Vector3};
+
+use super::vertex::Vertex;
+
+/// Number of line segments each circle of a debug sphere is drawn with
+const SPHERE_SEGMENTS: usize = 32;
+
+/// The corners of OpenGL style clip space, as cgmath builds projections. The first four lie on the
+/// near plane and the last four on the far plane, each in the same winding
+#[rustfmt::skip]
+const CLIP_CORNERS: [[f32; 3]; 8] = [
+    [-1.0, -1.0, -1.0], [1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [-1.0, 1.0, -1.0],
+    [-1.0, -1.0, 1.0], [1.0, -1.0, 1.0], [1.0, 1.0, 1.0], [-1.0, 1.0, 1.0],
+];
+
+/// Pairs of box corners joined by an edge, for corners ordered as CLIP_CORNERS
+#[rustfmt::skip]
+const BOX_EDGES: [(usize, usize); 12] = [
+    (0, 1), (1, 2), (2, 3), (3, 0),
+    (4, 5), (5, 6), (6, 7), (7, 4),
+    (0, 4), (1, 5), (2, 6), (3, 7),
+];
+
+/// Immediate-mode debug lines in world space. Primitives are drawn by every camera of the frame
+/// they were added in, after the renderer's render pass has drawn the scene, and are then cleared.
+/// Primitives added while depth testing is enabled are hidden behind the scene, others are drawn
+/// over it
+pub struct DebugDraw {
+    depth_test_: bool,
+    /// line list vertices of the primitives drawn with depth testing
+    depth_tested_: Vec<Vertex>,
+    /// line list vertices of the primitives drawn over the scene
+    overlay_: Vec<Vertex>,
+}
+
+impl DebugDraw {
+    /// Creates an empty debug draw with depth testing enabled
+    pub fn new() -> DebugDraw {
+        DebugDraw {
+            depth_test_: true,
+            depth_tested_: vec![],
+            overlay_: vec![],
+        }
+    }
+
+    /// Sets whether the primitives added from now on are hidden behind the scene
+    pub fn set_depth_test(&mut self, enabled: bool) {
+        self.depth_test_ = enabled;
+    }
+
+    pub fn get_depth_test(&self) -> bool {
+        self.depth_test_
+    }
+
+    /// Adds a line from a to b
+    pub fn line(&mut self, a: Point3<f32>, b: Point3<f32>, color: Vector3<f32>) {
+        let vertices = if self.depth_test_ {
+            &mut self.depth_tested_
+        } else {
+            &mut self.overlay_
+        };
+
+        vertices.push(DebugDraw::vertex(a, color));
+        vertices.push(DebugDraw::vertex(b, color));
+    }
+
+    /// Adds the edges of an axis aligned bounding box
+    pub fn aabb(&mut self, min: Point3<f32>, max: Point3<f32>, color: Vector3<f32>) {
+        let corners: Vec<Point3<f32>> = CLIP_CORNERS
+            .iter()
+            .map(|corner| {
+                Point3::new(
+                    if corner[0] < 0.0 { min.x } else { max.x },
+                    if corner[1] < 0.0 { min.y } else { max.y },
+                    if corner[2] < 0.0 { min.z } else { max.z },
+                )
+            })
+            .collect();
+
+        self.box_edges(&corners, color);
+    }
+
+    /// Adds a sphere as three circles around its center, one in each axis plane
+    pub fn sphere(&mut self, center: Point3<f32>, radius: f32, color: Vector3<f32>) {
+        let circle: Vec<(f32, f32)> = (0..=SPHERE_SEGMENTS)
+            .map(|segment| {
+                let angle = 2.0 * PI * segment as f32 / SPHERE_SEGMENTS as f32;
+                (angle.cos() * radius, angle.sin() * radius)
+            })
+            .collect();
+
+        for pair in circle.windows(2) {
+            let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+
+            self.line(
+                center + Vector3::new(x0, y0, 0.0),
+                center + Vector3::new(x1, y1, 0.0),
+                color,
+            );
+            self.line(
+                center + Vector3::new(x0, 0.0, y0),
+                center + Vector3::new(x1, 0.0, y1),
+                color,
+            );
+            self.line(
+                center + Vector3::new(0.0, x0, y0),
+                center + Vector3::new(0.0, x1, y1),
+                color,
+            );
+        }
+    }
+
+    /// Adds the x, y and z axes of a transform in red, green and blue, each size units long
+    /// before the transform is applied
+    pub fn axes(&mut self, transform: Matrix4<f32>, size: f32) {
+        let origin = transform.transform_point(Point3::origin());
+
+        let axes = [
+            (Vector3::unit_x(), Vector3::new(1.0, 0.0, 0.0)),
+            (Vector3::unit_y(), Vector3::new(0.0, 1.0, 0.0)),
+            (Vector3::unit_z(), Vector3::new(0.0, 0.0, 1.0)),
+        ];
+
+        for (axis, color) in axes.iter() {
+            let end = transform.transform_point(Point3::from_vec(axis * size));
+            self.line(origin, end, *color);
+        }
+    }
+
+    /// Adds the edges of the frustum of a view projection matrix, such as a camera's projection
+    /// times its view. Nothing is added if the matrix cannot be inverted
+    pub fn frustum(&mut self, view_projection: Matrix4<f32>, color: Vector3<f32>) {
+        let inverse = match view_projection.invert() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+
+        let corners: Vec<Point3<f32>> = CLIP_CORNERS
+            .iter()
+            .map(|corner| inverse.transform_point(Point3::from(*corner)))
+            .collect();
+
+        self.box_edges(&corners, color);
+    }
+
+    /// Removes every primitive
+    pub fn clear(&mut self) {
+        self.depth_tested_.clear();
+        self.overlay_.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.depth_tested_.is_empty() && self.overlay_.is_empty()
+    }
+
+    /// Returns the line list vertices of the primitives drawn with or without depth testing
+    pub fn get_vertices(&self, depth_tested: bool) -> &[Vertex] {
+        if depth_tested {
+            &self.depth_tested_
+        } else {
+            &self.overlay_
+        }
+    }
+
+    fn box_edges(&mut self, corners: &[Point3<f32>], color: Vector3<f32>) {
+        for (a, b) in BOX_EDGES.iter() {
+            self.line(corners[*a], corners[*b], color);
+        }
+    }
+
+    fn vertex(position: Point3<f32>, color: Vector3<f32>) -> Vertex {
+        Vertex {
+            position_: conv::array3(position),
+            normal_: [0.0, 0.0, 0.0],
+            color_: conv::array3(color),
+            uv_: [0.0, 0.0],
+        }
+    }
+}
+
+impl Default for DebugDraw {
+    fn default() -> DebugDraw {
+        DebugDraw::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, Matrix4, Point3, Vector3};
+
+    use super::DebugDraw;
+
+    #[test]
+    fn debug_draw_test() {
+        let mut debug_draw = DebugDraw::new();
+        let white = Vector3::new(1.0, 1.0, 1.0);
+
+        debug_draw.aabb(
+            Point3::new(-1.0, -1.0, -1.0),
+            Point3::new(1.0, 1.0, 1.0),
+            white,
+        );
+        debug_draw.set_depth_test(false);
+        debug_draw.axes(Matrix4::from_scale(2.0), 1.0);
+
+        assert_eq!(debug_draw.get_vertices(true).len(), 24);
+        assert_eq!(debug_draw.get_vertices(false).len(), 6);
+        assert_eq!(debug_draw.get_vertices(false)[1].position_, [2.0, 0.0, 0.0]);
+
+        let view_projection = cgmath::perspective(Deg(90.0), 1.0, 1.0, 10.0)
+            * Matrix4::look_at_rh(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, -1.0),
+                Vector3::unit_y(),
+            );
+
+        debug_draw.frustum(view_projection, white);
+
+        let far_corner = debug_draw.get_vertices(false)[6 + 8].position_;
+        assert!((far_corner[2] + 10.0).abs() < 1e-3);
+        assert!((far_corner[0].abs() - 10.0).abs() < 1e-3);
+
+        debug_draw.clear();
+        assert!(debug_draw.is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use std::path::Path;
+
+/// The signatures a Radiance file may start with
+const SIGNATURES: [&[u8]; 2] = [b"#?RADIANCE", b"#?RGBE"];
+
+/// Decoded linear RGB pixels of a Radiance HDR image, rows stored top to bottom, such as an
+/// equirectangular environment map
+#[derive(Clone, Debug)]
+pub struct HdrImageData {
+    pub width_: u32,
+    pub height_: u32,
+    /// three floats per pixel
+    pub pixels_: Vec<f32>,
+}
+
+impl HdrImageData {
+    /// Decodes a Radiance HDR file. Returns an error if the file cannot be read or decoded
+    pub fn from_file(path: &Path) -> Result<HdrImageData, String> {
+        let bytes =
+            std::fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+        HdrImageData::from_memory(&bytes).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    /// Decodes the contents of a Radiance HDR file with RGBE pixels, either run length encoded or
+    /// flat, stored top to bottom and left to right
+    pub fn from_memory(bytes: &[u8]) -> Result<HdrImageData, String> {
+        if !SIGNATURES
+            .iter()
+            .any(|signature| bytes.starts_with(signature))
+        {
+            return Err("Not a Radiance HDR file".to_string());
+        }
+
+        // The header is a list of lines ended by an empty one, followed by the resolution line
+        let mut offset = 0;
+
+        loop {
+            let line = read_line(bytes, &mut offset)?;
+
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(format!("Unsupported pixel format {}", format));
+                }
+            }
+        }
+
+        let resolution = read_line(bytes, &mut offset)?;
+        let (width, height) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (
+                width.parse::<u32>().map_err(|error| error.to_string())?,
+                height.parse::<u32>().map_err(|error| error.to_string())?,
+            ),
+            _ => {
+                return Err(format!(
+                    "Unsupported orientation {}, only -Y height +X width is",
+                    resolution
+                ))
+            }
+        };
+
+        if width == 0 || height == 0 {
+            return Err("Images must have a size".to_string());
+        }
+
+        // The resolution is checked against the bytes that follow before anything is allocated
+        // for it, so that a corrupt header cannot ask for more memory than its pixels could fill
+        let too_large = || format!("A {}x{} image does not fit in the file", width, height);
+        let min_size = min_scanline_size(width as usize)
+            .checked_mul(height as usize)
+            .ok_or_else(too_large)?;
+        let value_count = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|count| count.checked_mul(3))
+            .ok_or_else(too_large)?;
+
+        if min_size > bytes.len() - offset {
+            return Err(too_large());
+        }
+
+        let mut scanline = vec![[0u8; 4]; width as usize];
+        let mut pixels = Vec::with_capacity(value_count);
+
+        for _ in 0..height {
+            read_scanline(bytes, &mut offset, &mut scanline)?;
+
+            for rgbe in scanline.iter() {
+                pixels.extend_from_slice(&rgbe_to_rgb(*rgbe));
+            }
+        }
+
+        Ok(HdrImageData {
+            width_: width,
+            height_: height,
+            pixels_: pixels,
+        })
+    }
+}
+
+/// Returns the line starting at the offset, without its newline, and moves the offset past it
+fn read_line(bytes: &[u8], offset: &mut usize) -> Result<String, String> {
+    let remaining = &bytes[*offset..];
+    let length = remaining
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .ok_or("Truncated header")?;
+
+    *offset += length + 1;
+
+    Ok(String::from_utf8_lossy(&remaining[..length])
+        .trim()
+        .to_string())
+}
+
+/// Returns the fewest bytes a scanline of the given width can be stored in: four per pixel if
+/// flat, or if run length encoded its start and, for each channel, a count and a repeated byte
+/// for every run of at most 127 pixels
+fn min_scanline_size(width: usize) -> usize {
+    match (8..0x8000).contains(&width) {
+        true => 4 + 4 * 2 * width.div_ceil(127),
+        false => width.saturating_mul(4),
+    }
+}
+
+/// Decodes one scanline of RGBE pixels starting at the offset, and moves the offset past it
+fn read_scanline(bytes: &[u8], offset: &mut usize, scanline: &mut [[u8; 4]]) -> Result<(), String> {
+    let width = scanline.len();
+    let start = bytes.get(*offset..*offset + 4).ok_or("Truncated pixels")?;
+
+    // Run length encoded scanlines start with 2, 2 and their width, and store each channel in
+    // turn. Scanlines too narrow or too wide to be encoded are stored flat
+    let encoded = (8..0x8000).contains(&width)
+        && start[0] == 2
+        && start[1] == 2
+        && start[2] & 0x80 == 0
+        && ((start[2] as usize) << 8 | start[3] as usize) == width;
+
+    if !encoded {
+        for pixel in scanline.iter_mut() {
+            let rgbe = bytes.get(*offset..*offset + 4).ok_or("Truncated pixels")?;
+            pixel.copy_from_slice(rgbe);
+            *offset += 4;
+        }
+
+        return Ok(());
+    }
+
+    *offset += 4;
+
+    for channel in 0..4 {
+        let mut x = 0;
+
+        while x < width {
+            let count = *bytes.get(*offset).ok_or("Truncated pixels")? as usize;
+            *offset += 1;
+
+            // Counts above 128 repeat the following byte, others are followed by that many bytes
+            let (run, repeated) = match count > 128 {
+                true => (count - 128, true),
+                false => (count, false),
+            };
+
+            if run == 0 || x + run > width {
+                return Err("Corrupt run length encoded scanline".to_string());
+            }
+
+            for pixel in scanline[x..x + run].iter_mut() {
+                pixel[channel] = *bytes.get(*offset).ok_or("Truncated pixels")?;
+
+                if !repeated {
+                    *offset += 1;
+                }
+            }
+
+            if repeated {
+                *offset += 1;
+            }
+
+            x += run;
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts a pixel of 8 bit mantissas sharing an exponent to linear floats
+fn rgbe_to_rgb(rgbe: [u8; 4]) -> [f32; 3] {
+    if rgbe[3] == 0 {
+        return [0.0; 3];
+    }
+
+    // Each mantissa is a fraction of 256 scaled by 2 to the power of the biased exponent
+    let scale = 2.0f32.powi(rgbe[3] as i32 - (128 + 8));
+
+    [
+        rgbe[0] as f32 * scale,
+        rgbe[1] as f32 * scale,
+        rgbe[2] as f32 * scale,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_hdr_test() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 8\n".to_vec();
+
+        // A run length encoded scanline of eight pixels of 1.0, 0.5, 0.0: one run per channel
+        bytes.extend_from_slice(&[2, 2, 0, 8]);
+        bytes.extend_from_slice(&[136, 128, 136, 64, 136, 0, 136, 129]);
+
+        // A flat scanline, as it does not start with 2, 2, whose last pixel is 2.0, 0.0, 0.0
+        for x in 0..8 {
+            match x {
+                7 => bytes.extend_from_slice(&[128, 0, 0, 130]),
+                _ => bytes.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+
+        let image = HdrImageData::from_memory(&bytes).unwrap();
+
+        assert_eq!((image.width_, image.height_), (8, 2));
+        assert_eq!(&image.pixels_[..3], &[1.0, 0.5, 0.0]);
+        assert_eq!(&image.pixels_[21..24], &[1.0, 0.5, 0.0]);
+        assert_eq!(&image.pixels_[24..27], &[0.0, 0.0, 0.0]);
+        assert_eq!(&image.pixels_[45..], &[2.0, 0.0, 0.0]);
+        assert!(HdrImageData::from_memory(&bytes[..bytes.len() - 4]).is_err());
+
+        // A resolution far larger than the pixels that follow, whose value count also overflows
+        // a u32
+        let mut huge = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 100000 +X 100000\n".to_vec();
+        huge.extend_from_slice(&bytes[bytes.len() - 32..]);
+        assert!(HdrImageData::from_memory(&huge).is_err());
+    }
+}
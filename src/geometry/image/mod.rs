@@ -0,0 +1,79 @@
+use std::path::Path;
+
+pub mod hdr;
+pub mod texture_data;
+
+/// Decoded RGBA8 pixels of an image, rows stored top to bottom, ready to be uploaded as a texture
+#[derive(Clone, Debug)]
+pub struct ImageData {
+    pub width_: u32,
+    pub height_: u32,
+    pub pixels_: Vec<u8>,
+}
+
+impl ImageData {
+    /// Decodes a PNG or JPEG file into RGBA8 pixels. Returns an error if the file cannot be read
+    /// or decoded
+    pub fn from_file(path: &Path) -> Result<ImageData, String> {
+        let bytes =
+            std::fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+        ImageData::from_memory(&bytes).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    /// Decodes an encoded PNG or JPEG image into RGBA8 pixels. Returns an error if the format is
+    /// not recognized or the image cannot be decoded
+    pub fn from_memory(bytes: &[u8]) -> Result<ImageData, String> {
+        let image = ::image::load_from_memory(bytes).map_err(|error| error.to_string())?;
+        let rgba = image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+
+        Ok(ImageData {
+            width_: width,
+            height_: height,
+            pixels_: rgba.into_raw(),
+        })
+    }
+
+    /// Wraps raw RGBA8 pixels. Panics if there are not width * height * 4 bytes
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> ImageData {
+        assert_eq!(
+            pixels.len(),
+            (width * height * 4) as usize,
+            "A {}x{} RGBA image needs {} bytes",
+            width,
+            height,
+            width * height * 4
+        );
+
+        ImageData {
+            width_: width,
+            height_: height,
+            pixels_: pixels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_png_test() {
+        // A 2x1 PNG with an opaque red and a half transparent blue pixel
+        let png = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00,
+            0x00, 0xf4, 0x22, 0x7f, 0x8a, 0x00, 0x00, 0x00, 0x0e, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0x9c, 0x63, 0xf8, 0xcf, 0xc0, 0x00, 0x42, 0x0d, 0x00, 0x0f, 0x7a, 0x03, 0x7e, 0x77,
+            0xe9, 0x7f, 0x97, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60,
+            0x82,
+        ];
+
+        let image = ImageData::from_memory(&png).unwrap();
+
+        assert_eq!((image.width_, image.height_), (2, 1));
+        assert_eq!(image.pixels_, vec![255, 0, 0, 255, 0, 0, 255, 128]);
+        assert!(ImageData::from_memory(&png[..20]).is_err());
+    }
+}
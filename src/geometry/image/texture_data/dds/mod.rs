@@ -0,0 +1,251 @@
+use super::{
+    check_level_count, read_slice, read_u32, TextureData, TextureFormat, ASTC_BLOCK_SIZES,
+};
+
+/// The 4 bytes every DDS file starts with
+pub const MAGIC: [u8; 4] = *b"DDS ";
+
+/// Size of the magic and the header, after which either the DX10 header or the texels begin
+const HEADER_END: usize = 128;
+/// Size of the DX10 header following the header when the pixel format's FourCC is DX10
+const DX10_HEADER_SIZE: usize = 20;
+
+/// Pixel format flag marking the FourCC as valid
+const DDPF_FOURCC: u32 = 0x4;
+/// Pixel format flag marking the RGB bit count and masks as valid
+const DDPF_RGB: u32 = 0x40;
+/// Caps2 flag marking the file as a cube map
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+/// DX10 misc flag marking the texture array as an array of cube maps
+const RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+/// Parses a DDS file, either with a legacy FourCC or RGBA pixel format or with a DX10 header
+/// naming a DXGI format. DXGI has no ETC2 formats, so DDS files cannot store them. Volume
+/// textures are not supported
+pub fn parse(bytes: &[u8]) -> Result<TextureData, String> {
+    let height = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 16)?;
+    let depth = read_u32(bytes, 24)?;
+    let mip_count = read_u32(bytes, 28)?.max(1);
+    let pixel_format_flags = read_u32(bytes, 80)?;
+    let four_cc = read_slice(bytes, 84, 4)?;
+    let caps2 = read_u32(bytes, 112)?;
+
+    if depth > 1 {
+        return Err("Volume textures are not supported".to_string());
+    }
+
+    check_level_count(width, height, mip_count)?;
+
+    let mut data_offset = HEADER_END;
+    let mut layers = 1;
+    let mut cube = caps2 & DDSCAPS2_CUBEMAP != 0;
+    // Whether the texels are stored as BGRA and must be swizzled to RGBA
+    let mut bgra = false;
+
+    let (format, srgb) = if pixel_format_flags & DDPF_FOURCC != 0 && four_cc == b"DX10" {
+        let dxgi_format = read_u32(bytes, HEADER_END)?;
+        let misc_flag = read_u32(bytes, HEADER_END + 8)?;
+
+        layers = read_u32(bytes, HEADER_END + 12)?.max(1);
+        cube = misc_flag & RESOURCE_MISC_TEXTURECUBE != 0;
+        data_offset += DX10_HEADER_SIZE;
+
+        dxgi_texture_format(dxgi_format)
+            .ok_or_else(|| format!("DXGI format {} is not supported", dxgi_format))?
+    } else if pixel_format_flags & DDPF_FOURCC != 0 {
+        let format = match four_cc {
+            b"DXT1" => TextureFormat::Bc1Rgba,
+            b"DXT2" | b"DXT3" => TextureFormat::Bc2,
+            b"DXT4" | b"DXT5" => TextureFormat::Bc3,
+            b"ATI1" | b"BC4U" => TextureFormat::Bc4,
+            b"BC4S" => TextureFormat::Bc4Snorm,
+            b"ATI2" | b"BC5U" => TextureFormat::Bc5,
+            b"BC5S" => TextureFormat::Bc5Snorm,
+            _ => {
+                return Err(format!(
+                    "FourCC {} is not supported",
+                    String::from_utf8_lossy(four_cc)
+                ))
+            }
+        };
+
+        (format, false)
+    } else if pixel_format_flags & DDPF_RGB != 0 && read_u32(bytes, 88)? == 32 {
+        match (read_u32(bytes, 92)?, read_u32(bytes, 100)?) {
+            (0x0000_00ff, 0x00ff_0000) => (),
+            (0x00ff_0000, 0x0000_00ff) => bgra = true,
+            _ => return Err("Only RGBA8 and BGRA8 uncompressed formats are supported".to_string()),
+        }
+
+        (TextureFormat::Rgba8, false)
+    } else {
+        return Err("Only RGBA8, BGRA8 and block-compressed formats are supported".to_string());
+    };
+
+    if cube {
+        layers = layers
+            .checked_mul(6)
+            .ok_or_else(|| format!("{} cube maps are too many", layers))?;
+    }
+
+    let mut texture = TextureData {
+        format_: format,
+        srgb_: srgb,
+        width_: width,
+        height_: height,
+        layers_: layers,
+        cube_: cube,
+        levels_: vec![vec![]; mip_count as usize],
+        generate_mipmaps_: false,
+    };
+
+    // DDS stores every mip level of a layer before the next layer, while levels of TextureData
+    // each hold every layer
+    for _ in 0..layers {
+        for level in 0..mip_count as usize {
+            let (level_width, level_height) = texture.level_extent(level);
+            let size = format
+                .image_size(level_width, level_height)
+                .ok_or_else(|| format!("A {}x{} image is too large", level_width, level_height))?;
+
+            texture.levels_[level].extend_from_slice(read_slice(bytes, data_offset, size)?);
+            data_offset += size;
+        }
+    }
+
+    if bgra {
+        for level in texture.levels_.iter_mut() {
+            for texel in level.chunks_exact_mut(4) {
+                texel.swap(0, 2);
+            }
+        }
+    }
+
+    texture.validate()?;
+
+    Ok(texture)
+}
+
+/// Maps a DXGI_FORMAT value to a texture format and whether it is sRGB encoded
+fn dxgi_texture_format(dxgi_format: u32) -> Option<(TextureFormat, bool)> {
+    let format = match dxgi_format {
+        28 => (TextureFormat::Rgba8, false),
+        29 => (TextureFormat::Rgba8, true),
+        71 => (TextureFormat::Bc1Rgba, false),
+        72 => (TextureFormat::Bc1Rgba, true),
+        74 => (TextureFormat::Bc2, false),
+        75 => (TextureFormat::Bc2, true),
+        77 => (TextureFormat::Bc3, false),
+        78 => (TextureFormat::Bc3, true),
+        80 => (TextureFormat::Bc4, false),
+        81 => (TextureFormat::Bc4Snorm, false),
+        83 => (TextureFormat::Bc5, false),
+        84 => (TextureFormat::Bc5Snorm, false),
+        95 => (TextureFormat::Bc6hUfloat, false),
+        96 => (TextureFormat::Bc6hSfloat, false),
+        98 => (TextureFormat::Bc7, false),
+        99 => (TextureFormat::Bc7, true),
+        // ASTC formats each take four values: typeless, linear and sRGB, then one unused
+        133..=187 if dxgi_format % 4 >= 2 => {
+            let (width, height) = ASTC_BLOCK_SIZES[((dxgi_format - 133) / 4) as usize];
+            (TextureFormat::Astc(width, height), dxgi_format % 4 == 3)
+        }
+        _ => return None,
+    };
+
+    Some(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        // A 2x1 BGRA8 cube map with a single mip level
+        let mut bytes = MAGIC.to_vec();
+        bytes.resize(HEADER_END, 0);
+        bytes[12..16].copy_from_slice(&1_u32.to_le_bytes());
+        bytes[16..20].copy_from_slice(&2_u32.to_le_bytes());
+        bytes[80..84].copy_from_slice(&DDPF_RGB.to_le_bytes());
+        bytes[88..92].copy_from_slice(&32_u32.to_le_bytes());
+        bytes[92..96].copy_from_slice(&0x00ff_0000_u32.to_le_bytes());
+        bytes[100..104].copy_from_slice(&0x0000_00ff_u32.to_le_bytes());
+        bytes[112..116].copy_from_slice(&DDSCAPS2_CUBEMAP.to_le_bytes());
+
+        for face in 0..6 {
+            bytes.extend_from_slice(&[face, 1, 2, 255, face, 3, 4, 255]);
+        }
+
+        let texture = parse(&bytes).unwrap();
+
+        assert_eq!(texture.format_, TextureFormat::Rgba8);
+        assert!(texture.cube_);
+        assert_eq!(texture.layers_, 6);
+        assert_eq!(&texture.levels_[0][40..48], &[2, 1, 5, 255, 4, 3, 5, 255]);
+        assert!(parse(&bytes[..170]).is_err());
+    }
+
+    #[test]
+    fn parse_malformed_test() {
+        // A 1x1 RGBA8 texture claiming 33 mip levels
+        let mut bytes = MAGIC.to_vec();
+        bytes.resize(HEADER_END, 0);
+        bytes[12..16].copy_from_slice(&1_u32.to_le_bytes());
+        bytes[16..20].copy_from_slice(&1_u32.to_le_bytes());
+        bytes[28..32].copy_from_slice(&33_u32.to_le_bytes());
+        bytes[80..84].copy_from_slice(&DDPF_RGB.to_le_bytes());
+        bytes[88..92].copy_from_slice(&32_u32.to_le_bytes());
+        bytes[92..96].copy_from_slice(&0x0000_00ff_u32.to_le_bytes());
+        bytes[100..104].copy_from_slice(&0x00ff_0000_u32.to_le_bytes());
+        bytes.extend_from_slice(&[255; 4]);
+
+        assert!(parse(&bytes).is_err());
+
+        bytes[28..32].copy_from_slice(&1_u32.to_le_bytes());
+        assert!(parse(&bytes).is_ok());
+
+        // A DX10 array of cube maps whose face count overflows
+        let mut bytes = bytes[..HEADER_END].to_vec();
+        bytes[80..84].copy_from_slice(&DDPF_FOURCC.to_le_bytes());
+        bytes[84..88].copy_from_slice(b"DX10");
+        bytes.extend_from_slice(&28_u32.to_le_bytes());
+        bytes.extend_from_slice(&3_u32.to_le_bytes());
+        bytes.extend_from_slice(&RESOURCE_MISC_TEXTURECUBE.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&0_u32.to_le_bytes());
+        bytes.extend_from_slice(&[255; 4]);
+
+        assert!(parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn parse_oversized_test() {
+        // A header-only RGBA8 texture whose size in bytes overflows
+        let mut bytes = MAGIC.to_vec();
+        bytes.resize(4096, 0);
+        bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        bytes[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        bytes[80..84].copy_from_slice(&DDPF_RGB.to_le_bytes());
+        bytes[88..92].copy_from_slice(&32_u32.to_le_bytes());
+        bytes[92..96].copy_from_slice(&0x0000_00ff_u32.to_le_bytes());
+        bytes[100..104].copy_from_slice(&0x00ff_0000_u32.to_le_bytes());
+
+        assert!(parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn dxgi_texture_format_test() {
+        assert_eq!(dxgi_texture_format(99), Some((TextureFormat::Bc7, true)));
+        assert_eq!(
+            dxgi_texture_format(134),
+            Some((TextureFormat::Astc(4, 4), false))
+        );
+        assert_eq!(
+            dxgi_texture_format(187),
+            Some((TextureFormat::Astc(12, 12), true))
+        );
+        assert_eq!(dxgi_texture_format(133), None);
+    }
+}
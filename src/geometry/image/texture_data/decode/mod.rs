@@ -0,0 +1,370 @@
+//! Decoders for single blocks of block-compressed formats. Each writes the 16 texels of a 4x4
+//! block as RGBA8, in rows from top to bottom
+
+/// ETC1 and ETC2 intensity modifiers, indexed by table codeword
+const ETC_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+/// Distances between paint colors of ETC2's T and H modes
+const ETC2_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+/// EAC alpha modifiers, indexed by table codeword
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+pub fn bc1_rgb(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decode_bc1_colors(block, texels, true);
+
+    for texel in texels.iter_mut() {
+        texel[3] = 255;
+    }
+}
+
+pub fn bc1_rgba(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decode_bc1_colors(block, texels, true);
+}
+
+pub fn bc2(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decode_bc1_colors(&block[8..], texels, false);
+
+    for (i, texel) in texels.iter_mut().enumerate() {
+        let alpha = (block[i / 2] >> (4 * (i % 2))) & 0xf;
+        texel[3] = alpha * 17;
+    }
+}
+
+pub fn bc3(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decode_bc1_colors(&block[8..], texels, false);
+    decode_bc4_channel(block, texels, 3);
+}
+
+pub fn bc4(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    *texels = [[0, 0, 0, 255]; 16];
+    decode_bc4_channel(block, texels, 0);
+}
+
+pub fn bc5(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    *texels = [[0, 0, 0, 255]; 16];
+    decode_bc4_channel(block, texels, 0);
+    decode_bc4_channel(&block[8..], texels, 1);
+}
+
+pub fn etc2_rgb8(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decode_etc2_colors(block, texels, false);
+}
+
+pub fn etc2_rgb8a1(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decode_etc2_colors(block, texels, true);
+}
+
+pub fn etc2_rgba8(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decode_etc2_colors(&block[8..], texels, false);
+
+    let base = block[0] as i32;
+    let multiplier = (block[1] >> 4) as i32;
+    let modifiers = EAC_MODIFIERS[(block[1] & 0xf) as usize];
+    let indices = block[2..8]
+        .iter()
+        .fold(0_u64, |indices, byte| indices << 8 | *byte as u64);
+
+    for pixel in 0..16 {
+        let index = (indices >> (45 - 3 * pixel)) & 0x7;
+        let alpha = base + modifiers[index as usize] * multiplier;
+
+        texels[etc_texel(pixel)][3] = alpha.clamp(0, 255) as u8;
+    }
+}
+
+/// Decodes the two RGB565 endpoints and 2 bit indices shared by BC1, BC2 and BC3. Only BC1
+/// switches to three colors and transparent black when the first endpoint is not greater
+fn decode_bc1_colors(block: &[u8], texels: &mut [[u8; 4]; 16], allow_alpha: bool) {
+    let endpoint0 = u16::from_le_bytes([block[0], block[1]]);
+    let endpoint1 = u16::from_le_bytes([block[2], block[3]]);
+    let color0 = rgb565(endpoint0);
+    let color1 = rgb565(endpoint1);
+
+    let mix = |weight0: u32, weight1: u32| {
+        let total = weight0 + weight1;
+        let mut color = [0, 0, 0, 255];
+
+        for channel in 0..3 {
+            color[channel] = ((color0[channel] as u32 * weight0 + color1[channel] as u32 * weight1)
+                / total) as u8;
+        }
+
+        color
+    };
+
+    let colors = match endpoint0 > endpoint1 || !allow_alpha {
+        true => [color0, color1, mix(2, 1), mix(1, 2)],
+        false => [color0, color1, mix(1, 1), [0, 0, 0, 0]],
+    };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    for (i, texel) in texels.iter_mut().enumerate() {
+        *texel = colors[((indices >> (2 * i)) & 0x3) as usize];
+    }
+}
+
+/// Decodes a BC4 block, as also used for BC3's alpha and BC5's two channels, into one channel
+fn decode_bc4_channel(block: &[u8], texels: &mut [[u8; 4]; 16], channel: usize) {
+    let value0 = block[0] as u32;
+    let value1 = block[1] as u32;
+
+    let mut values = [value0, value1, 0, 0, 0, 0, 0, 255];
+
+    if value0 > value1 {
+        for i in 1..7 {
+            values[i + 1] = ((7 - i as u32) * value0 + i as u32 * value1) / 7;
+        }
+    } else {
+        for i in 1..5 {
+            values[i + 1] = ((5 - i as u32) * value0 + i as u32 * value1) / 5;
+        }
+    }
+
+    let indices = block[2..8]
+        .iter()
+        .rev()
+        .fold(0_u64, |indices, byte| indices << 8 | *byte as u64);
+
+    for (i, texel) in texels.iter_mut().enumerate() {
+        texel[channel] = values[((indices >> (3 * i)) & 0x7) as usize] as u8;
+    }
+}
+
+/// Decodes an ETC2 RGB block. With punchthrough alpha, the differential bit instead marks the
+/// block opaque, and otherwise texels with index 2 are transparent black
+fn decode_etc2_colors(block: &[u8], texels: &mut [[u8; 4]; 16], punchthrough: bool) {
+    let differential = block[3] & 0x2 != 0;
+    let opaque = !punchthrough || differential;
+
+    if !differential && !punchthrough {
+        let base0 = [block[0] >> 4, block[1] >> 4, block[2] >> 4].map(|value| value * 17);
+        let base1 = [block[0] & 0xf, block[1] & 0xf, block[2] & 0xf].map(|value| value * 17);
+
+        decode_etc1_subblocks(block, texels, base0, base1, opaque);
+        return;
+    }
+
+    let red = (block[0] >> 3) as i32 + signed_3_bits(block[0]);
+    let green = (block[1] >> 3) as i32 + signed_3_bits(block[1]);
+    let blue = (block[2] >> 3) as i32 + signed_3_bits(block[2]);
+
+    if !(0..32).contains(&red) {
+        decode_etc2_t_mode(block, texels, opaque);
+    } else if !(0..32).contains(&green) {
+        decode_etc2_h_mode(block, texels, opaque);
+    } else if !(0..32).contains(&blue) {
+        decode_etc2_planar_mode(block, texels);
+    } else {
+        let base0 = [block[0] >> 3, block[1] >> 3, block[2] >> 3].map(extend_5_bits);
+        let base1 = [red as u8, green as u8, blue as u8].map(extend_5_bits);
+
+        decode_etc1_subblocks(block, texels, base0, base1, opaque);
+    }
+}
+
+/// Decodes the two subblocks of ETC1's individual and differential modes, split vertically or
+/// horizontally depending on the flip bit
+fn decode_etc1_subblocks(
+    block: &[u8],
+    texels: &mut [[u8; 4]; 16],
+    base0: [u8; 3],
+    base1: [u8; 3],
+    opaque: bool,
+) {
+    let flip = block[3] & 0x1 != 0;
+    let tables = [(block[3] >> 5) as usize, ((block[3] >> 2) & 0x7) as usize];
+
+    for pixel in 0..16 {
+        let (x, y) = (pixel / 4, pixel % 4);
+        let subblock = match flip {
+            true => (y >= 2) as usize,
+            false => (x >= 2) as usize,
+        };
+        let base = [base0, base1][subblock];
+        let modifiers = ETC_MODIFIERS[tables[subblock]];
+
+        let modifier = match (etc_index(block, pixel), opaque) {
+            (2, false) => {
+                texels[etc_texel(pixel)] = [0, 0, 0, 0];
+                continue;
+            }
+            (0, false) => 0,
+            (0, true) => modifiers[0],
+            (1, _) => modifiers[1],
+            (2, true) => -modifiers[0],
+            _ => -modifiers[1],
+        };
+
+        texels[etc_texel(pixel)] = offset_color(base, modifier);
+    }
+}
+
+fn decode_etc2_t_mode(block: &[u8], texels: &mut [[u8; 4]; 16], opaque: bool) {
+    let color0 = [
+        ((block[0] >> 1) & 0xc) | (block[0] & 0x3),
+        block[1] >> 4,
+        block[1] & 0xf,
+    ]
+    .map(|value| value * 17);
+    let color1 = [block[2] >> 4, block[2] & 0xf, block[3] >> 4].map(|value| value * 17);
+    let distance = ETC2_DISTANCES[(((block[3] >> 1) & 0x6) | (block[3] & 0x1)) as usize];
+
+    let paint = [
+        offset_color(color0, 0),
+        offset_color(color1, distance),
+        offset_color(color1, 0),
+        offset_color(color1, -distance),
+    ];
+
+    decode_etc2_paint(block, texels, paint, opaque);
+}
+
+fn decode_etc2_h_mode(block: &[u8], texels: &mut [[u8; 4]; 16], opaque: bool) {
+    let color0 = [
+        (block[0] >> 3) & 0xf,
+        ((block[0] << 1) & 0xe) | ((block[1] >> 4) & 0x1),
+        (block[1] & 0x8) | ((block[1] << 1) & 0x6) | (block[2] >> 7),
+    ];
+    let color1 = [
+        (block[2] >> 3) & 0xf,
+        ((block[2] << 1) & 0xe) | (block[3] >> 7),
+        (block[3] >> 3) & 0xf,
+    ];
+
+    let packed = |color: [u8; 3]| (color[0] as u32) << 8 | (color[1] as u32) << 4 | color[2] as u32;
+    let distance_index =
+        (block[3] & 0x4) | ((block[3] << 1) & 0x2) | (packed(color0) >= packed(color1)) as u8;
+    let distance = ETC2_DISTANCES[distance_index as usize];
+
+    let color0 = color0.map(|value| value * 17);
+    let color1 = color1.map(|value| value * 17);
+
+    let paint = [
+        offset_color(color0, distance),
+        offset_color(color0, -distance),
+        offset_color(color1, distance),
+        offset_color(color1, -distance),
+    ];
+
+    decode_etc2_paint(block, texels, paint, opaque);
+}
+
+fn decode_etc2_paint(block: &[u8], texels: &mut [[u8; 4]; 16], paint: [[u8; 4]; 4], opaque: bool) {
+    for pixel in 0..16 {
+        let index = etc_index(block, pixel);
+
+        texels[etc_texel(pixel)] = match index == 2 && !opaque {
+            true => [0, 0, 0, 0],
+            false => paint[index],
+        };
+    }
+}
+
+fn decode_etc2_planar_mode(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    let extend_6_bits = |value: u8| (value << 2) | (value >> 4);
+    let extend_7_bits = |value: u8| (value << 1) | (value >> 6);
+
+    let origin = [
+        extend_6_bits((block[0] >> 1) & 0x3f),
+        extend_7_bits(((block[0] & 0x1) << 6) | ((block[1] >> 1) & 0x3f)),
+        extend_6_bits(
+            ((block[1] & 0x1) << 5) | (block[2] & 0x18) | ((block[2] & 0x3) << 1) | (block[3] >> 7),
+        ),
+    ];
+    let horizontal = [
+        extend_6_bits((((block[3] >> 2) & 0x1f) << 1) | (block[3] & 0x1)),
+        extend_7_bits(block[4] >> 1),
+        extend_6_bits(((block[4] & 0x1) << 5) | (block[5] >> 3)),
+    ];
+    let vertical = [
+        extend_6_bits(((block[5] & 0x7) << 3) | (block[6] >> 5)),
+        extend_7_bits(((block[6] & 0x1f) << 2) | (block[7] >> 6)),
+        extend_6_bits(block[7] & 0x3f),
+    ];
+
+    for (i, texel) in texels.iter_mut().enumerate() {
+        let (x, y) = ((i % 4) as i32, (i / 4) as i32);
+
+        for channel in 0..3 {
+            let origin = origin[channel] as i32;
+            let value = (x * (horizontal[channel] as i32 - origin)
+                + y * (vertical[channel] as i32 - origin)
+                + 4 * origin
+                + 2)
+                >> 2;
+
+            texel[channel] = value.clamp(0, 255) as u8;
+        }
+
+        texel[3] = 255;
+    }
+}
+
+/// Returns the 2 bit index of an ETC pixel, numbered down each column from the left
+fn etc_index(block: &[u8], pixel: usize) -> usize {
+    let indices = u32::from_be_bytes([block[4], block[5], block[6], block[7]]);
+    let msb = (indices >> (pixel + 16)) & 0x1;
+    let lsb = (indices >> pixel) & 0x1;
+
+    (msb << 1 | lsb) as usize
+}
+
+/// Converts an ETC pixel number, counted down each column, to a texel index counted along rows
+fn etc_texel(pixel: usize) -> usize {
+    (pixel % 4) * 4 + pixel / 4
+}
+
+fn offset_color(base: [u8; 3], offset: i32) -> [u8; 4] {
+    let channel = |value: u8| (value as i32 + offset).clamp(0, 255) as u8;
+
+    [channel(base[0]), channel(base[1]), channel(base[2]), 255]
+}
+
+fn signed_3_bits(byte: u8) -> i32 {
+    (((byte & 0x7) << 5) as i8 >> 5) as i32
+}
+
+fn extend_5_bits(value: u8) -> u8 {
+    (value << 3) | (value >> 2)
+}
+
+fn rgb565(color: u16) -> [u8; 4] {
+    let red = ((color >> 11) & 0x1f) as u8;
+    let green = ((color >> 5) & 0x3f) as u8;
+    let blue = (color & 0x1f) as u8;
+
+    [
+        extend_5_bits(red),
+        (green << 2) | (green >> 4),
+        extend_5_bits(blue),
+        255,
+    ]
+}
@@ -0,0 +1,211 @@
+use super::{
+    check_level_count, read_slice, read_u32, read_u64, TextureData, TextureFormat, ASTC_BLOCK_SIZES,
+};
+
+/// The 12 bytes every KTX2 file starts with
+pub const IDENTIFIER: [u8; 12] = [
+    0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a,
+];
+
+/// Size of the header and the index of the data format descriptor, key/value and supercompression
+/// data, after which the level index begins
+const LEVEL_INDEX_OFFSET: usize = 80;
+
+/// Parses a KTX2 file. Supercompressed files, 3D textures and formats other than RGBA8 and the
+/// BC, ETC2 and ASTC block formats are not supported
+pub fn parse(bytes: &[u8]) -> Result<TextureData, String> {
+    let vk_format = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?;
+    let depth = read_u32(bytes, 28)?;
+    let layer_count = read_u32(bytes, 32)?;
+    let face_count = read_u32(bytes, 36)?;
+    let level_count = read_u32(bytes, 40)?;
+    let supercompression_scheme = read_u32(bytes, 44)?;
+
+    if supercompression_scheme != 0 {
+        return Err(format!(
+            "Supercompression scheme {} is not supported",
+            supercompression_scheme
+        ));
+    }
+
+    if depth > 1 {
+        return Err("3D textures are not supported".to_string());
+    }
+
+    check_level_count(width, height, level_count)?;
+
+    let (format, srgb) = texture_format(vk_format)
+        .ok_or_else(|| format!("VkFormat {} is not supported", vk_format))?;
+    let layers = layer_count.max(1).checked_mul(face_count).ok_or_else(|| {
+        format!(
+            "{} layers of {} faces are too many",
+            layer_count, face_count
+        )
+    })?;
+
+    let mut texture = TextureData {
+        format_: format,
+        srgb_: srgb,
+        width_: width,
+        height_: height,
+        layers_: layers,
+        cube_: face_count == 6,
+        levels_: vec![],
+        // A level count of 0 asks for mip levels to be generated from the one level stored
+        generate_mipmaps_: level_count == 0,
+    };
+
+    for level in 0..level_count.max(1) as usize {
+        let index_offset = LEVEL_INDEX_OFFSET + level * 24;
+        let byte_offset = read_u64(bytes, index_offset)? as usize;
+        let byte_length = read_u64(bytes, index_offset + 8)? as usize;
+
+        texture
+            .levels_
+            .push(read_slice(bytes, byte_offset, byte_length)?.to_vec());
+    }
+
+    texture.validate()?;
+
+    Ok(texture)
+}
+
+/// Maps a VkFormat value to a texture format and whether it is sRGB encoded
+fn texture_format(vk_format: u32) -> Option<(TextureFormat, bool)> {
+    let format = match vk_format {
+        37 | 43 => TextureFormat::Rgba8,
+        131 | 132 => TextureFormat::Bc1Rgb,
+        133 | 134 => TextureFormat::Bc1Rgba,
+        135 | 136 => TextureFormat::Bc2,
+        137 | 138 => TextureFormat::Bc3,
+        139 => TextureFormat::Bc4,
+        140 => TextureFormat::Bc4Snorm,
+        141 => TextureFormat::Bc5,
+        142 => TextureFormat::Bc5Snorm,
+        143 => TextureFormat::Bc6hUfloat,
+        144 => TextureFormat::Bc6hSfloat,
+        145 | 146 => TextureFormat::Bc7,
+        147 | 148 => TextureFormat::Etc2Rgb8,
+        149 | 150 => TextureFormat::Etc2Rgb8A1,
+        151 | 152 => TextureFormat::Etc2Rgba8,
+        157..=184 => {
+            let (width, height) = ASTC_BLOCK_SIZES[((vk_format - 157) / 2) as usize];
+            TextureFormat::Astc(width, height)
+        }
+        _ => return None,
+    };
+
+    // Every sRGB format directly follows its linear counterpart, which for the block formats
+    // means odd values are linear and even ones sRGB
+    let srgb = match vk_format {
+        43 => true,
+        37 | 139..=144 => false,
+        _ => vk_format & 1 == 0,
+    };
+
+    Some((format, srgb))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        // An 8x4 BC1 sRGB texture with two mip levels
+        let mut bytes = IDENTIFIER.to_vec();
+
+        for word in [132_u32, 1, 8, 4, 0, 0, 1, 2, 0].iter() {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        bytes.resize(LEVEL_INDEX_OFFSET, 0);
+
+        for (offset, length) in [(128_u64, 16_u64), (144, 8)].iter() {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            bytes.extend_from_slice(&length.to_le_bytes());
+            bytes.extend_from_slice(&length.to_le_bytes());
+        }
+
+        bytes.resize(128, 0);
+        bytes.extend_from_slice(&[1; 16]);
+        bytes.extend_from_slice(&[2; 8]);
+
+        let texture = parse(&bytes).unwrap();
+
+        assert_eq!(texture.format_, TextureFormat::Bc1Rgb);
+        assert!(texture.srgb_);
+        assert_eq!(
+            (texture.width_, texture.height_, texture.layers_),
+            (8, 4, 1)
+        );
+        assert_eq!(texture.levels_, vec![vec![1; 16], vec![2; 8]]);
+        assert!(parse(&bytes[..140]).is_err());
+    }
+
+    #[test]
+    fn parse_malformed_test() {
+        // A 1x1 RGBA8 texture with one level, followed by its texels
+        let mut bytes = IDENTIFIER.to_vec();
+
+        for word in [37_u32, 1, 1, 1, 0, 0, 1, 1, 0].iter() {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        bytes.resize(LEVEL_INDEX_OFFSET, 0);
+
+        for word in [104_u64, 4, 4].iter() {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&[255; 4]);
+        assert!(parse(&bytes).is_ok());
+
+        // An offset and length whose sum overflows
+        let mut huge = bytes.clone();
+        huge[80..88].copy_from_slice(&u64::MAX.to_le_bytes());
+        huge[88..96].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(parse(&huge).is_err());
+
+        // More mip levels than a 1x1 texture has
+        let mut levels = bytes.clone();
+        levels[40..44].copy_from_slice(&33_u32.to_le_bytes());
+        assert!(parse(&levels).is_err());
+
+        // A layer count whose faces overflow
+        let mut layers = bytes;
+        layers[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+        layers[36..40].copy_from_slice(&6_u32.to_le_bytes());
+        assert!(parse(&layers).is_err());
+    }
+
+    #[test]
+    fn parse_oversized_test() {
+        // A 65536x65536 RGBA8 array whose layers take more bytes than fit in a usize
+        let mut bytes = IDENTIFIER.to_vec();
+
+        for word in [37_u32, 1, 65536, 65536, 0, u32::MAX, 1, 1, 0].iter() {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        bytes.resize(LEVEL_INDEX_OFFSET, 0);
+
+        for word in [104_u64, 4, 4].iter() {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&[255; 4]);
+        assert!(parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn texture_format_test() {
+        assert_eq!(texture_format(37), Some((TextureFormat::Rgba8, false)));
+        assert_eq!(texture_format(146), Some((TextureFormat::Bc7, true)));
+        assert_eq!(texture_format(140), Some((TextureFormat::Bc4Snorm, false)));
+        assert_eq!(texture_format(172), Some((TextureFormat::Astc(8, 8), true)));
+        assert_eq!(texture_format(0), None);
+    }
+}
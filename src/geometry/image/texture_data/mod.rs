@@ -0,0 +1,444 @@
+use std::path::Path;
+
+use super::ImageData;
+
+mod dds;
+mod decode;
+mod ktx2;
+
+/// The block sizes of the ASTC formats, in the order both VkFormat and DXGI_FORMAT list them
+pub const ASTC_BLOCK_SIZES: [(u32, u32); 14] = [
+    (4, 4),
+    (5, 4),
+    (5, 5),
+    (6, 5),
+    (6, 6),
+    (8, 5),
+    (8, 6),
+    (8, 8),
+    (10, 5),
+    (10, 6),
+    (10, 8),
+    (10, 10),
+    (12, 10),
+    (12, 12),
+];
+
+/// The layout of the texels of a texture. Block-compressed formats store blocks of texels of a
+/// fixed size, and formats that store colors can be either linear or sRGB encoded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    Rgba8,
+    /// four half floats per texel, for high dynamic range colors
+    Rgba16Float,
+    /// BC1 without alpha
+    Bc1Rgb,
+    /// BC1 with 1 bit alpha
+    Bc1Rgba,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc4Snorm,
+    Bc5,
+    Bc5Snorm,
+    Bc6hUfloat,
+    Bc6hSfloat,
+    Bc7,
+    Etc2Rgb8,
+    /// ETC2 with 1 bit alpha
+    Etc2Rgb8A1,
+    /// ETC2 with EAC alpha
+    Etc2Rgba8,
+    /// ASTC with the given block width and height
+    Astc(u32, u32),
+}
+
+impl TextureFormat {
+    /// Returns the width and height of the blocks the format stores texels in
+    pub fn block_extent(&self) -> (u32, u32) {
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Rgba16Float => (1, 1),
+            TextureFormat::Astc(width, height) => (*width, *height),
+            _ => (4, 4),
+        }
+    }
+
+    /// Returns the number of bytes each block of texels is stored in
+    pub fn block_size(&self) -> usize {
+        match self {
+            TextureFormat::Rgba8 => 4,
+            TextureFormat::Rgba16Float
+            | TextureFormat::Bc1Rgb
+            | TextureFormat::Bc1Rgba
+            | TextureFormat::Bc4
+            | TextureFormat::Bc4Snorm
+            | TextureFormat::Etc2Rgb8
+            | TextureFormat::Etc2Rgb8A1 => 8,
+            _ => 16,
+        }
+    }
+
+    /// Returns the number of bytes an image of the given size takes in this format, or None if
+    /// that does not fit in a usize
+    pub fn image_size(&self, width: u32, height: u32) -> Option<usize> {
+        let (block_width, block_height) = self.block_extent();
+
+        (width.div_ceil(block_width) as usize)
+            .checked_mul(height.div_ceil(block_height) as usize)?
+            .checked_mul(self.block_size())
+    }
+}
+
+/// Texels of a texture loaded from a KTX2 or DDS file, in the format they are stored in, with
+/// every mip level and array layer the file contains, or assembled from the faces of a cube map
+#[derive(Clone, Debug)]
+pub struct TextureData {
+    pub format_: TextureFormat,
+    /// true if the texels are sRGB encoded colors
+    pub srgb_: bool,
+    pub width_: u32,
+    pub height_: u32,
+    /// number of array layers, six for each cube map
+    pub layers_: u32,
+    /// true if every six layers are the +X, -X, +Y, -Y, +Z and -Z faces of a cube map
+    pub cube_: bool,
+    /// the texels of each mip level, largest first, each holding every layer in order
+    pub levels_: Vec<Vec<u8>>,
+    /// true if the file asks for its mip levels to be generated from the first one
+    pub generate_mipmaps_: bool,
+}
+
+impl TextureData {
+    /// Loads a KTX2 or DDS file, recognized by its contents. Returns an error if the file cannot
+    /// be read, is neither, or stores a format or layout that is not supported
+    pub fn from_file(path: &Path) -> Result<TextureData, String> {
+        let bytes =
+            std::fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+        TextureData::from_memory(&bytes).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    /// Parses the contents of a KTX2 or DDS file
+    pub fn from_memory(bytes: &[u8]) -> Result<TextureData, String> {
+        if bytes.starts_with(&ktx2::IDENTIFIER) {
+            ktx2::parse(bytes)
+        } else if bytes.starts_with(&dds::MAGIC) {
+            dds::parse(bytes)
+        } else {
+            Err("Not a KTX2 or DDS file".to_string())
+        }
+    }
+
+    /// Builds an RGBA8 cube map from six square images of equal size, in the order +X, -X, +Y,
+    /// -Y, +Z and -Z, whose mip levels are generated once uploaded. Returns an error if there are
+    /// not six faces or their sizes differ
+    pub fn from_cube_faces(faces: &[ImageData]) -> Result<TextureData, String> {
+        if faces.len() != 6 {
+            return Err(format!(
+                "A cube map needs 6 faces rather than {}",
+                faces.len()
+            ));
+        }
+
+        let size = faces[0].width_;
+
+        if let Some(face) = faces
+            .iter()
+            .find(|face| face.width_ != size || face.height_ != size)
+        {
+            return Err(format!(
+                "Cube map faces must all be {}x{} rather than {}x{}",
+                size, size, face.width_, face.height_
+            ));
+        }
+
+        let texture = TextureData {
+            format_: TextureFormat::Rgba8,
+            srgb_: false,
+            width_: size,
+            height_: size,
+            layers_: 6,
+            cube_: true,
+            levels_: vec![faces
+                .iter()
+                .flat_map(|face| face.pixels_.iter().cloned())
+                .collect()],
+            generate_mipmaps_: true,
+        };
+
+        texture.validate()?;
+
+        Ok(texture)
+    }
+
+    /// Builds an RGBA8 cube map from an image laying its faces out as a cross. A horizontal cross
+    /// is 4 faces wide and 3 high, with -X, +Z, +X and -Z along the middle row and +Y above and
+    /// -Y below +Z. A vertical cross is 3 faces wide and 4 high, with -Z below -Y, upside down.
+    /// Returns an error if the image is neither
+    pub fn from_cross(image: &ImageData) -> Result<TextureData, String> {
+        // The column and row of each face, in layer order, and whether it is upside down
+        let (size, cells) = if image.width_ * 3 == image.height_ * 4 {
+            (
+                image.width_ / 4,
+                [
+                    (2, 1, false),
+                    (0, 1, false),
+                    (1, 0, false),
+                    (1, 2, false),
+                    (1, 1, false),
+                    (3, 1, false),
+                ],
+            )
+        } else if image.width_ * 4 == image.height_ * 3 {
+            (
+                image.width_ / 3,
+                [
+                    (2, 1, false),
+                    (0, 1, false),
+                    (1, 0, false),
+                    (1, 2, false),
+                    (1, 1, false),
+                    (1, 3, true),
+                ],
+            )
+        } else {
+            return Err(format!(
+                "A {}x{} image is neither a 4x3 nor a 3x4 cross of square faces",
+                image.width_, image.height_
+            ));
+        };
+
+        let faces: Vec<_> = cells
+            .iter()
+            .map(|(column, row, upside_down)| {
+                let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+
+                for y in 0..size {
+                    for x in 0..size {
+                        let (x, y) = match upside_down {
+                            true => (size - 1 - x, size - 1 - y),
+                            false => (x, y),
+                        };
+                        let offset =
+                            (((row * size + y) * image.width_ + column * size + x) * 4) as usize;
+
+                        pixels.extend_from_slice(&image.pixels_[offset..offset + 4]);
+                    }
+                }
+
+                ImageData::from_rgba(size, size, pixels)
+            })
+            .collect();
+
+        TextureData::from_cube_faces(&faces)
+    }
+
+    /// Returns true if a file at the given path would be loaded as TextureData rather than as an
+    /// ImageData, judging by its extension
+    pub fn is_texture_file(path: &Path) -> bool {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) => {
+                extension.eq_ignore_ascii_case("ktx2") || extension.eq_ignore_ascii_case("dds")
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the width and height of a mip level
+    pub fn level_extent(&self, level: usize) -> (u32, u32) {
+        (
+            (self.width_ >> level).max(1),
+            (self.height_ >> level).max(1),
+        )
+    }
+
+    /// Decodes every mip level and layer into RGBA8 texels, for devices that cannot sample the
+    /// stored format. BC1 through BC5 and ETC2 can be decoded; signed BC4 and BC5, BC6H, BC7 and
+    /// ASTC return an error
+    pub fn decode_rgba8(&self) -> Result<TextureData, String> {
+        let decode_block: fn(&[u8], &mut [[u8; 4]; 16]) = match self.format_ {
+            TextureFormat::Rgba8 => return Ok(self.clone()),
+            TextureFormat::Bc1Rgb => decode::bc1_rgb,
+            TextureFormat::Bc1Rgba => decode::bc1_rgba,
+            TextureFormat::Bc2 => decode::bc2,
+            TextureFormat::Bc3 => decode::bc3,
+            TextureFormat::Bc4 => decode::bc4,
+            TextureFormat::Bc5 => decode::bc5,
+            TextureFormat::Etc2Rgb8 => decode::etc2_rgb8,
+            TextureFormat::Etc2Rgb8A1 => decode::etc2_rgb8a1,
+            TextureFormat::Etc2Rgba8 => decode::etc2_rgba8,
+            format => return Err(format!("{:?} textures cannot be decoded", format)),
+        };
+
+        let block_size = self.format_.block_size();
+        let mut levels = vec![];
+
+        for (level, texels) in self.levels_.iter().enumerate() {
+            let (width, height) = self.level_extent(level);
+            let blocks_wide = width.div_ceil(4);
+            let too_large = || format!("Mip level {} is too large to decode", level);
+            let layer_size = self
+                .format_
+                .image_size(width, height)
+                .ok_or_else(too_large)?;
+            let pixel_layer_size = TextureFormat::Rgba8
+                .image_size(width, height)
+                .ok_or_else(too_large)?;
+            let pixel_size = pixel_layer_size
+                .checked_mul(self.layers_ as usize)
+                .ok_or_else(too_large)?;
+            let mut pixels = vec![0; pixel_size];
+            let mut block_pixels = [[0; 4]; 16];
+
+            for layer in 0..self.layers_ as usize {
+                let layer_texels = texels
+                    .get(layer * layer_size..(layer + 1) * layer_size)
+                    .ok_or_else(|| format!("Mip level {} is missing texels", level))?;
+                let layer_pixels = &mut pixels[layer * pixel_layer_size..];
+
+                for (block, block_texels) in layer_texels.chunks_exact(block_size).enumerate() {
+                    decode_block(block_texels, &mut block_pixels);
+
+                    let block_x = (block as u32 % blocks_wide) * 4;
+                    let block_y = (block as u32 / blocks_wide) * 4;
+
+                    // Blocks overhanging the edge of the image are cropped
+                    for (i, pixel) in block_pixels.iter().enumerate() {
+                        let x = block_x + i as u32 % 4;
+                        let y = block_y + i as u32 / 4;
+
+                        if x < width && y < height {
+                            let offset = ((y * width + x) * 4) as usize;
+                            layer_pixels[offset..offset + 4].copy_from_slice(pixel);
+                        }
+                    }
+                }
+            }
+
+            levels.push(pixels);
+        }
+
+        Ok(TextureData {
+            format_: TextureFormat::Rgba8,
+            levels_: levels,
+            ..self.clone()
+        })
+    }
+
+    /// Checks that every mip level holds the texels of every layer
+    fn validate(&self) -> Result<(), String> {
+        if self.width_ == 0 || self.height_ == 0 || self.layers_ == 0 {
+            return Err("Textures must have a size and at least one layer".to_string());
+        }
+
+        for (level, texels) in self.levels_.iter().enumerate() {
+            let (width, height) = self.level_extent(level);
+            let expected = self
+                .format_
+                .image_size(width, height)
+                .and_then(|size| size.checked_mul(self.layers_ as usize))
+                .ok_or_else(|| format!("Mip level {} is too large", level))?;
+
+            if texels.len() != expected {
+                return Err(format!(
+                    "Mip level {} holds {} bytes rather than {}",
+                    level,
+                    texels.len(),
+                    expected
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, String> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .ok_or_else(|| "Unexpected end of file".to_string())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, String> {
+    Ok(read_u32(bytes, offset)? as u64 | (read_u32(bytes, offset + 4)? as u64) << 32)
+}
+
+fn read_slice(bytes: &[u8], offset: usize, length: usize) -> Result<&[u8], String> {
+    offset
+        .checked_add(length)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or_else(|| "Unexpected end of file".to_string())
+}
+
+/// Returns an error if a texture of the given size cannot have the given number of mip levels,
+/// each half the size of the previous one down to 1x1
+fn check_level_count(width: u32, height: u32, level_count: u32) -> Result<(), String> {
+    let max_level_count = 32 - width.max(height).leading_zeros();
+
+    if level_count > max_level_count {
+        return Err(format!(
+            "A {}x{} texture cannot have {} mip levels",
+            width, height, level_count
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_size_test() {
+        assert_eq!(TextureFormat::Rgba8.image_size(3, 2), Some(24));
+        assert_eq!(TextureFormat::Bc1Rgb.image_size(5, 4), Some(16));
+        assert_eq!(TextureFormat::Bc7.image_size(1, 1), Some(16));
+        assert_eq!(TextureFormat::Astc(6, 5).image_size(12, 11), Some(6 * 16));
+        assert_eq!(TextureFormat::Rgba8.image_size(u32::MAX, u32::MAX), None);
+    }
+
+    #[test]
+    fn decode_rgba8_test() {
+        // A single 2x2 BC1 level: white color endpoint 0, black endpoint 1 and every texel
+        // using endpoint 1
+        let texture = TextureData {
+            format_: TextureFormat::Bc1Rgb,
+            srgb_: false,
+            width_: 2,
+            height_: 2,
+            layers_: 1,
+            cube_: false,
+            levels_: vec![vec![0xff, 0xff, 0x00, 0x00, 0x55, 0x55, 0x55, 0x55]],
+            generate_mipmaps_: false,
+        };
+
+        let decoded = texture.decode_rgba8().unwrap();
+
+        assert_eq!(decoded.format_, TextureFormat::Rgba8);
+        assert_eq!(decoded.levels_, vec![[0, 0, 0, 255].repeat(4)]);
+        assert!(TextureData {
+            format_: TextureFormat::Bc7,
+            levels_: vec![vec![0; 16]],
+            ..texture
+        }
+        .decode_rgba8()
+        .is_err());
+    }
+
+    #[test]
+    fn cube_cross_test() {
+        // A vertical cross of 2x2 faces: each pixel's red channel is its index in the image
+        let pixels = (0..48u8).flat_map(|index| vec![index, 0, 0, 255]).collect();
+        let cube = TextureData::from_cross(&ImageData::from_rgba(6, 8, pixels)).unwrap();
+        let red: Vec<_> = cube.levels_[0].chunks(4).map(|pixel| pixel[0]).collect();
+
+        assert_eq!((cube.width_, cube.height_, cube.layers_), (2, 2, 6));
+        assert!(cube.cube_);
+        // +X is the middle row's right face and -Z the bottom face, rotated half a turn
+        assert_eq!(&red[..4], &[16, 17, 22, 23]);
+        assert_eq!(&red[20..], &[45, 44, 39, 38]);
+        assert!(TextureData::from_cross(&ImageData::from_rgba(2, 2, vec![0; 16])).is_err());
+    }
+}
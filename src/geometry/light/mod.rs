@@ -0,0 +1,432 @@
+use cgmath::{
+    Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Vector3, Vector4,
+};
+
+use super::camera::Camera;
+
+/// The maximum number of cascades a directional light's shadow can be split into
+pub const MAX_CASCADES: usize = 4;
+
+/// Weight of the logarithmic split scheme against the uniform one when splitting a camera frustum
+/// into cascades. Logarithmic splits give nearby cascades more resolution
+const CASCADE_SPLIT_LAMBDA: f32 = 0.75;
+
+/// Maps OpenGL clip space depth, from -1 to 1, onto vulkan's depth range of 0 to 1
+#[rustfmt::skip]
+const VULKAN_DEPTH_CORRECTION: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightType {
+    /// Infinitely far away, lighting everything from one direction like the sun
+    Directional,
+    /// Shines from a position in a cone around its direction
+    Spot,
+    /// Shines from a position in every direction. Point lights cast no shadows
+    Point,
+}
+
+/// How the light of point and spot lights falls off with distance d, scaling it by
+/// 1 / (constant + linear * d + quadratic * d^2)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attenuation {
+    pub constant_: f32,
+    pub linear_: f32,
+    pub quadratic_: f32,
+}
+
+impl Default for Attenuation {
+    /// Falls off with the inverse square of the distance, as physical lights do
+    fn default() -> Attenuation {
+        Attenuation {
+            constant_: 0.0,
+            linear_: 0.0,
+            quadratic_: 1.0,
+        }
+    }
+}
+
+/// How a light renders and samples its shadow map
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowSettings {
+    /// Width and height in texels of the shadow map, or of each cascade of a directional light
+    pub resolution_: u32,
+    /// Constant depth offset applied when rendering the shadow map, to avoid shadow acne
+    pub constant_bias_: f32,
+    /// Depth offset scaled by the slope of each rendered triangle
+    pub slope_bias_: f32,
+    /// Radius in texels of the percentage closer filtering kernel. 0 samples the shadow map once
+    pub pcf_radius_: u32,
+    /// Number of cascades a directional light splits the camera frustum into, from 1 to
+    /// MAX_CASCADES. Ignored by spot lights
+    pub cascades_: u32,
+    /// Distance from the camera up to which directional lights cast shadows
+    pub distance_: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> ShadowSettings {
+        ShadowSettings {
+            resolution_: 2048,
+            constant_bias_: 1.25,
+            slope_bias_: 1.75,
+            pcf_radius_: 1,
+            cascades_: MAX_CASCADES as u32,
+            distance_: 100.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    pub light_type_: LightType,
+    /// World space position of spot and point lights. Unused by directional lights
+    pub position_: Point3<f32>,
+    /// World space direction the light shines in. Unused by point lights
+    pub direction_: Vector3<f32>,
+    pub color_: Vector3<f32>,
+    /// Scale of the color
+    pub intensity_: f32,
+    /// Full opening angle of a spot light's cone
+    pub cone_angle_: Rad<f32>,
+    /// Full opening angle of the part of a spot light's cone that is fully lit. The light fades
+    /// out between it and cone_angle_
+    pub inner_cone_angle_: Rad<f32>,
+    /// Distance at which a spot or point light stops having an effect, also the far plane of a
+    /// spot light's shadow map. 0 gives point lights unlimited range
+    pub range_: f32,
+    pub attenuation_: Attenuation,
+    /// Shadow map settings, or None if the light casts no shadows
+    pub shadow_: Option<ShadowSettings>,
+}
+
+impl Light {
+    pub fn directional(direction: Vector3<f32>, color: Vector3<f32>) -> Light {
+        Light {
+            light_type_: LightType::Directional,
+            position_: Point3::origin(),
+            direction_: direction.normalize(),
+            color_: color,
+            intensity_: 1.0,
+            cone_angle_: Deg(45.0).into(),
+            inner_cone_angle_: Deg(36.0).into(),
+            range_: 0.0,
+            attenuation_: Attenuation::default(),
+            shadow_: None,
+        }
+    }
+
+    pub fn spot(
+        position: Point3<f32>,
+        direction: Vector3<f32>,
+        color: Vector3<f32>,
+        cone_angle: Rad<f32>,
+        range: f32,
+    ) -> Light {
+        Light {
+            light_type_: LightType::Spot,
+            position_: position,
+            direction_: direction.normalize(),
+            color_: color,
+            intensity_: 1.0,
+            cone_angle_: cone_angle,
+            inner_cone_angle_: cone_angle * 0.8,
+            range_: range,
+            attenuation_: Attenuation::default(),
+            shadow_: None,
+        }
+    }
+
+    pub fn point(position: Point3<f32>, color: Vector3<f32>, range: f32) -> Light {
+        Light {
+            light_type_: LightType::Point,
+            position_: position,
+            direction_: Vector3::unit_z(),
+            color_: color,
+            intensity_: 1.0,
+            cone_angle_: Deg(45.0).into(),
+            inner_cone_angle_: Deg(36.0).into(),
+            range_: range,
+            attenuation_: Attenuation::default(),
+            shadow_: None,
+        }
+    }
+
+    /// Returns the light with the given intensity
+    pub fn with_intensity(mut self, intensity: f32) -> Light {
+        self.intensity_ = intensity;
+        self
+    }
+
+    /// Returns the light with the given distance falloff
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Light {
+        self.attenuation_ = attenuation;
+        self
+    }
+
+    /// Returns the light with shadows enabled
+    pub fn with_shadow(mut self, shadow: ShadowSettings) -> Light {
+        self.shadow_ = Some(shadow);
+        self
+    }
+
+    /// Returns the number of shadow maps the light renders: one per cascade for directional
+    /// lights and a single one for spot lights. Zero if the light casts no shadows or is a point
+    /// light
+    pub fn get_shadow_map_count(&self) -> u32 {
+        match (self.shadow_, self.light_type_) {
+            (None, _) | (_, LightType::Point) => 0,
+            (Some(shadow), LightType::Directional) => {
+                shadow.cascades_.max(1).min(MAX_CASCADES as u32)
+            }
+            (Some(_), LightType::Spot) => 1,
+        }
+    }
+
+    /// Returns the view projection of each of the light's shadow maps, mapping world space onto
+    /// vulkan clip space, together with the view space distance from the camera at which each
+    /// cascade ends. Directional light cascades are fitted around slices of the camera frustum,
+    /// which must have an OpenGL style perspective projection such as cgmath::perspective. Returns
+    /// no shadow maps if the light casts no shadows or is a point light
+    ///
+    /// # Arguments
+    ///
+    /// * 'camera' - the camera the shadows are seen from
+    ///
+    pub fn get_shadow_view_projections(&self, camera: &Camera) -> (Vec<Matrix4<f32>>, Vec<f32>) {
+        let shadow = match self.shadow_ {
+            Some(shadow) => shadow,
+            None => return (vec![], vec![]),
+        };
+
+        match self.light_type_ {
+            LightType::Point => (vec![], vec![]),
+            LightType::Spot => {
+                let view = Matrix4::look_at_rh(
+                    self.position_,
+                    self.position_ + self.direction_,
+                    up_vector(self.direction_),
+                );
+                let projection =
+                    cgmath::perspective(self.cone_angle_, 1.0, self.range_ / 100.0, self.range_);
+
+                (
+                    vec![VULKAN_DEPTH_CORRECTION * projection * view],
+                    vec![self.range_],
+                )
+            }
+            LightType::Directional => {
+                let (near, far) = get_near_far(&camera.projection_);
+                let far = far.min(near + shadow.distance_);
+                let splits = cascade_splits(near, far, self.get_shadow_map_count());
+
+                let view_projections = splits
+                    .iter()
+                    .enumerate()
+                    .map(|(cascade, split_far)| {
+                        let split_near = if cascade == 0 {
+                            near
+                        } else {
+                            splits[cascade - 1]
+                        };
+                        self.fit_cascade(camera, split_near, *split_far, shadow.resolution_)
+                    })
+                    .collect();
+
+                (view_projections, splits)
+            }
+        }
+    }
+
+    /// Fits an orthographic shadow projection around the bounding sphere of a slice of the camera
+    /// frustum. Sizing by the sphere and snapping to whole texels keeps the shadow edges from
+    /// shimmering as the camera moves and turns
+    fn fit_cascade(
+        &self,
+        camera: &Camera,
+        split_near: f32,
+        split_far: f32,
+        resolution: u32,
+    ) -> Matrix4<f32> {
+        let corners = get_frustum_slice_corners(camera, split_near, split_far);
+
+        let center = corners
+            .iter()
+            .fold(Vector3::new(0.0, 0.0, 0.0), |sum, corner| {
+                sum + corner.to_vec()
+            })
+            / corners.len() as f32;
+
+        let radius = corners
+            .iter()
+            .map(|corner| (corner.to_vec() - center).magnitude())
+            .fold(0.0, f32::max);
+        let radius = (radius * 16.0).ceil() / 16.0;
+
+        // Casters up to two radii outside the slice towards the light still cast into it
+        let center = Point3::from_vec(center);
+        let view = Matrix4::look_at_rh(
+            center - self.direction_ * radius * 3.0,
+            center,
+            up_vector(self.direction_),
+        );
+        let projection = cgmath::ortho(-radius, radius, -radius, radius, 0.0, radius * 4.0);
+        let view_projection = projection * view;
+
+        let origin = view_projection * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let texels = resolution as f32 / 2.0;
+        let snap = Matrix4::from_translation(Vector3::new(
+            (origin.x * texels).round() / texels - origin.x,
+            (origin.y * texels).round() / texels - origin.y,
+            0.0,
+        ));
+
+        VULKAN_DEPTH_CORRECTION * snap * view_projection
+    }
+}
+
+/// Splits the view space depth range between near and far into cascades, returning the distance
+/// at which each cascade ends
+///
+/// # Arguments
+///
+/// * 'near' - distance of the camera's near plane
+///
+/// * 'far' - distance up to which shadows are cast
+///
+/// * 'cascades' - the number of cascades
+///
+pub fn cascade_splits(near: f32, far: f32, cascades: u32) -> Vec<f32> {
+    (1..=cascades)
+        .map(|cascade| {
+            let fraction = cascade as f32 / cascades as f32;
+            let logarithmic = near * (far / near).powf(fraction);
+            let uniform = near + (far - near) * fraction;
+
+            CASCADE_SPLIT_LAMBDA * logarithmic + (1.0 - CASCADE_SPLIT_LAMBDA) * uniform
+        })
+        .collect()
+}
+
+/// Returns the near and far plane distances of an OpenGL style perspective projection
+pub(crate) fn get_near_far(projection: &Matrix4<f32>) -> (f32, f32) {
+    let (c22, c32) = (projection.z.z, projection.w.z);
+
+    (c32 / (c22 - 1.0), c32 / (c22 + 1.0))
+}
+
+/// Returns the world space corners of the part of the camera frustum between two view space
+/// distances
+fn get_frustum_slice_corners(camera: &Camera, near: f32, far: f32) -> Vec<Point3<f32>> {
+    let (camera_near, camera_far) = get_near_far(&camera.projection_);
+    let inverse = (camera.projection_ * camera.view_)
+        .invert()
+        .expect("Camera view projection is not invertible");
+
+    let unproject = |x: f32, y: f32, z: f32| {
+        let corner = inverse * Vector4::new(x, y, z, 1.0);
+        corner.truncate() / corner.w
+    };
+
+    let mut corners = vec![];
+
+    for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].iter() {
+        let near_corner = unproject(*x, *y, -1.0);
+        let far_corner = unproject(*x, *y, 1.0);
+
+        for distance in [near, far].iter() {
+            let t = (distance - camera_near) / (camera_far - camera_near);
+            corners.push(Point3::from_vec(
+                near_corner + (far_corner - near_corner) * t,
+            ));
+        }
+    }
+
+    corners
+}
+
+/// Returns an up vector for a view looking in the given direction, avoiding one parallel to it
+fn up_vector(direction: Vector3<f32>) -> Vector3<f32> {
+    if direction.y.abs() > 0.99 {
+        Vector3::unit_z()
+    } else {
+        Vector3::unit_y()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, Matrix4, Point3, Vector3, Vector4};
+
+    use super::{Light, LightType, ShadowSettings};
+    use crate::geometry::camera::Camera;
+
+    #[test]
+    fn cascade_splits_test() {
+        let splits = super::cascade_splits(0.1, 100.0, 4);
+
+        assert_eq!(splits.len(), 4);
+        assert!(splits.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!((splits[3] - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn shadow_view_projection_test() {
+        let camera = Camera::new(
+            Matrix4::look_at_rh(
+                Point3::new(0.0, 2.0, 5.0),
+                Point3::new(0.0, 0.0, 0.0),
+                Vector3::unit_y(),
+            ),
+            cgmath::perspective(Deg(60.0), 16.0 / 9.0, 0.1, 50.0),
+        );
+
+        let spot = Light::spot(
+            Point3::new(0.0, 10.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+            Deg(60.0).into(),
+            20.0,
+        )
+        .with_shadow(ShadowSettings::default());
+
+        // A point on the spot light's axis lands in the middle of its shadow map
+        let (view_projections, _) = spot.get_shadow_view_projections(&camera);
+        let clip = view_projections[0] * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        assert!((clip.x / clip.w).abs() < 1e-4 && (clip.y / clip.w).abs() < 1e-4);
+        assert!(clip.z / clip.w > 0.0 && clip.z / clip.w < 1.0);
+
+        let sun = Light::directional(Vector3::new(-0.4, -1.0, -0.3), Vector3::new(1.0, 1.0, 1.0))
+            .with_shadow(ShadowSettings::default());
+
+        // The point the camera looks at is inside the first cascade that reaches it
+        let (view_projections, splits) = sun.get_shadow_view_projections(&camera);
+        assert_eq!(view_projections.len(), 4);
+
+        let cascade = splits.iter().position(|split| *split > 5.4).unwrap();
+        let clip = view_projections[cascade] * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        assert!(clip.x.abs() <= 1.0 && clip.y.abs() <= 1.0);
+        assert!(clip.z >= 0.0 && clip.z <= 1.0);
+    }
+
+    #[test]
+    fn point_light_test() {
+        let camera = Camera::new(Matrix4::from_scale(1.0), Matrix4::from_scale(1.0));
+        let light = Light::point(
+            Point3::new(0.0, 2.0, 0.0),
+            Vector3::new(1.0, 0.5, 0.0),
+            10.0,
+        )
+        .with_intensity(5.0)
+        .with_shadow(ShadowSettings::default());
+
+        assert_eq!(light.light_type_, LightType::Point);
+        assert_eq!(light.intensity_, 5.0);
+        assert_eq!(light.get_shadow_map_count(), 0);
+        assert!(light.get_shadow_view_projections(&camera).0.is_empty());
+    }
+}
@@ -1,5 +1,5 @@
 use super::vertex::Vertex;
-use cgmath::Vector3;
+use cgmath::{Point3, Vector3};
 
 #[repr(C)]
 #[derive(Clone, Debug)]
@@ -52,6 +52,35 @@ impl Mesh {
         }
     }
 
+    /// Returns the minimum and maximum corners of the axis aligned box bounding the mesh's
+    /// vertices, or two points at the origin if the mesh has no vertices
+    pub fn get_bounds(&self) -> (Point3<f32>, Point3<f32>) {
+        let mut vertices = self
+            .vertices_
+            .iter()
+            .map(|vertex| Point3::from(vertex.position_));
+
+        let first = match vertices.next() {
+            Some(first) => first,
+            None => return (Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, 0.0)),
+        };
+
+        vertices.fold((first, first), |(min, max), position| {
+            (
+                Point3::new(
+                    min.x.min(position.x),
+                    min.y.min(position.y),
+                    min.z.min(position.z),
+                ),
+                Point3::new(
+                    max.x.max(position.x),
+                    max.y.max(position.y),
+                    max.z.max(position.z),
+                ),
+            )
+        })
+    }
+
     fn generate_vertex_normals(vertices: &mut Vec<Vertex>, indices: &Vec<u32>) {
         for face in indices.chunks(3) {
             let vert_a = vertices[face[0] as usize].position_;
@@ -1,5 +1,12 @@
+pub mod camera;
+pub mod debug_draw;
+pub mod image;
+pub mod light;
 pub mod mesh;
 pub mod model;
 pub mod palette;
 pub mod scene;
+pub mod sprite;
+pub mod text;
 pub mod vertex;
+pub mod viewport;
@@ -2,7 +2,11 @@ use cgmath::{Matrix4, SquareMatrix};
 
 use std::collections::HashMap;
 
+use super::camera::{Camera, ALL_LAYERS};
+use super::light::Light;
 use super::palette::MeshID;
+use crate::render_sequence::material::MaterialID;
+use crate::vulkan::TextureID;
 
 pub type InstanceID = u64;
 
@@ -12,6 +16,11 @@ pub struct Instance {
     pub mesh_id_: MeshID,
     pub transform_: Matrix4<f32>,
     pub dirty_: bool,
+    /// Bit mask of the layers this instance belongs to, matched against each camera's layer mask
+    pub layer_mask_: u32,
+    /// The material the instance is drawn with, or None to draw it with the renderer's main pass
+    /// pipeline
+    pub material_id_: Option<MaterialID>,
 }
 
 impl Instance {
@@ -23,6 +32,8 @@ impl Instance {
                 None => Matrix4::identity(),
             },
             dirty_: true,
+            layer_mask_: ALL_LAYERS,
+            material_id_: None,
         }
     }
 }
@@ -34,6 +45,14 @@ pub struct Scene {
     curr_instance_id_: InstanceID,
     pub view_: Matrix4<f32>,
     pub projection_: Matrix4<f32>,
+    /// Cameras the scene is drawn from. If empty, the scene is drawn once using view_ and
+    /// projection_
+    pub cameras_: Vec<Camera>,
+    /// Lights of the scene. Lights with shadow settings render shadow maps every frame
+    pub lights_: Vec<Light>,
+    /// Cube map drawn behind the scene as a skybox, which materials can also sample for
+    /// reflections. None draws nothing behind the scene
+    pub environment_: Option<TextureID>,
 }
 
 impl Scene {
@@ -43,6 +62,9 @@ impl Scene {
             curr_instance_id_: 0,
             view_: Matrix4::identity(),
             projection_: Matrix4::identity(),
+            cameras_: vec![],
+            lights_: vec![],
+            environment_: None,
         }
     }
 
@@ -67,4 +89,39 @@ impl Scene {
     pub fn get_instances(&self) -> Vec<Instance> {
         self.instances_.values().cloned().collect()
     }
+
+    /// Returns every instance of the scene along with its ID
+    pub fn iter_instances(&self) -> impl Iterator<Item = (&InstanceID, &Instance)> {
+        self.instances_.iter()
+    }
+
+    /// Adds a camera to draw the scene from and returns its index
+    pub fn add_camera(&mut self, camera: Camera) -> usize {
+        self.cameras_.push(camera);
+        self.cameras_.len() - 1
+    }
+
+    pub fn get_mut_camera(&mut self, index: usize) -> &mut Camera {
+        self.cameras_.get_mut(index).expect("No such camera")
+    }
+
+    /// Adds a light to the scene and returns its index
+    pub fn add_light(&mut self, light: Light) -> usize {
+        self.lights_.push(light);
+        self.lights_.len() - 1
+    }
+
+    pub fn get_mut_light(&mut self, index: usize) -> &mut Light {
+        self.lights_.get_mut(index).expect("No such light")
+    }
+
+    /// Returns the cameras the scene is drawn from, falling back to a single full viewport camera
+    /// built from view_ and projection_ if no cameras have been added
+    pub fn get_cameras(&self) -> Vec<Camera> {
+        if self.cameras_.is_empty() {
+            vec![Camera::new(self.view_, self.projection_)]
+        } else {
+            self.cameras_.clone()
+        }
+    }
 }
@@ -0,0 +1,304 @@
+use cgmath::{Matrix4, Point3, Rad, Transform, Vector2, Vector4};
+
+use super::camera::Camera;
+use super::text::FontAtlas;
+use super::vertex::Vertex;
+use crate::vulkan::TextureID;
+
+/// Corners of a sprite's quad relative to its center and size, as two triangles. The first corner
+/// is the top left of the texture area
+const QUAD_CORNERS: [[f32; 2]; 6] = [
+    [-0.5, -0.5],
+    [0.5, -0.5],
+    [0.5, 0.5],
+    [-0.5, -0.5],
+    [0.5, 0.5],
+    [-0.5, 0.5],
+];
+
+/// A textured quad drawn by a SpriteBatch
+#[derive(Clone, Copy, Debug)]
+pub struct Sprite {
+    pub texture_: TextureID,
+    /// The center of the sprite
+    pub position_: Vector2<f32>,
+    pub size_: Vector2<f32>,
+    /// Rotation around the sprite's center, turning its x axis towards its y axis
+    pub rotation_: Rad<f32>,
+    /// The area of the texture drawn, as the texture coordinates of its top left and bottom right
+    /// corners
+    pub uv_rect_: [f32; 4],
+    /// Color the texture is multiplied by
+    pub tint_: Vector4<f32>,
+    /// Sprites of lower layers are drawn first, and so appear behind sprites of higher layers
+    pub layer_: i32,
+    /// Whether the texture's alpha holds a signed distance field rather than coverage, as font
+    /// atlases may
+    pub distance_field_: bool,
+}
+
+impl Sprite {
+    /// Creates an untinted, unrotated sprite showing the whole texture in layer 0
+    pub fn new(texture: TextureID, position: Vector2<f32>, size: Vector2<f32>) -> Sprite {
+        Sprite {
+            texture_: texture,
+            position_: position,
+            size_: size,
+            rotation_: Rad(0.0),
+            uv_rect_: [0.0, 0.0, 1.0, 1.0],
+            tint_: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            layer_: 0,
+            distance_field_: false,
+        }
+    }
+}
+
+/// A run of sprite vertices drawn with the same texture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpriteDraw {
+    pub texture_: TextureID,
+    pub first_vertex_: u32,
+    pub vertex_count_: u32,
+}
+
+/// Sprites drawn over a renderer's next frame, after its scene and debug lines. Sprites are
+/// sorted by layer and, within a layer, grouped by texture so that each texture is bound once per
+/// layer. Sprites sharing a layer and texture are drawn in the order they were added. World
+/// sprites are placed in the scene by a transform and drawn by every camera, hidden behind the
+/// scene's geometry
+pub struct SpriteBatch {
+    /// the camera sprites are drawn with, or None to draw in pixels of the renderer's viewport
+    camera_: Option<Camera>,
+    sprites_: Vec<Sprite>,
+    world_sprites_: Vec<(Sprite, Matrix4<f32>)>,
+}
+
+impl SpriteBatch {
+    /// Creates an empty batch drawing in pixels, with the origin at the top left of the renderer's
+    /// viewport and y pointing down
+    pub fn new() -> SpriteBatch {
+        SpriteBatch {
+            camera_: None,
+            sprites_: vec![],
+            world_sprites_: vec![],
+        }
+    }
+
+    /// Sets the camera sprites are drawn with, usually with an orthographic projection, or None to
+    /// draw in pixels. Sprites lie in the z = 0 plane of the camera's world
+    pub fn set_camera(&mut self, camera: Option<Camera>) {
+        self.camera_ = camera;
+    }
+
+    pub fn get_camera(&self) -> Option<&Camera> {
+        self.camera_.as_ref()
+    }
+
+    /// Adds a sprite to the next frame
+    pub fn draw(&mut self, sprite: Sprite) {
+        self.sprites_.push(sprite);
+    }
+
+    /// Adds a sprite to the scene of the next frame. The sprite lies in the xy plane of the
+    /// transform, which places it in world space
+    pub fn draw_world(&mut self, sprite: Sprite, transform: Matrix4<f32>) {
+        self.world_sprites_.push((sprite, transform));
+    }
+
+    /// Adds a string drawn with a font atlas, laid out as FontAtlas::layout does
+    ///
+    /// # Arguments
+    ///
+    /// * 'atlas' - the font atlas, uploaded as a texture
+    ///
+    /// * 'text' - the string to draw
+    ///
+    /// * 'position' - the top left corner of the text
+    ///
+    /// * 'color' - the color of the text
+    ///
+    /// * 'max_width' - the width lines are wrapped at, or None to only break lines at newlines
+    ///
+    pub fn text(
+        &mut self,
+        atlas: &FontAtlas,
+        text: &str,
+        position: Vector2<f32>,
+        color: Vector4<f32>,
+        max_width: Option<f32>,
+    ) {
+        self.sprites_
+            .extend(atlas.build_sprites(text, position, color, max_width));
+    }
+
+    /// Adds a string to the scene of the next frame. The text starts at the origin of the
+    /// transform's xy plane and runs along x with y up, one unit per pixel of the font atlas
+    ///
+    /// # Arguments
+    ///
+    /// * 'atlas' - the font atlas, uploaded as a texture
+    ///
+    /// * 'text' - the string to draw
+    ///
+    /// * 'transform' - places the text in world space
+    ///
+    /// * 'color' - the color of the text
+    ///
+    /// * 'max_width' - the width lines are wrapped at, or None to only break lines at newlines
+    ///
+    pub fn world_text(
+        &mut self,
+        atlas: &FontAtlas,
+        text: &str,
+        transform: Matrix4<f32>,
+        color: Vector4<f32>,
+        max_width: Option<f32>,
+    ) {
+        for mut sprite in atlas.build_sprites(text, Vector2::new(0.0, 0.0), color, max_width) {
+            // Layouts point y down, so glyphs are mirrored to keep them upright with y up
+            sprite.position_.y = -sprite.position_.y;
+            sprite.size_.y = -sprite.size_.y;
+
+            self.world_sprites_.push((sprite, transform));
+        }
+    }
+
+    /// Removes every sprite
+    pub fn clear(&mut self) {
+        self.sprites_.clear();
+        self.world_sprites_.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites_.is_empty() && self.world_sprites_.is_empty()
+    }
+
+    pub fn get_sprites(&self) -> &[Sprite] {
+        &self.sprites_
+    }
+
+    /// Returns the vertices of every sprite as a triangle list, in draw order, and the runs of
+    /// them sharing a texture. The alpha of each sprite's tint is stored in the x component of its
+    /// vertices' normal, and the y component is 1 for distance field sprites
+    ///
+    /// # Arguments
+    ///
+    /// * 'world' - whether to build the world sprites rather than the others
+    ///
+    pub fn build_draws(&self, world: bool) -> (Vec<Vertex>, Vec<SpriteDraw>) {
+        let mut sprites: Vec<(&Sprite, Option<&Matrix4<f32>>)> = if world {
+            self.world_sprites_
+                .iter()
+                .map(|(sprite, transform)| (sprite, Some(transform)))
+                .collect()
+        } else {
+            self.sprites_.iter().map(|sprite| (sprite, None)).collect()
+        };
+        sprites.sort_by_key(|(sprite, _)| (sprite.layer_, sprite.texture_));
+
+        let mut vertices = Vec::with_capacity(sprites.len() * QUAD_CORNERS.len());
+        let mut draws: Vec<SpriteDraw> = vec![];
+
+        for (sprite, transform) in sprites {
+            let first_vertex = vertices.len() as u32;
+            let (sin, cos) = (sprite.rotation_.0.sin(), sprite.rotation_.0.cos());
+            let [u0, v0, u1, v1] = sprite.uv_rect_;
+
+            for corner in QUAD_CORNERS.iter() {
+                let x = corner[0] * sprite.size_.x;
+                let y = corner[1] * sprite.size_.y;
+
+                let mut position = Point3::new(
+                    sprite.position_.x + x * cos - y * sin,
+                    sprite.position_.y + x * sin + y * cos,
+                    0.0,
+                );
+
+                if let Some(transform) = transform {
+                    position = transform.transform_point(position);
+                }
+
+                vertices.push(Vertex {
+                    position_: position.into(),
+                    normal_: [
+                        sprite.tint_.w,
+                        if sprite.distance_field_ { 1.0 } else { 0.0 },
+                        0.0,
+                    ],
+                    color_: [sprite.tint_.x, sprite.tint_.y, sprite.tint_.z],
+                    uv_: [
+                        if corner[0] < 0.0 { u0 } else { u1 },
+                        if corner[1] < 0.0 { v0 } else { v1 },
+                    ],
+                });
+            }
+
+            match draws.last_mut() {
+                Some(draw) if draw.texture_ == sprite.texture_ => {
+                    draw.vertex_count_ += QUAD_CORNERS.len() as u32
+                }
+                _ => draws.push(SpriteDraw {
+                    texture_: sprite.texture_,
+                    first_vertex_: first_vertex,
+                    vertex_count_: QUAD_CORNERS.len() as u32,
+                }),
+            }
+        }
+
+        (vertices, draws)
+    }
+}
+
+impl Default for SpriteBatch {
+    fn default() -> SpriteBatch {
+        SpriteBatch::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Rad, Vector2};
+
+    use super::{Sprite, SpriteBatch};
+
+    #[test]
+    fn build_draws_test() {
+        let mut batch = SpriteBatch::new();
+
+        let mut background = Sprite::new(1, Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0));
+        background.layer_ = -1;
+
+        let mut rotated = Sprite::new(2, Vector2::new(10.0, 10.0), Vector2::new(2.0, 4.0));
+        rotated.rotation_ = Rad(std::f32::consts::FRAC_PI_2);
+
+        batch.draw(Sprite::new(
+            2,
+            Vector2::new(0.0, 0.0),
+            Vector2::new(1.0, 1.0),
+        ));
+        batch.draw(Sprite::new(
+            3,
+            Vector2::new(0.0, 0.0),
+            Vector2::new(1.0, 1.0),
+        ));
+        batch.draw(rotated);
+        batch.draw(background);
+
+        let (vertices, draws) = batch.build_draws(false);
+
+        assert_eq!(vertices.len(), 24);
+        assert_eq!(draws.len(), 3);
+        assert_eq!(
+            draws.iter().map(|draw| draw.texture_).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(draws[1].first_vertex_, 6);
+        assert_eq!(draws[1].vertex_count_, 12);
+
+        // The top left corner of the rotated sprite turns to its top right
+        let corner = vertices[12].position_;
+        assert!((corner[0] - 12.0).abs() < 1e-5);
+        assert!((corner[1] - 9.0).abs() < 1e-5);
+        assert_eq!(vertices[12].uv_, [0.0, 0.0]);
+    }
+}
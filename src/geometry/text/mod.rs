@@ -0,0 +1,513 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use cgmath::{Vector2, Vector4};
+use rusttype::{point, GlyphId, Scale};
+
+use super::image::ImageData;
+use super::sprite::Sprite;
+use crate::vulkan::TextureID;
+
+/// Width of font atlases in pixels. Atlases grow downwards to fit their glyphs
+const ATLAS_WIDTH: u32 = 1024;
+/// Empty pixels around each glyph of a bitmap atlas, so that filtering does not bleed between
+/// glyphs
+const BITMAP_PADDING: u32 = 1;
+
+/// The printable ASCII characters
+pub const ASCII: &str = " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`\
+abcdefghijklmnopqrstuvwxyz{|}~";
+
+/// A TrueType or OpenType font
+#[derive(Clone)]
+pub struct Font {
+    font_: rusttype::Font<'static>,
+}
+
+impl Font {
+    /// Reads a TTF or OTF file. Returns an error if the file cannot be read or parsed
+    pub fn from_file(path: &Path) -> Result<Font, String> {
+        let bytes =
+            std::fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+        Font::from_memory(bytes).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    /// Parses the contents of a TTF or OTF file. Returns an error if they cannot be parsed
+    pub fn from_memory(bytes: Vec<u8>) -> Result<Font, String> {
+        rusttype::Font::try_from_vec(bytes)
+            .map(|font| Font { font_: font })
+            .ok_or_else(|| "Not a valid TrueType or OpenType font".to_string())
+    }
+}
+
+/// How the glyphs of a font atlas are stored
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AtlasMode {
+    /// Glyph coverage, sharp at the atlas's pixel size
+    Bitmap,
+    /// Signed distance to the glyph's outline, spread over the given number of pixels on either
+    /// side, which stays sharp when text is scaled up
+    DistanceField { spread_: u32 },
+}
+
+/// A glyph stored in a font atlas
+#[derive(Clone, Copy, Debug)]
+struct AtlasGlyph {
+    id_: GlyphId,
+    /// the top left corner of the glyph's image relative to the pen position on the baseline
+    offset_: Vector2<f32>,
+    size_: Vector2<f32>,
+    /// the texture coordinates of the glyph's top left and bottom right corners
+    uv_rect_: [f32; 4],
+    advance_: f32,
+}
+
+/// A glyph placed by FontAtlas::layout
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphQuad {
+    /// The top left corner of the glyph, relative to the top left of the text
+    pub position_: Vector2<f32>,
+    pub size_: Vector2<f32>,
+    /// The texture coordinates of the glyph's top left and bottom right corners in the atlas
+    pub uv_rect_: [f32; 4],
+}
+
+/// The glyphs of a font rasterized at one pixel size into an RGBA image, white with the glyph in
+/// the alpha channel. Once uploaded as a texture, text is drawn with it as sprites
+pub struct FontAtlas {
+    font_: Font,
+    scale_: Scale,
+    mode_: AtlasMode,
+    glyphs_: HashMap<char, AtlasGlyph>,
+    image_: ImageData,
+    texture_: Option<TextureID>,
+}
+
+impl FontAtlas {
+    /// Rasterizes the given characters of a font. Characters the font lacks are drawn as its
+    /// missing glyph
+    ///
+    /// # Arguments
+    ///
+    /// * 'font' - the font to rasterize
+    ///
+    /// * 'pixel_size' - the distance from the font's ascent to its descent, in pixels
+    ///
+    /// * 'mode' - whether glyphs are stored as coverage or as distance fields
+    ///
+    /// * 'characters' - the characters text drawn with the atlas may use, such as ASCII
+    ///
+    pub fn new(font: &Font, pixel_size: f32, mode: AtlasMode, characters: &str) -> FontAtlas {
+        let scale = Scale::uniform(pixel_size);
+        let padding = match mode {
+            AtlasMode::Bitmap => BITMAP_PADDING,
+            AtlasMode::DistanceField { spread_ } => spread_ + 1,
+        };
+
+        let mut characters: Vec<char> = characters.chars().collect();
+        characters.sort();
+        characters.dedup();
+
+        let mut packer = ShelfPacker::new(ATLAS_WIDTH);
+        let mut images = vec![];
+
+        for character in characters {
+            let glyph = font.font_.glyph(character).scaled(scale);
+            let advance = glyph.h_metrics().advance_width;
+            let id = glyph.id();
+            let glyph = glyph.positioned(point(0.0, 0.0));
+
+            let bounds = match glyph.pixel_bounding_box() {
+                Some(bounds) => bounds,
+                None => {
+                    // Whitespace has an advance but nothing to draw
+                    images.push((character, id, advance, None));
+                    continue;
+                }
+            };
+
+            let width = bounds.width() as u32;
+            let height = bounds.height() as u32;
+            let mut coverage = vec![0.0; (width * height) as usize];
+
+            glyph.draw(|x, y, value| coverage[(y * width + x) as usize] = value);
+
+            let (x, y) = packer.pack(width + 2 * padding, height + 2 * padding);
+
+            images.push((
+                character,
+                id,
+                advance,
+                Some((coverage, width, height, bounds.min, x, y)),
+            ));
+        }
+
+        let atlas_height = packer.get_height().max(1);
+        let mut pixels = vec![255; (ATLAS_WIDTH * atlas_height * 4) as usize];
+
+        for alpha in pixels.iter_mut().skip(3).step_by(4) {
+            *alpha = 0;
+        }
+
+        let mut glyphs = HashMap::new();
+
+        for (character, id, advance, image) in images {
+            let mut atlas_glyph = AtlasGlyph {
+                id_: id,
+                offset_: Vector2::new(0.0, 0.0),
+                size_: Vector2::new(0.0, 0.0),
+                uv_rect_: [0.0, 0.0, 0.0, 0.0],
+                advance_: advance,
+            };
+
+            if let Some((coverage, width, height, min, x, y)) = image {
+                let padded_width = width + 2 * padding;
+                let padded_height = height + 2 * padding;
+
+                let alpha = match mode {
+                    AtlasMode::Bitmap => pad(&coverage, width, height, padding),
+                    AtlasMode::DistanceField { spread_ } => distance_field(
+                        &pad(&coverage, width, height, padding),
+                        padded_width,
+                        padded_height,
+                        spread_,
+                    ),
+                };
+
+                for row in 0..padded_height {
+                    for column in 0..padded_width {
+                        let pixel = ((y + row) * ATLAS_WIDTH + x + column) as usize;
+                        let value = alpha[(row * padded_width + column) as usize];
+                        pixels[pixel * 4 + 3] = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                    }
+                }
+
+                atlas_glyph.offset_ =
+                    Vector2::new(min.x as f32 - padding as f32, min.y as f32 - padding as f32);
+                atlas_glyph.size_ = Vector2::new(padded_width as f32, padded_height as f32);
+                atlas_glyph.uv_rect_ = [
+                    x as f32 / ATLAS_WIDTH as f32,
+                    y as f32 / atlas_height as f32,
+                    (x + padded_width) as f32 / ATLAS_WIDTH as f32,
+                    (y + padded_height) as f32 / atlas_height as f32,
+                ];
+            }
+
+            glyphs.insert(character, atlas_glyph);
+        }
+
+        FontAtlas {
+            font_: font.clone(),
+            scale_: scale,
+            mode_: mode,
+            glyphs_: glyphs,
+            image_: ImageData::from_rgba(ATLAS_WIDTH, atlas_height, pixels),
+            texture_: None,
+        }
+    }
+
+    /// Returns the atlas image, to be uploaded as a linear, non-sRGB texture
+    pub fn get_image(&self) -> &ImageData {
+        &self.image_
+    }
+
+    pub fn get_mode(&self) -> AtlasMode {
+        self.mode_
+    }
+
+    /// Sets the texture the atlas image was uploaded to
+    pub fn set_texture(&mut self, texture: TextureID) {
+        self.texture_ = Some(texture);
+    }
+
+    pub fn get_texture(&self) -> Option<TextureID> {
+        self.texture_
+    }
+
+    /// Returns the distance between the baselines of consecutive lines, in pixels
+    pub fn get_line_height(&self) -> f32 {
+        let v_metrics = self.font_.font_.v_metrics(self.scale_);
+        v_metrics.ascent - v_metrics.descent + v_metrics.line_gap
+    }
+
+    /// Places the glyphs of a UTF-8 string, applying the font's kerning. Lines are broken at
+    /// newlines and, if a maximum width is given, before words that would not fit on the line.
+    /// Characters missing from the atlas are skipped. Positions are in pixels with y pointing
+    /// down, relative to the top left corner of the text
+    ///
+    /// # Arguments
+    ///
+    /// * 'text' - the string to lay out
+    ///
+    /// * 'max_width' - the width lines are wrapped at, or None to only break lines at newlines
+    ///
+    pub fn layout(&self, text: &str, max_width: Option<f32>) -> Vec<GlyphQuad> {
+        let ascent = self.font_.font_.v_metrics(self.scale_).ascent;
+        let line_height = self.get_line_height();
+
+        let mut quads: Vec<GlyphQuad> = vec![];
+        let mut pen = Vector2::new(0.0, ascent);
+        let mut previous: Option<GlyphId> = None;
+        // the first quad and pen position of the word being laid out
+        let mut word_start: Option<(usize, f32)> = None;
+
+        for character in text.chars() {
+            if character == '\n' {
+                pen = Vector2::new(0.0, pen.y + line_height);
+                previous = None;
+                word_start = None;
+                continue;
+            }
+
+            let glyph = match self.glyphs_.get(&character) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+
+            if let Some(previous) = previous {
+                pen.x += self
+                    .font_
+                    .font_
+                    .pair_kerning(self.scale_, previous, glyph.id_);
+            }
+
+            previous = Some(glyph.id_);
+
+            if character.is_whitespace() {
+                pen.x += glyph.advance_;
+                word_start = None;
+                continue;
+            }
+
+            let (first_quad, word_x) = *word_start.get_or_insert((quads.len(), pen.x));
+
+            // Words are moved to the next line as a whole, unless they start the line
+            if let Some(max_width) = max_width {
+                if pen.x + glyph.advance_ > max_width && word_x > 0.0 {
+                    for quad in quads[first_quad..].iter_mut() {
+                        quad.position_ += Vector2::new(-word_x, line_height);
+                    }
+
+                    pen += Vector2::new(-word_x, line_height);
+                    word_start = Some((first_quad, 0.0));
+                }
+            }
+
+            if glyph.size_.x > 0.0 {
+                quads.push(GlyphQuad {
+                    position_: pen + glyph.offset_,
+                    size_: glyph.size_,
+                    uv_rect_: glyph.uv_rect_,
+                });
+            }
+
+            pen.x += glyph.advance_;
+        }
+
+        quads
+    }
+
+    /// Lays out a string like layout and returns a sprite for each glyph, drawn with the atlas's
+    /// texture. Panics if the atlas has no texture
+    ///
+    /// # Arguments
+    ///
+    /// * 'text' - the string to draw
+    ///
+    /// * 'position' - the top left corner of the text, in pixels
+    ///
+    /// * 'color' - the color of the text
+    ///
+    /// * 'max_width' - the width lines are wrapped at, or None to only break lines at newlines
+    ///
+    pub fn build_sprites(
+        &self,
+        text: &str,
+        position: Vector2<f32>,
+        color: Vector4<f32>,
+        max_width: Option<f32>,
+    ) -> Vec<Sprite> {
+        let texture = self
+            .texture_
+            .expect("The font atlas has not been uploaded as a texture");
+
+        self.layout(text, max_width)
+            .iter()
+            .map(|quad| {
+                let mut sprite = Sprite::new(
+                    texture,
+                    position + quad.position_ + quad.size_ / 2.0,
+                    quad.size_,
+                );
+                sprite.uv_rect_ = quad.uv_rect_;
+                sprite.tint_ = color;
+                sprite.distance_field_ = self.mode_ != AtlasMode::Bitmap;
+                sprite
+            })
+            .collect()
+    }
+}
+
+/// Places rectangles left to right in rows of a fixed width, starting a new row below the tallest
+/// rectangle of the last one when a rectangle does not fit
+struct ShelfPacker {
+    width_: u32,
+    x_: u32,
+    y_: u32,
+    row_height_: u32,
+}
+
+impl ShelfPacker {
+    fn new(width: u32) -> ShelfPacker {
+        ShelfPacker {
+            width_: width,
+            x_: 0,
+            y_: 0,
+            row_height_: 0,
+        }
+    }
+
+    /// Returns the top left corner of a new rectangle. Panics if it is wider than the packer
+    fn pack(&mut self, width: u32, height: u32) -> (u32, u32) {
+        assert!(width <= self.width_, "Glyph is wider than the font atlas");
+
+        if self.x_ + width > self.width_ {
+            self.x_ = 0;
+            self.y_ += self.row_height_;
+            self.row_height_ = 0;
+        }
+
+        let corner = (self.x_, self.y_);
+
+        self.x_ += width;
+        self.row_height_ = self.row_height_.max(height);
+
+        corner
+    }
+
+    /// Returns the height of the rows packed so far
+    fn get_height(&self) -> u32 {
+        self.y_ + self.row_height_
+    }
+}
+
+/// Surrounds an image of the given size with the given number of empty pixels on every side
+fn pad(values: &[f32], width: u32, height: u32, padding: u32) -> Vec<f32> {
+    let padded_width = width + 2 * padding;
+    let mut padded = vec![0.0; (padded_width * (height + 2 * padding)) as usize];
+
+    for row in 0..height {
+        for column in 0..width {
+            padded[((row + padding) * padded_width + column + padding) as usize] =
+                values[(row * width + column) as usize];
+        }
+    }
+
+    padded
+}
+
+/// Converts glyph coverage into distances to the glyph's outline, searching up to spread pixels
+/// away. The outline maps to 0.5, with greater values inside the glyph
+fn distance_field(coverage: &[f32], width: u32, height: u32, spread: u32) -> Vec<f32> {
+    let spread = spread.max(1) as i32;
+    let inside = |x: i32, y: i32| coverage[(y as u32 * width + x as u32) as usize] >= 0.5;
+
+    let mut distances = vec![0.0; coverage.len()];
+
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let is_inside = inside(x, y);
+            let mut nearest = spread as f32;
+
+            for dy in -spread..=spread {
+                for dx in -spread..=spread {
+                    let (sx, sy) = (x + dx, y + dy);
+
+                    let outside_image =
+                        sx < 0 || sy < 0 || sx >= width as i32 || sy >= height as i32;
+                    let opposite = if outside_image {
+                        is_inside
+                    } else {
+                        inside(sx, sy) != is_inside
+                    };
+
+                    if opposite {
+                        nearest = nearest.min(((dx * dx + dy * dy) as f32).sqrt());
+                    }
+                }
+            }
+
+            let signed = if is_inside { nearest } else { -nearest };
+            distances[(y as u32 * width + x as u32) as usize] =
+                0.5 + signed / (2.0 * spread as f32);
+        }
+    }
+
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{AtlasMode, Font, FontAtlas, GlyphQuad, ShelfPacker, ASCII};
+
+    #[test]
+    fn shelf_packer_test() {
+        let mut packer = ShelfPacker::new(10);
+
+        assert_eq!(packer.pack(6, 3), (0, 0));
+        assert_eq!(packer.pack(4, 5), (6, 0));
+        assert_eq!(packer.pack(2, 2), (0, 5));
+        assert_eq!(packer.get_height(), 7);
+    }
+
+    #[test]
+    fn distance_field_test() {
+        // A 3x3 block in the middle of a 9x9 image
+        let mut coverage = vec![0.0; 81];
+        for y in 3..6 {
+            coverage[y * 9 + 3..y * 9 + 6]
+                .iter_mut()
+                .for_each(|value| *value = 1.0);
+        }
+
+        let distances = super::distance_field(&coverage, 9, 9, 4);
+
+        assert!(distances[4 * 9 + 4] > distances[4 * 9 + 3]);
+        assert!(distances[4 * 9 + 3] > 0.5);
+        assert!(distances[4 * 9 + 2] < 0.5);
+        assert!(distances[4 * 9] < distances[4 * 9 + 2]);
+    }
+
+    #[test]
+    fn layout_test() {
+        let font = Font::from_file(Path::new("test/fonts/DejaVuSansMono.ttf")).unwrap();
+        let atlas = FontAtlas::new(&font, 16.0, AtlasMode::Bitmap, ASCII);
+        let advance = atlas.glyphs_[&'a'].advance_;
+        let line_height = atlas.get_line_height();
+        let moved_down = |quad: &GlyphQuad, unmoved: &GlyphQuad| {
+            (quad.position_.x - unmoved.position_.x).abs() < 1e-3
+                && (quad.position_.y - unmoved.position_.y - line_height).abs() < 1e-3
+        };
+
+        // 'é' is not in the atlas, so it is skipped without moving the pen
+        assert_eq!(atlas.layout("ab\u{e9}c", None), atlas.layout("abc", None));
+
+        let lines = atlas.layout("ab\ncd", None);
+        let second_line = atlas.layout("cd", None);
+        assert_eq!(lines.len(), 4);
+        assert!(moved_down(&lines[2], &second_line[0]));
+        assert!(moved_down(&lines[3], &second_line[1]));
+
+        // "cdef" would cross the maximum width at 'e', so the whole word moves to the next line
+        let wrapped = atlas.layout("ab cdef", Some(5.5 * advance));
+        let word = atlas.layout("cdef", None);
+        assert_eq!(wrapped.len(), 6);
+        assert_eq!(wrapped[..2], atlas.layout("ab", None)[..]);
+        for (quad, unwrapped) in wrapped[2..].iter().zip(word.iter()) {
+            assert!(moved_down(quad, unwrapped));
+        }
+    }
+}
@@ -0,0 +1,109 @@
+/// A rectangle within a render target, given as fractions of the target's width and height so that
+/// it stays valid when the target is resized. (0, 0) is the top left corner of the target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x_: f32,
+    pub y_: f32,
+    pub width_: f32,
+    pub height_: f32,
+}
+
+impl Viewport {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Viewport {
+        Viewport {
+            x_: x,
+            y_: y,
+            width_: width,
+            height_: height,
+        }
+    }
+
+    /// A viewport covering the whole render target
+    pub fn full() -> Viewport {
+        Viewport::new(0.0, 0.0, 1.0, 1.0)
+    }
+
+    /// The largest viewport with the given aspect ratio that fits centered in a render target of
+    /// the given aspect ratio, leaving bars at the top and bottom or left and right
+    ///
+    /// # Arguments
+    ///
+    /// * 'aspect_ratio' - width / height of the content drawn to the viewport
+    ///
+    /// * 'target_aspect_ratio' - width / height of the render target
+    ///
+    pub fn letterbox(aspect_ratio: f32, target_aspect_ratio: f32) -> Viewport {
+        if aspect_ratio > target_aspect_ratio {
+            let height = target_aspect_ratio / aspect_ratio;
+            Viewport::new(0.0, (1.0 - height) / 2.0, 1.0, height)
+        } else {
+            let width = aspect_ratio / target_aspect_ratio;
+            Viewport::new((1.0 - width) / 2.0, 0.0, width, 1.0)
+        }
+    }
+
+    /// Returns this viewport placed inside another viewport, treating its fractions as relative to
+    /// the outer viewport rather than the whole render target
+    pub fn within(&self, outer: &Viewport) -> Viewport {
+        Viewport::new(
+            outer.x_ + self.x_ * outer.width_,
+            outer.y_ + self.y_ * outer.height_,
+            self.width_ * outer.width_,
+            self.height_ * outer.height_,
+        )
+    }
+
+    /// Returns whether this viewport and another share any area. Viewports that only touch along an
+    /// edge do not overlap
+    pub fn overlaps(&self, other: &Viewport) -> bool {
+        self.x_ < other.x_ + other.width_
+            && other.x_ < self.x_ + self.width_
+            && self.y_ < other.y_ + other.height_
+            && other.y_ < self.y_ + self.height_
+    }
+
+    /// Returns the width / height of this viewport within a render target of the given size
+    pub fn get_aspect_ratio(&self, target_width: u32, target_height: u32) -> f32 {
+        (self.width_ * target_width as f32) / (self.height_ * target_height as f32)
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Viewport {
+        Viewport::full()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Viewport;
+
+    #[test]
+    fn letterbox_test() {
+        // 4:3 content on a 16:9 target gets bars on the left and right
+        let pillarbox = Viewport::letterbox(4.0 / 3.0, 16.0 / 9.0);
+        assert!((pillarbox.width_ - 0.75).abs() < 1e-6);
+        assert!((pillarbox.x_ - 0.125).abs() < 1e-6);
+        assert_eq!(pillarbox.height_, 1.0);
+
+        // 21:9 content on a 16:9 target gets bars on the top and bottom
+        let letterbox = Viewport::letterbox(21.0 / 9.0, 16.0 / 9.0);
+        assert_eq!(letterbox.width_, 1.0);
+        assert!((letterbox.get_aspect_ratio(1920, 1080) - 21.0 / 9.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn overlaps_test() {
+        // A picture in picture camera drawn over the top right of a fullscreen one
+        let main = Viewport::full();
+        let inset = Viewport::new(0.7, 0.05, 0.25, 0.25);
+        assert!(main.overlaps(&inset));
+        assert!(inset.overlaps(&main));
+
+        // Split screen halves only share an edge
+        let left = Viewport::new(0.0, 0.0, 0.5, 1.0);
+        let right = Viewport::new(0.5, 0.0, 0.5, 1.0);
+        assert!(!left.overlaps(&right));
+        assert!(right.within(&main).overlaps(&inset.within(&main)));
+    }
+}
@@ -0,0 +1,272 @@
+use super::material::{MaterialDescription, MaterialValue};
+use super::render_pass::pipeline::shader::{Shader, ShaderStage};
+use super::render_pass::pipeline::state::{
+    BlendState, CompareOp, CullMode, DepthBias, PrimitiveTopology,
+};
+use super::render_pass::pipeline::PipelineDescription;
+use super::render_pass::RenderPassDescription;
+
+/// Returns the path of a shader shipped with the engine
+pub(crate) fn builtin_shader_path(name: &str) -> String
+{
+    format!("{}/shaders/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+/// Builds a two subpass deferred render pass. The first subpass writes world space positions,
+/// normals and albedo of the scene's instances to a G-buffer, and the second reads them back as
+/// input attachments and shades every pixel with the scene's shadow casting lights, or a single
+/// directional light if it has none
+pub fn deferred() -> RenderPassDescription
+{
+    let gbuffer = PipelineDescription::from_shaders(&vec![
+        Shader::from_glsl(&builtin_shader_path("deferred/gbuffer.vert"), ShaderStage::Vertex),
+        Shader::from_glsl(&builtin_shader_path("deferred/gbuffer.frag"), ShaderStage::Fragment),
+    ]);
+
+    let mut lighting = PipelineDescription::from_shaders(&vec![
+        Shader::from_glsl(&builtin_shader_path("deferred/lighting.vert"), ShaderStage::Vertex),
+        Shader::from_glsl(&builtin_shader_path("deferred/lighting.frag"), ShaderStage::Fragment),
+    ]);
+
+    // The lighting pass covers the screen once, so it neither culls nor tests against the
+    // G-buffer's depth
+    lighting.rasterization_.cull_mode_ = CullMode::None;
+    lighting.depth_stencil_.depth_test_ = false;
+    lighting.depth_stencil_.depth_write_ = false;
+
+    RenderPassDescription::from_pipelines(gbuffer, vec![lighting])
+}
+
+/// Builds the depth only pipeline shadow maps are rendered with. It reads the per-instance
+/// transform from descriptor set 0 and the light's view projection from a push constant. Both
+/// faces are rendered so that shadows work for open meshes, and the depth bias is set per light
+pub fn shadow_depth() -> PipelineDescription
+{
+    let mut shadow = PipelineDescription::from_shaders(&vec![Shader::from_glsl(
+        &builtin_shader_path("shadow/depth.vert"),
+        ShaderStage::Vertex,
+    )]);
+
+    shadow.rasterization_.cull_mode_ = CullMode::None;
+    shadow.rasterization_.depth_bias_ = Some(DepthBias::default());
+    shadow.rasterization_.dynamic_depth_bias_ = true;
+
+    shadow
+}
+
+/// Returns the compute shader assigning the scene's lights to the clusters of the first camera's
+/// view frustum, declaring the bindings of lights.glsl in descriptor set 1
+pub fn cluster_light_culling() -> Shader
+{
+    Shader::from_glsl(&builtin_shader_path("clustered/cull_lights.comp"), ShaderStage::Compute)
+}
+
+/// Builds the line list pipeline debug draws are rendered with. Lines are drawn in world space
+/// with the camera's view and projection push constants and a color per vertex. They never write
+/// depth, and are hidden behind the scene only if depth_test is true
+pub fn debug_lines(depth_test: bool) -> PipelineDescription
+{
+    let mut lines = PipelineDescription::from_shaders(&vec![
+        Shader::from_glsl(&builtin_shader_path("debug/line.vert"), ShaderStage::Vertex),
+        Shader::from_glsl(&builtin_shader_path("debug/line.frag"), ShaderStage::Fragment),
+    ]);
+
+    lines.topology_ = PrimitiveTopology::LineList;
+    lines.rasterization_.cull_mode_ = CullMode::None;
+    lines.depth_stencil_.depth_test_ = depth_test;
+    lines.depth_stencil_.depth_write_ = false;
+    lines.set_presented_output();
+
+    lines
+}
+
+/// Builds the pipeline occlusion queries draw instances' bounding boxes with. Boxes are given in
+/// world space, drawn with the camera's view and projection push constants, and are depth tested
+/// against the scene without writing color or depth
+pub fn occlusion_proxies() -> PipelineDescription
+{
+    let mut proxies = PipelineDescription::from_shaders(&vec![
+        Shader::from_glsl(&builtin_shader_path("occlusion/proxy.vert"), ShaderStage::Vertex),
+        Shader::from_glsl(&builtin_shader_path("occlusion/proxy.frag"), ShaderStage::Fragment),
+    ]);
+
+    // Both faces are drawn, so boxes the camera's near plane cuts into still cover fragments
+    proxies.rasterization_.cull_mode_ = CullMode::None;
+    proxies.depth_stencil_.depth_compare_op_ = CompareOp::LessOrEqual;
+    proxies.depth_stencil_.depth_write_ = false;
+    proxies.set_blend_state(0, BlendState::no_writes());
+    proxies.set_presented_output();
+
+    proxies
+}
+
+/// Builds the pipeline sprite batches are rendered with. Sprites are textured quads, read from the
+/// combined image sampler at set 0 binding 0 and multiplied by their tint. They are alpha blended
+/// over the scene in the order they are drawn without writing depth, and are hidden behind the
+/// scene only if depth_test is true
+pub fn sprites(depth_test: bool) -> PipelineDescription
+{
+    let mut sprites = PipelineDescription::from_shaders(&vec![
+        Shader::from_glsl(&builtin_shader_path("sprite/sprite.vert"), ShaderStage::Vertex),
+        Shader::from_glsl(&builtin_shader_path("sprite/sprite.frag"), ShaderStage::Fragment),
+    ]);
+
+    sprites.rasterization_.cull_mode_ = CullMode::None;
+    sprites.depth_stencil_.depth_test_ = depth_test;
+    sprites.depth_stencil_.depth_write_ = false;
+    sprites.set_blend_state(0, BlendState::alpha());
+    sprites.set_presented_output();
+
+    sprites
+}
+
+/// Builds the pipeline environments are drawn as a skybox with. A screen covering triangle at the
+/// far plane looks up the cube map at set 0 binding 0 in the direction of each pixel, using the
+/// camera's view and projection push constants. It is depth tested for equality without writing
+/// depth, so it only covers pixels no geometry was drawn to
+pub fn skybox() -> PipelineDescription
+{
+    let mut skybox = PipelineDescription::from_shaders(&vec![
+        Shader::from_glsl(&builtin_shader_path("skybox/skybox.vert"), ShaderStage::Vertex),
+        Shader::from_glsl(&builtin_shader_path("skybox/skybox.frag"), ShaderStage::Fragment),
+    ]);
+
+    skybox.rasterization_.cull_mode_ = CullMode::None;
+    skybox.depth_stencil_.depth_compare_op_ = CompareOp::Equal;
+    skybox.depth_stencil_.depth_write_ = false;
+    skybox.set_presented_output();
+
+    skybox
+}
+
+/// Returns the compute shader converting an equirectangular environment map, read from a storage
+/// buffer at set 0 binding 0, into the half float faces of a cube map written to binding 1
+pub fn equirect_to_cube() -> Shader
+{
+    Shader::from_glsl(&builtin_shader_path("environment/equirect_to_cube.comp"), ShaderStage::Compute)
+}
+
+/// Builds a physically based material following glTF's metallic-roughness model, with Lambert
+/// diffuse and GGX specular reflection of the scene's lights, or of a single directional light if
+/// it has none. Its parameters are the factors baseColorFactor, metallicFactor,
+/// roughnessFactor, normalScale, occlusionStrength, emissiveFactor, alphaCutoff and
+/// environmentStrength, and the textures baseColorTexture, metallicRoughnessTexture (roughness in
+/// green, metallic in blue), normalTexture, occlusionTexture and emissiveTexture. Base color and
+/// emissive textures should be loaded as sRGB. Missing textures read white, so normalScale starts
+/// at 0 and should be set to 1 along with a normal texture. The cube map environmentTexture, such
+/// as the scene's environment, is reflected in place of a constant ambient light once
+/// environmentStrength is raised from 0
+pub fn pbr() -> MaterialDescription
+{
+    let pipeline = PipelineDescription::from_shaders(&vec![
+        Shader::from_glsl(&builtin_shader_path("pbr/pbr.vert"), ShaderStage::Vertex),
+        Shader::from_glsl(&builtin_shader_path("pbr/pbr.frag"), ShaderStage::Fragment),
+    ]);
+
+    let mut material = MaterialDescription::new(pipeline);
+
+    material.set_parameter("baseColorFactor", MaterialValue::Vec4([1.0, 1.0, 1.0, 1.0]));
+    material.set_parameter("emissiveFactor", MaterialValue::Vec3([0.0, 0.0, 0.0]));
+    material.set_parameter("metallicFactor", MaterialValue::Float(1.0));
+    material.set_parameter("roughnessFactor", MaterialValue::Float(1.0));
+    material.set_parameter("normalScale", MaterialValue::Float(0.0));
+    material.set_parameter("occlusionStrength", MaterialValue::Float(1.0));
+    material.set_parameter("alphaCutoff", MaterialValue::Float(0.0));
+    material.set_parameter("environmentStrength", MaterialValue::Float(0.0));
+
+    material
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render_sequence::render_pass::pipeline::shader::descriptor::DescriptorType;
+    use crate::render_sequence::render_pass::pipeline::state::CompareOp;
+    use crate::render_sequence::render_pass::pipeline::shader::ShaderDescription;
+
+    #[test]
+    fn deferred_render_pass_test()
+    {
+        let render_pass = super::deferred();
+
+        let attachments = render_pass.get_intermediate_attachments();
+
+        assert_eq!(attachments.len(), 3);
+        assert!(attachments.iter().all(|attachment| attachment.subpass_ == 0));
+
+        let input_attachments = render_pass.subpasses_[0].get_input_attachments();
+
+        assert_eq!(input_attachments.len(), 3);
+        assert!(input_attachments.iter().all(|(_, _, index)| (*index as usize) < attachments.len()));
+        assert!(!render_pass.subpasses_[0].has_vertex_input());
+    }
+
+    #[test]
+    fn pbr_material_test()
+    {
+        let material = super::pbr();
+        let descriptors = material.get_descriptors();
+
+        assert_eq!(descriptors.len(), 7);
+        assert_eq!(descriptors[0].descriptor_type_, DescriptorType::UniformBuffer);
+        assert!(descriptors[1..].iter().all(|descriptor| descriptor.descriptor_type_ == DescriptorType::CombinedImageSampler));
+        assert!(descriptors[6].cube_ && !descriptors[5].cube_);
+        assert!(material.find_mismatched_parameters().is_empty());
+    }
+
+    #[test]
+    fn debug_lines_test()
+    {
+        let lines = super::debug_lines(false);
+
+        assert!(lines.has_vertex_input());
+        assert!(!lines.depth_stencil_.depth_test_);
+        assert!(lines.shaders_.iter().all(|shader| shader.descriptor_sets_.is_empty()));
+        assert_eq!(lines.get_fragment_outputs().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn occlusion_proxies_test()
+    {
+        let proxies = super::occlusion_proxies();
+
+        assert!(!proxies.depth_stencil_.depth_write_);
+        assert!(!proxies.get_blend_state(0).write_red_);
+        assert_eq!(proxies.get_fragment_outputs().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn sprites_test()
+    {
+        let sprites = super::sprites(true);
+
+        let descriptor_set = sprites.shaders_[1].descriptor_sets_.first().unwrap();
+
+        assert_eq!(descriptor_set.set_, 0);
+        assert_eq!(descriptor_set.descriptors_[0].descriptor_type_, DescriptorType::CombinedImageSampler);
+        assert!(sprites.get_blend_state(0).blend_.is_some());
+    }
+
+    #[test]
+    fn skybox_test()
+    {
+        let skybox = super::skybox();
+
+        let descriptor_set = skybox.shaders_[1].descriptor_sets_.first().unwrap();
+
+        assert!(!skybox.has_vertex_input());
+        assert_eq!(skybox.depth_stencil_.depth_compare_op_, CompareOp::Equal);
+        assert!(descriptor_set.descriptors_[0].cube_);
+    }
+
+    #[test]
+    fn cluster_light_culling_test()
+    {
+        let shader = ShaderDescription::from_shader(&super::cluster_light_culling());
+
+        let descriptor_set = shader.descriptor_sets_.iter().find(|descriptor_set| descriptor_set.set_ == 1).unwrap();
+        let mut bindings: Vec<u32> = descriptor_set.descriptors_.iter().map(|descriptor| descriptor.binding_).collect();
+        bindings.sort();
+
+        assert_eq!(bindings, vec![2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,230 @@
+use super::render_pass::pipeline::shader::descriptor::{DescriptorDescription, DescriptorType};
+use super::render_pass::pipeline::PipelineDescription;
+use crate::vulkan::TextureID;
+
+pub type MaterialID = u64;
+
+/// The descriptor set material parameters are read from. Set 0 holds instance uniforms and set 1
+/// holds shadows
+pub const MATERIAL_SET: u32 = 2;
+
+/// The value of a material parameter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaterialValue {
+    Float(f32),
+    Int(i32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    /// A vec4, such as an RGBA color
+    Vec4([f32; 4]),
+    /// A texture read through a combined image sampler
+    Texture(TextureID),
+}
+
+impl MaterialValue {
+
+    /// Returns the bytes the value is stored as in a uniform block, or None for textures
+    pub fn to_bytes(&self) -> Option<Vec<u8>>
+    {
+        let floats: &[f32] = match self
+        {
+            MaterialValue::Float(value) => std::slice::from_ref(value),
+            MaterialValue::Int(value) => return Some(value.to_le_bytes().to_vec()),
+            MaterialValue::Vec2(value) => value,
+            MaterialValue::Vec3(value) => value,
+            MaterialValue::Vec4(value) => value,
+            MaterialValue::Texture(_) => return None,
+        };
+
+        Some(floats.iter().flat_map(|float| float.to_le_bytes().to_vec()).collect())
+    }
+}
+
+/// A pipeline together with values for the parameters its shaders read from the material set.
+/// Values are matched by name against the members of the set's uniform blocks and the names of
+/// its combined image samplers. The pipeline is built for the first subpass of a renderer's render
+/// pass, so it must write the same fragment outputs as that subpass's pipeline and declare instance
+/// uniforms at set 0 the same way
+#[derive(Clone)]
+pub struct MaterialDescription {
+    pub pipeline_: PipelineDescription,
+    pub parameters_: Vec<(String, MaterialValue)>,
+}
+
+impl MaterialDescription {
+
+    /// Creates a material without parameter values. Uniform block members without a value are
+    /// zero and samplers without a texture read a white texture
+    pub fn new(pipeline: PipelineDescription) -> MaterialDescription
+    {
+        MaterialDescription{ pipeline_: pipeline, parameters_: vec![] }
+    }
+
+    /// Sets the value of a parameter, replacing any earlier value of the same name
+    pub fn set_parameter(&mut self, name: &str, value: MaterialValue)
+    {
+        match self.parameters_.iter_mut().find(|(parameter, _)| parameter == name)
+        {
+            Some((_, parameter_value)) => *parameter_value = value,
+            None => self.parameters_.push((name.to_string(), value)),
+        }
+    }
+
+    /// Returns the value of a parameter, or None if it has not been set
+    pub fn get_parameter(&self, name: &str) -> Option<MaterialValue>
+    {
+        self.parameters_
+            .iter()
+            .find(|(parameter, _)| parameter == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Returns the descriptors the pipeline's shaders declare in the material set, once per
+    /// binding, ordered by binding
+    pub fn get_descriptors(&self) -> Vec<DescriptorDescription>
+    {
+        let mut descriptors: Vec<DescriptorDescription> = vec![];
+
+        for shader in self.pipeline_.shaders_.iter()
+        {
+            for descriptor_set in shader.descriptor_sets_.iter().filter(|descriptor_set| descriptor_set.set_ == MATERIAL_SET)
+            {
+                for descriptor in descriptor_set.descriptors_.iter()
+                {
+                    if !descriptors.iter().any(|known| known.binding_ == descriptor.binding_)
+                    {
+                        descriptors.push(descriptor.clone());
+                    }
+                }
+            }
+        }
+
+        descriptors.sort_by_key(|descriptor| descriptor.binding_);
+        descriptors
+    }
+
+    /// Lays out the values of the parameters named after members of a uniform block into the bytes
+    /// of the block. Members without a value, or with a value of a different size, are zero
+    pub fn build_block_data(&self, descriptor: &DescriptorDescription) -> Vec<u8>
+    {
+        let mut bytes = vec![0; descriptor.padded_size_.max(descriptor.size_) as usize];
+
+        for member in descriptor.members_.iter()
+        {
+            let value_bytes = match self.get_parameter(&member.name_).and_then(|value| value.to_bytes())
+            {
+                Some(value_bytes) if value_bytes.len() == member.size_ as usize => value_bytes,
+                _ => continue,
+            };
+
+            let offset = member.offset_ as usize;
+            bytes[offset..offset + value_bytes.len()].copy_from_slice(&value_bytes);
+        }
+
+        bytes
+    }
+
+    /// Returns the texture of the parameter named after a combined image sampler, or None if it
+    /// has none
+    pub fn get_texture(&self, descriptor: &DescriptorDescription) -> Option<TextureID>
+    {
+        match self.get_parameter(&descriptor.name_)
+        {
+            Some(MaterialValue::Texture(texture_id)) => Some(texture_id),
+            _ => None,
+        }
+    }
+
+    /// Returns a message for every parameter that names neither a uniform block member nor a
+    /// sampler of the material set, or whose value does not fit what it names
+    pub fn find_mismatched_parameters(&self) -> Vec<String>
+    {
+        let descriptors = self.get_descriptors();
+        let mut messages = vec![];
+
+        for (name, value) in self.parameters_.iter()
+        {
+            let sampler = descriptors.iter().find(|descriptor| {
+                descriptor.descriptor_type_ == DescriptorType::CombinedImageSampler && &descriptor.name_ == name
+            });
+
+            let member = descriptors
+                .iter()
+                .filter(|descriptor| descriptor.descriptor_type_ == DescriptorType::UniformBuffer)
+                .flat_map(|descriptor| descriptor.members_.iter())
+                .find(|member| &member.name_ == name);
+
+            match (value.to_bytes(), sampler, member)
+            {
+                (None, Some(_), _) => {}
+                (Some(bytes), _, Some(member)) if bytes.len() == member.size_ as usize => {}
+                (Some(bytes), _, Some(member)) => messages.push(format!(
+                    "Material parameter {} has {} bytes, but the member it names has {}",
+                    name,
+                    bytes.len(),
+                    member.size_
+                )),
+                (None, None, _) => messages.push(format!(
+                    "Material parameter {} is a texture, but set {} has no sampler of that name",
+                    name, MATERIAL_SET
+                )),
+                (Some(_), _, None) => messages.push(format!(
+                    "Material parameter {} names no uniform block member of set {}",
+                    name, MATERIAL_SET
+                )),
+            }
+        }
+
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_sequence::render_pass::pipeline::shader::descriptor::BlockMemberDescription;
+    use crate::render_sequence::render_pass::pipeline::state::{DepthStencilState, PrimitiveTopology, RasterizationState};
+
+    fn member(name: &str, offset: u32, size: u32) -> BlockMemberDescription
+    {
+        BlockMemberDescription{ name_: name.to_string(), offset_: offset, size_: size }
+    }
+
+    #[test]
+    fn build_block_data_test()
+    {
+        let mut material = MaterialDescription::new(PipelineDescription{
+            shaders_: vec![],
+            topology_: PrimitiveTopology::TriangleList,
+            rasterization_: RasterizationState::default(),
+            depth_stencil_: DepthStencilState::default(),
+            blend_states_: vec![],
+        });
+
+        material.set_parameter("base_color", MaterialValue::Vec4([1.0, 0.5, 0.0, 1.0]));
+        material.set_parameter("roughness", MaterialValue::Float(0.25));
+        material.set_parameter("roughness", MaterialValue::Float(0.75));
+        material.set_parameter("metallic", MaterialValue::Vec2([1.0, 1.0]));
+
+        let block = DescriptorDescription{
+            name_: "material".to_string(),
+            binding_: 0,
+            descriptor_type_: DescriptorType::UniformBuffer,
+            count_: 1,
+            size_: 24,
+            padded_size_: 32,
+            input_attachment_index_: 0,
+            cube_: false,
+            members_: vec![member("base_color", 0, 16), member("roughness", 16, 4), member("metallic", 20, 4)],
+        };
+
+        let bytes = material.build_block_data(&block);
+
+        assert_eq!(material.parameters_.len(), 3);
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(&bytes[4..8], &0.5_f32.to_le_bytes());
+        assert_eq!(&bytes[16..20], &0.75_f32.to_le_bytes());
+        assert_eq!(&bytes[20..24], &[0; 4]);
+        assert_eq!(MaterialValue::Texture(3).to_bytes(), None);
+    }
+}
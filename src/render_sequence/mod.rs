@@ -1,2 +1,6 @@
+pub mod builtin;
+pub mod material;
+pub mod post_process;
 pub mod render_pass;
-pub mod reflection_utils;
\ No newline at end of file
+pub mod reflection_utils;
+pub mod shader_watcher;
@@ -0,0 +1,277 @@
+use super::builtin::builtin_shader_path;
+use super::render_pass::pipeline::shader::{Shader, ShaderStage};
+use super::render_pass::pipeline::state::CullMode;
+use super::render_pass::pipeline::PipelineDescription;
+
+/// An image read or written by a post-processing pass
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PostImage {
+    /// The HDR target the renderer's render pass draws the scene into
+    Scene,
+    /// One of the two full resolution HDR images passes of the chain alternate between
+    Chain(usize),
+    /// One of the two half resolution HDR images bloom is extracted and blurred in
+    Bloom(usize),
+    /// The lookup table of the chain's i-th color grading effect
+    ColorLut(usize),
+    /// The swapchain image, only written by the final blit
+    Swapchain,
+}
+
+/// The curve tonemapping maps HDR colors into displayable range with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TonemapOperator {
+    Reinhard,
+    Aces,
+}
+
+/// A 3D color lookup table with size texels along each axis. Texels are ordered with red varying
+/// fastest, then green and then blue
+#[derive(Clone, Debug)]
+pub struct ColorLut {
+    pub size_: u32,
+    pub texels_: Vec<[u8; 4]>,
+}
+
+impl ColorLut {
+
+    /// Creates a lookup table from its texels. Panics if there are not size^3 texels
+    pub fn new(size: u32, texels: Vec<[u8; 4]>) -> ColorLut
+    {
+        assert_eq!(texels.len(), (size * size * size) as usize, "A color lookup table of size {} needs {} texels", size, size * size * size);
+
+        ColorLut{ size_: size, texels_: texels }
+    }
+
+    /// Creates a lookup table mapping every color to itself, as a starting point for grading
+    pub fn identity(size: u32) -> ColorLut
+    {
+        let scale = 255.0 / (size - 1).max(1) as f32;
+        let mut texels = vec![];
+
+        for blue in 0..size
+        {
+            for green in 0..size
+            {
+                for red in 0..size
+                {
+                    texels.push([
+                        (red as f32 * scale).round() as u8,
+                        (green as f32 * scale).round() as u8,
+                        (blue as f32 * scale).round() as u8,
+                        255,
+                    ]);
+                }
+            }
+        }
+
+        ColorLut{ size_: size, texels_: texels }
+    }
+}
+
+/// A step of a post-processing chain
+#[derive(Clone)]
+pub enum PostEffect {
+    /// Scales HDR color by an exposure and maps it into displayable range
+    Tonemap{ operator: TonemapOperator, exposure: f32 },
+    /// Adds a blurred copy of the light brighter than threshold, scaled by intensity
+    Bloom{ threshold: f32, intensity: f32 },
+    /// Fast approximate antialiasing. Expects tonemapped colors
+    Fxaa,
+    /// Maps colors through a lookup table. Expects tonemapped colors
+    ColorGrading(ColorLut),
+    /// A user fragment shader. It reads the previous image as `source` at set 0, binding 0 and
+    /// texture coordinates at location 0, and writes location 0. The optional push constant block
+    /// `{ vec4 parameters; vec2 texelSize; }` receives the given parameters and the texel size of
+    /// source. shaders/post/post.glsl declares all of these
+    Custom{ shader: Shader, parameters: [f32; 4] },
+}
+
+/// A single fullscreen pass of a post-processing chain, drawing its output from its input images
+#[derive(Clone)]
+pub struct PostPassDescription {
+    pub pipeline_: PipelineDescription,
+    /// Images bound to the combined image samplers at bindings 0, 1, ... of set 0
+    pub inputs_: Vec<PostImage>,
+    pub output_: PostImage,
+    /// Pushed as the parameters member of the pass's push constants
+    pub parameters_: [f32; 4],
+}
+
+/// An ordered list of post-processing effects applied to a renderer's HDR scene image, ending with
+/// a blit into the swapchain image
+#[derive(Clone)]
+pub struct PostProcessChain {
+    pub effects_: Vec<PostEffect>,
+}
+
+impl PostProcessChain {
+
+    /// Creates a chain without effects, which only copies the HDR image into the swapchain image
+    pub fn new() -> PostProcessChain
+    {
+        PostProcessChain{ effects_: vec![] }
+    }
+
+    /// Appends an effect to the chain and returns its index
+    pub fn add_effect(&mut self, effect: PostEffect) -> usize
+    {
+        self.effects_.push(effect);
+        self.effects_.len() - 1
+    }
+
+    /// Returns the lookup tables of the chain's color grading effects, in chain order
+    pub fn get_color_luts(&self) -> Vec<&ColorLut>
+    {
+        self.effects_
+            .iter()
+            .filter_map(|effect| match effect
+            {
+                PostEffect::ColorGrading(lut) => Some(lut),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Expands the chain's effects into the fullscreen passes that implement them. The first pass
+    /// reads the scene image, every following pass reads the output of the one before it, and
+    /// the final pass blits into the swapchain image. Compiles the built-in post shaders
+    pub fn build_passes(&self) -> Vec<PostPassDescription>
+    {
+        let mut passes = vec![];
+        let mut current = PostImage::Scene;
+        let mut color_luts = 0;
+
+        for effect in self.effects_.iter()
+        {
+            let next = match current
+            {
+                PostImage::Chain(0) => PostImage::Chain(1),
+                _ => PostImage::Chain(0),
+            };
+
+            match effect
+            {
+                PostEffect::Tonemap{ operator, exposure } =>
+                {
+                    let operator = match operator
+                    {
+                        TonemapOperator::Reinhard => 0.0,
+                        TonemapOperator::Aces => 1.0,
+                    };
+
+                    passes.push(builtin_pass("tonemap.frag", vec![current], next, [*exposure, operator, 0.0, 0.0]));
+                }
+                PostEffect::Bloom{ threshold, intensity } =>
+                {
+                    passes.push(builtin_pass("bloom_threshold.frag", vec![current], PostImage::Bloom(0), [*threshold, 0.0, 0.0, 0.0]));
+                    passes.push(builtin_pass("blur.frag", vec![PostImage::Bloom(0)], PostImage::Bloom(1), [1.0, 0.0, 0.0, 0.0]));
+                    passes.push(builtin_pass("blur.frag", vec![PostImage::Bloom(1)], PostImage::Bloom(0), [0.0, 1.0, 0.0, 0.0]));
+                    passes.push(builtin_pass("bloom_composite.frag", vec![current, PostImage::Bloom(0)], next, [*intensity, 0.0, 0.0, 0.0]));
+                }
+                PostEffect::Fxaa =>
+                {
+                    passes.push(builtin_pass("fxaa.frag", vec![current], next, [0.0; 4]));
+                }
+                PostEffect::ColorGrading(lut) =>
+                {
+                    passes.push(builtin_pass("color_grading.frag", vec![current, PostImage::ColorLut(color_luts)], next, [lut.size_ as f32, 0.0, 0.0, 0.0]));
+                    color_luts += 1;
+                }
+                PostEffect::Custom{ shader, parameters } =>
+                {
+                    passes.push(PostPassDescription{
+                        pipeline_: fullscreen_pipeline(shader.clone()),
+                        inputs_: vec![current],
+                        output_: next,
+                        parameters_: *parameters,
+                    });
+                }
+            }
+
+            current = next;
+        }
+
+        passes.push(builtin_pass("blit.frag", vec![current], PostImage::Swapchain, [0.0; 4]));
+
+        passes
+    }
+}
+
+impl Default for PostProcessChain {
+    /// Bloom, ACES tonemapping and then FXAA
+    fn default() -> PostProcessChain
+    {
+        PostProcessChain{
+            effects_: vec![
+                PostEffect::Bloom{ threshold: 1.0, intensity: 0.05 },
+                PostEffect::Tonemap{ operator: TonemapOperator::Aces, exposure: 1.0 },
+                PostEffect::Fxaa,
+            ],
+        }
+    }
+}
+
+/// Builds a pipeline drawing a fullscreen triangle with the given fragment shader
+fn fullscreen_pipeline(fragment: Shader) -> PipelineDescription
+{
+    let vertex = Shader::from_glsl(&builtin_shader_path("post/fullscreen.vert"), ShaderStage::Vertex);
+
+    let mut pipeline = PipelineDescription::from_shaders(&vec![vertex, fragment]);
+
+    pipeline.rasterization_.cull_mode_ = CullMode::None;
+    pipeline.depth_stencil_.depth_test_ = false;
+    pipeline.depth_stencil_.depth_write_ = false;
+
+    pipeline
+}
+
+fn builtin_pass(fragment: &str, inputs: Vec<PostImage>, output: PostImage, parameters: [f32; 4]) -> PostPassDescription
+{
+    let fragment = Shader::from_glsl(&builtin_shader_path(&format!("post/{}", fragment)), ShaderStage::Fragment);
+
+    PostPassDescription{
+        pipeline_: fullscreen_pipeline(fragment),
+        inputs_: inputs,
+        output_: output,
+        parameters_: parameters,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_passes_test()
+    {
+        let mut chain = PostProcessChain::new();
+        chain.add_effect(PostEffect::Bloom{ threshold: 1.0, intensity: 0.1 });
+        chain.add_effect(PostEffect::Tonemap{ operator: TonemapOperator::Reinhard, exposure: 1.0 });
+
+        let passes = chain.build_passes();
+        let outputs: Vec<PostImage> = passes.iter().map(|pass| pass.output_).collect();
+
+        assert_eq!(outputs, vec![
+            PostImage::Bloom(0),
+            PostImage::Bloom(1),
+            PostImage::Bloom(0),
+            PostImage::Chain(0),
+            PostImage::Chain(1),
+            PostImage::Swapchain,
+        ]);
+        assert_eq!(passes[0].inputs_, vec![PostImage::Scene]);
+        assert_eq!(passes[3].inputs_, vec![PostImage::Scene, PostImage::Bloom(0)]);
+        assert_eq!(passes[5].inputs_, vec![PostImage::Chain(1)]);
+    }
+
+    #[test]
+    fn identity_color_lut_test()
+    {
+        let lut = ColorLut::identity(2);
+
+        assert_eq!(lut.texels_.len(), 8);
+        assert_eq!(lut.texels_[1], [255, 0, 0, 255]);
+        assert_eq!(lut.texels_[6], [0, 255, 255, 255]);
+    }
+}
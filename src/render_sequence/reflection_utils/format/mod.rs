@@ -1,5 +1,6 @@
 use spirv_reflect::types::ReflectFormat;
 
+#[derive(Clone)]
 #[allow(non_camel_case_types)]
 pub enum Format {
     UNDEFINED,
@@ -15,6 +16,9 @@ pub enum Format {
     R32G32B32A32_UINT,
     R32G32B32A32_SINT,
     R32G32B32A32_SFLOAT,
+    R8G8B8A8_UNORM,
+    R8G8B8A8_SRGB,
+    R16G16B16A16_SFLOAT,
 }
 
 impl Format {
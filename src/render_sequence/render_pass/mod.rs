@@ -1,7 +1,19 @@
 pub mod pipeline;
 
+use std::path::PathBuf;
+
 use pipeline::PipelineDescription;
+use pipeline::shader::attribute::AttributeDescription;
+
+/// A fragment output that is not presented, stored in an attachment for later subpasses to read
+#[derive(Clone)]
+pub struct IntermediateAttachment {
+    /// Index of the subpass writing the attachment, where the main pass is subpass 0
+    pub subpass_: usize,
+    pub output_: AttributeDescription,
+}
 
+#[derive(Clone)]
 pub struct RenderPassDescription {
     pub mainpass_: PipelineDescription,
     pub subpasses_: Vec<PipelineDescription>,
@@ -9,28 +21,115 @@ pub struct RenderPassDescription {
 
 impl RenderPassDescription {
 
-    pub fn from_pipelines(mainpass: PipelineDescription, subpasses: Vec<PipelineDescription>) -> RenderPassDescription
+    /// Creates a render pass description from the pipeline of its first subpass and the pipelines
+    /// of any following subpasses. Location 0 of the final subpass's fragment shader is presented
+    pub fn from_pipelines(mut mainpass: PipelineDescription, mut subpasses: Vec<PipelineDescription>) -> RenderPassDescription
     {
+        match subpasses.last_mut()
+        {
+            Some(last) => last.set_presented_output(),
+            None => mainpass.set_presented_output(),
+        }
+
         RenderPassDescription{ mainpass_: mainpass, subpasses_: subpasses }
     }
 
+    /// Returns the pipelines of every subpass in order, starting with the main pass
+    pub fn get_pipelines(&self) -> Vec<&PipelineDescription>
+    {
+        let mut pipelines = vec![&self.mainpass_];
+        pipelines.extend(self.subpasses_.iter());
+        pipelines
+    }
+
+    /// Returns an attachment for every fragment output that is not presented, in subpass order
+    /// and then by location. A subpass input with input_attachment_index i reads the i-th of these
+    /// attachments, so the outputs of a G-buffer pass at locations 0, 1 and 2 are read by a
+    /// following lighting pass at input attachment indices 0, 1 and 2
+    pub fn get_intermediate_attachments(&self) -> Vec<IntermediateAttachment>
+    {
+        let mut attachments = vec![];
+
+        for (subpass, pipeline) in self.get_pipelines().iter().enumerate()
+        {
+            if let Some(outputs) = pipeline.get_fragment_outputs()
+            {
+                for output in outputs.iter().filter(|output| !output.present_)
+                {
+                    attachments.push(IntermediateAttachment{ subpass_: subpass, output_: output.clone() });
+                }
+            }
+        }
+
+        attachments
+    }
+
+    /// Returns the index of the final subpass if pipelines drawn over the scene, such as debug
+    /// lines or sprites, can be added to it. They must write every color attachment of the
+    /// subpass, so this is only the case when it writes nothing but the presented color
+    pub fn final_subpass_overlay_index(&self) -> Option<u32>
+    {
+        let final_outputs = self.get_pipelines().last()
+            .and_then(|pipeline| pipeline.get_fragment_outputs())
+            .map_or(0, |outputs| outputs.len());
+
+        match final_outputs
+        {
+            1 => Some(self.subpasses_.len() as u32),
+            _ => None,
+        }
+    }
+
+    /// Compiles and reflects the shaders of every pipeline again. Returns the first compile error
+    pub fn recompile(&self) -> Result<RenderPassDescription, String>
+    {
+        let mut subpasses = vec![];
+
+        for subpass in self.subpasses_.iter()
+        {
+            subpasses.push(subpass.recompile()?);
+        }
+
+        Ok(RenderPassDescription{ mainpass_: self.mainpass_.recompile()?, subpasses_: subpasses })
+    }
+
+    /// Returns the GLSL files the shaders of every pipeline were compiled from
+    pub fn get_source_files(&self) -> Vec<PathBuf>
+    {
+        let mut source_files = self.mainpass_.get_source_files();
+
+        for subpass in self.subpasses_.iter()
+        {
+            source_files.append(&mut subpass.get_source_files());
+        }
+
+        source_files.sort();
+        source_files.dedup();
+        source_files
+    }
+
 }
 
 #[cfg(test)]
 mod tests {
     use crate::render_sequence::render_pass::pipeline::PipelineDescription;
-    /*
-        #[test]
-        fn create_render_pass_description_test()
-        {
-            let vert = super::pipeline::shader::Shader::from_glsl("test/shaders/descriptor_set.vert", super::pipeline::shader::ShaderStage::Vertex);
-            let frag = super::pipeline::shader::Shader::from_glsl("test/shaders/descriptor_set.frag", super::pipeline::shader::ShaderStage::Fragment);
 
-            let shaders = vec![vert, frag];
+    #[test]
+    fn create_render_pass_description_test()
+    {
+        let vert = super::pipeline::shader::Shader::from_glsl("test/shaders/descriptor_set.vert", super::pipeline::shader::ShaderStage::Vertex);
+        let frag = super::pipeline::shader::Shader::from_glsl("test/shaders/forward.frag", super::pipeline::shader::ShaderStage::Fragment);
+
+        let shaders = vec![vert, frag];
 
-            let pipeline = PipelineDescription::from_shaders(&shaders);
+        let pipeline = PipelineDescription::from_shaders(&shaders);
 
-            let breakpoint = true;
-        }
-    */
+        let render_pass = super::RenderPassDescription::from_pipelines(pipeline, vec![]);
+
+        let outputs = render_pass.mainpass_.get_fragment_outputs().unwrap();
+
+        assert_eq!(outputs.len(), 1);
+        assert!(outputs[0].present_);
+        assert_eq!(render_pass.final_subpass_overlay_index(), Some(0));
+    }
 }
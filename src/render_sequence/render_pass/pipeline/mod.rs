@@ -1,9 +1,23 @@
 pub mod shader;
+pub mod state;
 
-use shader::{Shader, ShaderDescription};
+use std::path::PathBuf;
 
+use shader::attribute::AttributeDescription;
+use shader::descriptor::DescriptorType;
+use shader::{Shader, ShaderDescription, ShaderStage};
+use state::{BlendState, DepthStencilState, PrimitiveTopology, RasterizationState};
+
+/// Reflected shaders of a pipeline together with its fixed-function state. The state defaults to
+/// filled, back face culled triangle lists with a less-than depth test and opaque color outputs
+#[derive(Clone)]
 pub struct PipelineDescription {
     pub shaders_: Vec<ShaderDescription>,
+    pub topology_: PrimitiveTopology,
+    pub rasterization_: RasterizationState,
+    pub depth_stencil_: DepthStencilState,
+    /// Blend state per fragment output, indexed by location. Outputs without an entry are opaque
+    pub blend_states_: Vec<BlendState>,
 }
 
 impl PipelineDescription {
@@ -17,7 +31,133 @@ impl PipelineDescription {
             shader_descriptions.push(ShaderDescription::from_shader(shader));
         }
 
-        PipelineDescription{shaders_: shader_descriptions}
+        PipelineDescription{
+            shaders_: shader_descriptions,
+            topology_: PrimitiveTopology::TriangleList,
+            rasterization_: RasterizationState::default(),
+            depth_stencil_: DepthStencilState::default(),
+            blend_states_: vec![],
+        }
+    }
+
+    /// Compiles every shader of the pipeline again from its GLSL source and reflects the results,
+    /// keeping the pipeline's fixed-function state. Returns the first compile error, leaving this
+    /// description untouched
+    pub fn recompile(&self) -> Result<PipelineDescription, String>
+    {
+        let mut shaders = vec![];
+
+        for shader_description in self.shaders_.iter()
+        {
+            shaders.push(shader_description.shader_.recompile()?);
+        }
+
+        let mut description = self.clone();
+        description.shaders_ = shaders.iter().map(ShaderDescription::from_shader).collect();
+
+        let presented = self
+            .get_fragment_outputs()
+            .is_some_and(|outputs| outputs.iter().any(|output| output.present_));
+
+        if presented
+        {
+            description.set_presented_output();
+        }
+
+        Ok(description)
+    }
+
+    /// Returns the GLSL files, including #included files, the pipeline's shaders were compiled
+    /// from
+    pub fn get_source_files(&self) -> Vec<PathBuf>
+    {
+        self.shaders_
+            .iter()
+            .flat_map(|shader_description| shader_description.shader_.sources_.iter().cloned())
+            .collect()
+    }
+
+    /// Returns the blend state of the fragment output at the given location
+    pub fn get_blend_state(&self, location: u32) -> BlendState
+    {
+        self.blend_states_
+            .get(location as usize)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Sets the blend state of the fragment output at the given location
+    pub fn set_blend_state(&mut self, location: u32, blend_state: BlendState)
+    {
+        let index = location as usize;
+
+        if self.blend_states_.len() <= index
+        {
+            self.blend_states_.resize(index + 1, BlendState::default());
+        }
+
+        self.blend_states_[index] = blend_state;
+    }
+
+    /// Returns the outputs of the pipeline's fragment shader, or None if the pipeline has no
+    /// fragment shader
+    pub fn get_fragment_outputs(&self) -> Option<&Vec<AttributeDescription>>
+    {
+        self.shaders_
+            .iter()
+            .find(|shader| shader.shader_.stage_ == ShaderStage::Fragment)
+            .map(|shader| &shader.output_)
+    }
+
+    fn get_mut_fragment_outputs(&mut self) -> Option<&mut Vec<AttributeDescription>>
+    {
+        self.shaders_
+            .iter_mut()
+            .find(|shader| shader.shader_.stage_ == ShaderStage::Fragment)
+            .map(|shader| &mut shader.output_)
+    }
+
+    /// Returns true if the pipeline's vertex shader reads vertex attributes. Pipelines without
+    /// vertex inputs generate their vertices, as fullscreen passes do
+    pub fn has_vertex_input(&self) -> bool
+    {
+        self.shaders_
+            .iter()
+            .find(|shader| shader.shader_.stage_ == ShaderStage::Vertex)
+            .is_some_and(|shader| !shader.input_.is_empty())
+    }
+
+    /// Returns the set, binding and input_attachment_index of every subpass input the pipeline's
+    /// shaders read
+    pub fn get_input_attachments(&self) -> Vec<(u32, u32, u32)>
+    {
+        let mut input_attachments = vec![];
+
+        for shader in self.shaders_.iter()
+        {
+            for descriptor_set in shader.descriptor_sets_.iter()
+            {
+                for descriptor in descriptor_set.descriptors_.iter()
+                    .filter(|descriptor| descriptor.descriptor_type_ == DescriptorType::InputAttachment)
+                {
+                    input_attachments.push((descriptor_set.set_, descriptor.binding_, descriptor.input_attachment_index_));
+                }
+            }
+        }
+
+        input_attachments
+    }
+
+    /// Marks the fragment output at location 0 as the attachment presented to the swapchain
+    pub fn set_presented_output(&mut self)
+    {
+        if let Some(outputs) = self.get_mut_fragment_outputs()
+        {
+            for output in outputs.iter_mut()
+            {
+                output.present_ = output.location_ == 0;
+            }
+        }
     }
     /*
     pub fn from_shaders(shaders: &Vec<Shader>) -> PipelineDescription
@@ -2,6 +2,7 @@ use crate::render_sequence::reflection_utils::format::Format;
 
 use spirv_reflect::types::ReflectInterfaceVariable;
 
+#[derive(Clone)]
 pub struct AttributeDescription {
     pub location_: u32,
     pub offset_: u32,
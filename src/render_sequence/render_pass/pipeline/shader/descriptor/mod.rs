@@ -1,5 +1,9 @@
-use spirv_reflect::types::{ReflectDescriptorBinding, ReflectDescriptorSet, ReflectDescriptorType};
+use spirv_reflect::types::{
+    ReflectBlockVariable, ReflectDescriptorBinding, ReflectDescriptorSet, ReflectDescriptorType,
+    ReflectDimension,
+};
 
+#[derive(Clone, Debug, PartialEq)]
 pub enum DescriptorType {
     Undefined,
     Sampler,
@@ -38,6 +42,7 @@ impl DescriptorType {
     }
 }
 
+#[derive(Clone)]
 pub struct DescriptorSetDescription {
     pub set_: u32,
     pub descriptors_: Vec<DescriptorDescription>,
@@ -73,23 +78,61 @@ impl DescriptorSetDescription {
     }
 }
 
+/// A member of a uniform or storage block
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockMemberDescription {
+    pub name_: String,
+    /// Byte offset of the member from the start of the block
+    pub offset_: u32,
+    pub size_: u32,
+}
+
+impl BlockMemberDescription {
+    pub fn from_reflection(member: &ReflectBlockVariable) -> BlockMemberDescription {
+        BlockMemberDescription {
+            name_: member.name.clone(),
+            offset_: member.offset,
+            size_: member.size,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct DescriptorDescription {
+    /// The name of the variable declared at the binding, such as a sampler's name or a uniform
+    /// block's instance name
+    pub name_: String,
     pub binding_: u32,
     pub descriptor_type_: DescriptorType,
     pub count_: u32,
     pub size_: u32,
     pub padded_size_: u32,
+    /// The input_attachment_index of a subpass input. Zero for other descriptor types
+    pub input_attachment_index_: u32,
+    /// true if the descriptor is an image or sampler of a cube map
+    pub cube_: bool,
+    /// The top level members of a uniform or storage block. Empty for other descriptor types
+    pub members_: Vec<BlockMemberDescription>,
 }
 
 impl DescriptorDescription {
     /// TODO: Make this work with images and arrays
     pub fn from_reflection(descriptor: &ReflectDescriptorBinding) -> DescriptorDescription {
         DescriptorDescription {
+            name_: descriptor.name.clone(),
             binding_: descriptor.binding,
             descriptor_type_: DescriptorType::from_reflect_type(&descriptor.descriptor_type),
             count_: descriptor.count,
             size_: descriptor.block.size,
             padded_size_: descriptor.block.padded_size,
+            input_attachment_index_: descriptor.input_attachment_index,
+            cube_: descriptor.image.dim == ReflectDimension::Cube,
+            members_: descriptor
+                .block
+                .members
+                .iter()
+                .map(BlockMemberDescription::from_reflection)
+                .collect(),
         }
     }
 }
@@ -1,9 +1,11 @@
+use std::cell::RefCell;
 use std::fs;
+use std::path::{Path, PathBuf};
 
 use shaderc;
-use shaderc::ShaderKind;
+use shaderc::{IncludeType, ResolvedInclude, ShaderKind};
 use spirv_reflect::ShaderModule;
-use spirv_reflect::types::ReflectStorageClass::PushConstant;
+use spirv_reflect::types::{ReflectDecorationFlags, ReflectInterfaceVariable};
 
 use attribute::AttributeDescription;
 use descriptor::DescriptorSetDescription;
@@ -14,7 +16,7 @@ pub mod descriptor;
 pub mod push_constant;
 
 #[repr(C)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ShaderStage {
     Vertex,
     Fragment,
@@ -29,8 +31,12 @@ pub enum ShaderStage {
 pub struct Shader {
     pub bytes_: Vec<u8>,
     pub stage_: ShaderStage,
+    /// The GLSL file the shader was compiled from followed by every file it includes. Empty for
+    /// shaders created from SPIR-V bytes
+    pub sources_: Vec<PathBuf>,
 }
 
+#[derive(Clone)]
 pub struct ShaderDescription {
     pub shader_: Shader,
     pub input_: Vec<AttributeDescription>,
@@ -49,7 +55,7 @@ impl ShaderDescription
 
         let reflect_input = reflection.enumerate_input_variables(None).expect("Could not enumerate input variables");
 
-        for input_reflection in reflect_input.iter()
+        for input_reflection in reflect_input.iter().filter(|variable| !is_built_in(variable))
         {
             input.push(AttributeDescription::from_reflection(input_reflection));
         }
@@ -58,11 +64,14 @@ impl ShaderDescription
 
         let reflect_output = reflection.enumerate_output_variables(None).expect("Could not enumerate output varibales");
 
-        for output_reflection in reflect_output.iter()
+        for output_reflection in reflect_output.iter().filter(|variable| !is_built_in(variable))
         {
             output.push(AttributeDescription::from_reflection(output_reflection));
         }
 
+        input.sort_by_key(|attribute| attribute.location_);
+        output.sort_by_key(|attribute| attribute.location_);
+
         let mut push_constants = vec![];
 
         let reflect_push_constants = reflection.enumerate_push_constant_blocks(None).expect("Could not enumerate push constants");
@@ -91,12 +100,51 @@ impl ShaderDescription
     }
 }
 
+/// Returns true if an interface variable is a built-in such as gl_Position or gl_VertexIndex, rather
+/// than a user declared attribute
+fn is_built_in(variable: &ReflectInterfaceVariable) -> bool
+{
+    variable.decoration_flags.contains(ReflectDecorationFlags::BUILT_IN)
+}
+
 impl Shader {
+    /// Compiles a GLSL file into a shader. Panics if the file cannot be read or compiled
     pub fn from_glsl(path: &str, shader_stage: ShaderStage) -> Shader {
-        let mut compiler = shaderc::Compiler::new().unwrap();
-        let options = shaderc::CompileOptions::new().unwrap();
+        Shader::try_from_glsl(path, shader_stage).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Compiles a GLSL file into a shader, resolving #include directives relative to the including
+    /// file. Returns the compiler's error message if the file cannot be read or compiled
+    pub fn try_from_glsl(path: &str, shader_stage: ShaderStage) -> Result<Shader, String> {
+        let includes = RefCell::new(vec![]);
+
+        let mut compiler = shaderc::Compiler::new().expect("Could not create shader compiler");
+        let mut options =
+            shaderc::CompileOptions::new().expect("Could not create shader compile options");
+
+        options.set_include_callback(|requested, include_type, requesting, _depth| {
+            let include_path = match include_type {
+                IncludeType::Relative => Path::new(requesting)
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""))
+                    .join(requested),
+                IncludeType::Standard => PathBuf::from(requested),
+            };
 
-        let glsl = fs::read_to_string(path).unwrap();
+            let content = fs::read_to_string(&include_path).map_err(|error| {
+                format!("Could not read include {}: {}", include_path.display(), error)
+            })?;
+
+            includes.borrow_mut().push(include_path.clone());
+
+            Ok(ResolvedInclude {
+                resolved_name: include_path.to_string_lossy().into_owned(),
+                content,
+            })
+        });
+
+        let glsl = fs::read_to_string(path)
+            .map_err(|error| format!("Could not read shader {}: {}", path, error))?;
         let bytes = compiler
             .compile_into_spirv(
                 glsl.as_str(),
@@ -112,17 +160,37 @@ impl Shader {
                 "main",
                 Some(&options),
             )
-            .unwrap()
+            .map_err(|error| error.to_string())?
             .as_binary_u8()
             .to_vec();
 
-        Shader::from_bytes(bytes, shader_stage)
+        // The include callback borrows the include list until the options are dropped
+        drop(options);
+
+        let mut sources = vec![PathBuf::from(path)];
+        sources.append(&mut includes.into_inner());
+
+        Ok(Shader {
+            bytes_: bytes,
+            stage_: shader_stage,
+            sources_: sources,
+        })
+    }
+
+    /// Compiles the shader again from its GLSL source, picking up changes to the source and its
+    /// includes. Shaders created from SPIR-V bytes are returned unchanged
+    pub fn recompile(&self) -> Result<Shader, String> {
+        match self.sources_.first() {
+            Some(path) => Shader::try_from_glsl(&path.to_string_lossy(), self.stage_.clone()),
+            None => Ok(self.clone()),
+        }
     }
 
     pub fn from_bytes(bytes: Vec<u8>, shader_stage: ShaderStage) -> Shader {
         Shader {
             bytes_: bytes,
             stage_: shader_stage,
+            sources_: vec![],
         }
     }
 
@@ -143,6 +211,36 @@ mod tests {
         let shader_module = shader.reflect();
     }
 
+    #[test]
+    fn track_include_sources_test() {
+        let shader = super::Shader::from_glsl(
+            "test/shaders/include.frag",
+            super::ShaderStage::Fragment,
+        );
+
+        assert_eq!(shader.sources_.len(), 2);
+        assert!(shader.sources_[1].ends_with("common.glsl"));
+    }
+
+    #[test]
+    fn reflect_compute_shader_test() {
+        let shader = super::Shader::from_glsl(
+            "test/shaders/particles.comp",
+            super::ShaderStage::Compute,
+        );
+
+        let description = super::ShaderDescription::from_shader(&shader);
+
+        let descriptors = &description.descriptor_sets_[0].descriptors_;
+
+        assert_eq!(descriptors.len(), 2);
+        assert!(descriptors.iter().any(|descriptor| descriptor.descriptor_type_
+            == super::descriptor::DescriptorType::StorageBuffer));
+        assert!(descriptors.iter().any(|descriptor| descriptor.descriptor_type_
+            == super::descriptor::DescriptorType::StorageImage));
+        assert_eq!(description.push_constants_.len(), 1);
+    }
+
     #[test]
     fn enumerate_descriptor_set_test() {
         let shader = super::Shader::from_glsl(
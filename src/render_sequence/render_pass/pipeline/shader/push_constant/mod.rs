@@ -1,5 +1,6 @@
 use spirv_reflect::types::ReflectBlockVariable;
 
+#[derive(Clone)]
 pub struct PushConstantDescription {
     pub offset_: u32,
     pub size_: u32,
@@ -0,0 +1,264 @@
+/// Which faces of a primitive are discarded by the rasterizer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+/// The winding order of a front facing primitive
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrontFace {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// How primitives are rasterized: filled, as wireframe or as points
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+
+/// How the vertices of a draw are assembled into primitives
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrimitiveTopology {
+    PointList,
+    LineList,
+    LineStrip,
+    TriangleList,
+    TriangleStrip,
+    TriangleFan,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompareOp {
+    Never,
+    Less,
+    Equal,
+    LessOrEqual,
+    Greater,
+    NotEqual,
+    GreaterOrEqual,
+    Always,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    IncrementAndClamp,
+    DecrementAndClamp,
+    Invert,
+    IncrementAndWrap,
+    DecrementAndWrap,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendOp {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+/// Constant and slope scaled offset added to the depth of every fragment, used to avoid acne when
+/// rendering shadow maps or decals
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DepthBias {
+    pub constant_factor_: f32,
+    pub clamp_: f32,
+    pub slope_factor_: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RasterizationState {
+    pub cull_mode_: CullMode,
+    pub front_face_: FrontFace,
+    pub polygon_mode_: PolygonMode,
+    pub depth_bias_: Option<DepthBias>,
+    /// Whether the depth bias is set while recording draws instead of being fixed in the
+    /// pipeline, letting one pipeline render with a different bias per draw
+    pub dynamic_depth_bias_: bool,
+}
+
+impl Default for RasterizationState {
+    fn default() -> RasterizationState
+    {
+        RasterizationState {
+            cull_mode_: CullMode::Back,
+            front_face_: FrontFace::Clockwise,
+            polygon_mode_: PolygonMode::Fill,
+            depth_bias_: None,
+            dynamic_depth_bias_: false,
+        }
+    }
+}
+
+/// Stencil operations for one facing of a primitive
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StencilOpState {
+    pub fail_op_: StencilOp,
+    pub pass_op_: StencilOp,
+    pub depth_fail_op_: StencilOp,
+    pub compare_op_: CompareOp,
+    pub compare_mask_: u32,
+    pub write_mask_: u32,
+    pub reference_: u32,
+}
+
+impl Default for StencilOpState {
+    fn default() -> StencilOpState
+    {
+        StencilOpState {
+            fail_op_: StencilOp::Keep,
+            pass_op_: StencilOp::Keep,
+            depth_fail_op_: StencilOp::Keep,
+            compare_op_: CompareOp::Always,
+            compare_mask_: 0,
+            write_mask_: 0,
+            reference_: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthStencilState {
+    pub depth_test_: bool,
+    pub depth_write_: bool,
+    pub depth_compare_op_: CompareOp,
+    pub stencil_test_: bool,
+    pub front_: StencilOpState,
+    pub back_: StencilOpState,
+}
+
+impl Default for DepthStencilState {
+    fn default() -> DepthStencilState
+    {
+        DepthStencilState {
+            depth_test_: true,
+            depth_write_: true,
+            depth_compare_op_: CompareOp::Less,
+            stencil_test_: false,
+            front_: StencilOpState::default(),
+            back_: StencilOpState::default(),
+        }
+    }
+}
+
+/// Blend state of a single color attachment. A blend state of None writes the fragment color
+/// unchanged
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlendState {
+    pub blend_: Option<BlendEquation>,
+    pub write_red_: bool,
+    pub write_green_: bool,
+    pub write_blue_: bool,
+    pub write_alpha_: bool,
+}
+
+/// Factors and operations combining the fragment color (src) with the attachment color (dst)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlendEquation {
+    pub src_color_factor_: BlendFactor,
+    pub dst_color_factor_: BlendFactor,
+    pub color_op_: BlendOp,
+    pub src_alpha_factor_: BlendFactor,
+    pub dst_alpha_factor_: BlendFactor,
+    pub alpha_op_: BlendOp,
+}
+
+impl BlendState {
+    /// Overwrites the attachment with the fragment color
+    pub fn opaque() -> BlendState
+    {
+        BlendState::from_equation(None)
+    }
+
+    /// Standard transparency: src * src.a + dst * (1 - src.a)
+    pub fn alpha() -> BlendState
+    {
+        BlendState::from_equation(Some(BlendEquation {
+            src_color_factor_: BlendFactor::SrcAlpha,
+            dst_color_factor_: BlendFactor::OneMinusSrcAlpha,
+            color_op_: BlendOp::Add,
+            src_alpha_factor_: BlendFactor::One,
+            dst_alpha_factor_: BlendFactor::OneMinusSrcAlpha,
+            alpha_op_: BlendOp::Add,
+        }))
+    }
+
+    /// Adds the fragment color, weighted by its alpha, to the attachment: src * src.a + dst
+    pub fn additive() -> BlendState
+    {
+        BlendState::from_equation(Some(BlendEquation {
+            src_color_factor_: BlendFactor::SrcAlpha,
+            dst_color_factor_: BlendFactor::One,
+            color_op_: BlendOp::Add,
+            src_alpha_factor_: BlendFactor::One,
+            dst_alpha_factor_: BlendFactor::One,
+            alpha_op_: BlendOp::Add,
+        }))
+    }
+
+    /// Transparency for colors already multiplied by their alpha: src + dst * (1 - src.a)
+    pub fn premultiplied() -> BlendState
+    {
+        BlendState::from_equation(Some(BlendEquation {
+            src_color_factor_: BlendFactor::One,
+            dst_color_factor_: BlendFactor::OneMinusSrcAlpha,
+            color_op_: BlendOp::Add,
+            src_alpha_factor_: BlendFactor::One,
+            dst_alpha_factor_: BlendFactor::OneMinusSrcAlpha,
+            alpha_op_: BlendOp::Add,
+        }))
+    }
+
+    /// Leaves the attachment unchanged, for pipelines that only test or write depth
+    pub fn no_writes() -> BlendState
+    {
+        BlendState {
+            blend_: None,
+            write_red_: false,
+            write_green_: false,
+            write_blue_: false,
+            write_alpha_: false,
+        }
+    }
+
+    fn from_equation(blend: Option<BlendEquation>) -> BlendState
+    {
+        BlendState {
+            blend_: blend,
+            write_red_: true,
+            write_green_: true,
+            write_blue_: true,
+            write_alpha_: true,
+        }
+    }
+}
+
+impl Default for BlendState {
+    fn default() -> BlendState
+    {
+        BlendState::opaque()
+    }
+}
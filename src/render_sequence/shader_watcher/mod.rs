@@ -0,0 +1,58 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Watches GLSL files, including #included files, such as the sources of a RenderPassDescription
+/// or of a material's pipeline, by polling their modification times
+#[derive(Default)]
+pub struct ShaderWatcher {
+    modified_times_: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl ShaderWatcher {
+    /// Creates a watcher watching no files
+    pub fn new() -> ShaderWatcher
+    {
+        ShaderWatcher::default()
+    }
+
+    /// Starts watching the given files. Files already watched keep the modification time they
+    /// were last polled with, so changes made since are still reported
+    pub fn watch(&mut self, source_files: Vec<PathBuf>)
+    {
+        for path in source_files
+        {
+            if let Entry::Vacant(entry) = self.modified_times_.entry(path)
+            {
+                let modified_time = ShaderWatcher::get_modified_time(entry.key());
+                entry.insert(modified_time);
+            }
+        }
+    }
+
+    /// Returns every watched file that was modified, created or removed since the last poll
+    pub fn poll(&mut self) -> Vec<PathBuf>
+    {
+        let mut changed = vec![];
+
+        for (path, modified_time) in self.modified_times_.iter_mut()
+        {
+            let current_time = ShaderWatcher::get_modified_time(path);
+
+            if current_time != *modified_time
+            {
+                *modified_time = current_time;
+                changed.push(path.clone());
+            }
+        }
+
+        changed
+    }
+
+    fn get_modified_time(path: &PathBuf) -> Option<SystemTime>
+    {
+        fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
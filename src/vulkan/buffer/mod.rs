@@ -88,6 +88,13 @@ impl Buffer {
     pub unsafe fn unmap(&self, device: &ash::Device) {
         device.unmap_memory(self.device_memory_handle_);
     }
+
+    /// Destroys the buffer and frees its memory. The buffer must not be in use by the device
+    pub unsafe fn destroy(&self, device: &ash::Device) {
+        device.destroy_buffer(self.buffer_handle_, None);
+        device.free_memory(self.device_memory_handle_, None);
+    }
+
     pub unsafe fn copy_from_data(
         &mut self,
         data: *const c_void,
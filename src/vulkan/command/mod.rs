@@ -40,4 +40,8 @@ impl CommandDispatch {
             command_buffers_: command_buffers,
         }
     }
+    /// Destroys the command pool, freeing its command buffers. The device must not be using them
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe { device.destroy_command_pool(self.command_pool_, None) };
+    }
 }
@@ -0,0 +1,191 @@
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use ash::Device;
+
+use crate::render_sequence::render_pass::pipeline::shader::{ShaderDescription, ShaderStage};
+use crate::vulkan::descriptor;
+use crate::vulkan::render_pass::pipeline::{
+    descriptor_set_layout, pipeline_layout, push_constant, shader, PipelineError,
+};
+
+/// A compute pipeline built from a reflected compute shader, together with one descriptor set per
+/// set index declared by the shader
+pub struct ComputePipeline {
+    pub pipeline_handle_: vk::Pipeline,
+    pub layout_: vk::PipelineLayout,
+    pub descriptor_set_layouts_: Vec<vk::DescriptorSetLayout>,
+    pub push_constant_ranges_: Vec<vk::PushConstantRange>,
+    pub descriptor_sets_: Vec<vk::DescriptorSet>,
+    /// the reflected bindings of each descriptor set, used to look up binding types
+    bindings_: Vec<Vec<vk::DescriptorSetLayoutBinding>>,
+    descriptor_pool_: vk::DescriptorPool,
+}
+
+impl ComputePipeline {
+    /// Builds a compute pipeline from a reflected compute shader and allocates its descriptor
+    /// sets. Returns an error if the shader's descriptor bindings cannot be merged. Panics if the
+    /// shader is not a compute shader or any vulkan object cannot be created
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the pipeline is created on
+    ///
+    /// * 'shader_description' - the reflected compute shader
+    ///
+    /// * 'pipeline_cache' - cache used to speed up pipeline compilation
+    ///
+    pub fn new(
+        device: &Device,
+        shader_description: &ShaderDescription,
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<ComputePipeline, PipelineError> {
+        if shader_description.shader_.stage_ != ShaderStage::Compute {
+            panic!(
+                "Compute pipelines need a compute shader, found a {:?} shader",
+                shader_description.shader_.stage_
+            );
+        }
+
+        let shader_descriptions = vec![shader_description.clone()];
+
+        let bindings = descriptor_set_layout::merge_bindings(&shader_descriptions)?;
+        let descriptor_set_layouts = bindings
+            .iter()
+            .map(|set_bindings| descriptor_set_layout::from_bindings(device, set_bindings))
+            .collect::<Vec<_>>();
+        let push_constant_ranges = push_constant::from_shader_descriptions(&shader_descriptions);
+        let layout = pipeline_layout::new(device, &push_constant_ranges, &descriptor_set_layouts);
+
+        let vulkan_shader = shader::VulkanShader::new(device, &shader_description.shader_);
+
+        let pipeline_create_info = vk::ComputePipelineCreateInfo {
+            s_type: vk::StructureType::COMPUTE_PIPELINE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineCreateFlags::empty(),
+            stage: vulkan_shader.build_pipeline_shader_stage_create_info(),
+            layout,
+            base_pipeline_handle: vk::Pipeline::null(),
+            base_pipeline_index: -1,
+        };
+
+        let pipelines = unsafe {
+            device
+                .create_compute_pipelines(pipeline_cache, &[pipeline_create_info], None)
+                .expect("Could not create compute pipeline")
+        };
+
+        vulkan_shader.destroy(device);
+
+        let descriptor_pool = descriptor::create_pool(device, &bindings, 1);
+        let descriptor_sets =
+            descriptor::allocate_sets(device, descriptor_pool, &descriptor_set_layouts);
+
+        Ok(ComputePipeline {
+            pipeline_handle_: pipelines[0],
+            layout_: layout,
+            descriptor_set_layouts_: descriptor_set_layouts,
+            push_constant_ranges_: push_constant_ranges,
+            descriptor_sets_: descriptor_sets,
+            bindings_: bindings,
+            descriptor_pool_: descriptor_pool,
+        })
+    }
+
+    /// Returns the descriptor set with the given set index and the type of the given binding in
+    /// it. Panics if the shader does not declare the binding
+    pub fn get_binding(&self, set: u32, binding: u32) -> (vk::DescriptorSet, vk::DescriptorType) {
+        let descriptor_type = self
+            .bindings_
+            .get(set as usize)
+            .and_then(|set_bindings| {
+                set_bindings
+                    .iter()
+                    .find(|set_binding| set_binding.binding == binding)
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "Compute shader declares no binding {} in descriptor set {}",
+                    binding, set
+                )
+            })
+            .descriptor_type;
+
+        (self.descriptor_sets_[set as usize], descriptor_type)
+    }
+
+    /// Records binding this pipeline and its descriptor sets, pushing constants and dispatching
+    /// the given number of workgroups
+    pub fn record_dispatch(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        group_count: [u32; 3],
+        push_constants: &[u8],
+    ) {
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.pipeline_handle_,
+            );
+
+            if !self.descriptor_sets_.is_empty() {
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    self.layout_,
+                    0,
+                    &self.descriptor_sets_,
+                    &[],
+                );
+            }
+
+            if !push_constants.is_empty() {
+                let declared_size = self
+                    .push_constant_ranges_
+                    .iter()
+                    .map(|range| range.offset + range.size)
+                    .max()
+                    .unwrap_or(0);
+
+                if push_constants.len() as u32 > declared_size {
+                    panic!(
+                        "Pushed {} bytes of constants to a compute shader declaring {}",
+                        push_constants.len(),
+                        declared_size
+                    );
+                }
+
+                device.cmd_push_constants(
+                    command_buffer,
+                    self.layout_,
+                    vk::ShaderStageFlags::COMPUTE,
+                    0,
+                    push_constants,
+                );
+            }
+
+            device.cmd_dispatch(
+                command_buffer,
+                group_count[0],
+                group_count[1],
+                group_count[2],
+            );
+        }
+    }
+
+    /// Destroys the pipeline, its layout, descriptor set layouts and descriptor sets
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_pipeline(self.pipeline_handle_, None);
+            device.destroy_pipeline_layout(self.layout_, None);
+            device.destroy_descriptor_pool(self.descriptor_pool_, None);
+
+            for descriptor_set_layout in self.descriptor_set_layouts_.iter() {
+                device.destroy_descriptor_set_layout(*descriptor_set_layout, None);
+            }
+        }
+    }
+}
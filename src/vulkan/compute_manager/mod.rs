@@ -0,0 +1,728 @@
+use std::collections::HashMap;
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use ash::Device;
+
+use super::command::CommandDispatch;
+use super::descriptor;
+use super::physical_device::PhysicalDevice;
+use crate::render_sequence::render_pass::pipeline::shader::{Shader, ShaderDescription};
+
+use compute_pipeline::ComputePipeline;
+use ownership::OwnershipTransfer;
+use storage::{StorageBuffer, StorageImage};
+
+pub(crate) mod compute_pipeline;
+mod ownership;
+mod storage;
+
+/// Number of frames whose compute work may be in flight at once
+const MAX_FRAMES_IN_FLIGHT: usize = 2;
+/// Index of the submission slot used for immediate work, after one slot per frame in flight
+const IMMEDIATE_SLOT: usize = MAX_FRAMES_IN_FLIGHT;
+
+pub type ComputePipelineID = u64;
+pub type StorageBufferID = u64;
+pub type StorageImageID = u64;
+
+/// A single dispatch of a compute pipeline
+#[derive(Clone, Debug)]
+pub struct ComputeDispatch {
+    pub pipeline_: ComputePipelineID,
+    /// number of workgroups dispatched in x, y and z
+    pub group_count_: [u32; 3],
+    /// bytes pushed to the pipeline's push constant block, starting at offset 0
+    pub push_constants_: Vec<u8>,
+}
+
+/// Owns compute pipelines and the storage buffers and images they operate on, and submits compute
+/// work either immediately or once per frame before any renderer draws.
+///
+/// When the device has a compute-only queue family, work is submitted to that family's queue so it
+/// can overlap graphics work. Storage resources are owned by the graphics family between
+/// submissions: each submission releases them to the compute family, dispatches, and releases them
+/// back, with semaphores ordering the three batches. Without a compute-only family, work is
+/// submitted to the graphics queue and ordered by pipeline barriers alone
+pub struct ComputeManager {
+    pipelines_: HashMap<ComputePipelineID, ComputePipeline>,
+    storage_buffers_: HashMap<StorageBufferID, StorageBuffer>,
+    storage_images_: HashMap<StorageImageID, StorageImage>,
+    curr_id_: u64,
+    frame_dispatches_: Vec<ComputeDispatch>,
+    /// one command buffer per frame in flight, followed by one for immediate submissions
+    command_dispatch_: CommandDispatch,
+    /// a release and an acquire command buffer per submission slot, recorded for the graphics
+    /// queue when compute work runs on a separate queue family
+    graphics_command_dispatch_: CommandDispatch,
+    /// signalled per submission slot once the graphics queue releases storage resources
+    released_semaphores_: Vec<vk::Semaphore>,
+    /// signalled per submission slot once compute work completes and releases storage resources
+    compute_finished_semaphores_: Vec<vk::Semaphore>,
+    frame_fences_: Vec<vk::Fence>,
+    immediate_fence_: vk::Fence,
+    queue_: vk::Queue,
+    graphics_queue_: vk::Queue,
+    /// transfer of storage resources from the compute queue family back to the graphics queue
+    /// family. None if compute work is submitted to the graphics queue
+    to_graphics_: Option<OwnershipTransfer>,
+    current_frame_: usize,
+}
+
+impl ComputeManager {
+    pub fn new(device: &Device, physical_device: &PhysicalDevice) -> ComputeManager {
+        let graphics_queue_family_index = physical_device
+            .queue_family_indices_
+            .graphics_compute_
+            .expect("No graphics and compute queue family");
+
+        // Prefer a compute-only family so compute work can run alongside graphics work, falling
+        // back to the queue renderers draw with
+        let queue_family_index = physical_device
+            .queue_family_indices_
+            .compute_
+            .unwrap_or(graphics_queue_family_index);
+
+        let num_slots = IMMEDIATE_SLOT as u32 + 1;
+
+        let command_dispatch = CommandDispatch::new(device, queue_family_index, num_slots);
+        let graphics_command_dispatch =
+            CommandDispatch::new(device, graphics_queue_family_index, num_slots * 2);
+
+        let queue = unsafe { device.get_device_queue(queue_family_index, 0) };
+        let graphics_queue = unsafe { device.get_device_queue(graphics_queue_family_index, 0) };
+
+        let to_graphics = match queue_family_index == graphics_queue_family_index {
+            true => None,
+            false => Some(OwnershipTransfer {
+                src_queue_family_index_: queue_family_index,
+                dst_queue_family_index_: graphics_queue_family_index,
+                src_stage_mask_: vk::PipelineStageFlags::COMPUTE_SHADER,
+                src_access_mask_: vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                dst_stage_mask_: vk::PipelineStageFlags::DRAW_INDIRECT
+                    | vk::PipelineStageFlags::VERTEX_INPUT
+                    | vk::PipelineStageFlags::VERTEX_SHADER
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags::COMPUTE_SHADER,
+                dst_access_mask_: vk::AccessFlags::INDIRECT_COMMAND_READ
+                    | vk::AccessFlags::VERTEX_ATTRIBUTE_READ
+                    | vk::AccessFlags::INDEX_READ
+                    | vk::AccessFlags::SHADER_READ
+                    | vk::AccessFlags::SHADER_WRITE,
+            }),
+        };
+
+        let semaphore_create_info = vk::SemaphoreCreateInfo {
+            s_type: vk::StructureType::SEMAPHORE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::SemaphoreCreateFlags::empty(),
+        };
+
+        let mut released_semaphores = vec![];
+        let mut compute_finished_semaphores = vec![];
+
+        for _ in 0..num_slots {
+            unsafe {
+                released_semaphores.push(
+                    device
+                        .create_semaphore(&semaphore_create_info, None)
+                        .expect("Could not create semaphore"),
+                );
+                compute_finished_semaphores.push(
+                    device
+                        .create_semaphore(&semaphore_create_info, None)
+                        .expect("Could not create semaphore"),
+                );
+            }
+        }
+
+        let fence_create_info = vk::FenceCreateInfo {
+            s_type: vk::StructureType::FENCE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::FenceCreateFlags::SIGNALED,
+        };
+
+        let mut frame_fences = vec![];
+
+        for _ in 0..MAX_FRAMES_IN_FLIGHT {
+            frame_fences.push(unsafe {
+                device
+                    .create_fence(&fence_create_info, None)
+                    .expect("Could not create fence")
+            });
+        }
+
+        let immediate_fence = unsafe {
+            device
+                .create_fence(&fence_create_info, None)
+                .expect("Could not create fence")
+        };
+
+        ComputeManager {
+            pipelines_: HashMap::new(),
+            storage_buffers_: HashMap::new(),
+            storage_images_: HashMap::new(),
+            curr_id_: 0,
+            frame_dispatches_: vec![],
+            command_dispatch_: command_dispatch,
+            graphics_command_dispatch_: graphics_command_dispatch,
+            released_semaphores_: released_semaphores,
+            compute_finished_semaphores_: compute_finished_semaphores,
+            frame_fences_: frame_fences,
+            immediate_fence_: immediate_fence,
+            queue_: queue,
+            graphics_queue_: graphics_queue,
+            to_graphics_: to_graphics,
+            current_frame_: 0,
+        }
+    }
+
+    /// Returns true if compute work is submitted to a queue separate from the graphics queue
+    pub fn is_async(&self) -> bool {
+        self.to_graphics_.is_some()
+    }
+
+    /// Reflects a compute shader and builds a compute pipeline from it. Panics if the shader is
+    /// not a compute shader or its descriptor bindings are invalid
+    pub fn create_pipeline(
+        &mut self,
+        device: &Device,
+        shader: &Shader,
+        pipeline_cache: vk::PipelineCache,
+    ) -> ComputePipelineID {
+        let shader_description = ShaderDescription::from_shader(shader);
+
+        let pipeline = ComputePipeline::new(device, &shader_description, pipeline_cache)
+            .unwrap_or_else(|error| panic!("Could not build compute pipeline: {}", error));
+
+        let pipeline_id = self.next_id();
+        self.pipelines_.insert(pipeline_id, pipeline);
+        pipeline_id
+    }
+
+    pub fn create_storage_buffer(
+        &mut self,
+        instance: &ash::Instance,
+        device: &Device,
+        physical_device: &PhysicalDevice,
+        size: vk::DeviceSize,
+    ) -> StorageBufferID {
+        let storage_buffer = StorageBuffer::new(instance, device, physical_device, size);
+
+        let storage_buffer_id = self.next_id();
+        self.storage_buffers_
+            .insert(storage_buffer_id, storage_buffer);
+        storage_buffer_id
+    }
+
+    pub fn create_storage_image(
+        &mut self,
+        instance: &ash::Instance,
+        device: &Device,
+        physical_device: &PhysicalDevice,
+        extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> StorageImageID {
+        let storage_image = StorageImage::new(instance, device, physical_device, extent, format);
+
+        // The transition is submitted to the graphics queue, which owns storage resources
+        // between compute submissions
+        let command_buffer = self.graphics_command_dispatch_.command_buffers_[IMMEDIATE_SLOT * 2];
+
+        unsafe {
+            device
+                .reset_fences(&[self.immediate_fence_])
+                .expect("Could not reset fence");
+        }
+
+        self.record_command_buffer(device, command_buffer, |command_buffer| {
+            storage_image.record_initial_transition(device, command_buffer);
+        });
+        self.submit(
+            device,
+            self.graphics_queue_,
+            command_buffer,
+            None,
+            None,
+            self.immediate_fence_,
+        );
+        self.wait_for_fence(device, self.immediate_fence_);
+
+        let storage_image_id = self.next_id();
+        self.storage_images_.insert(storage_image_id, storage_image);
+        storage_image_id
+    }
+
+    /// Copies data into a storage buffer, waiting for the device to finish any work using it
+    pub fn write_storage_buffer(
+        &mut self,
+        device: &Device,
+        storage_buffer_id: StorageBufferID,
+        offset: vk::DeviceSize,
+        data: &[u8],
+    ) {
+        ComputeManager::wait_idle(device);
+
+        self.storage_buffers_
+            .get_mut(&storage_buffer_id)
+            .expect("No such storage buffer")
+            .write(device, offset, data);
+    }
+
+    /// Returns the contents of a storage buffer, waiting for the device to finish any work writing
+    /// it
+    pub fn read_storage_buffer(
+        &mut self,
+        device: &Device,
+        storage_buffer_id: StorageBufferID,
+    ) -> Vec<u8> {
+        ComputeManager::wait_idle(device);
+
+        self.storage_buffers_
+            .get_mut(&storage_buffer_id)
+            .expect("No such storage buffer")
+            .read(device)
+    }
+
+    /// Returns the vulkan buffer backing a storage buffer, for binding it to graphics pipelines
+    pub fn get_storage_buffer(&self, storage_buffer_id: StorageBufferID) -> &StorageBuffer {
+        self.storage_buffers_
+            .get(&storage_buffer_id)
+            .expect("No such storage buffer")
+    }
+
+    /// Returns the vulkan image backing a storage image, for binding it to graphics pipelines
+    pub fn get_storage_image(&self, storage_image_id: StorageImageID) -> &StorageImage {
+        self.storage_images_
+            .get(&storage_image_id)
+            .expect("No such storage image")
+    }
+
+    /// Binds a whole storage buffer to a binding of a compute pipeline
+    pub fn bind_storage_buffer(
+        &self,
+        device: &Device,
+        pipeline_id: ComputePipelineID,
+        set: u32,
+        binding: u32,
+        storage_buffer_id: StorageBufferID,
+    ) {
+        let (descriptor_set, descriptor_type) =
+            self.get_pipeline(pipeline_id).get_binding(set, binding);
+        let storage_buffer = self.get_storage_buffer(storage_buffer_id);
+
+        // Descriptor sets must not be updated while a pending dispatch uses them
+        ComputeManager::wait_idle(device);
+
+        descriptor::write_buffer(
+            device,
+            descriptor_set,
+            binding,
+            descriptor_type,
+            storage_buffer.buffer_.buffer_handle_,
+            0,
+            vk::WHOLE_SIZE,
+        );
+    }
+
+    /// Binds a storage image to a binding of a compute pipeline
+    pub fn bind_storage_image(
+        &self,
+        device: &Device,
+        pipeline_id: ComputePipelineID,
+        set: u32,
+        binding: u32,
+        storage_image_id: StorageImageID,
+    ) {
+        let (descriptor_set, descriptor_type) =
+            self.get_pipeline(pipeline_id).get_binding(set, binding);
+        let storage_image = self.get_storage_image(storage_image_id);
+
+        ComputeManager::wait_idle(device);
+
+        descriptor::write_image(
+            device,
+            descriptor_set,
+            binding,
+            descriptor_type,
+            storage_image.image_view_,
+            vk::Sampler::null(),
+            vk::ImageLayout::GENERAL,
+        );
+    }
+
+    /// Submits dispatches immediately and waits for them to complete
+    pub fn dispatch(&self, device: &Device, dispatches: &[ComputeDispatch]) {
+        self.submit_compute(
+            device,
+            IMMEDIATE_SLOT,
+            self.immediate_fence_,
+            |command_buffer| {
+                self.record_dispatches(device, command_buffer, dispatches);
+            },
+        );
+
+        self.wait_for_fence(device, self.immediate_fence_);
+    }
+
+    /// Adds a dispatch that is submitted every frame, before any renderer draws. Returns the index
+    /// of the dispatch
+    pub fn add_frame_dispatch(&mut self, dispatch: ComputeDispatch) -> usize {
+        self.get_pipeline(dispatch.pipeline_);
+        self.frame_dispatches_.push(dispatch);
+        self.frame_dispatches_.len() - 1
+    }
+
+    pub fn get_mut_frame_dispatch(&mut self, index: usize) -> &mut ComputeDispatch {
+        self.frame_dispatches_
+            .get_mut(index)
+            .expect("No such frame dispatch")
+    }
+
+    pub fn clear_frame_dispatches(&mut self) {
+        self.frame_dispatches_.clear();
+    }
+
+    /// Submits this frame's dispatches. Results are visible to graphics work submitted afterwards
+    pub fn update(&mut self, device: &Device) {
+        if self.frame_dispatches_.is_empty() {
+            return;
+        }
+
+        let fence = self.frame_fences_[self.current_frame_];
+
+        // The slot's command buffers and semaphores are free once its last submission completes
+        self.wait_for_fence(device, fence);
+
+        self.submit_compute(device, self.current_frame_, fence, |command_buffer| {
+            self.record_dispatches(device, command_buffer, &self.frame_dispatches_);
+        });
+
+        self.current_frame_ = (self.current_frame_ + 1) % MAX_FRAMES_IN_FLIGHT;
+    }
+
+    /// Destroys a compute pipeline once the device has finished using it. It must not be used by a
+    /// frame dispatch
+    pub fn destroy_pipeline(&mut self, device: &Device, pipeline_id: ComputePipelineID) {
+        ComputeManager::wait_idle(device);
+
+        self.pipelines_
+            .remove(&pipeline_id)
+            .expect("No such compute pipeline")
+            .destroy(device);
+    }
+
+    /// Destroys a storage buffer once the device has finished using it
+    pub fn destroy_storage_buffer(&mut self, device: &Device, storage_buffer_id: StorageBufferID) {
+        ComputeManager::wait_idle(device);
+
+        let storage_buffer = self
+            .storage_buffers_
+            .remove(&storage_buffer_id)
+            .expect("No such storage buffer");
+
+        unsafe { storage_buffer.buffer_.destroy(device) };
+    }
+
+    /// Destroys every compute pipeline, storage buffer and storage image along with the command
+    /// buffers and synchronisation objects. The device must be idle
+    pub fn destroy(&mut self, device: &Device) {
+        for pipeline in self.pipelines_.values() {
+            pipeline.destroy(device);
+        }
+
+        for storage_buffer in self.storage_buffers_.values() {
+            unsafe { storage_buffer.buffer_.destroy(device) };
+        }
+
+        for storage_image in self.storage_images_.values() {
+            storage_image.destroy(device);
+        }
+
+        self.command_dispatch_.destroy(device);
+
+        unsafe {
+            for fence in self.frame_fences_.iter() {
+                device.destroy_fence(*fence, None);
+            }
+
+            device.destroy_fence(self.immediate_fence_, None);
+
+            for semaphore in self
+                .released_semaphores_
+                .iter()
+                .chain(self.compute_finished_semaphores_.iter())
+            {
+                device.destroy_semaphore(*semaphore, None);
+            }
+        }
+
+        self.graphics_command_dispatch_.destroy(device);
+
+        self.pipelines_.clear();
+        self.storage_buffers_.clear();
+        self.storage_images_.clear();
+        self.frame_dispatches_.clear();
+    }
+
+    fn get_pipeline(&self, pipeline_id: ComputePipelineID) -> &ComputePipeline {
+        self.pipelines_
+            .get(&pipeline_id)
+            .expect("No such compute pipeline")
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = self.curr_id_;
+        self.curr_id_ += 1;
+        id
+    }
+
+    /// Records compute work and submits it, signalling the fence once its results are visible to
+    /// the graphics queue. On a separate compute queue this is three batches: the graphics queue
+    /// releases storage resources, the compute queue acquires them, runs the work and releases
+    /// them, then the graphics queue acquires them back
+    fn submit_compute<F>(&self, device: &Device, slot: usize, fence: vk::Fence, record: F)
+    where
+        F: FnOnce(vk::CommandBuffer),
+    {
+        let command_buffer = self.command_dispatch_.command_buffers_[slot];
+
+        unsafe {
+            device
+                .reset_fences(&[fence])
+                .expect("Could not reset fence");
+        }
+
+        let to_graphics = match &self.to_graphics_ {
+            Some(to_graphics) => to_graphics,
+            None => {
+                self.record_command_buffer(device, command_buffer, record);
+                self.submit(device, self.queue_, command_buffer, None, None, fence);
+                return;
+            }
+        };
+
+        let to_compute = to_graphics.reversed();
+
+        let buffers: Vec<vk::Buffer> = self
+            .storage_buffers_
+            .values()
+            .map(|storage_buffer| storage_buffer.buffer_.buffer_handle_)
+            .collect();
+        let images: Vec<vk::Image> = self
+            .storage_images_
+            .values()
+            .map(|storage_image| storage_image.image_)
+            .collect();
+
+        let release_command_buffer = self.graphics_command_dispatch_.command_buffers_[slot * 2];
+        let acquire_command_buffer = self.graphics_command_dispatch_.command_buffers_[slot * 2 + 1];
+
+        self.record_command_buffer(device, release_command_buffer, |command_buffer| {
+            to_compute.record_release(device, command_buffer, &buffers, &images);
+        });
+
+        self.record_command_buffer(device, command_buffer, |command_buffer| {
+            to_compute.record_acquire(device, command_buffer, &buffers, &images);
+            record(command_buffer);
+            to_graphics.record_release(device, command_buffer, &buffers, &images);
+        });
+
+        self.record_command_buffer(device, acquire_command_buffer, |command_buffer| {
+            to_graphics.record_acquire(device, command_buffer, &buffers, &images);
+        });
+
+        let released_semaphore = self.released_semaphores_[slot];
+        let compute_finished_semaphore = self.compute_finished_semaphores_[slot];
+
+        self.submit(
+            device,
+            self.graphics_queue_,
+            release_command_buffer,
+            None,
+            Some(released_semaphore),
+            vk::Fence::null(),
+        );
+        self.submit(
+            device,
+            self.queue_,
+            command_buffer,
+            Some((released_semaphore, to_compute.dst_stage_mask_)),
+            Some(compute_finished_semaphore),
+            vk::Fence::null(),
+        );
+        self.submit(
+            device,
+            self.graphics_queue_,
+            acquire_command_buffer,
+            Some((compute_finished_semaphore, to_graphics.dst_stage_mask_)),
+            None,
+            fence,
+        );
+    }
+
+    /// Records the given dispatches, each followed by a barrier making its writes visible to later
+    /// dispatches and host reads. On the graphics queue the barrier also covers draws, vertex
+    /// fetching and indirect draws; a separate compute queue hands results to graphics through an
+    /// ownership transfer instead
+    fn record_dispatches(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        dispatches: &[ComputeDispatch],
+    ) {
+        // Earlier draws may still read the storage resources the dispatches write, as vertices,
+        // indices or in shaders. Work on a separate queue is ordered by the release semaphore
+        if !dispatches.is_empty() && !self.is_async() {
+            let memory_barrier = vk::MemoryBarrier {
+                s_type: vk::StructureType::MEMORY_BARRIER,
+                p_next: ptr::null(),
+                src_access_mask: vk::AccessFlags::INDIRECT_COMMAND_READ
+                    | vk::AccessFlags::VERTEX_ATTRIBUTE_READ
+                    | vk::AccessFlags::INDEX_READ
+                    | vk::AccessFlags::SHADER_READ,
+                dst_access_mask: vk::AccessFlags::SHADER_WRITE,
+            };
+
+            unsafe {
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::DRAW_INDIRECT
+                        | vk::PipelineStageFlags::VERTEX_INPUT
+                        | vk::PipelineStageFlags::VERTEX_SHADER
+                        | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    vk::PipelineStageFlags::COMPUTE_SHADER,
+                    vk::DependencyFlags::empty(),
+                    &[memory_barrier],
+                    &[],
+                    &[],
+                );
+            }
+        }
+
+        for dispatch in dispatches.iter() {
+            self.get_pipeline(dispatch.pipeline_).record_dispatch(
+                device,
+                command_buffer,
+                dispatch.group_count_,
+                dispatch.push_constants_.as_slice(),
+            );
+
+            let mut dst_stage_mask =
+                vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::HOST;
+            let mut dst_access_mask = vk::AccessFlags::SHADER_READ
+                | vk::AccessFlags::SHADER_WRITE
+                | vk::AccessFlags::HOST_READ;
+
+            if !self.is_async() {
+                dst_stage_mask |= vk::PipelineStageFlags::DRAW_INDIRECT
+                    | vk::PipelineStageFlags::VERTEX_INPUT
+                    | vk::PipelineStageFlags::VERTEX_SHADER
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER;
+                dst_access_mask |= vk::AccessFlags::INDIRECT_COMMAND_READ
+                    | vk::AccessFlags::VERTEX_ATTRIBUTE_READ
+                    | vk::AccessFlags::INDEX_READ;
+            }
+
+            let memory_barrier = vk::MemoryBarrier {
+                s_type: vk::StructureType::MEMORY_BARRIER,
+                p_next: ptr::null(),
+                src_access_mask: vk::AccessFlags::SHADER_WRITE,
+                dst_access_mask,
+            };
+
+            unsafe {
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::COMPUTE_SHADER,
+                    dst_stage_mask,
+                    vk::DependencyFlags::empty(),
+                    &[memory_barrier],
+                    &[],
+                    &[],
+                );
+            }
+        }
+    }
+
+    fn record_command_buffer<F>(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        record: F,
+    ) where
+        F: FnOnce(vk::CommandBuffer),
+    {
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo {
+            s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+            p_next: ptr::null(),
+            flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+            p_inheritance_info: ptr::null(),
+        };
+
+        unsafe {
+            device
+                .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+                .expect("Could not begin recording compute command buffer");
+        }
+
+        record(command_buffer);
+
+        unsafe {
+            device
+                .end_command_buffer(command_buffer)
+                .expect("Could not end recording compute command buffer");
+        }
+    }
+
+    /// Submits a single command buffer, optionally waiting on a semaphore at the given stages and
+    /// signalling another semaphore
+    fn submit(
+        &self,
+        device: &Device,
+        queue: vk::Queue,
+        command_buffer: vk::CommandBuffer,
+        wait: Option<(vk::Semaphore, vk::PipelineStageFlags)>,
+        signal: Option<vk::Semaphore>,
+        fence: vk::Fence,
+    ) {
+        let wait_semaphores: Vec<vk::Semaphore> = wait.iter().map(|wait| wait.0).collect();
+        let wait_stages: Vec<vk::PipelineStageFlags> = wait.iter().map(|wait| wait.1).collect();
+        let signal_semaphores: Vec<vk::Semaphore> = signal.into_iter().collect();
+
+        let submit_info = vk::SubmitInfo {
+            s_type: vk::StructureType::SUBMIT_INFO,
+            p_next: ptr::null(),
+            wait_semaphore_count: wait_semaphores.len() as u32,
+            p_wait_semaphores: wait_semaphores.as_ptr(),
+            p_wait_dst_stage_mask: wait_stages.as_ptr(),
+            command_buffer_count: 1,
+            p_command_buffers: &command_buffer,
+            signal_semaphore_count: signal_semaphores.len() as u32,
+            p_signal_semaphores: signal_semaphores.as_ptr(),
+        };
+
+        unsafe {
+            device
+                .queue_submit(queue, &[submit_info], fence)
+                .expect("Could not submit compute work");
+        }
+    }
+
+    fn wait_for_fence(&self, device: &Device, fence: vk::Fence) {
+        unsafe {
+            device
+                .wait_for_fences(&[fence], true, u64::MAX)
+                .expect("Wait for fences failed");
+        }
+    }
+
+    fn wait_idle(device: &Device) {
+        unsafe {
+            device
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use ash::Device;
+
+/// Describes moving exclusive ownership of storage resources from one queue family to another.
+/// Each transfer is recorded twice: released on a queue of the source family, then acquired on a
+/// queue of the destination family after a semaphore wait on the release
+pub struct OwnershipTransfer {
+    pub src_queue_family_index_: u32,
+    pub dst_queue_family_index_: u32,
+    /// stages on the source queue whose accesses must complete before the release
+    pub src_stage_mask_: vk::PipelineStageFlags,
+    pub src_access_mask_: vk::AccessFlags,
+    /// stages on the destination queue that wait for the acquire. The semaphore the acquiring
+    /// submission waits on must use the same stages
+    pub dst_stage_mask_: vk::PipelineStageFlags,
+    pub dst_access_mask_: vk::AccessFlags,
+}
+
+impl OwnershipTransfer {
+    /// Returns the transfer moving resources back in the opposite direction
+    pub fn reversed(&self) -> OwnershipTransfer {
+        OwnershipTransfer {
+            src_queue_family_index_: self.dst_queue_family_index_,
+            dst_queue_family_index_: self.src_queue_family_index_,
+            src_stage_mask_: self.dst_stage_mask_,
+            src_access_mask_: self.dst_access_mask_,
+            dst_stage_mask_: self.src_stage_mask_,
+            dst_access_mask_: self.src_access_mask_,
+        }
+    }
+
+    /// Records the release half of the transfer, to be submitted on the source queue
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer was allocated from
+    ///
+    /// * 'command_buffer' - command buffer of the source queue family to record into
+    ///
+    /// * 'buffers' - buffers whose ownership is released
+    ///
+    /// * 'images' - images in the GENERAL layout whose ownership is released
+    ///
+    pub fn record_release(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        buffers: &[vk::Buffer],
+        images: &[vk::Image],
+    ) {
+        self.record_barriers(
+            device,
+            command_buffer,
+            buffers,
+            images,
+            (self.src_stage_mask_, vk::PipelineStageFlags::BOTTOM_OF_PIPE),
+            (self.src_access_mask_, vk::AccessFlags::empty()),
+        );
+    }
+
+    /// Records the acquire half of the transfer, to be submitted on the destination queue
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer was allocated from
+    ///
+    /// * 'command_buffer' - command buffer of the destination queue family to record into
+    ///
+    /// * 'buffers' - buffers whose ownership is acquired
+    ///
+    /// * 'images' - images in the GENERAL layout whose ownership is acquired
+    ///
+    pub fn record_acquire(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        buffers: &[vk::Buffer],
+        images: &[vk::Image],
+    ) {
+        self.record_barriers(
+            device,
+            command_buffer,
+            buffers,
+            images,
+            (self.dst_stage_mask_, self.dst_stage_mask_),
+            (vk::AccessFlags::empty(), self.dst_access_mask_),
+        );
+    }
+
+    fn record_barriers(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        buffers: &[vk::Buffer],
+        images: &[vk::Image],
+        stage_masks: (vk::PipelineStageFlags, vk::PipelineStageFlags),
+        access_masks: (vk::AccessFlags, vk::AccessFlags),
+    ) {
+        if buffers.is_empty() && images.is_empty() {
+            return;
+        }
+
+        let buffer_memory_barriers: Vec<vk::BufferMemoryBarrier> = buffers
+            .iter()
+            .map(|buffer| vk::BufferMemoryBarrier {
+                s_type: vk::StructureType::BUFFER_MEMORY_BARRIER,
+                p_next: ptr::null(),
+                src_access_mask: access_masks.0,
+                dst_access_mask: access_masks.1,
+                src_queue_family_index: self.src_queue_family_index_,
+                dst_queue_family_index: self.dst_queue_family_index_,
+                buffer: *buffer,
+                offset: 0,
+                size: vk::WHOLE_SIZE,
+            })
+            .collect();
+
+        let image_memory_barriers: Vec<vk::ImageMemoryBarrier> = images
+            .iter()
+            .map(|image| vk::ImageMemoryBarrier {
+                s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+                p_next: ptr::null(),
+                src_access_mask: access_masks.0,
+                dst_access_mask: access_masks.1,
+                old_layout: vk::ImageLayout::GENERAL,
+                new_layout: vk::ImageLayout::GENERAL,
+                src_queue_family_index: self.src_queue_family_index_,
+                dst_queue_family_index: self.dst_queue_family_index_,
+                image: *image,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+            })
+            .collect();
+
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                stage_masks.0,
+                stage_masks.1,
+                vk::DependencyFlags::empty(),
+                &[],
+                &buffer_memory_barriers,
+                &image_memory_barriers,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    #[test]
+    fn reverse_ownership_transfer_test() {
+        let to_graphics = super::OwnershipTransfer {
+            src_queue_family_index_: 1,
+            dst_queue_family_index_: 0,
+            src_stage_mask_: vk::PipelineStageFlags::COMPUTE_SHADER,
+            src_access_mask_: vk::AccessFlags::SHADER_WRITE,
+            dst_stage_mask_: vk::PipelineStageFlags::VERTEX_INPUT,
+            dst_access_mask_: vk::AccessFlags::VERTEX_ATTRIBUTE_READ,
+        };
+
+        let to_compute = to_graphics.reversed();
+
+        assert_eq!(to_compute.src_queue_family_index_, 0);
+        assert_eq!(to_compute.dst_queue_family_index_, 1);
+        assert_eq!(
+            to_compute.src_stage_mask_,
+            vk::PipelineStageFlags::VERTEX_INPUT
+        );
+        assert_eq!(
+            to_compute.dst_stage_mask_,
+            vk::PipelineStageFlags::COMPUTE_SHADER
+        );
+        assert_eq!(to_compute.dst_access_mask_, vk::AccessFlags::SHADER_WRITE);
+    }
+}
@@ -0,0 +1,230 @@
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use ash::Device;
+
+use crate::vulkan::buffer::Buffer;
+use crate::vulkan::physical_device::PhysicalDevice;
+
+/// A buffer compute shaders can read and write, that can also be used as a vertex, index or
+/// indirect buffer by graphics pipelines. The buffer is host visible so that it can be filled and
+/// read back without staging
+pub struct StorageBuffer {
+    pub buffer_: Buffer,
+    pub size_: vk::DeviceSize,
+}
+
+impl StorageBuffer {
+    pub fn new(
+        instance: &ash::Instance,
+        device: &Device,
+        physical_device: &PhysicalDevice,
+        size: vk::DeviceSize,
+    ) -> StorageBuffer {
+        let buffer = Buffer::new(
+            instance,
+            device,
+            physical_device,
+            size,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::VERTEX_BUFFER
+                | vk::BufferUsageFlags::INDEX_BUFFER
+                | vk::BufferUsageFlags::INDIRECT_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_SRC
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        );
+
+        StorageBuffer {
+            buffer_: buffer,
+            size_: size,
+        }
+    }
+
+    /// Copies data into the buffer at the given byte offset. The buffer must not be in use by the
+    /// device. Panics if the data does not fit
+    pub fn write(&mut self, device: &Device, offset: vk::DeviceSize, data: &[u8]) {
+        if offset + data.len() as vk::DeviceSize > self.size_ {
+            panic!(
+                "Writing {} bytes at offset {} overflows a storage buffer of {} bytes",
+                data.len(),
+                offset,
+                self.size_
+            );
+        }
+
+        unsafe {
+            let mapped = self
+                .buffer_
+                .map(device, offset, data.len() as vk::DeviceSize);
+            ptr::copy_nonoverlapping(data.as_ptr(), mapped as *mut u8, data.len());
+            self.buffer_.unmap(device);
+        }
+    }
+
+    /// Returns a copy of the buffer's contents. The buffer must not be in use by the device
+    pub fn read(&mut self, device: &Device) -> Vec<u8> {
+        let mut data = vec![0u8; self.size_ as usize];
+
+        unsafe {
+            let mapped = self.buffer_.map(device, 0, self.size_);
+            ptr::copy_nonoverlapping(mapped as *const u8, data.as_mut_ptr(), data.len());
+            self.buffer_.unmap(device);
+        }
+
+        data
+    }
+}
+
+/// A 2D image compute shaders can read and write, that graphics pipelines can also sample. The
+/// image is kept in the GENERAL layout
+pub struct StorageImage {
+    pub image_: vk::Image,
+    pub image_view_: vk::ImageView,
+    device_memory_: vk::DeviceMemory,
+}
+
+impl StorageImage {
+    /// Creates the image in the UNDEFINED layout. It must be transitioned to GENERAL with
+    /// record_initial_transition before use
+    pub fn new(
+        instance: &ash::Instance,
+        device: &Device,
+        physical_device: &PhysicalDevice,
+        extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> StorageImage {
+        let image_create_info = vk::ImageCreateInfo {
+            s_type: vk::StructureType::IMAGE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::ImageCreateFlags::empty(),
+            image_type: vk::ImageType::TYPE_2D,
+            format,
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: vk::SampleCountFlags::TYPE_1,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_SRC
+                | vk::ImageUsageFlags::TRANSFER_DST,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 0,
+            p_queue_family_indices: ptr::null(),
+            initial_layout: vk::ImageLayout::UNDEFINED,
+        };
+
+        let image = unsafe {
+            device
+                .create_image(&image_create_info, None)
+                .expect("Could not create storage image")
+        };
+
+        let memory_requirements = unsafe { device.get_image_memory_requirements(image) };
+
+        let memory_allocate_info = vk::MemoryAllocateInfo {
+            s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
+            p_next: ptr::null(),
+            allocation_size: memory_requirements.size,
+            memory_type_index: physical_device
+                .get_suitable_memory_type_index(
+                    instance,
+                    memory_requirements.memory_type_bits,
+                    vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                )
+                .expect("Could not find suitable memory type for storage image"),
+        };
+
+        let device_memory = unsafe {
+            device
+                .allocate_memory(&memory_allocate_info, None)
+                .expect("Could not allocate memory for storage image")
+        };
+
+        unsafe {
+            device
+                .bind_image_memory(image, device_memory, 0)
+                .expect("Could not bind storage image memory");
+        }
+
+        let image_view_create_info = vk::ImageViewCreateInfo {
+            s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::ImageViewCreateFlags::empty(),
+            image,
+            view_type: vk::ImageViewType::TYPE_2D,
+            format,
+            components: vk::ComponentMapping {
+                r: vk::ComponentSwizzle::IDENTITY,
+                g: vk::ComponentSwizzle::IDENTITY,
+                b: vk::ComponentSwizzle::IDENTITY,
+                a: vk::ComponentSwizzle::IDENTITY,
+            },
+            subresource_range: StorageImage::subresource_range(),
+        };
+
+        let image_view = unsafe {
+            device
+                .create_image_view(&image_view_create_info, None)
+                .expect("Could not create storage image view")
+        };
+
+        StorageImage {
+            image_: image,
+            image_view_: image_view,
+            device_memory_: device_memory,
+        }
+    }
+
+    /// Records the transition of a newly created image into the GENERAL layout
+    pub fn record_initial_transition(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        let image_memory_barrier = vk::ImageMemoryBarrier {
+            s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+            p_next: ptr::null(),
+            src_access_mask: vk::AccessFlags::empty(),
+            dst_access_mask: vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::GENERAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: self.image_,
+            subresource_range: StorageImage::subresource_range(),
+        };
+
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[image_memory_barrier],
+            );
+        }
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_image_view(self.image_view_, None);
+            device.destroy_image(self.image_, None);
+            device.free_memory(self.device_memory_, None);
+        }
+    }
+
+    fn subresource_range() -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk::{
+    Buffer, DescriptorBufferInfo, DescriptorImageInfo, DescriptorPool, DescriptorPoolCreateFlags,
+    DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSet, DescriptorSetAllocateInfo,
+    DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorType, DeviceSize, ImageLayout,
+    ImageView, Sampler, StructureType, WriteDescriptorSet,
+};
+use ash::Device;
+
+/// Creates a descriptor pool large enough for the given number of copies of each of the given
+/// sets. Returns a null pool if the sets have no bindings, as a pool cannot be created without
+/// sizes
+///
+/// # Arguments
+///
+/// * 'device' - the logical device the pool is created on
+///
+/// * 'bindings' - bindings of each set, as returned by descriptor_set_layout::merge_bindings
+///
+/// * 'num_copies' - how many times each set will be allocated from the pool
+///
+pub fn create_pool(
+    device: &Device,
+    bindings: &[Vec<DescriptorSetLayoutBinding>],
+    num_copies: u32,
+) -> DescriptorPool {
+    let mut pool_sizes: Vec<DescriptorPoolSize> = vec![];
+
+    for binding in bindings.iter().flatten() {
+        match pool_sizes
+            .iter_mut()
+            .find(|pool_size| pool_size.ty == binding.descriptor_type)
+        {
+            Some(pool_size) => pool_size.descriptor_count += binding.descriptor_count * num_copies,
+            None => pool_sizes.push(DescriptorPoolSize {
+                ty: binding.descriptor_type,
+                descriptor_count: binding.descriptor_count * num_copies,
+            }),
+        }
+    }
+
+    if pool_sizes.is_empty() {
+        return DescriptorPool::null();
+    }
+
+    let descriptor_pool_create_info = DescriptorPoolCreateInfo {
+        s_type: StructureType::DESCRIPTOR_POOL_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: DescriptorPoolCreateFlags::empty(),
+        max_sets: bindings.len() as u32 * num_copies,
+        pool_size_count: pool_sizes.len() as u32,
+        p_pool_sizes: pool_sizes.as_ptr(),
+    };
+
+    unsafe {
+        device
+            .create_descriptor_pool(&descriptor_pool_create_info, None)
+            .expect("Could not create descriptor pool")
+    }
+}
+
+/// Allocates one descriptor set for each of the given layouts. Returns no sets if the pool is null
+///
+/// # Arguments
+///
+/// * 'device' - the logical device the pool was created on
+///
+/// * 'descriptor_pool' - the pool to allocate from
+///
+/// * 'layouts' - the layout of each allocated set
+///
+pub fn allocate_sets(
+    device: &Device,
+    descriptor_pool: DescriptorPool,
+    layouts: &[DescriptorSetLayout],
+) -> Vec<DescriptorSet> {
+    if descriptor_pool == DescriptorPool::null() || layouts.is_empty() {
+        return vec![];
+    }
+
+    let descriptor_set_allocate_info = DescriptorSetAllocateInfo {
+        s_type: StructureType::DESCRIPTOR_SET_ALLOCATE_INFO,
+        p_next: ptr::null(),
+        descriptor_pool,
+        descriptor_set_count: layouts.len() as u32,
+        p_set_layouts: layouts.as_ptr(),
+    };
+
+    unsafe {
+        device
+            .allocate_descriptor_sets(&descriptor_set_allocate_info)
+            .expect("Could not allocate descriptor sets")
+    }
+}
+
+/// Points a buffer binding of a descriptor set, such as a uniform or storage buffer, at a range of
+/// a buffer. The descriptor set must not be in use by any pending command buffer
+///
+/// # Arguments
+///
+/// * 'device' - the logical device the descriptor set was allocated from
+///
+/// * 'descriptor_set' - the descriptor set to update
+///
+/// * 'binding' - the binding within the descriptor set
+///
+/// * 'descriptor_type' - the type the binding was declared with
+///
+/// * 'buffer' - the buffer to bind
+///
+/// * 'offset' - byte offset of the bound range within the buffer
+///
+/// * 'range' - byte size of the bound range, or WHOLE_SIZE
+///
+pub fn write_buffer(
+    device: &Device,
+    descriptor_set: DescriptorSet,
+    binding: u32,
+    descriptor_type: DescriptorType,
+    buffer: Buffer,
+    offset: DeviceSize,
+    range: DeviceSize,
+) {
+    let buffer_info = DescriptorBufferInfo {
+        buffer,
+        offset,
+        range,
+    };
+
+    let write_descriptor_set = WriteDescriptorSet {
+        s_type: StructureType::WRITE_DESCRIPTOR_SET,
+        p_next: ptr::null(),
+        dst_set: descriptor_set,
+        dst_binding: binding,
+        dst_array_element: 0,
+        descriptor_count: 1,
+        descriptor_type,
+        p_image_info: ptr::null(),
+        p_buffer_info: &buffer_info,
+        p_texel_buffer_view: ptr::null(),
+    };
+
+    unsafe { device.update_descriptor_sets(&[write_descriptor_set], &[]) };
+}
+
+/// Points an image binding of a descriptor set, such as a storage image or combined image
+/// sampler, at an image view. The descriptor set must not be in use by any pending command buffer
+///
+/// # Arguments
+///
+/// * 'device' - the logical device the descriptor set was allocated from
+///
+/// * 'descriptor_set' - the descriptor set to update
+///
+/// * 'binding' - the binding within the descriptor set
+///
+/// * 'descriptor_type' - the type the binding was declared with
+///
+/// * 'image_view' - the image view to bind
+///
+/// * 'sampler' - the sampler to bind, or a null sampler for bindings without one
+///
+/// * 'image_layout' - the layout the image will be in when it is accessed through the binding
+///
+pub fn write_image(
+    device: &Device,
+    descriptor_set: DescriptorSet,
+    binding: u32,
+    descriptor_type: DescriptorType,
+    image_view: ImageView,
+    sampler: Sampler,
+    image_layout: ImageLayout,
+) {
+    let image_info = DescriptorImageInfo {
+        sampler,
+        image_view,
+        image_layout,
+    };
+
+    let write_descriptor_set = WriteDescriptorSet {
+        s_type: StructureType::WRITE_DESCRIPTOR_SET,
+        p_next: ptr::null(),
+        dst_set: descriptor_set,
+        dst_binding: binding,
+        dst_array_element: 0,
+        descriptor_count: 1,
+        descriptor_type,
+        p_image_info: &image_info,
+        p_buffer_info: ptr::null(),
+        p_texel_buffer_view: ptr::null(),
+    };
+
+    unsafe { device.update_descriptor_sets(&[write_descriptor_set], &[]) };
+}
+
+/// Points the elements of an array of image bindings, such as an array of combined image
+/// samplers, at image views. Element i is bound to the i-th view. The descriptor set must not be
+/// in use by any pending command buffer
+///
+/// # Arguments
+///
+/// * 'device' - the logical device the descriptor set was allocated from
+///
+/// * 'descriptor_set' - the descriptor set to update
+///
+/// * 'binding' - the binding within the descriptor set
+///
+/// * 'descriptor_type' - the type the binding was declared with
+///
+/// * 'image_views' - the image view to bind to each array element
+///
+/// * 'sampler' - the sampler to bind to every element, or a null sampler for bindings without one
+///
+/// * 'image_layout' - the layout the images will be in when they are accessed through the binding
+///
+pub fn write_images(
+    device: &Device,
+    descriptor_set: DescriptorSet,
+    binding: u32,
+    descriptor_type: DescriptorType,
+    image_views: &[ImageView],
+    sampler: Sampler,
+    image_layout: ImageLayout,
+) {
+    let image_infos: Vec<DescriptorImageInfo> = image_views
+        .iter()
+        .map(|image_view| DescriptorImageInfo {
+            sampler,
+            image_view: *image_view,
+            image_layout,
+        })
+        .collect();
+
+    let write_descriptor_set = WriteDescriptorSet {
+        s_type: StructureType::WRITE_DESCRIPTOR_SET,
+        p_next: ptr::null(),
+        dst_set: descriptor_set,
+        dst_binding: binding,
+        dst_array_element: 0,
+        descriptor_count: image_infos.len() as u32,
+        descriptor_type,
+        p_image_info: image_infos.as_ptr(),
+        p_buffer_info: ptr::null(),
+        p_texel_buffer_view: ptr::null(),
+    };
+
+    unsafe { device.update_descriptor_sets(&[write_descriptor_set], &[]) };
+}
@@ -8,6 +8,7 @@ use std::collections::HashMap;
 use std::os::raw::c_void;
 
 use ash::version::DeviceV1_0;
+use cgmath::Point3;
 
 const MAX_VERTICES: u64 = 10000;
 const MAX_INDICES: u64 = 1000000;
@@ -17,6 +18,8 @@ pub struct MeshLocation {
     pub index_offset_: usize,
     pub vertex_count_: usize,
     pub index_count_: usize,
+    /// The minimum and maximum corners of the mesh's bounding box, in the mesh's space
+    pub bounds_: (Point3<f32>, Point3<f32>),
 }
 
 pub struct GeometryManager {
@@ -108,6 +111,7 @@ impl GeometryManager {
                     index_offset_: self.indices_.len(),
                     vertex_count_: mesh.vertices_.len(),
                     index_count_: mesh.indices_.len(),
+                    bounds_: mesh.get_bounds(),
                 },
             );
 
@@ -128,6 +132,7 @@ impl GeometryManager {
                 index_offset_: self.indices_.len(),
                 vertex_count_: mesh.vertices_.len(),
                 index_count_: mesh.indices_.len(),
+                bounds_: mesh.get_bounds(),
             },
         );
 
@@ -24,6 +24,24 @@ impl LogicalDevice {
             .queue_family_indices_
             .make_device_queue_create_infos();
 
+        // Wireframe and point polygon modes need fill_mode_non_solid, multisampled renderers may
+        // shade per sample with sample_rate_shading, renderers may count shader invocations with
+        // pipeline_statistics_query, and pipelines may blend color outputs differently with
+        // independent_blend and clamp their depth bias with depth_bias_clamp. Enable each where
+        // supported
+        let supported_features = unsafe {
+            instance.get_physical_device_features(physical_device.physical_device_handle_)
+        };
+
+        let enabled_features = vk::PhysicalDeviceFeatures {
+            fill_mode_non_solid: supported_features.fill_mode_non_solid,
+            sample_rate_shading: supported_features.sample_rate_shading,
+            pipeline_statistics_query: supported_features.pipeline_statistics_query,
+            independent_blend: supported_features.independent_blend,
+            depth_bias_clamp: supported_features.depth_bias_clamp,
+            ..Default::default()
+        };
+
         let device_create_info = vk::DeviceCreateInfo {
             s_type: vk::StructureType::DEVICE_CREATE_INFO,
             p_next: ptr::null(),
@@ -35,7 +53,7 @@ impl LogicalDevice {
             pp_enabled_layer_names: *validation_layers,
             enabled_extension_count: *num_extension_names,
             pp_enabled_extension_names: *extension_names,
-            p_enabled_features: ptr::null(),
+            p_enabled_features: &enabled_features,
         };
 
         let device = unsafe {
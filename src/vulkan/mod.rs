@@ -3,8 +3,10 @@ use std::ffi::CString;
 use std::os::raw::c_char;
 use std::os::raw::c_ulong;
 use std::os::raw::c_void;
+use std::path::PathBuf;
 use std::ptr;
 
+use ash::version::DeviceV1_0;
 use ash::version::EntryV1_0;
 use ash::vk;
 use ash::vk::make_version;
@@ -12,25 +14,51 @@ use ash::vk::make_version;
 use render_pass::pipeline;
 use utility::debug;
 
+use crate::render_sequence::builtin;
+use crate::render_sequence::material::{MaterialDescription, MaterialID, MaterialValue};
+use crate::render_sequence::post_process::PostProcessChain;
+use crate::render_sequence::reflection_utils::format::Format;
+use crate::render_sequence::render_pass::pipeline::shader::descriptor::DescriptorType;
+use crate::render_sequence::render_pass::pipeline::shader::Shader;
+use crate::render_sequence::render_pass::RenderPassDescription;
+use crate::vulkan::compute_manager::ComputeManager;
 use crate::vulkan::geometry_manager::GeometryManager;
 use crate::vulkan::logical_device::LogicalDevice;
+use crate::vulkan::pipeline_cache::PipelineCache;
+use crate::vulkan::renderer::light_culling::ClusterGrid;
+use crate::vulkan::renderer::profiler::FrameTimings;
 use crate::vulkan::renderer::Renderer;
+use crate::vulkan::texture_manager::TextureManager;
 use crate::vulkan::utility::debug::build_debug_messenger_create_info;
 use crate::vulkan::utility::platforms::required_extension_names;
 
 use super::geometry;
+use super::geometry::debug_draw::DebugDraw;
+use super::geometry::image::hdr::HdrImageData;
+use super::geometry::image::texture_data::{TextureData, TextureFormat};
+use super::geometry::image::ImageData;
 use super::geometry::palette::Palette;
+use super::geometry::sprite::SpriteBatch;
+use super::geometry::text::FontAtlas;
+use super::geometry::viewport::Viewport;
 
 mod buffer;
 mod command;
+mod compute_manager;
+mod descriptor;
 mod geometry_manager;
 mod logical_device;
 mod physical_device;
+mod pipeline_cache;
 mod render_pass;
 pub mod renderer;
 mod surface;
+mod texture_manager;
 mod utility;
 
+pub use compute_manager::{ComputeDispatch, ComputePipelineID, StorageBufferID, StorageImageID};
+pub use texture_manager::TextureID;
+
 pub type RendererID = u64;
 
 /// Used to configure a vulkan::Engine on Engine creation
@@ -43,6 +71,10 @@ pub struct EngineCreateInfo {
     pub validation_layers_: *const *const c_char,
     pub num_device_extension_names_: u32,
     pub device_extension_names_: *const *const c_char,
+    /// Optional file compiled pipelines are cached in between runs. The cache is loaded when the
+    /// Engine is created, if it was written by the same device and driver, and saved when the
+    /// Engine is dropped or save_pipeline_cache is called
+    pub pipeline_cache_path_: Option<PathBuf>,
 }
 
 /// Manages all aspects of the vulkan context, and contains functionality for rendering to multiple
@@ -60,6 +92,14 @@ pub struct Engine {
     /// GeometryManager handles loading geometry to the GPU. Geometry is global to each renderer so
     /// is stored at the engine level
     geometry_manager_: GeometryManager,
+    /// ComputeManager owns compute pipelines and the storage resources they read and write, and
+    /// submits compute work ahead of each frame's rendering
+    compute_manager_: ComputeManager,
+    /// TextureManager owns the textures renderers sample. Textures are shared by all renderers so
+    /// are stored at the engine level
+    texture_manager_: TextureManager,
+    /// cache shared by all pipelines created by this engine, optionally persisted to disk
+    pipeline_cache_: PipelineCache,
     /// a collection of renderers, each representing a single window, containing the functionality
     /// to render to that window
     renderers_: HashMap<RendererID, Renderer>,
@@ -112,12 +152,30 @@ impl Engine {
             physical_devices.first().unwrap(),
         );
 
+        // Create a ComputeManager to handle compute pipelines and dispatches
+        let compute_manager =
+            ComputeManager::new(&logical_device.device_, physical_devices.first().unwrap());
+
+        // Create a TextureManager to upload and own textures
+        let texture_manager =
+            TextureManager::new(&logical_device.device_, physical_devices.first().unwrap());
+
+        // Create the pipeline cache, loading previously compiled pipelines if a cache file exists
+        let pipeline_cache = PipelineCache::new(
+            &logical_device.device_,
+            physical_devices.first().unwrap(),
+            create_info.pipeline_cache_path_.as_deref(),
+        );
+
         Engine {
             entry_: entry,
             instance_: instance,
             physical_devices_: physical_devices,
             logical_device_: logical_device,
             geometry_manager_: geometry_manager,
+            compute_manager_: compute_manager,
+            texture_manager_: texture_manager,
+            pipeline_cache_: pipeline_cache,
             renderers_: HashMap::new(),
             curr_renderer_id_: 0,
             debug_: debug,
@@ -166,6 +224,773 @@ impl Engine {
     pub fn get_renderer(&self, renderer_id: RendererID) -> &Renderer {
         self.renderers_.get(&renderer_id).expect("No such renderer")
     }
+
+    /// Returns the debug lines the given renderer draws over its next frame, to add lines, boxes,
+    /// spheres, axes and frustums to. Panics if there is no such renderer
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - The renderer id associated with the renderer to draw with
+    ///
+    pub fn debug_draw(&mut self, renderer_id: RendererID) -> &mut DebugDraw {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .get_mut_debug_draw()
+    }
+
+    /// Returns the sprites the given renderer draws over its next frame, after its scene and debug
+    /// lines. Panics if there is no such renderer
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - The renderer id associated with the renderer to draw with
+    ///
+    pub fn sprite_batch(&mut self, renderer_id: RendererID) -> &mut SpriteBatch {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .get_mut_sprite_batch()
+    }
+
+    /// Returns the GPU time taken by each pass of the given renderer's most recent frame whose
+    /// timings are available. Panics if there is no such renderer
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - The renderer id associated with the renderer to query
+    ///
+    pub fn last_frame_timings(&self, renderer_id: RendererID) -> &FrameTimings {
+        self.get_renderer(renderer_id).last_frame_timings()
+    }
+
+    /// Enables or disables counting the vertex and fragment shader invocations of each pass of the
+    /// given renderer's frames, reported with their timings. Panics if there is no such renderer
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - the renderer to change
+    ///
+    /// * 'enabled' - whether to count shader invocations
+    ///
+    pub fn set_pipeline_statistics(&mut self, renderer_id: RendererID, enabled: bool) {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .set_pipeline_statistics(enabled);
+    }
+
+    /// Enables or disables skipping instances the given renderer found hidden behind its scene in
+    /// previous frames. Panics if there is no such renderer
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - the renderer to change
+    ///
+    /// * 'enabled' - whether to cull occluded instances
+    ///
+    pub fn set_occlusion_culling(&mut self, renderer_id: RendererID, enabled: bool) {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .set_occlusion_culling(enabled);
+    }
+
+    /// Builds the render pass described by a RenderPassDescription and makes it the render pass
+    /// used by the given renderer. Panics if there is no such renderer
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - The renderer id associated with the renderer to configure
+    ///
+    /// * 'description' - reflected description of the render pass and its pipelines
+    ///
+    pub fn set_render_pass(&mut self, renderer_id: RendererID, description: &RenderPassDescription) {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .set_render_pass(
+                &self.logical_device_.device_,
+                description,
+                self.pipeline_cache_.cache_handle_,
+            );
+    }
+
+    /// Enables or disables shader hot reload for the given renderer. While enabled, the renderer's
+    /// pipelines are rebuilt whenever one of their GLSL sources or includes changes. Panics if
+    /// there is no such renderer
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - The renderer id associated with the renderer to configure
+    ///
+    /// * 'enabled' - whether shader sources should be watched
+    ///
+    pub fn set_shader_hot_reload(&mut self, renderer_id: RendererID, enabled: bool) {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .set_shader_hot_reload(enabled);
+    }
+
+    /// Writes all pipelines compiled so far to the pipeline cache file given in EngineCreateInfo.
+    /// Does nothing if no cache file was given
+    pub fn save_pipeline_cache(&self) -> std::io::Result<()> {
+        self.pipeline_cache_.save(&self.logical_device_.device_)
+    }
+
+    /// Sets the number of samples per pixel the given renderer renders with, clamped to what the
+    /// device supports, and rebuilds its render pass. Returns the sample count in use. Panics if
+    /// there is no such renderer
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - the renderer to multisample
+    ///
+    /// * 'samples' - the requested number of samples per pixel, 1 disables multisampling
+    ///
+    pub fn set_msaa(&mut self, renderer_id: RendererID, samples: u32) -> u32 {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .set_sample_count(&self.logical_device_.device_, samples)
+    }
+
+    /// Enables or disables sample rate shading for the given renderer. Panics if there is no such
+    /// renderer
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - the renderer to change
+    ///
+    /// * 'min_sample_shading' - the minimum fraction of samples shaded individually, or None to
+    ///   shade each pixel once
+    ///
+    pub fn set_sample_shading(&mut self, renderer_id: RendererID, min_sample_shading: Option<f32>) {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .set_sample_shading(&self.logical_device_.device_, min_sample_shading);
+    }
+
+    /// Sets the post-processing chain of the given renderer, or removes it with None. Panics if
+    /// there is no such renderer or a pass of the chain cannot be built
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - the renderer to post-process
+    ///
+    /// * 'chain' - the effects applied to the renderer's HDR scene image, in order
+    ///
+    pub fn set_post_process(&mut self, renderer_id: RendererID, chain: Option<&PostProcessChain>) {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .set_post_process(
+                &self.instance_,
+                &self.logical_device_.device_,
+                self.physical_devices_.first().unwrap(),
+                chain,
+            );
+    }
+
+    /// Changes how the given renderer splits the first camera's view frustum into clusters when
+    /// assigning lights to them. Finer grids cull more lights from each fragment but take longer to
+    /// cull. Panics if there is no such renderer
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - the renderer to configure
+    ///
+    /// * 'grid' - the number of clusters across, down and in depth and the lights each can hold
+    ///
+    pub fn set_light_clusters(&mut self, renderer_id: RendererID, grid: ClusterGrid) {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .set_light_clusters(
+                &self.instance_,
+                &self.logical_device_.device_,
+                self.physical_devices_.first().unwrap(),
+                grid,
+            );
+    }
+
+    /// Restricts the given renderer to drawing into a rectangle of its window. Panics if there is
+    /// no such renderer
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - The renderer id associated with the renderer to configure
+    ///
+    /// * 'viewport' - the rectangle to draw to, as fractions of the window size
+    ///
+    pub fn set_viewport(&mut self, renderer_id: RendererID, viewport: Viewport) {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .set_viewport(viewport);
+    }
+
+    /// Loads a texture that renderers can sample. PNG and JPEG files get a generated mip chain,
+    /// while KTX2 and DDS files are loaded with the mip levels and array layers they contain, in
+    /// their block-compressed format if the device supports it and decoded otherwise. Returns a
+    /// handle identifying the texture. Panics if the file cannot be read, decoded or sampled
+    ///
+    /// # Arguments
+    ///
+    /// * 'path' - path of the image file
+    ///
+    /// * 'srgb' - whether the image holds sRGB encoded colors rather than linear data such as
+    ///   normals or roughness. KTX2 and DDS files with an sRGB format are always sRGB
+    ///
+    pub fn load_texture(&mut self, path: &str, srgb: bool) -> TextureID {
+        let path = std::path::Path::new(path);
+
+        if TextureData::is_texture_file(path) {
+            let texture_data = TextureData::from_file(path)
+                .unwrap_or_else(|error| panic!("Could not load texture: {}", error));
+
+            return self
+                .texture_manager_
+                .create_texture_from_data(
+                    &self.instance_,
+                    &self.logical_device_.device_,
+                    self.physical_devices_.first().unwrap(),
+                    &texture_data,
+                    srgb,
+                )
+                .unwrap_or_else(|error| {
+                    panic!("Could not load texture {}: {}", path.display(), error)
+                });
+        }
+
+        let image = ImageData::from_file(path)
+            .unwrap_or_else(|error| panic!("Could not load texture: {}", error));
+
+        self.texture_manager_.create_texture(
+            &self.instance_,
+            &self.logical_device_.device_,
+            self.physical_devices_.first().unwrap(),
+            &image,
+            srgb,
+        )
+    }
+
+    /// Creates a texture from raw RGBA8 pixels. Returns a handle identifying the texture. Panics
+    /// if there are not width * height * 4 bytes
+    ///
+    /// # Arguments
+    ///
+    /// * 'width' - width of the texture in pixels
+    ///
+    /// * 'height' - height of the texture in pixels
+    ///
+    /// * 'pixels' - the pixels, rows ordered top to bottom
+    ///
+    /// * 'srgb' - whether the pixels are sRGB encoded colors rather than linear data
+    ///
+    pub fn create_texture(&mut self, width: u32, height: u32, pixels: &[u8], srgb: bool) -> TextureID {
+        let image = ImageData::from_rgba(width, height, pixels.to_vec());
+
+        self.texture_manager_.create_texture(
+            &self.instance_,
+            &self.logical_device_.device_,
+            self.physical_devices_.first().unwrap(),
+            &image,
+            srgb,
+        )
+    }
+
+    /// Uploads the image of a font atlas as a texture, which text drawn with the atlas samples.
+    /// Returns a handle identifying the texture
+    ///
+    /// # Arguments
+    ///
+    /// * 'atlas' - the font atlas, which is given the texture
+    ///
+    pub fn upload_font_atlas(&mut self, atlas: &mut FontAtlas) -> TextureID {
+        let texture_id = self.texture_manager_.create_texture(
+            &self.instance_,
+            &self.logical_device_.device_,
+            self.physical_devices_.first().unwrap(),
+            atlas.get_image(),
+            false,
+        );
+
+        atlas.set_texture(texture_id);
+        texture_id
+    }
+
+    /// Loads a cube map to set as a scene's environment_, which is drawn as a skybox and can be
+    /// given to materials for reflections. Radiance HDR files hold an equirectangular map, which
+    /// is converted to a cube map whose faces are a quarter as wide. KTX2 and DDS files must hold
+    /// a cube map, and PNG and JPEG images lay the faces out as a cross, as read by
+    /// TextureData::from_cross. Returns a handle identifying the texture. Panics if the file
+    /// cannot be read or decoded, or does not hold a cube map
+    ///
+    /// # Arguments
+    ///
+    /// * 'path' - path of the environment file
+    ///
+    pub fn load_environment(&mut self, path: &str) -> TextureID {
+        let path = std::path::Path::new(path);
+
+        let is_hdr = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("hdr"));
+
+        if is_hdr {
+            let image = HdrImageData::from_file(path)
+                .unwrap_or_else(|error| panic!("Could not load environment: {}", error));
+
+            return self.create_environment_from_equirectangular(&image, (image.width_ / 4).max(1));
+        }
+
+        let texture_data = match TextureData::is_texture_file(path) {
+            true => TextureData::from_file(path),
+            false => ImageData::from_file(path).and_then(|image| TextureData::from_cross(&image)),
+        }
+        .unwrap_or_else(|error| panic!("Could not load environment: {}", error));
+
+        if !texture_data.cube_ {
+            panic!(
+                "Could not load environment {}: not a cube map",
+                path.display()
+            );
+        }
+
+        self.create_environment(&texture_data)
+    }
+
+    /// Loads a cube map to set as a scene's environment_ from six square PNG or JPEG images of
+    /// equal size. Returns a handle identifying the texture. Panics if an image cannot be read or
+    /// decoded, or the sizes differ
+    ///
+    /// # Arguments
+    ///
+    /// * 'paths' - paths of the +X, -X, +Y, -Y, +Z and -Z faces
+    ///
+    pub fn load_environment_faces(&mut self, paths: &[&str]) -> TextureID {
+        let faces = paths
+            .iter()
+            .map(|path| ImageData::from_file(std::path::Path::new(path)))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|faces| TextureData::from_cube_faces(&faces))
+            .unwrap_or_else(|error| panic!("Could not load environment: {}", error));
+
+        self.create_environment(&faces)
+    }
+
+    /// Converts an equirectangular map into a half float cube map with a compute pass, to set as
+    /// a scene's environment_. The faces are read back and uploaded as a texture with a generated
+    /// mip chain. Returns a handle identifying the texture
+    ///
+    /// # Arguments
+    ///
+    /// * 'image' - the equirectangular map, with +Y at the top row and -Z at the middle column
+    ///
+    /// * 'face_size' - width and height of each face in pixels
+    ///
+    pub fn create_environment_from_equirectangular(&mut self, image: &HdrImageData, face_size: u32) -> TextureID {
+        let mut equirect = Vec::with_capacity(image.pixels_.len() / 3 * 16);
+
+        for rgb in image.pixels_.chunks_exact(3) {
+            for channel in [rgb[0], rgb[1], rgb[2], 1.0].iter() {
+                equirect.extend_from_slice(&channel.to_le_bytes());
+            }
+        }
+
+        // Each face texel is written as four half floats
+        let cube_size = 6 * face_size as u64 * face_size as u64 * 8;
+
+        let pipeline_id = self.create_compute_pipeline(&builtin::equirect_to_cube());
+        let equirect_buffer = self.create_storage_buffer(equirect.len() as u64);
+        let cube_buffer = self.create_storage_buffer(cube_size);
+
+        self.write_storage_buffer(equirect_buffer, 0, &equirect);
+        self.bind_storage_buffer(pipeline_id, 0, 0, equirect_buffer);
+        self.bind_storage_buffer(pipeline_id, 0, 1, cube_buffer);
+
+        let push_constants = [image.width_, image.height_, face_size]
+            .iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect();
+
+        self.dispatch(&ComputeDispatch {
+            pipeline_: pipeline_id,
+            group_count_: [face_size.div_ceil(8), face_size.div_ceil(8), 6],
+            push_constants_: push_constants,
+        });
+
+        let texels = self.read_storage_buffer(cube_buffer);
+
+        let device = &self.logical_device_.device_;
+        self.compute_manager_.destroy_pipeline(device, pipeline_id);
+        self.compute_manager_
+            .destroy_storage_buffer(device, equirect_buffer);
+        self.compute_manager_
+            .destroy_storage_buffer(device, cube_buffer);
+
+        self.create_environment(&TextureData {
+            format_: TextureFormat::Rgba16Float,
+            srgb_: false,
+            width_: face_size,
+            height_: face_size,
+            layers_: 6,
+            cube_: true,
+            levels_: vec![texels],
+            generate_mipmaps_: true,
+        })
+    }
+
+    /// Creates the texture of a cube map environment. Colors of 8 bit images are sRGB encoded
+    fn create_environment(&mut self, texture_data: &TextureData) -> TextureID {
+        self.texture_manager_
+            .create_texture_from_data(
+                &self.instance_,
+                &self.logical_device_.device_,
+                self.physical_devices_.first().unwrap(),
+                texture_data,
+                texture_data.format_ == TextureFormat::Rgba8,
+            )
+            .unwrap_or_else(|error| panic!("Could not create environment: {}", error))
+    }
+
+    /// Binds a texture to the combined image sampler at the given set and binding of every
+    /// pipeline of a renderer declaring one there. The binding is kept when the renderer's render
+    /// pass is rebuilt. Panics if the renderer or texture do not exist, or the set is 0 or 1,
+    /// which hold instance uniforms and shadows
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - the renderer whose pipelines sample the texture
+    ///
+    /// * 'set' - descriptor set index of the sampler
+    ///
+    /// * 'binding' - binding index within the set
+    ///
+    /// * 'texture_id' - handle of the texture to bind
+    ///
+    pub fn bind_texture(&mut self, renderer_id: RendererID, set: u32, binding: u32, texture_id: TextureID) {
+        let texture = self.texture_manager_.get_texture(texture_id);
+
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .bind_texture(
+                &self.logical_device_.device_,
+                set,
+                binding,
+                texture.image_view_,
+                texture.sampler_,
+            );
+    }
+
+    /// Destroys a texture once the device has finished using it. Renderers the texture is bound to
+    /// must bind another texture in its place before drawing again. Panics if there is no such
+    /// texture
+    ///
+    /// # Arguments
+    ///
+    /// * 'texture_id' - handle of the texture to destroy
+    ///
+    pub fn destroy_texture(&mut self, texture_id: TextureID) {
+        unsafe {
+            self.logical_device_
+                .device_
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+        }
+
+        self.texture_manager_
+            .destroy_texture(&self.logical_device_.device_, texture_id);
+    }
+
+    /// Creates a material that instances of scenes drawn by a renderer can be drawn with in place
+    /// of the renderer's main pass pipeline, by setting their material_id_. The material's
+    /// pipeline is built for the first subpass of the renderer's render pass. Samplers of set
+    /// MATERIAL_SET without a texture parameter read a white texture. Returns a handle
+    /// identifying the material within that renderer. Panics if the renderer does not exist or
+    /// the pipeline cannot be built
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - the renderer drawing with the material
+    ///
+    /// * 'description' - the material's pipeline and parameter values
+    ///
+    pub fn create_material(&mut self, renderer_id: RendererID, description: &MaterialDescription) -> MaterialID {
+        let textures = self.get_material_textures(description);
+
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .create_material(
+                &self.instance_,
+                &self.logical_device_.device_,
+                self.physical_devices_.first().unwrap(),
+                description,
+                &textures,
+            )
+            .unwrap_or_else(|error| panic!("Could not build material: {}", error))
+    }
+
+    /// Sets a parameter of a material, replacing any earlier value, once the renderer has
+    /// finished drawing with it. Panics if the renderer or material do not exist
+    ///
+    /// # Arguments
+    ///
+    /// * 'renderer_id' - the renderer the material was created for
+    ///
+    /// * 'material_id' - handle of the material
+    ///
+    /// * 'name' - the name of the uniform block member or sampler the value is for
+    ///
+    /// * 'value' - the new value
+    ///
+    pub fn set_material_parameter(&mut self, renderer_id: RendererID, material_id: MaterialID, name: &str, value: MaterialValue) {
+        let mut description = self
+            .get_renderer(renderer_id)
+            .get_material_description(material_id)
+            .clone();
+        description.set_parameter(name, value);
+
+        let textures = self.get_material_textures(&description);
+
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .set_material_parameters(&self.logical_device_.device_, material_id, &description, &textures);
+    }
+
+    /// Destroys a material once the renderer has finished drawing with it. Instances must no
+    /// longer use it. Panics if the renderer or material do not exist
+    pub fn destroy_material(&mut self, renderer_id: RendererID, material_id: MaterialID) {
+        self.renderers_
+            .get_mut(&renderer_id)
+            .expect("No such renderer")
+            .destroy_material(&self.logical_device_.device_, material_id);
+    }
+
+    /// Returns the binding, image view and sampler of the texture every combined image sampler of
+    /// a material's set reads
+    fn get_material_textures(&mut self, description: &MaterialDescription) -> Vec<(u32, vk::ImageView, vk::Sampler)> {
+        let mut textures = vec![];
+
+        for descriptor in description
+            .get_descriptors()
+            .iter()
+            .filter(|descriptor| descriptor.descriptor_type_ == DescriptorType::CombinedImageSampler)
+        {
+            let texture_id = match description.get_texture(descriptor) {
+                Some(texture_id) => texture_id,
+                None if descriptor.cube_ => self.texture_manager_.get_default_cube_texture(
+                    &self.instance_,
+                    &self.logical_device_.device_,
+                    self.physical_devices_.first().unwrap(),
+                ),
+                None => self.texture_manager_.get_default_texture(
+                    &self.instance_,
+                    &self.logical_device_.device_,
+                    self.physical_devices_.first().unwrap(),
+                ),
+            };
+
+            let texture = self.texture_manager_.get_texture(texture_id);
+            textures.push((descriptor.binding_, texture.image_view_, texture.sampler_));
+        }
+
+        textures
+    }
+
+    /// Returns true if compute work runs on a dedicated compute queue, overlapping graphics work,
+    /// rather than on the graphics queue
+    pub fn has_async_compute(&self) -> bool {
+        self.compute_manager_.is_async()
+    }
+
+    /// Reflects a compute shader and builds a compute pipeline from it. Returns a handle
+    /// identifying the pipeline. Panics if the shader is not a compute shader
+    ///
+    /// # Arguments
+    ///
+    /// * 'shader' - the compute shader the pipeline runs
+    ///
+    pub fn create_compute_pipeline(&mut self, shader: &Shader) -> ComputePipelineID {
+        self.compute_manager_.create_pipeline(
+            &self.logical_device_.device_,
+            shader,
+            self.pipeline_cache_.cache_handle_,
+        )
+    }
+
+    /// Creates a host visible buffer that compute shaders can read and write, and that can also be
+    /// used as a vertex, index or indirect draw buffer. Returns a handle identifying the buffer
+    ///
+    /// # Arguments
+    ///
+    /// * 'size' - size of the buffer in bytes
+    ///
+    pub fn create_storage_buffer(&mut self, size: u64) -> StorageBufferID {
+        self.compute_manager_.create_storage_buffer(
+            &self.instance_,
+            &self.logical_device_.device_,
+            self.physical_devices_.first().unwrap(),
+            size,
+        )
+    }
+
+    /// Copies data into a storage buffer. Waits for the device to become idle first. Panics if
+    /// there is no such buffer or the data does not fit
+    ///
+    /// # Arguments
+    ///
+    /// * 'storage_buffer_id' - handle of the buffer to write
+    ///
+    /// * 'offset' - byte offset into the buffer to write at
+    ///
+    /// * 'data' - bytes to write
+    ///
+    pub fn write_storage_buffer(&mut self, storage_buffer_id: StorageBufferID, offset: u64, data: &[u8]) {
+        self.compute_manager_.write_storage_buffer(
+            &self.logical_device_.device_,
+            storage_buffer_id,
+            offset,
+            data,
+        );
+    }
+
+    /// Returns the contents of a storage buffer. Waits for the device to become idle first. Panics
+    /// if there is no such buffer
+    ///
+    /// # Arguments
+    ///
+    /// * 'storage_buffer_id' - handle of the buffer to read
+    ///
+    pub fn read_storage_buffer(&mut self, storage_buffer_id: StorageBufferID) -> Vec<u8> {
+        self.compute_manager_
+            .read_storage_buffer(&self.logical_device_.device_, storage_buffer_id)
+    }
+
+    /// Creates an image that compute shaders can read and write, kept in the GENERAL layout.
+    /// Returns a handle identifying the image
+    ///
+    /// # Arguments
+    ///
+    /// * 'width' - width of the image in pixels
+    ///
+    /// * 'height' - height of the image in pixels
+    ///
+    /// * 'format' - format of the image's texels
+    ///
+    pub fn create_storage_image(&mut self, width: u32, height: u32, format: Format) -> StorageImageID {
+        self.compute_manager_.create_storage_image(
+            &self.instance_,
+            &self.logical_device_.device_,
+            self.physical_devices_.first().unwrap(),
+            vk::Extent2D { width, height },
+            pipeline::conversion::format_to_vulkan_format(&format),
+        )
+    }
+
+    /// Binds a storage buffer to a storage buffer or uniform buffer binding of a compute pipeline.
+    /// Panics if the pipeline or buffer do not exist, or the pipeline has no such binding
+    ///
+    /// # Arguments
+    ///
+    /// * 'pipeline_id' - handle of the compute pipeline
+    ///
+    /// * 'set' - descriptor set index of the binding
+    ///
+    /// * 'binding' - binding index within the set
+    ///
+    /// * 'storage_buffer_id' - handle of the buffer to bind
+    ///
+    pub fn bind_storage_buffer(
+        &mut self,
+        pipeline_id: ComputePipelineID,
+        set: u32,
+        binding: u32,
+        storage_buffer_id: StorageBufferID,
+    ) {
+        self.compute_manager_.bind_storage_buffer(
+            &self.logical_device_.device_,
+            pipeline_id,
+            set,
+            binding,
+            storage_buffer_id,
+        );
+    }
+
+    /// Binds a storage image to a storage image binding of a compute pipeline. Panics if the
+    /// pipeline or image do not exist, or the pipeline has no such binding
+    ///
+    /// # Arguments
+    ///
+    /// * 'pipeline_id' - handle of the compute pipeline
+    ///
+    /// * 'set' - descriptor set index of the binding
+    ///
+    /// * 'binding' - binding index within the set
+    ///
+    /// * 'storage_image_id' - handle of the image to bind
+    ///
+    pub fn bind_storage_image(
+        &mut self,
+        pipeline_id: ComputePipelineID,
+        set: u32,
+        binding: u32,
+        storage_image_id: StorageImageID,
+    ) {
+        self.compute_manager_.bind_storage_image(
+            &self.logical_device_.device_,
+            pipeline_id,
+            set,
+            binding,
+            storage_image_id,
+        );
+    }
+
+    /// Runs a compute dispatch immediately and waits for it to complete, so its results can be
+    /// read back straight away
+    ///
+    /// # Arguments
+    ///
+    /// * 'dispatch' - the pipeline, workgroup count and push constants to dispatch with
+    ///
+    pub fn dispatch(&mut self, dispatch: &ComputeDispatch) {
+        self.compute_manager_
+            .dispatch(&self.logical_device_.device_, std::slice::from_ref(dispatch));
+    }
+
+    /// Adds a dispatch that runs every frame, in the order added, before any renderer draws.
+    /// Returns the index of the dispatch
+    ///
+    /// # Arguments
+    ///
+    /// * 'dispatch' - the pipeline, workgroup count and push constants to dispatch with
+    ///
+    pub fn add_frame_dispatch(&mut self, dispatch: ComputeDispatch) -> usize {
+        self.compute_manager_.add_frame_dispatch(dispatch)
+    }
+
+    /// Returns a per-frame dispatch so its workgroup count or push constants can be changed.
+    /// Panics if there is no such dispatch
+    ///
+    /// # Arguments
+    ///
+    /// * 'index' - index returned when the dispatch was added
+    ///
+    pub fn get_mut_frame_dispatch(&mut self, index: usize) -> &mut ComputeDispatch {
+        self.compute_manager_.get_mut_frame_dispatch(index)
+    }
+
+    /// Removes all per-frame dispatches
+    pub fn clear_frame_dispatches(&mut self) {
+        self.compute_manager_.clear_frame_dispatches();
+    }
     /*
         pub fn set_renderer_pipeline(& mut self, renderer_id: RendererID, pipeline: pipeline::Pipeline, render_pass: pipeline::render_pass1::RenderPass, descriptor_set_layout: ash::vk::DescriptorSetLayout)
         {
@@ -189,9 +1014,15 @@ impl Engine {
     */
     pub fn update(&mut self) {
         self.geometry_manager_.update(&self.logical_device_.device_);
+        // Compute work is submitted first so that renderers see its results this frame
+        self.compute_manager_.update(&self.logical_device_.device_);
 
         for renderer in self.renderers_.values_mut() {
-            renderer.update(&self.logical_device_.device_, &self.geometry_manager_);
+            renderer.update(
+                &self.logical_device_.device_,
+                &self.geometry_manager_,
+                &self.texture_manager_,
+            );
         }
     }
 
@@ -273,3 +1104,22 @@ impl Engine {
         }
     }
 }
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        unsafe {
+            self.logical_device_
+                .device_
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+        }
+
+        if let Err(error) = self.save_pipeline_cache() {
+            println!("Could not save pipeline cache: {}", error);
+        }
+
+        self.compute_manager_.destroy(&self.logical_device_.device_);
+        self.texture_manager_.destroy(&self.logical_device_.device_);
+        self.pipeline_cache_.destroy(&self.logical_device_.device_);
+    }
+}
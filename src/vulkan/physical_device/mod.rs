@@ -90,6 +90,8 @@ pub struct PhysicalDevice {
     pub physical_device_handle_: vk::PhysicalDevice,
     pub device_name_: String,
     pub queue_family_indices_: QueueFamilyIndices,
+    pub properties_: vk::PhysicalDeviceProperties,
+    pub features_: vk::PhysicalDeviceFeatures,
 }
 
 impl PhysicalDevice {
@@ -104,6 +106,7 @@ impl PhysicalDevice {
     ///
     pub fn new(instance: &ash::Instance, physical_device: vk::PhysicalDevice) -> PhysicalDevice {
         let device_properties = unsafe { instance.get_physical_device_properties(physical_device) };
+        let device_features = unsafe { instance.get_physical_device_features(physical_device) };
         let device_name = tools::c_char_array_to_string(&device_properties.device_name);
         let queue_family_indices =
             PhysicalDevice::select_queue_family_indices(instance, physical_device);
@@ -111,6 +114,8 @@ impl PhysicalDevice {
             physical_device_handle_: physical_device,
             device_name_: device_name,
             queue_family_indices_: queue_family_indices,
+            properties_: device_properties,
+            features_: device_features,
         }
     }
 
@@ -157,14 +162,13 @@ impl PhysicalDevice {
         let device_memory_properties =
             unsafe { instance.get_physical_device_memory_properties(self.physical_device_handle_) };
 
-        for (i, memory_type) in device_memory_properties.memory_types.iter().enumerate() {
-            if (type_bitmask & (1 << i)) > 0
-                && (memory_type.property_flags.contains(require_properties))
-            {
-                return Some(i as u32);
-            }
-        }
-        None
+        find_memory_type_index(&device_memory_properties, type_bitmask, require_properties)
+    }
+
+    /// Returns the sample counts supported by both color and depth framebuffer attachments
+    pub fn get_supported_sample_counts(&self) -> vk::SampleCountFlags {
+        self.properties_.limits.framebuffer_color_sample_counts
+            & self.properties_.limits.framebuffer_depth_sample_counts
     }
 
     pub fn get_supported_format(
@@ -323,3 +327,82 @@ fn is_suitable(
             device_extensions,
         );
 }
+
+/// Returns the index of the first memory type allowed by a resource's memory type bitmask that has
+/// all of the required properties, or None if there is no such memory type
+///
+/// # Arguments
+///
+/// * 'memory_properties' - memory properties of the physical device
+///
+/// * 'type_bitmask' - the memory type bits of the resource's memory requirements
+///
+/// * 'require_properties' - properties the memory type must have
+///
+pub fn find_memory_type_index(
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    type_bitmask: u32,
+    require_properties: vk::MemoryPropertyFlags,
+) -> Option<u32> {
+    for (i, memory_type) in memory_properties.memory_types
+        [..memory_properties.memory_type_count as usize]
+        .iter()
+        .enumerate()
+    {
+        if (type_bitmask & (1 << i)) > 0
+            && (memory_type.property_flags.contains(require_properties))
+        {
+            return Some(i as u32);
+        }
+    }
+    None
+}
+
+/// Returns the highest supported sample count that does not exceed the requested count. A single
+/// sample is always supported
+///
+/// # Arguments
+///
+/// * 'requested' - the requested number of samples per pixel
+///
+/// * 'supported' - the sample counts the device supports
+///
+pub fn clamp_sample_count(requested: u32, supported: vk::SampleCountFlags) -> vk::SampleCountFlags {
+    [
+        vk::SampleCountFlags::TYPE_64,
+        vk::SampleCountFlags::TYPE_32,
+        vk::SampleCountFlags::TYPE_16,
+        vk::SampleCountFlags::TYPE_8,
+        vk::SampleCountFlags::TYPE_4,
+        vk::SampleCountFlags::TYPE_2,
+    ]
+    .iter()
+    .cloned()
+    .find(|samples| samples.as_raw() <= requested && supported.contains(*samples))
+    .unwrap_or(vk::SampleCountFlags::TYPE_1)
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    #[test]
+    fn clamp_sample_count_test() {
+        let supported = vk::SampleCountFlags::TYPE_1
+            | vk::SampleCountFlags::TYPE_2
+            | vk::SampleCountFlags::TYPE_4;
+
+        assert_eq!(
+            super::clamp_sample_count(8, supported),
+            vk::SampleCountFlags::TYPE_4
+        );
+        assert_eq!(
+            super::clamp_sample_count(3, supported),
+            vk::SampleCountFlags::TYPE_2
+        );
+        assert_eq!(
+            super::clamp_sample_count(0, supported),
+            vk::SampleCountFlags::TYPE_1
+        );
+    }
+}
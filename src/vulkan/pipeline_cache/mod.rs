@@ -0,0 +1,178 @@
+use std::convert::TryInto;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use ash::Device;
+
+use super::physical_device::PhysicalDevice;
+
+/// Identifies pipeline cache files written by this engine
+const CACHE_MAGIC: &[u8; 4] = b"FGPC";
+/// Incremented whenever the layout of the cache file header changes
+const CACHE_FORMAT_VERSION: u32 = 1;
+/// magic, format version, vendor id, device id, driver version, pipeline cache uuid, data size
+const HEADER_SIZE: usize = 4 + 4 + 4 + 4 + 4 + vk::UUID_SIZE + 8;
+
+/// An engine-owned vulkan pipeline cache, optionally backed by a file so that pipelines compiled
+/// in one run of the application do not need to be compiled again in the next
+pub struct PipelineCache {
+    pub cache_handle_: vk::PipelineCache,
+    path_: Option<PathBuf>,
+    header_: Vec<u8>,
+}
+
+impl PipelineCache {
+    /// Creates a pipeline cache, seeded with the contents of the cache file if one is given and
+    /// it was written by the same device and driver. Otherwise the cache starts out empty. Panics
+    /// if the vulkan pipeline cache cannot be created
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device pipelines are created on
+    ///
+    /// * 'physical_device' - the physical device the logical device was created from
+    ///
+    /// * 'path' - optional file the cache is loaded from and saved to
+    ///
+    pub fn new(
+        device: &Device,
+        physical_device: &PhysicalDevice,
+        path: Option<&Path>,
+    ) -> PipelineCache {
+        let header = PipelineCache::build_header(&physical_device.properties_);
+
+        let initial_data = match path {
+            Some(path) => match fs::read(path) {
+                Ok(contents) => PipelineCache::validate(&header, &contents).unwrap_or_else(|| {
+                    println!(
+                        "Ignoring pipeline cache {}: written by a different device or driver",
+                        path.display()
+                    );
+                    vec![]
+                }),
+                Err(_) => vec![],
+            },
+            None => vec![],
+        };
+
+        let pipeline_cache_create_info = vk::PipelineCacheCreateInfo {
+            s_type: vk::StructureType::PIPELINE_CACHE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineCacheCreateFlags::empty(),
+            initial_data_size: initial_data.len(),
+            p_initial_data: initial_data.as_ptr() as *const std::ffi::c_void,
+        };
+
+        let cache_handle = unsafe {
+            device
+                .create_pipeline_cache(&pipeline_cache_create_info, None)
+                .expect("Could not create pipeline cache")
+        };
+
+        PipelineCache {
+            cache_handle_: cache_handle,
+            path_: path.map(Path::to_path_buf),
+            header_: header,
+        }
+    }
+
+    /// Writes the contents of the cache to the cache file. Does nothing if the cache has no file
+    pub fn save(&self, device: &Device) -> io::Result<()> {
+        let path = match &self.path_ {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let data = unsafe {
+            device
+                .get_pipeline_cache_data(self.cache_handle_)
+                .map_err(|error| io::Error::other(error.to_string()))?
+        };
+
+        let mut contents = self.header_.clone();
+        contents.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        contents.extend_from_slice(&data);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Write to a temporary file first so a crash mid-write cannot leave a truncated cache
+        let temporary_path = path.with_extension("tmp");
+        fs::write(&temporary_path, &contents)?;
+        fs::rename(&temporary_path, path)
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe { device.destroy_pipeline_cache(self.cache_handle_, None) };
+    }
+
+    /// Builds the part of the file header identifying the device and driver, without the size of
+    /// the cache data
+    fn build_header(properties: &vk::PhysicalDeviceProperties) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+
+        header.extend_from_slice(CACHE_MAGIC);
+        header.extend_from_slice(&CACHE_FORMAT_VERSION.to_le_bytes());
+        header.extend_from_slice(&properties.vendor_id.to_le_bytes());
+        header.extend_from_slice(&properties.device_id.to_le_bytes());
+        header.extend_from_slice(&properties.driver_version.to_le_bytes());
+        header.extend_from_slice(&properties.pipeline_cache_uuid);
+
+        header
+    }
+
+    /// Returns the cache data stored in a cache file if its header matches the expected header and
+    /// the data is complete
+    fn validate(header: &[u8], contents: &[u8]) -> Option<Vec<u8>> {
+        if contents.len() < HEADER_SIZE || contents[..header.len()] != *header {
+            return None;
+        }
+
+        let size_bytes: [u8; 8] = contents[header.len()..HEADER_SIZE].try_into().ok()?;
+        let size = u64::from_le_bytes(size_bytes) as usize;
+        let data = &contents[HEADER_SIZE..];
+
+        if data.len() != size {
+            return None;
+        }
+
+        Some(data.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use super::PipelineCache;
+
+    #[test]
+    fn validate_cache_header_test() {
+        let mut properties = vk::PhysicalDeviceProperties {
+            vendor_id: 0x10de,
+            driver_version: 42,
+            ..Default::default()
+        };
+
+        let header = PipelineCache::build_header(&properties);
+        let data = vec![1u8, 2, 3];
+
+        let mut contents = header.clone();
+        contents.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        contents.extend_from_slice(&data);
+
+        assert_eq!(PipelineCache::validate(&header, &contents), Some(data));
+
+        properties.driver_version = 43;
+        let new_driver_header = PipelineCache::build_header(&properties);
+        assert_eq!(PipelineCache::validate(&new_driver_header, &contents), None);
+
+        contents.pop();
+        assert_eq!(PipelineCache::validate(&header, &contents), None);
+    }
+}
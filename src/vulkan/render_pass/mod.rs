@@ -11,12 +11,12 @@ use ash::vk::{
 use ash::Device;
 use std::ptr;
 
-use pipeline::Pipeline;
-
 use pipeline::conversion;
+use pipeline::{Multisample, Pipeline, PipelineError, PipelineFeatures};
 
-use crate::render_sequence::render_pass::RenderPassDescription;
 use crate::render_sequence::render_pass::pipeline::shader::attribute::AttributeDescription;
+use crate::render_sequence::render_pass::pipeline::PipelineDescription;
+use crate::render_sequence::render_pass::{IntermediateAttachment, RenderPassDescription};
 
 #[repr(C)]
 #[derive(Clone, Debug)]
@@ -24,63 +24,282 @@ pub struct RenderPass {
     pub vk_render_pass_: vk::RenderPass,
     pub mainpass_: Pipeline,
     pub subpasses_: Vec<Pipeline>,
+    /// formats of the intermediate attachments, which follow the presented swapchain image and
+    /// precede the depth buffer in the render pass's attachment list
+    pub attachment_formats_: Vec<vk::Format>,
+    pub multisample_: Multisample,
+    /// optional device features the pipelines of the render pass may use
+    pub features_: PipelineFeatures,
 }
 
-impl RenderPass {
+/// A render pass along with the description it was built from and the cache its pipelines are
+/// built with, for passes that build their own pipelines against the current render pass
+#[derive(Clone, Copy)]
+pub struct RenderPassContext<'a> {
+    pub render_pass_: &'a RenderPass,
+    pub description_: &'a RenderPassDescription,
+    pub pipeline_cache_: vk::PipelineCache,
+}
+
+impl<'a> RenderPassContext<'a> {
+    /// Builds a pipeline drawn over the scene in the final subpass, after the subpass's own draws.
+    /// Returns None if the final subpass writes more than the presented color, reporting that the
+    /// overlay is not drawn. Panics if the pipeline cannot be built
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the render pass was created on
+    ///
+    /// * 'overlay' - the pipeline drawn over the scene
+    ///
+    /// * 'overlay_name' - what the pipeline draws, for reporting
+    ///
+    pub fn build_overlay_pipeline(
+        &self,
+        device: &Device,
+        overlay: &PipelineDescription,
+        overlay_name: &str,
+    ) -> Option<Pipeline> {
+        let subpass = match self.description_.final_subpass_overlay_index() {
+            Some(subpass) => subpass,
+            None => {
+                println!(
+                    "Not drawing {}: the final subpass writes more than the presented color",
+                    overlay_name
+                );
+                return None;
+            }
+        };
 
-    /*
+        let pipeline = Pipeline::from_pipeline_descriptor(
+            device,
+            overlay,
+            self.render_pass_.vk_render_pass_,
+            subpass,
+            &self.render_pass_.multisample_,
+            &self.render_pass_.features_,
+            self.pipeline_cache_,
+        )
+        .unwrap_or_else(|error| panic!("Could not build {} pipeline: {}", overlay_name, error));
+
+        Some(pipeline)
+    }
+}
+
+impl RenderPass {
+    /// Builds a render pass, and a pipeline for each of its subpasses, from a
+    /// RenderPassDescription. The presented fragment output of the final subpass is written to the
+    /// swapchain image, and every other fragment output gets an intermediate attachment that later
+    /// subpasses can read as an input attachment. When post-processing, the presented output is
+    /// written to an offscreen target instead. All subpasses share the depth buffer. When
+    /// multisampled, subpasses render to multisampled copies of the swapchain image and the
+    /// intermediate attachments, which follow the depth buffer, and resolve into the single
+    /// sampled ones. Returns an error if a subpass reads an input attachment no earlier subpass
+    /// writes, or a pipeline cannot be built from its reflected shaders
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the render pass is created on
+    ///
+    /// * 'description' - reflected description of the render pass and its pipelines
+    ///
+    /// * 'present' - the format of the image presented attachments write to, and the layout the
+    ///   presented attachment is left in, PRESENT_SRC_KHR for a swapchain image or
+    ///   SHADER_READ_ONLY_OPTIMAL for an offscreen target sampled afterwards
+    ///
+    /// * 'depth_format' - the format of the swapchain depth buffer
+    ///
+    /// * 'multisample' - the sample count and sample shading of every subpass
+    ///
+    /// * 'features' - the optional device features the pipelines may use
+    ///
+    /// * 'pipeline_cache' - cache used to speed up pipeline compilation
+    ///
     pub fn from_description(
         device: &Device,
         description: &RenderPassDescription,
-        surface_format: Option<vk::Format>,
-        depth_format: Option<vk::Format>,
-    ) -> RenderPass {
-        let mut color_attachments = vec![];
-        let mut color_attachment_refs = vec![];
+        present: (vk::Format, ImageLayout),
+        depth_format: vk::Format,
+        multisample: Multisample,
+        features: PipelineFeatures,
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<RenderPass, PipelineError> {
+        let (surface_format, present_layout) = present;
+        let pipelines = description.get_pipelines();
+        let intermediate_attachments = description.get_intermediate_attachments();
+
+        for (subpass, pipeline) in pipelines.iter().enumerate() {
+            for (_, _, input_attachment_index) in pipeline.get_input_attachments() {
+                match intermediate_attachments.get(input_attachment_index as usize) {
+                    Some(attachment) if attachment.subpass_ < subpass => {}
+                    _ => {
+                        return Err(PipelineError::InvalidInputAttachment {
+                            subpass: subpass as u32,
+                            input_attachment_index,
+                        })
+                    }
+                }
+            }
+        }
 
-        for attachment_description in description.pipeline_.output_.attributes_.iter() {
-            color_attachments.push(RenderPass::attachment_from_description(
-                attachment_description,
+        let presented_output = pipelines
+            .last()
+            .unwrap()
+            .get_fragment_outputs()
+            .and_then(|outputs| outputs.iter().find(|output| output.present_))
+            .expect("The final subpass must write a color output at location 0");
+
+        let multisampled = multisample.is_multisampled();
+
+        // The swapchain image is attachment 0, followed by the intermediate attachments and then
+        // the depth buffer. Multisampled copies of the swapchain image and intermediate
+        // attachments follow in the same order, so a color attachment's multisampled copy is
+        // found by offsetting its index past the depth buffer
+        let mut presented_attachment = RenderPass::attachment_from_description(
+            presented_output,
+            surface_format,
+            SampleCountFlags::TYPE_1,
+            multisampled,
+        );
+        presented_attachment.final_layout = present_layout;
+
+        let mut attachments = vec![presented_attachment];
+
+        for attachment in intermediate_attachments.iter() {
+            attachments.push(RenderPass::attachment_from_description(
+                &attachment.output_,
                 surface_format,
+                SampleCountFlags::TYPE_1,
+                multisampled,
             ));
-            color_attachment_refs.push(AttachmentReference {
-                attachment: attachment_description.location_,
-                layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-            });
         }
 
-        let depth_attachment = RenderPass::build_depth_attachment(depth_format.unwrap());
         let depth_attachment_reference = AttachmentReference {
-            attachment: color_attachments.len() as u32,
-            layout: ash::vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            attachment: attachments.len() as u32,
+            layout: ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
         };
 
-        let subpass = SubpassDescription {
-            flags: SubpassDescriptionFlags::empty(),
-            pipeline_bind_point: PipelineBindPoint::GRAPHICS,
-            input_attachment_count: 0,
-            p_input_attachments: ptr::null(),
-            color_attachment_count: 1,
-            p_color_attachments: color_attachment_refs.as_ptr(),
-            p_resolve_attachments: ptr::null(),
-            p_depth_stencil_attachment: [depth_attachment_reference].as_ptr(),
-            preserve_attachment_count: 0,
-            p_preserve_attachments: ptr::null(),
-        };
+        attachments.push(RenderPass::build_depth_attachment(
+            depth_format,
+            multisample.samples_,
+        ));
+
+        let multisampled_offset = attachments.len() as u32;
+
+        if multisampled {
+            attachments.push(RenderPass::attachment_from_description(
+                presented_output,
+                surface_format,
+                multisample.samples_,
+                false,
+            ));
+
+            for attachment in intermediate_attachments.iter() {
+                attachments.push(RenderPass::attachment_from_description(
+                    &attachment.output_,
+                    surface_format,
+                    multisample.samples_,
+                    false,
+                ));
+            }
+        }
 
-        let mut attachments = color_attachments.clone();
-        attachments.push(depth_attachment);
+        let mut color_attachment_refs = vec![];
+        let mut resolve_attachment_refs = vec![];
+        let mut input_attachment_refs = vec![];
+        let mut preserve_attachments = vec![];
+
+        for (subpass, pipeline) in pipelines.iter().enumerate() {
+            let refs = RenderPass::build_color_attachment_refs(
+                subpass,
+                pipeline,
+                &intermediate_attachments,
+            );
+
+            // Subpasses render to the multisampled copies and resolve into the attachments
+            // presented or read by later subpasses
+            if multisampled {
+                color_attachment_refs.push(
+                    refs.iter()
+                        .map(|reference| match reference.attachment {
+                            vk::ATTACHMENT_UNUSED => *reference,
+                            attachment => AttachmentReference {
+                                attachment: attachment + multisampled_offset,
+                                layout: reference.layout,
+                            },
+                        })
+                        .collect(),
+                );
+                resolve_attachment_refs.push(refs);
+            } else {
+                color_attachment_refs.push(refs);
+                resolve_attachment_refs.push(vec![]);
+            }
+
+            input_attachment_refs.push(RenderPass::build_input_attachment_refs(pipeline));
+            preserve_attachments.push(RenderPass::build_preserve_attachments(
+                subpass,
+                &pipelines,
+                &intermediate_attachments,
+            ));
+        }
 
-        let subpass_dependencies = SubpassDependency {
+        let subpasses: Vec<SubpassDescription> = (0..pipelines.len())
+            .map(|subpass| SubpassDescription {
+                flags: SubpassDescriptionFlags::empty(),
+                pipeline_bind_point: PipelineBindPoint::GRAPHICS,
+                input_attachment_count: input_attachment_refs[subpass].len() as u32,
+                p_input_attachments: input_attachment_refs[subpass].as_ptr(),
+                color_attachment_count: color_attachment_refs[subpass].len() as u32,
+                p_color_attachments: color_attachment_refs[subpass].as_ptr(),
+                p_resolve_attachments: if multisampled {
+                    resolve_attachment_refs[subpass].as_ptr()
+                } else {
+                    ptr::null()
+                },
+                p_depth_stencil_attachment: &depth_attachment_reference,
+                preserve_attachment_count: preserve_attachments[subpass].len() as u32,
+                p_preserve_attachments: preserve_attachments[subpass].as_ptr(),
+            })
+            .collect();
+
+        // Also waits for post-processing passes of earlier frames still sampling an offscreen target
+        let mut subpass_dependencies = vec![SubpassDependency {
             src_subpass: vk::SUBPASS_EXTERNAL,
             dst_subpass: 0,
-            src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-            dst_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                | PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                | PipelineStageFlags::FRAGMENT_SHADER,
+            dst_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                | PipelineStageFlags::EARLY_FRAGMENT_TESTS,
             src_access_mask: AccessFlags::empty(),
             dst_access_mask: AccessFlags::COLOR_ATTACHMENT_READ
-                | AccessFlags::COLOR_ATTACHMENT_WRITE,
+                | AccessFlags::COLOR_ATTACHMENT_WRITE
+                | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
             dependency_flags: DependencyFlags::empty(),
-        };
+        }];
+
+        // Each subpass waits for the attachment writes of the one before it. Input attachments are
+        // read at the same pixel they were written, so the dependencies are by region
+        for subpass in 1..pipelines.len() as u32 {
+            subpass_dependencies.push(SubpassDependency {
+                src_subpass: subpass - 1,
+                dst_subpass: subpass,
+                src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                dst_stage_mask: PipelineStageFlags::FRAGMENT_SHADER
+                    | PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: AccessFlags::INPUT_ATTACHMENT_READ
+                    | AccessFlags::COLOR_ATTACHMENT_READ
+                    | AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: DependencyFlags::BY_REGION,
+            });
+        }
 
         let render_pass_create_info = RenderPassCreateInfo {
             s_type: StructureType::RENDER_PASS_CREATE_INFO,
@@ -88,10 +307,10 @@ impl RenderPass {
             flags: RenderPassCreateFlags::empty(),
             attachment_count: attachments.len() as u32,
             p_attachments: attachments.as_ptr(),
-            subpass_count: 1,
-            p_subpasses: &subpass,
-            dependency_count: 1,
-            p_dependencies: &subpass_dependencies,
+            subpass_count: subpasses.len() as u32,
+            p_subpasses: subpasses.as_ptr(),
+            dependency_count: subpass_dependencies.len() as u32,
+            p_dependencies: subpass_dependencies.as_ptr(),
         };
 
         let render_pass = unsafe {
@@ -99,33 +318,258 @@ impl RenderPass {
                 .create_render_pass(&render_pass_create_info, None)
                 .expect("could not create render pass")
         };
-        let pipeline = Pipeline::from_pipeline_descriptor(device, &description.pipeline_);
 
-        RenderPass {
-            vk_render_pass_: Default::default(),
-            entry_: pipeline,
-            subpass_pipelines_: vec![],
+        let mut subpass_pipelines: Vec<Pipeline> = vec![];
+
+        for (subpass, pipeline) in pipelines.iter().enumerate() {
+            match Pipeline::from_pipeline_descriptor(
+                device,
+                pipeline,
+                render_pass,
+                subpass as u32,
+                &multisample,
+                &features,
+                pipeline_cache,
+            ) {
+                Ok(pipeline) => subpass_pipelines.push(pipeline),
+                Err(error) => {
+                    for pipeline in subpass_pipelines.iter() {
+                        pipeline.destroy(device);
+                    }
+                    unsafe { device.destroy_render_pass(render_pass, None) };
+                    return Err(error);
+                }
+            }
+        }
+
+        let mainpass = subpass_pipelines.remove(0);
+
+        Ok(RenderPass {
+            vk_render_pass_: render_pass,
+            mainpass_: mainpass,
+            subpasses_: subpass_pipelines,
+            attachment_formats_: intermediate_attachments
+                .iter()
+                .map(|attachment| conversion::format_to_vulkan_format(&attachment.output_.format_))
+                .collect(),
+            multisample_: multisample,
+            features_: features,
+        })
+    }
+
+    /// Returns the pipeline of a subpass, where the main pass is subpass 0
+    pub fn get_pipeline(&self, subpass: usize) -> &Pipeline {
+        match subpass {
+            0 => &self.mainpass_,
+            _ => &self.subpasses_[subpass - 1],
         }
     }
 
+    /// Pairs the pipeline of every subpass, in subpass order, with the pipeline description it
+    /// was built from
+    ///
+    /// # Arguments
+    ///
+    /// * 'description' - the description the render pass was built from
+    ///
+    pub fn get_pipelines<'a>(
+        &'a self,
+        description: &'a RenderPassDescription,
+    ) -> Vec<(&'a PipelineDescription, &'a Pipeline)> {
+        description
+            .get_pipelines()
+            .into_iter()
+            .enumerate()
+            .map(|(subpass, pipeline_description)| {
+                (pipeline_description, self.get_pipeline(subpass))
+            })
+            .collect()
+    }
+
+    /// Returns a clear value for every attachment. The swapchain image is cleared to opaque black,
+    /// intermediate attachments to zero and the depth buffer to the far plane. Multisampled copies
+    /// are cleared like the attachments they resolve into
+    pub fn build_clear_values(&self) -> Vec<vk::ClearValue> {
+        let mut color_clear_values = vec![vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: [0.0, 0.0, 0.0, 1.0],
+            },
+        }];
+
+        for _ in self.attachment_formats_.iter() {
+            color_clear_values.push(vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+            });
+        }
+
+        let mut clear_values = color_clear_values.clone();
+
+        clear_values.push(vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue {
+                depth: 1.0,
+                stencil: 0,
+            },
+        });
+
+        if self.multisample_.is_multisampled() {
+            clear_values.append(&mut color_clear_values);
+        }
+
+        clear_values
+    }
+
+    /// Destroys the render pass and the pipelines of all of its subpasses
+    pub fn destroy(&self, device: &Device) {
+        self.mainpass_.destroy(device);
+
+        for subpass in self.subpasses_.iter() {
+            subpass.destroy(device);
+        }
+
+        unsafe { device.destroy_render_pass(self.vk_render_pass_, None) };
+    }
+
+    /// Builds the color attachment references of a subpass, indexed by fragment output location.
+    /// Locations without an output are unused
+    fn build_color_attachment_refs(
+        subpass: usize,
+        pipeline: &PipelineDescription,
+        intermediate_attachments: &[IntermediateAttachment],
+    ) -> Vec<AttachmentReference> {
+        let outputs = match pipeline.get_fragment_outputs() {
+            Some(outputs) => outputs,
+            None => return vec![],
+        };
+
+        let num_locations = outputs
+            .iter()
+            .map(|output| output.location_ + 1)
+            .max()
+            .unwrap_or(0);
+
+        let mut refs = vec![
+            AttachmentReference {
+                attachment: vk::ATTACHMENT_UNUSED,
+                layout: ImageLayout::UNDEFINED,
+            };
+            num_locations as usize
+        ];
+
+        for output in outputs.iter() {
+            let attachment = match output.present_ {
+                true => 0,
+                false => {
+                    1 + intermediate_attachments
+                        .iter()
+                        .position(|attachment| {
+                            attachment.subpass_ == subpass
+                                && attachment.output_.location_ == output.location_
+                        })
+                        .unwrap() as u32
+                }
+            };
+
+            refs[output.location_ as usize] = AttachmentReference {
+                attachment,
+                layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            };
+        }
+
+        refs
+    }
+
+    /// Builds the input attachment references of a subpass, indexed by input_attachment_index.
+    /// Input attachment i is the i-th intermediate attachment
+    fn build_input_attachment_refs(pipeline: &PipelineDescription) -> Vec<AttachmentReference> {
+        let input_attachments = pipeline.get_input_attachments();
+
+        let num_indices = input_attachments
+            .iter()
+            .map(|(_, _, index)| index + 1)
+            .max()
+            .unwrap_or(0);
+
+        let mut refs = vec![
+            AttachmentReference {
+                attachment: vk::ATTACHMENT_UNUSED,
+                layout: ImageLayout::UNDEFINED,
+            };
+            num_indices as usize
+        ];
+
+        for (_, _, index) in input_attachments.iter() {
+            refs[*index as usize] = AttachmentReference {
+                attachment: 1 + index,
+                layout: ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            };
+        }
+
+        refs
+    }
+
+    /// Returns the intermediate attachments a subpass neither writes nor reads, but whose contents
+    /// a later subpass reads
+    fn build_preserve_attachments(
+        subpass: usize,
+        pipelines: &[&PipelineDescription],
+        intermediate_attachments: &[IntermediateAttachment],
+    ) -> Vec<u32> {
+        let reads = |pipeline: &PipelineDescription, index: usize| {
+            pipeline
+                .get_input_attachments()
+                .iter()
+                .any(|(_, _, input_attachment_index)| *input_attachment_index as usize == index)
+        };
+
+        intermediate_attachments
+            .iter()
+            .enumerate()
+            .filter(|(index, attachment)| {
+                attachment.subpass_ < subpass
+                    && !reads(pipelines[subpass], *index)
+                    && pipelines[subpass + 1..]
+                        .iter()
+                        .any(|pipeline| reads(pipeline, *index))
+            })
+            .map(|(index, _)| 1 + index as u32)
+            .collect()
+    }
+
+    /// Describes a color attachment. Multisampled attachments are never stored or presented, and
+    /// resolve targets are not cleared since the resolve overwrites them
     fn attachment_from_description(
         description: &AttributeDescription,
-        surface_format: Option<vk::Format>,
+        surface_format: vk::Format,
+        samples: SampleCountFlags,
+        resolve_target: bool,
     ) -> AttachmentDescription {
+        let presented = description.present_ && samples == SampleCountFlags::TYPE_1;
+
         AttachmentDescription {
             flags: AttachmentDescriptionFlags::empty(),
             format: if description.present_ {
-                surface_format.unwrap()
+                surface_format
             } else {
                 conversion::format_to_vulkan_format(&description.format_)
             },
-            samples: SampleCountFlags::TYPE_1,
-            load_op: AttachmentLoadOp::CLEAR,
-            store_op: AttachmentStoreOp::STORE,
+            samples,
+            load_op: if resolve_target {
+                AttachmentLoadOp::DONT_CARE
+            } else {
+                AttachmentLoadOp::CLEAR
+            },
+            // Intermediate attachments are only read by later subpasses of the same render pass
+            store_op: if presented {
+                AttachmentStoreOp::STORE
+            } else {
+                AttachmentStoreOp::DONT_CARE
+            },
             stencil_load_op: AttachmentLoadOp::DONT_CARE,
             stencil_store_op: AttachmentStoreOp::DONT_CARE,
             initial_layout: ImageLayout::UNDEFINED,
-            final_layout: if description.present_ {
+            final_layout: if presented {
                 ImageLayout::PRESENT_SRC_KHR
             } else {
                 ImageLayout::COLOR_ATTACHMENT_OPTIMAL
@@ -133,35 +577,22 @@ impl RenderPass {
         }
     }
 
-    fn build_color_attachment(surface_format: vk::Format) -> AttachmentDescription {
-        AttachmentDescription {
-            flags: AttachmentDescriptionFlags::empty(),
-            format: surface_format,
-            samples: SampleCountFlags::TYPE_1,
-            load_op: AttachmentLoadOp::CLEAR,
-            store_op: AttachmentStoreOp::STORE,
-            stencil_load_op: AttachmentLoadOp::DONT_CARE,
-            stencil_store_op: AttachmentStoreOp::DONT_CARE,
-            initial_layout: ImageLayout::UNDEFINED,
-            final_layout: ImageLayout::PRESENT_SRC_KHR,
-        }
-    }
-
-    fn build_depth_attachment(depth_format: ash::vk::Format) -> AttachmentDescription {
+    fn build_depth_attachment(
+        depth_format: vk::Format,
+        samples: SampleCountFlags,
+    ) -> AttachmentDescription {
         AttachmentDescription {
             flags: AttachmentDescriptionFlags::empty(),
             format: depth_format,
-            samples: SampleCountFlags::TYPE_1,
+            samples,
             load_op: AttachmentLoadOp::CLEAR,
             store_op: AttachmentStoreOp::DONT_CARE,
-            stencil_load_op: AttachmentLoadOp::DONT_CARE,
+            stencil_load_op: AttachmentLoadOp::CLEAR,
             stencil_store_op: AttachmentStoreOp::DONT_CARE,
             initial_layout: ImageLayout::UNDEFINED,
             final_layout: ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
         }
     }
-
-     */
 }
 
 /*
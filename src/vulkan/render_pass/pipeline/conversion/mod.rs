@@ -1,6 +1,9 @@
 use crate::render_sequence::reflection_utils::format::Format;
-use crate::render_sequence::render_pass::pipeline::shader::ShaderStage;
 use crate::render_sequence::render_pass::pipeline::shader::descriptor::DescriptorType;
+use crate::render_sequence::render_pass::pipeline::shader::ShaderStage;
+use crate::render_sequence::render_pass::pipeline::state::{
+    BlendFactor, BlendOp, CompareOp, CullMode, FrontFace, PolygonMode, PrimitiveTopology, StencilOp,
+};
 
 use ash::vk;
 
@@ -19,6 +22,9 @@ pub fn format_to_vulkan_format(format: &Format) -> vk::Format {
         Format::R32G32B32A32_UINT => vk::Format::R32G32B32A32_UINT,
         Format::R32G32B32A32_SINT => vk::Format::R32G32B32A32_SINT,
         Format::R32G32B32A32_SFLOAT => vk::Format::R32G32B32A32_SFLOAT,
+        Format::R8G8B8A8_UNORM => vk::Format::R8G8B8A8_UNORM,
+        Format::R8G8B8A8_SRGB => vk::Format::R8G8B8A8_SRGB,
+        Format::R16G16B16A16_SFLOAT => vk::Format::R16G16B16A16_SFLOAT,
     }
 }
 
@@ -52,3 +58,89 @@ pub fn descriptor_type_to_vulkan_descriptor_type(
         DescriptorType::AccelerationStructureNV => vk::DescriptorType::ACCELERATION_STRUCTURE_NV,
     }
 }
+
+pub fn cull_mode_to_vulkan_cull_mode(cull_mode: &CullMode) -> vk::CullModeFlags {
+    match cull_mode {
+        CullMode::None => vk::CullModeFlags::NONE,
+        CullMode::Front => vk::CullModeFlags::FRONT,
+        CullMode::Back => vk::CullModeFlags::BACK,
+        CullMode::FrontAndBack => vk::CullModeFlags::FRONT_AND_BACK,
+    }
+}
+
+pub fn front_face_to_vulkan_front_face(front_face: &FrontFace) -> vk::FrontFace {
+    match front_face {
+        FrontFace::Clockwise => vk::FrontFace::CLOCKWISE,
+        FrontFace::CounterClockwise => vk::FrontFace::COUNTER_CLOCKWISE,
+    }
+}
+
+pub fn polygon_mode_to_vulkan_polygon_mode(polygon_mode: &PolygonMode) -> vk::PolygonMode {
+    match polygon_mode {
+        PolygonMode::Fill => vk::PolygonMode::FILL,
+        PolygonMode::Line => vk::PolygonMode::LINE,
+        PolygonMode::Point => vk::PolygonMode::POINT,
+    }
+}
+
+pub fn topology_to_vulkan_topology(topology: &PrimitiveTopology) -> vk::PrimitiveTopology {
+    match topology {
+        PrimitiveTopology::PointList => vk::PrimitiveTopology::POINT_LIST,
+        PrimitiveTopology::LineList => vk::PrimitiveTopology::LINE_LIST,
+        PrimitiveTopology::LineStrip => vk::PrimitiveTopology::LINE_STRIP,
+        PrimitiveTopology::TriangleList => vk::PrimitiveTopology::TRIANGLE_LIST,
+        PrimitiveTopology::TriangleStrip => vk::PrimitiveTopology::TRIANGLE_STRIP,
+        PrimitiveTopology::TriangleFan => vk::PrimitiveTopology::TRIANGLE_FAN,
+    }
+}
+
+pub fn compare_op_to_vulkan_compare_op(compare_op: &CompareOp) -> vk::CompareOp {
+    match compare_op {
+        CompareOp::Never => vk::CompareOp::NEVER,
+        CompareOp::Less => vk::CompareOp::LESS,
+        CompareOp::Equal => vk::CompareOp::EQUAL,
+        CompareOp::LessOrEqual => vk::CompareOp::LESS_OR_EQUAL,
+        CompareOp::Greater => vk::CompareOp::GREATER,
+        CompareOp::NotEqual => vk::CompareOp::NOT_EQUAL,
+        CompareOp::GreaterOrEqual => vk::CompareOp::GREATER_OR_EQUAL,
+        CompareOp::Always => vk::CompareOp::ALWAYS,
+    }
+}
+
+pub fn stencil_op_to_vulkan_stencil_op(stencil_op: &StencilOp) -> vk::StencilOp {
+    match stencil_op {
+        StencilOp::Keep => vk::StencilOp::KEEP,
+        StencilOp::Zero => vk::StencilOp::ZERO,
+        StencilOp::Replace => vk::StencilOp::REPLACE,
+        StencilOp::IncrementAndClamp => vk::StencilOp::INCREMENT_AND_CLAMP,
+        StencilOp::DecrementAndClamp => vk::StencilOp::DECREMENT_AND_CLAMP,
+        StencilOp::Invert => vk::StencilOp::INVERT,
+        StencilOp::IncrementAndWrap => vk::StencilOp::INCREMENT_AND_WRAP,
+        StencilOp::DecrementAndWrap => vk::StencilOp::DECREMENT_AND_WRAP,
+    }
+}
+
+pub fn blend_factor_to_vulkan_blend_factor(blend_factor: &BlendFactor) -> vk::BlendFactor {
+    match blend_factor {
+        BlendFactor::Zero => vk::BlendFactor::ZERO,
+        BlendFactor::One => vk::BlendFactor::ONE,
+        BlendFactor::SrcColor => vk::BlendFactor::SRC_COLOR,
+        BlendFactor::OneMinusSrcColor => vk::BlendFactor::ONE_MINUS_SRC_COLOR,
+        BlendFactor::DstColor => vk::BlendFactor::DST_COLOR,
+        BlendFactor::OneMinusDstColor => vk::BlendFactor::ONE_MINUS_DST_COLOR,
+        BlendFactor::SrcAlpha => vk::BlendFactor::SRC_ALPHA,
+        BlendFactor::OneMinusSrcAlpha => vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+        BlendFactor::DstAlpha => vk::BlendFactor::DST_ALPHA,
+        BlendFactor::OneMinusDstAlpha => vk::BlendFactor::ONE_MINUS_DST_ALPHA,
+    }
+}
+
+pub fn blend_op_to_vulkan_blend_op(blend_op: &BlendOp) -> vk::BlendOp {
+    match blend_op {
+        BlendOp::Add => vk::BlendOp::ADD,
+        BlendOp::Subtract => vk::BlendOp::SUBTRACT,
+        BlendOp::ReverseSubtract => vk::BlendOp::REVERSE_SUBTRACT,
+        BlendOp::Min => vk::BlendOp::MIN,
+        BlendOp::Max => vk::BlendOp::MAX,
+    }
+}
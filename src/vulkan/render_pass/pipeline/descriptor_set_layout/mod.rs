@@ -7,24 +7,77 @@ use ash::vk::{
 };
 use ash::Device;
 
-use crate::render_sequence::render_pass::pipeline::shader::descriptor::{DescriptorSetDescription, DescriptorDescription};
-use crate::render_sequence::render_pass::pipeline::shader::ShaderStage;
+use crate::render_sequence::render_pass::pipeline::shader::descriptor::DescriptorDescription;
+use crate::render_sequence::render_pass::pipeline::shader::{ShaderDescription, ShaderStage};
 
 use super::conversion;
-/*
-pub fn from_description(
+use super::PipelineError;
+
+/// Creates one descriptor set layout per set index used by the given shaders. Set indices that no
+/// shader uses get an empty layout, so that layouts line up with set numbers in the pipeline layout
+pub fn from_shader_descriptions(
     device: &Device,
-    description: &DescriptorSetDescription,
-) -> DescriptorSetLayout {
-    let mut bindings = vec![];
+    shaders: &[ShaderDescription],
+) -> Result<Vec<DescriptorSetLayout>, PipelineError> {
+    let merged_bindings = merge_bindings(shaders)?;
+
+    Ok(merged_bindings
+        .iter()
+        .map(|bindings| from_bindings(device, bindings))
+        .collect())
+}
+
+/// Merges the descriptor sets of every shader in a pipeline into one list of bindings per set
+/// index. A binding used by more than one stage becomes a single binding visible to each of those
+/// stages. Returns an error if two stages declare the same binding with different types or counts
+pub fn merge_bindings(
+    shaders: &[ShaderDescription],
+) -> Result<Vec<Vec<DescriptorSetLayoutBinding>>, PipelineError> {
+    let mut merged_bindings: Vec<Vec<DescriptorSetLayoutBinding>> = vec![];
+
+    for shader in shaders.iter() {
+        for descriptor_set in shader.descriptor_sets_.iter() {
+            let set = descriptor_set.set_ as usize;
+
+            if merged_bindings.len() <= set {
+                merged_bindings.resize(set + 1, vec![]);
+            }
+
+            for descriptor in descriptor_set.descriptors_.iter() {
+                let binding = binding_from_description(descriptor, &shader.shader_.stage_);
+
+                match merged_bindings[set]
+                    .iter_mut()
+                    .find(|merged| merged.binding == binding.binding)
+                {
+                    Some(merged) => {
+                        if merged.descriptor_type != binding.descriptor_type
+                            || merged.descriptor_count != binding.descriptor_count
+                        {
+                            return Err(PipelineError::DescriptorConflict {
+                                set: descriptor_set.set_,
+                                binding: binding.binding,
+                            });
+                        }
+                        merged.stage_flags |= binding.stage_flags;
+                    }
+                    None => merged_bindings[set].push(binding),
+                }
+            }
+        }
+    }
 
-    for descriptor in description.descriptors_.iter() {
-        bindings.push(binding_from_description(
-            descriptor,
-            &description.shader_stage_,
-        ));
+    for bindings in merged_bindings.iter_mut() {
+        bindings.sort_by_key(|binding| binding.binding);
     }
 
+    Ok(merged_bindings)
+}
+
+pub fn from_bindings(
+    device: &Device,
+    bindings: &[DescriptorSetLayoutBinding],
+) -> DescriptorSetLayout {
     let descriptor_set_layout_create_info = DescriptorSetLayoutCreateInfo {
         s_type: StructureType::DESCRIPTOR_SET_LAYOUT_CREATE_INFO,
         p_next: ptr::null(),
@@ -50,105 +103,7 @@ pub fn binding_from_description(
             &description.descriptor_type_,
         ),
         descriptor_count: description.count_,
-        stage_flags: conversion::shader_stage_to_vulkan_shader_stage(&shader_stage),
-        p_immutable_samplers: ptr::null(),
-    }
-}
-*/
-/*
-pub struct DescriptorSetLayout
-{
-    pub layout_handle_: ash::vk::DescriptorSetLayout,
-}
-
-impl DescriptorSetLayout
-{
-    pub unsafe fn new(device: &ash::Device, bindings: Vec<DescriptorSetLayoutBinding>) -> DescriptorSetLayout
-    {
-        let layout_create_info = ash::vk::DescriptorSetLayoutCreateInfo{
-            s_type: ash::vk::StructureType::DESCRIPTOR_SET_LAYOUT_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: ash::vk::DescriptorSetLayoutCreateFlags::empty(),
-            binding_count: bindings.len() as u32,
-            p_bindings: bindings.as_ptr()
-        };
-
-        let layout_handle = device.create_descriptor_set_layout(&layout_create_info, None).expect("Could not create Descriptor Set Layout");
-
-        DescriptorSetLayout{ layout_handle_: layout_handle }
-    }
-
-
-}
-*/
-
-/*
-pub fn from_reflection(device: &ash::Device, reflect_descriptor_set: &ReflectDescriptorSet, shader_stage: ShaderKind) -> DescriptorSetLayout
-{
-    let mut bindings = vec![];
-
-    for binding in reflect_descriptor_set.bindings.iter()
-    {
-        bindings.push(convert_reflect_descriptor_binding(binding, shader_stage));
-    }
-
-    let layout_create_info = ash::vk::DescriptorSetLayoutCreateInfo{
-        s_type: ash::vk::StructureType::DESCRIPTOR_SET_LAYOUT_CREATE_INFO,
-        p_next: ptr::null(),
-        flags: ash::vk::DescriptorSetLayoutCreateFlags::empty(),
-        binding_count: bindings.len() as u32,
-        p_bindings: bindings.as_ptr()
-    };
-
-    unsafe{device.create_descriptor_set_layout(&layout_create_info, None).expect("Could not create Descriptor Set Layout")}
-}
-
-fn convert_reflect_descriptor_binding(reflect_descriptor_binding: &ReflectDescriptorBinding, shader_stage: ShaderKind) -> DescriptorSetLayoutBinding
-{
-    DescriptorSetLayoutBinding{
-        binding: reflect_descriptor_binding.binding,
-        descriptor_type: match reflect_descriptor_binding.descriptor_type
-        {
-            ReflectDescriptorType::UniformBuffer => DescriptorType::UNIFORM_BUFFER,
-            ReflectDescriptorType::UniformBufferDynamic => DescriptorType::UNIFORM_BUFFER_DYNAMIC,
-            ReflectDescriptorType::AccelerationStructureNV => DescriptorType::ACCELERATION_STRUCTURE_NV,
-            ReflectDescriptorType::CombinedImageSampler => DescriptorType::COMBINED_IMAGE_SAMPLER,
-            ReflectDescriptorType::InputAttachment => DescriptorType::INPUT_ATTACHMENT,
-            ReflectDescriptorType::SampledImage => DescriptorType::SAMPLED_IMAGE,
-            ReflectDescriptorType::Sampler => DescriptorType::SAMPLER,
-            ReflectDescriptorType::StorageBuffer => DescriptorType::STORAGE_BUFFER,
-            ReflectDescriptorType::StorageBufferDynamic => DescriptorType::STORAGE_BUFFER_DYNAMIC,
-            ReflectDescriptorType::StorageImage => DescriptorType::STORAGE_IMAGE,
-            ReflectDescriptorType::StorageTexelBuffer => DescriptorType::STORAGE_TEXEL_BUFFER,
-            _ => DescriptorType::default()
-        },
-
-        descriptor_count: reflect_descriptor_binding.count,
-        stage_flags: match shader_stage
-        {
-            ShaderKind::Vertex => ShaderStageFlags::VERTEX,
-            ShaderKind::Fragment => ShaderStageFlags::FRAGMENT,
-            ShaderKind::Compute => ShaderStageFlags::COMPUTE,
-            ShaderKind::Geometry => ShaderStageFlags::GEOMETRY,
-            ShaderKind::TessControl => ShaderStageFlags::TESSELLATION_CONTROL,
-            ShaderKind::TessEvaluation => ShaderStageFlags::TESSELLATION_EVALUATION,
-            _ => ShaderStageFlags::default(),
-        },
+        stage_flags: conversion::shader_stage_to_vulkan_shader_stage(shader_stage),
         p_immutable_samplers: ptr::null(),
     }
-
-}
-
-pub fn build_descriptor_set_layout_bindings() -> [DescriptorSetLayoutBinding; 1]
-{
-    [
-        ash::vk::DescriptorSetLayoutBinding{
-            binding: 0,
-            descriptor_type: DescriptorType::UNIFORM_BUFFER,
-            descriptor_count: 1,
-            stage_flags: ShaderStageFlags::VERTEX,
-            p_immutable_samplers: ptr::null(),
-        },
-    ]
 }
-*/
@@ -1,133 +1,90 @@
 use super::conversion;
+use super::PipelineError;
+use crate::geometry::vertex::Vertex;
 use crate::render_sequence::render_pass::pipeline::shader::attribute::AttributeDescription;
 
-
 use ash::vk::{VertexInputAttributeDescription, VertexInputBindingDescription, VertexInputRate};
 
-/*
-pub fn input_binding_from_description(
-    input_description: &AttributeDescription,
-) -> VertexInputBindingDescription {
-    VertexInputBindingDescription {
-        binding: 0,
-        stride: input_description.stride_,
-        input_rate: VertexInputRate::VERTEX,
-    }
-}
-
-pub fn input_attribute_from_description(
-    attribute_description: &AttributeDescription,
-) -> VertexInputAttributeDescription {
-    VertexInputAttributeDescription {
-        location: attribute_description.location_,
-        binding: 0,
-        format: conversion::format_to_vulkan_format(&attribute_description.format_),
-        offset: attribute_description.offset_,
+/// Returns the vertex buffer bindings needed to feed the given vertex shader inputs. Shaders with
+/// no inputs, such as fullscreen passes, need no vertex buffer
+pub fn input_bindings_from_descriptions(
+    attribute_descriptions: &[AttributeDescription],
+) -> Vec<VertexInputBindingDescription> {
+    if attribute_descriptions.is_empty() {
+        vec![]
+    } else {
+        vec![get_vertex_input_binding_description()]
     }
 }
 
+/// Maps reflected vertex shader inputs onto the members of the engine Vertex. Returns an error if
+/// an input has no Vertex member at its location, or if its format differs from that member's
 pub fn input_attributes_from_descriptions(
-    attribute_descriptions: &Vec<AttributeDescription>,
-) -> Vec<VertexInputAttributeDescription> {
+    attribute_descriptions: &[AttributeDescription],
+) -> Result<Vec<VertexInputAttributeDescription>, PipelineError> {
+    let vertex_attributes = get_vertex_attribute_descriptions();
+
     let mut vulkan_attribute_descriptions = vec![];
 
     for attribute_description in attribute_descriptions.iter() {
-        vulkan_attribute_descriptions.push(input_attribute_from_description(attribute_description));
-    }
-
-    vulkan_attribute_descriptions
-}
-*/
-/*
-pub fn binding_description_from_reflection(input_variables: &Vec<ReflectInterfaceVariable>) -> VertexInputBindingDescription
-{
-    let mut vertex_size = 0;
-
-    for variable in input_variables.iter()
-    {
-        vertex_size += reflection_utils::size_of_reflect_numeric_trait(&variable.numeric);
+        let vertex_attribute = vertex_attributes
+            .iter()
+            .find(|attribute| attribute.location == attribute_description.location_)
+            .ok_or(PipelineError::MissingVertexAttribute {
+                location: attribute_description.location_,
+            })?;
+
+        let format = conversion::format_to_vulkan_format(&attribute_description.format_);
+
+        if format != vertex_attribute.format {
+            return Err(PipelineError::VertexAttributeFormatMismatch {
+                location: attribute_description.location_,
+                expected: vertex_attribute.format,
+                found: format,
+            });
+        }
+
+        vulkan_attribute_descriptions.push(*vertex_attribute);
     }
 
-    VertexInputBindingDescription{
-        binding: 0,
-        stride: vertex_size,
-        input_rate: VertexInputRate::VERTEX
-    }
+    Ok(vulkan_attribute_descriptions)
 }
 
-pub fn attribute_descriptions_from_reflection(input_variables: &Vec<ReflectInterfaceVariable>) -> Vec<VertexInputAttributeDescription>
-{
-    let mut attribute_descriptions = vec![];
-    let mut curr_offset = 0u32;
-    for variable in input_variables.iter()
-    {
-        attribute_descriptions.push(VertexInputAttributeDescription{
-            location: variable.location,
-            binding: 0,
-            format: match variable.format
-            {
-                ReflectFormat::Undefined => Format::UNDEFINED,
-                ReflectFormat::R32_UINT => Format::R32_UINT,
-                ReflectFormat::R32_SINT => Format::R32_SINT,
-                ReflectFormat::R32_SFLOAT => Format::R32_SFLOAT,
-                ReflectFormat::R32G32_UINT => Format::R32G32_UINT,
-                ReflectFormat::R32G32_SINT => Format::R32G32_SINT,
-                ReflectFormat::R32G32_SFLOAT => Format::R32G32_SFLOAT,
-                ReflectFormat::R32G32B32_UINT => Format::R32G32B32_UINT,
-                ReflectFormat::R32G32B32_SINT => Format::R32G32B32_SINT,
-                ReflectFormat::R32G32B32_SFLOAT => Format::R32G32B32_SFLOAT,
-                ReflectFormat::R32G32B32A32_UINT => Format::R32G32B32A32_UINT,
-                ReflectFormat::R32G32B32A32_SINT => Format::R32G32B32A32_SINT,
-                ReflectFormat::R32G32B32A32_SFLOAT => Format::R32G32B32A32_SFLOAT,
-            },
-            offset: curr_offset,
-        });
-
-        curr_offset += reflection_utils::size_of_reflect_numeric_trait(&variable.numeric);
-    }
-
-    attribute_descriptions
-
-}
-
-
-
-pub fn get_vertex_input_binding_description() -> VertexInputBindingDescription
-{
-    ash::vk::VertexInputBindingDescription{
+/// Returns the binding description of the engine's interleaved vertex buffer
+pub fn get_vertex_input_binding_description() -> VertexInputBindingDescription {
+    VertexInputBindingDescription {
         binding: 0,
         stride: std::mem::size_of::<Vertex>() as u32,
-        input_rate: ash::vk::VertexInputRate::VERTEX,
+        input_rate: VertexInputRate::VERTEX,
     }
 }
 
-pub fn get_vertex_attribute_descriptions() -> [VertexInputAttributeDescription; 4]
-{
+/// Returns the attribute descriptions of every member of the engine Vertex, by location
+pub fn get_vertex_attribute_descriptions() -> [VertexInputAttributeDescription; 4] {
     [
-        VertexInputAttributeDescription{
+        VertexInputAttributeDescription {
             location: 0,
             binding: 0,
             format: ash::vk::Format::R32G32B32_SFLOAT,
-            offset: offset_of!(Vertex, position_) as u32
+            offset: offset_of!(Vertex, position_) as u32,
         },
-        VertexInputAttributeDescription{
+        VertexInputAttributeDescription {
             location: 1,
             binding: 0,
             format: ash::vk::Format::R32G32B32_SFLOAT,
-            offset: offset_of!(Vertex, normal_) as u32
+            offset: offset_of!(Vertex, normal_) as u32,
         },
-        VertexInputAttributeDescription{
+        VertexInputAttributeDescription {
             location: 2,
             binding: 0,
             format: ash::vk::Format::R32G32B32_SFLOAT,
-            offset: offset_of!(Vertex, color_) as u32
+            offset: offset_of!(Vertex, color_) as u32,
         },
-        VertexInputAttributeDescription{
+        VertexInputAttributeDescription {
             location: 3,
             binding: 0,
             format: ash::vk::Format::R32G32_SFLOAT,
-            offset:  offset_of!(Vertex, uv_) as u32
+            offset: offset_of!(Vertex, uv_) as u32,
         },
     ]
 }
-*/
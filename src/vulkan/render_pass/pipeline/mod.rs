@@ -1,78 +1,250 @@
+use std::fmt;
 use std::ptr;
 
 use ash::vk::{
-    GraphicsPipelineCreateInfo, PipelineCache, PipelineCreateFlags, PipelineLayout, StructureType,
+    DescriptorSetLayout, GraphicsPipelineCreateInfo, PipelineCache, PipelineCreateFlags,
+    PipelineLayout, PushConstantRange, RenderPass, ShaderStageFlags, StructureType,
 };
 use ash::Device;
 
+use crate::render_sequence::render_pass::pipeline::shader::ShaderStage;
+use crate::render_sequence::render_pass::pipeline::state::{
+    BlendState, DepthStencilState, PolygonMode, PrimitiveTopology, RasterizationState,
+    StencilOpState,
+};
 use crate::render_sequence::render_pass::pipeline::PipelineDescription;
+use crate::vulkan::physical_device::PhysicalDevice;
 use ash::version::DeviceV1_0;
 
 pub mod conversion;
 pub mod descriptor_set_layout;
 mod input_descriptions;
-mod pipeline_layout;
-mod push_constant;
-mod shader;
+pub mod pipeline_layout;
+pub mod push_constant;
+pub mod shader;
+
+/// Reasons a PipelineDescription cannot be built into a vulkan pipeline
+#[derive(Clone, Debug)]
+pub enum PipelineError {
+    /// A vertex shader input has no engine Vertex member at its location
+    MissingVertexAttribute { location: u32 },
+    /// A vertex shader input does not have the format of the engine Vertex member at its location
+    VertexAttributeFormatMismatch {
+        location: u32,
+        expected: ash::vk::Format,
+        found: ash::vk::Format,
+    },
+    /// Two shader stages declare the same descriptor binding with different types or counts
+    DescriptorConflict { set: u32, binding: u32 },
+    /// A subpass input reads an attachment that no earlier subpass writes
+    InvalidInputAttachment {
+        subpass: u32,
+        input_attachment_index: u32,
+    },
+    /// The pipeline's state needs an optional device feature the device does not support
+    UnsupportedFeature { feature: &'static str },
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::MissingVertexAttribute { location } => write!(
+                f,
+                "vertex shader input at location {} has no matching member in the engine Vertex",
+                location
+            ),
+            PipelineError::VertexAttributeFormatMismatch {
+                location,
+                expected,
+                found,
+            } => write!(
+                f,
+                "vertex shader input at location {} has format {:?}, but the engine Vertex \
+                 member at that location has format {:?}",
+                location, found, expected
+            ),
+            PipelineError::DescriptorConflict { set, binding } => write!(
+                f,
+                "descriptor at set {} binding {} is declared with different types or counts \
+                 in different shader stages",
+                set, binding
+            ),
+            PipelineError::InvalidInputAttachment {
+                subpass,
+                input_attachment_index,
+            } => write!(
+                f,
+                "subpass {} reads input attachment {}, which no earlier subpass writes",
+                subpass, input_attachment_index
+            ),
+            PipelineError::UnsupportedFeature { feature } => write!(
+                f,
+                "pipeline state needs the {} device feature, which the device does not support",
+                feature
+            ),
+        }
+    }
+}
+
+/// Multisampling of a render pass's attachments, shared by all of its pipelines
+#[derive(Clone, Copy, Debug)]
+pub struct Multisample {
+    pub samples_: ash::vk::SampleCountFlags,
+    /// Minimum fraction of samples shaded individually, from 0 to 1. None shades each pixel once
+    pub min_sample_shading_: Option<f32>,
+}
+
+impl Multisample {
+    pub fn is_multisampled(&self) -> bool {
+        self.samples_ != ash::vk::SampleCountFlags::TYPE_1
+    }
+}
+
+impl Default for Multisample {
+    fn default() -> Multisample {
+        Multisample {
+            samples_: ash::vk::SampleCountFlags::TYPE_1,
+            min_sample_shading_: None,
+        }
+    }
+}
+
+/// Optional device features pipeline state may rely on. Each is enabled on the logical device
+/// where the physical device supports it
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PipelineFeatures {
+    /// color outputs of a subpass may be blended differently
+    pub independent_blend_: bool,
+    /// a fixed depth bias may be clamped
+    pub depth_bias_clamp_: bool,
+    /// primitives may be rasterized as wireframe or points
+    pub fill_mode_non_solid_: bool,
+}
+
+impl PipelineFeatures {
+    pub fn from_physical_device(physical_device: &PhysicalDevice) -> PipelineFeatures {
+        PipelineFeatures {
+            independent_blend_: physical_device.features_.independent_blend == ash::vk::TRUE,
+            depth_bias_clamp_: physical_device.features_.depth_bias_clamp == ash::vk::TRUE,
+            fill_mode_non_solid_: physical_device.features_.fill_mode_non_solid == ash::vk::TRUE,
+        }
+    }
+}
 
 #[repr(C)]
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug)]
 pub struct Pipeline {
     pub pipeline_handle_: ash::vk::Pipeline,
     pub layout_: PipelineLayout,
+    pub descriptor_set_layouts_: Vec<DescriptorSetLayout>,
+    pub push_constant_ranges_: Vec<PushConstantRange>,
 }
 
 impl Pipeline {
-    /*
+    /// Builds a graphics pipeline for a subpass of a render pass from a reflected
+    /// PipelineDescription. Vertex shader inputs are mapped onto the engine Vertex layout,
+    /// descriptor sets and push constants of all stages are merged into one pipeline layout.
+    /// Returns an error if the reflected data cannot be mapped onto the engine's layouts, or its
+    /// state needs a device feature that is not supported. Panics if any vulkan object cannot be
+    /// created
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the pipeline is created on
+    ///
+    /// * 'pipeline_description' - reflected shader data describing the pipeline
+    ///
+    /// * 'render_pass' - the render pass the pipeline will be used in
+    ///
+    /// * 'subpass' - the index of the subpass the pipeline will be used in
+    ///
+    /// * 'multisample' - the sample count and sample shading of the subpass
+    ///
+    /// * 'features' - the optional device features the pipeline state may use
+    ///
+    /// * 'pipeline_cache' - cache used to speed up pipeline compilation
+    ///
     pub fn from_pipeline_descriptor(
         device: &Device,
         pipeline_description: &PipelineDescription,
-    ) -> Pipeline {
-        let input_binding_descriptions = [input_descriptions::input_binding_from_description(
-            &pipeline_description.input_,
-        )]
-        .to_vec();
-        let attribute_descriptions = input_descriptions::input_attributes_from_descriptions(
-            &pipeline_description.input_.attributes_,
-        );
+        render_pass: RenderPass,
+        subpass: u32,
+        multisample: &Multisample,
+        features: &PipelineFeatures,
+        pipeline_cache: PipelineCache,
+    ) -> Result<Pipeline, PipelineError> {
+        let no_vertex_inputs = vec![];
+        let vertex_inputs = match pipeline_description
+            .shaders_
+            .iter()
+            .find(|shader| shader.shader_.stage_ == ShaderStage::Vertex)
+        {
+            Some(vertex_shader) => &vertex_shader.input_,
+            None => &no_vertex_inputs,
+        };
+
+        let input_binding_descriptions =
+            input_descriptions::input_bindings_from_descriptions(vertex_inputs);
+        let attribute_descriptions =
+            input_descriptions::input_attributes_from_descriptions(vertex_inputs)?;
+
+        let push_constants =
+            push_constant::from_shader_descriptions(&pipeline_description.shaders_);
+
+        // Merge bindings before creating any vulkan objects, so that invalid descriptions do not
+        // leak layouts
+        descriptor_set_layout::merge_bindings(&pipeline_description.shaders_)?;
+        Pipeline::check_features(pipeline_description, features)?;
 
         let vertex_input_state = Pipeline::build_pipeline_vertex_input_state_create_info(
             &input_binding_descriptions,
             &attribute_descriptions,
         );
 
-        let input_assembly_state = Pipeline::build_pipeline_input_assembly_state_create_info();
+        let input_assembly_state = Pipeline::build_pipeline_input_assembly_state_create_info(
+            &pipeline_description.topology_,
+        );
 
-        let viewport = ash::vk::Viewport {
-            x: 0.0,
-            y: 0.0,
-            width: 1920f32,
-            height: 1080f32,
-            min_depth: 0.0,
-            max_depth: 1.0,
-        };
+        // Viewport and scissor are set per frame from the render target, so that pipelines do not
+        // need to be rebuilt when the window is resized
+        let mut dynamic_states = vec![
+            ash::vk::DynamicState::VIEWPORT,
+            ash::vk::DynamicState::SCISSOR,
+        ];
 
-        let scissor = ash::vk::Rect2D {
-            offset: ash::vk::Offset2D { x: 0, y: 0 },
-            extent: ash::vk::Extent2D {
-                width: 1920,
-                height: 1080,
-            },
-        };
+        if pipeline_description.rasterization_.dynamic_depth_bias_ {
+            dynamic_states.push(ash::vk::DynamicState::DEPTH_BIAS);
+        }
 
-        let viewport_state =
-            Pipeline::build_pipeline_viewport_state_create_info(&viewport, &scissor);
-        let rasterization_state = Pipeline::build_pipeline_rasterization_state_create_info();
-        let multisample_state = Pipeline::build_pipeline_multisample_state_create_info();
-        let depth_stencil_state = Pipeline::build_pipeline_depth_stencil_state_create_info();
-        let color_blend_attachment_state = Pipeline::build_pipeline_color_blend_attachment_state();
+        let dynamic_state = Pipeline::build_pipeline_dynamic_state_create_info(&dynamic_states);
+        let viewport_state = Pipeline::build_pipeline_viewport_state_create_info();
+        let rasterization_state = Pipeline::build_pipeline_rasterization_state_create_info(
+            &pipeline_description.rasterization_,
+        );
+        let multisample_state = Pipeline::build_pipeline_multisample_state_create_info(multisample);
+        let depth_stencil_state = Pipeline::build_pipeline_depth_stencil_state_create_info(
+            &pipeline_description.depth_stencil_,
+        );
+
+        let fragment_outputs = pipeline_description.get_fragment_outputs();
+        let color_blend_attachment_states: Vec<_> = match fragment_outputs {
+            Some(outputs) => outputs
+                .iter()
+                .map(|output| {
+                    Pipeline::build_pipeline_color_blend_attachment_state(
+                        &pipeline_description.get_blend_state(output.location_),
+                    )
+                })
+                .collect(),
+            None => vec![],
+        };
         let color_blend_state =
-            Pipeline::build_pipeline_color_blend_state_create_info(&color_blend_attachment_state);
+            Pipeline::build_pipeline_color_blend_state_create_info(&color_blend_attachment_states);
 
         let mut vulkan_shaders = vec![];
 
         for shader in pipeline_description.shaders_.iter() {
-            vulkan_shaders.push(shader::VulkanShader::new(device, shader));
+            vulkan_shaders.push(shader::VulkanShader::new(device, &shader.shader_));
         }
 
         let mut shader_stages = vec![];
@@ -81,20 +253,10 @@ impl Pipeline {
             shader_stages.push(shader.build_pipeline_shader_stage_create_info());
         }
 
-        let mut push_constants = vec![];
-
-        for push_constant_description in pipeline_description.push_constants_.iter() {
-            push_constants.push(push_constant::from_description(push_constant_description));
-        }
-
-        let mut descriptor_set_layouts = vec![];
-
-        for descriptor_set_description in pipeline_description.descriptor_sets_.iter() {
-            descriptor_set_layouts.push(descriptor_set_layout::from_description(
-                device,
-                descriptor_set_description,
-            ));
-        }
+        let descriptor_set_layouts = descriptor_set_layout::from_shader_descriptions(
+            device,
+            &pipeline_description.shaders_,
+        )?;
 
         let pipeline_layout =
             pipeline_layout::new(device, &push_constants, &descriptor_set_layouts);
@@ -113,26 +275,104 @@ impl Pipeline {
             p_multisample_state: &multisample_state,
             p_depth_stencil_state: &depth_stencil_state,
             p_color_blend_state: &color_blend_state,
-            p_dynamic_state: ptr::null(),
+            p_dynamic_state: &dynamic_state,
             layout: pipeline_layout,
-            render_pass: Default::default(),
-            subpass: 0,
-            base_pipeline_handle: Default::default(),
-            base_pipeline_index: 0,
+            render_pass,
+            subpass,
+            base_pipeline_handle: ash::vk::Pipeline::null(),
+            base_pipeline_index: -1,
         };
 
         let pipeline = unsafe {
             device
-                .create_graphics_pipelines(PipelineCache::null(), &[pipeline_create_info], None)
+                .create_graphics_pipelines(pipeline_cache, &[pipeline_create_info], None)
                 .expect("Could not create pipeline")
         };
 
-        Pipeline {
+        for shader in vulkan_shaders.iter() {
+            shader.destroy(device);
+        }
+
+        Ok(Pipeline {
             pipeline_handle_: *pipeline.first().unwrap(),
             layout_: pipeline_layout,
+            descriptor_set_layouts_: descriptor_set_layouts,
+            push_constant_ranges_: push_constants,
+        })
+    }
+
+    /// Returns an error if the description's state needs an optional device feature that is not
+    /// supported: color outputs blended differently without independentBlend, a clamped fixed
+    /// depth bias without depthBiasClamp, or wireframe or point rasterization without
+    /// fillModeNonSolid
+    fn check_features(
+        pipeline_description: &PipelineDescription,
+        features: &PipelineFeatures,
+    ) -> Result<(), PipelineError> {
+        if !features.independent_blend_ {
+            let blend_states: Vec<BlendState> = pipeline_description
+                .get_fragment_outputs()
+                .map(|outputs| {
+                    outputs
+                        .iter()
+                        .map(|output| pipeline_description.get_blend_state(output.location_))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            if blend_states
+                .iter()
+                .any(|blend_state| *blend_state != blend_states[0])
+            {
+                return Err(PipelineError::UnsupportedFeature {
+                    feature: "independentBlend",
+                });
+            }
+        }
+
+        let clamped = match pipeline_description.rasterization_.depth_bias_ {
+            Some(depth_bias) => depth_bias.clamp_ != 0.0,
+            None => false,
+        };
+
+        if clamped && !features.depth_bias_clamp_ {
+            return Err(PipelineError::UnsupportedFeature {
+                feature: "depthBiasClamp",
+            });
+        }
+
+        let non_solid = pipeline_description.rasterization_.polygon_mode_ != PolygonMode::Fill;
+
+        if non_solid && !features.fill_mode_non_solid_ {
+            return Err(PipelineError::UnsupportedFeature {
+                feature: "fillModeNonSolid",
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the shader stages that must be given when pushing constants to the given byte
+    /// range of this pipeline: the stages of every push constant range overlapping it
+    pub fn get_push_constant_stage_flags(&self, offset: u32, size: u32) -> ShaderStageFlags {
+        self.push_constant_ranges_
+            .iter()
+            .filter(|range| range.offset < offset + size && offset < range.offset + range.size)
+            .fold(ShaderStageFlags::empty(), |flags, range| {
+                flags | range.stage_flags
+            })
+    }
+
+    /// Destroys the pipeline, its layout and its descriptor set layouts
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_pipeline(self.pipeline_handle_, None);
+            device.destroy_pipeline_layout(self.layout_, None);
+            for descriptor_set_layout in self.descriptor_set_layouts_.iter() {
+                device.destroy_descriptor_set_layout(*descriptor_set_layout, None);
+            }
         }
     }
-*/
     /*
     pub fn from_reflection(device: &Device, shaders: &Vec<Shader>)
     {
@@ -280,80 +520,140 @@ impl Pipeline {
         }
     */
     fn build_pipeline_input_assembly_state_create_info(
+        topology: &PrimitiveTopology,
     ) -> ash::vk::PipelineInputAssemblyStateCreateInfo {
         ash::vk::PipelineInputAssemblyStateCreateInfo {
             s_type: ash::vk::StructureType::PIPELINE_INPUT_ASSEMBLY_STATE_CREATE_INFO,
             p_next: ptr::null(),
             flags: ash::vk::PipelineInputAssemblyStateCreateFlags::empty(),
-            topology: ash::vk::PrimitiveTopology::TRIANGLE_LIST,
+            topology: conversion::topology_to_vulkan_topology(topology),
             primitive_restart_enable: ash::vk::FALSE,
         }
     }
 
-    fn build_pipeline_viewport_state_create_info(
-        viewport: &ash::vk::Viewport,
-        scissor: &ash::vk::Rect2D,
-    ) -> ash::vk::PipelineViewportStateCreateInfo {
+    fn build_pipeline_viewport_state_create_info() -> ash::vk::PipelineViewportStateCreateInfo {
         ash::vk::PipelineViewportStateCreateInfo {
             s_type: ash::vk::StructureType::PIPELINE_VIEWPORT_STATE_CREATE_INFO,
             p_next: ptr::null(),
             flags: ash::vk::PipelineViewportStateCreateFlags::empty(),
             viewport_count: 1,
-            p_viewports: viewport,
+            p_viewports: ptr::null(),
             scissor_count: 1,
-            p_scissors: scissor,
+            p_scissors: ptr::null(),
+        }
+    }
+
+    fn build_pipeline_dynamic_state_create_info(
+        dynamic_states: &[ash::vk::DynamicState],
+    ) -> ash::vk::PipelineDynamicStateCreateInfo {
+        ash::vk::PipelineDynamicStateCreateInfo {
+            s_type: ash::vk::StructureType::PIPELINE_DYNAMIC_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: ash::vk::PipelineDynamicStateCreateFlags::empty(),
+            dynamic_state_count: dynamic_states.len() as u32,
+            p_dynamic_states: dynamic_states.as_ptr(),
         }
     }
 
     fn build_pipeline_rasterization_state_create_info(
+        rasterization: &RasterizationState,
     ) -> ash::vk::PipelineRasterizationStateCreateInfo {
+        let (depth_bias_enable, depth_bias) = match rasterization.depth_bias_ {
+            Some(depth_bias) => (ash::vk::TRUE, depth_bias),
+            None if rasterization.dynamic_depth_bias_ => (ash::vk::TRUE, Default::default()),
+            None => (ash::vk::FALSE, Default::default()),
+        };
+
         ash::vk::PipelineRasterizationStateCreateInfo {
             s_type: ash::vk::StructureType::PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
             p_next: ptr::null(),
             flags: ash::vk::PipelineRasterizationStateCreateFlags::empty(),
             depth_clamp_enable: ash::vk::FALSE,
             rasterizer_discard_enable: ash::vk::FALSE,
-            polygon_mode: ash::vk::PolygonMode::FILL,
-            cull_mode: ash::vk::CullModeFlags::BACK,
-            front_face: ash::vk::FrontFace::CLOCKWISE,
-            depth_bias_enable: ash::vk::FALSE,
-            depth_bias_constant_factor: 0.0,
-            depth_bias_clamp: 0.0,
-            depth_bias_slope_factor: 0.0,
+            polygon_mode: conversion::polygon_mode_to_vulkan_polygon_mode(
+                &rasterization.polygon_mode_,
+            ),
+            cull_mode: conversion::cull_mode_to_vulkan_cull_mode(&rasterization.cull_mode_),
+            front_face: conversion::front_face_to_vulkan_front_face(&rasterization.front_face_),
+            depth_bias_enable,
+            depth_bias_constant_factor: depth_bias.constant_factor_,
+            depth_bias_clamp: depth_bias.clamp_,
+            depth_bias_slope_factor: depth_bias.slope_factor_,
             line_width: 1.0,
         }
     }
 
-    fn build_pipeline_multisample_state_create_info() -> ash::vk::PipelineMultisampleStateCreateInfo
-    {
+    fn build_pipeline_multisample_state_create_info(
+        multisample: &Multisample,
+    ) -> ash::vk::PipelineMultisampleStateCreateInfo {
         ash::vk::PipelineMultisampleStateCreateInfo {
             s_type: ash::vk::StructureType::PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
             p_next: ptr::null(),
             flags: ash::vk::PipelineMultisampleStateCreateFlags::empty(),
-            rasterization_samples: ash::vk::SampleCountFlags::TYPE_1,
-            sample_shading_enable: ash::vk::FALSE,
-            min_sample_shading: 0.0,
+            rasterization_samples: multisample.samples_,
+            sample_shading_enable: match multisample.min_sample_shading_ {
+                Some(_) => ash::vk::TRUE,
+                None => ash::vk::FALSE,
+            },
+            min_sample_shading: multisample.min_sample_shading_.unwrap_or(0.0),
             p_sample_mask: ptr::null(),
             alpha_to_coverage_enable: ash::vk::FALSE,
             alpha_to_one_enable: ash::vk::FALSE,
         }
     }
 
-    fn build_pipeline_color_blend_attachment_state() -> ash::vk::PipelineColorBlendAttachmentState {
-        ash::vk::PipelineColorBlendAttachmentState {
-            blend_enable: ash::vk::FALSE,
-            src_color_blend_factor: ash::vk::BlendFactor::ZERO,
-            dst_color_blend_factor: ash::vk::BlendFactor::ZERO,
-            color_blend_op: ash::vk::BlendOp::ADD,
-            src_alpha_blend_factor: ash::vk::BlendFactor::ZERO,
-            dst_alpha_blend_factor: ash::vk::BlendFactor::ZERO,
-            alpha_blend_op: ash::vk::BlendOp::ADD,
-            color_write_mask: ash::vk::ColorComponentFlags::all(),
+    fn build_pipeline_color_blend_attachment_state(
+        blend_state: &BlendState,
+    ) -> ash::vk::PipelineColorBlendAttachmentState {
+        let mut color_write_mask = ash::vk::ColorComponentFlags::empty();
+
+        if blend_state.write_red_ {
+            color_write_mask |= ash::vk::ColorComponentFlags::R;
+        }
+        if blend_state.write_green_ {
+            color_write_mask |= ash::vk::ColorComponentFlags::G;
+        }
+        if blend_state.write_blue_ {
+            color_write_mask |= ash::vk::ColorComponentFlags::B;
+        }
+        if blend_state.write_alpha_ {
+            color_write_mask |= ash::vk::ColorComponentFlags::A;
+        }
+
+        match &blend_state.blend_ {
+            Some(blend) => ash::vk::PipelineColorBlendAttachmentState {
+                blend_enable: ash::vk::TRUE,
+                src_color_blend_factor: conversion::blend_factor_to_vulkan_blend_factor(
+                    &blend.src_color_factor_,
+                ),
+                dst_color_blend_factor: conversion::blend_factor_to_vulkan_blend_factor(
+                    &blend.dst_color_factor_,
+                ),
+                color_blend_op: conversion::blend_op_to_vulkan_blend_op(&blend.color_op_),
+                src_alpha_blend_factor: conversion::blend_factor_to_vulkan_blend_factor(
+                    &blend.src_alpha_factor_,
+                ),
+                dst_alpha_blend_factor: conversion::blend_factor_to_vulkan_blend_factor(
+                    &blend.dst_alpha_factor_,
+                ),
+                alpha_blend_op: conversion::blend_op_to_vulkan_blend_op(&blend.alpha_op_),
+                color_write_mask,
+            },
+            None => ash::vk::PipelineColorBlendAttachmentState {
+                blend_enable: ash::vk::FALSE,
+                src_color_blend_factor: ash::vk::BlendFactor::ONE,
+                dst_color_blend_factor: ash::vk::BlendFactor::ZERO,
+                color_blend_op: ash::vk::BlendOp::ADD,
+                src_alpha_blend_factor: ash::vk::BlendFactor::ONE,
+                dst_alpha_blend_factor: ash::vk::BlendFactor::ZERO,
+                alpha_blend_op: ash::vk::BlendOp::ADD,
+                color_write_mask,
+            },
         }
     }
 
     fn build_pipeline_color_blend_state_create_info(
-        color_blend_attachment_state: &ash::vk::PipelineColorBlendAttachmentState,
+        color_blend_attachment_states: &[ash::vk::PipelineColorBlendAttachmentState],
     ) -> ash::vk::PipelineColorBlendStateCreateInfo {
         ash::vk::PipelineColorBlendStateCreateInfo {
             s_type: ash::vk::StructureType::PIPELINE_COLOR_BLEND_STATE_CREATE_INFO,
@@ -361,39 +661,40 @@ impl Pipeline {
             flags: ash::vk::PipelineColorBlendStateCreateFlags::empty(),
             logic_op_enable: ash::vk::FALSE,
             logic_op: ash::vk::LogicOp::COPY,
-            attachment_count: 1,
-            p_attachments: color_blend_attachment_state,
+            attachment_count: color_blend_attachment_states.len() as u32,
+            p_attachments: color_blend_attachment_states.as_ptr(),
             blend_constants: [0.0, 0.0, 0.0, 0.0],
         }
     }
 
-    fn build_stencil_op_state() -> ash::vk::StencilOpState {
+    fn build_stencil_op_state(stencil: &StencilOpState) -> ash::vk::StencilOpState {
         ash::vk::StencilOpState {
-            fail_op: ash::vk::StencilOp::KEEP,
-            pass_op: ash::vk::StencilOp::KEEP,
-            depth_fail_op: ash::vk::StencilOp::KEEP,
-            compare_op: ash::vk::CompareOp::ALWAYS,
-            compare_mask: 0,
-            write_mask: 0,
-            reference: 0,
+            fail_op: conversion::stencil_op_to_vulkan_stencil_op(&stencil.fail_op_),
+            pass_op: conversion::stencil_op_to_vulkan_stencil_op(&stencil.pass_op_),
+            depth_fail_op: conversion::stencil_op_to_vulkan_stencil_op(&stencil.depth_fail_op_),
+            compare_op: conversion::compare_op_to_vulkan_compare_op(&stencil.compare_op_),
+            compare_mask: stencil.compare_mask_,
+            write_mask: stencil.write_mask_,
+            reference: stencil.reference_,
         }
     }
 
     fn build_pipeline_depth_stencil_state_create_info(
+        depth_stencil: &DepthStencilState,
     ) -> ash::vk::PipelineDepthStencilStateCreateInfo {
-        let stencil_op_state = Pipeline::build_stencil_op_state();
-
         ash::vk::PipelineDepthStencilStateCreateInfo {
             s_type: ash::vk::StructureType::PIPELINE_DEPTH_STENCIL_STATE_CREATE_INFO,
             p_next: ptr::null(),
             flags: ash::vk::PipelineDepthStencilStateCreateFlags::empty(),
-            depth_test_enable: ash::vk::TRUE,
-            depth_write_enable: ash::vk::TRUE,
-            depth_compare_op: ash::vk::CompareOp::LESS,
+            depth_test_enable: depth_stencil.depth_test_ as ash::vk::Bool32,
+            depth_write_enable: depth_stencil.depth_write_ as ash::vk::Bool32,
+            depth_compare_op: conversion::compare_op_to_vulkan_compare_op(
+                &depth_stencil.depth_compare_op_,
+            ),
             depth_bounds_test_enable: ash::vk::FALSE,
-            stencil_test_enable: ash::vk::FALSE,
-            front: stencil_op_state,
-            back: stencil_op_state,
+            stencil_test_enable: depth_stencil.stencil_test_ as ash::vk::Bool32,
+            front: Pipeline::build_stencil_op_state(&depth_stencil.front_),
+            back: Pipeline::build_stencil_op_state(&depth_stencil.back_),
             min_depth_bounds: 0.0,
             max_depth_bounds: 1.0,
         }
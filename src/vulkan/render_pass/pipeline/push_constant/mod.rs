@@ -2,40 +2,109 @@ use ash::vk::PushConstantRange;
 
 use super::conversion;
 use crate::render_sequence::render_pass::pipeline::shader::push_constant::PushConstantDescription;
+use crate::render_sequence::render_pass::pipeline::shader::{ShaderDescription, ShaderStage};
 
-/*
-pub fn from_description(description: &PushConstantDescription) -> PushConstantRange {
+pub fn from_description(
+    description: &PushConstantDescription,
+    shader_stage: &ShaderStage,
+) -> PushConstantRange {
     PushConstantRange {
-        stage_flags: conversion::shader_stage_to_vulkan_shader_stage(&description.shader_stage_),
+        stage_flags: conversion::shader_stage_to_vulkan_shader_stage(shader_stage),
         offset: description.offset_,
         size: description.size_,
     }
 }
-*/
-/*
-pub fn from_reflection(reflect_block_variables: &Vec<ReflectBlockVariable>, shader_stage: ShaderKind) -> Vec<PushConstantRange>
-{
-    let mut push_constant_ranges = vec![];
-
-
-    for variable in reflect_block_variables.iter()
-    {
-        /// TODO: Determine which size and offset values should be used
-        push_constant_ranges.push(    PushConstantRange{
-            stage_flags: match shader_stage{
-                ShaderKind::Vertex => ShaderStageFlags::VERTEX,
-                ShaderKind::Fragment => ShaderStageFlags::FRAGMENT,
-                ShaderKind::Compute => ShaderStageFlags::COMPUTE,
-                ShaderKind::Geometry => ShaderStageFlags::GEOMETRY,
-                ShaderKind::TessControl => ShaderStageFlags::TESSELLATION_CONTROL,
-                ShaderKind::TessEvaluation => ShaderStageFlags::TESSELLATION_EVALUATION,
-                _ => ShaderStageFlags::default(),
-            },
-            offset: variable.absolute_offset,
-            size: variable.size,
-        })
+
+/// Collects the push constant blocks of every shader in a pipeline into push constant ranges.
+/// Each stage gets a single range spanning all of its blocks, as a pipeline layout may give a
+/// stage at most one range, and stages whose ranges span the same bytes share one
+pub fn from_shader_descriptions(shaders: &[ShaderDescription]) -> Vec<PushConstantRange> {
+    let mut ranges = vec![];
+
+    for shader in shaders.iter() {
+        for push_constant in shader.push_constants_.iter() {
+            ranges.push(from_description(push_constant, &shader.shader_.stage_));
+        }
     }
 
-    push_constant_ranges
+    merge_ranges(ranges)
+}
+
+fn merge_ranges(ranges: Vec<PushConstantRange>) -> Vec<PushConstantRange> {
+    let mut stage_ranges: Vec<PushConstantRange> = vec![];
+
+    // Blocks of the same stage are merged from the lowest offset to the highest end
+    for range in ranges.into_iter() {
+        match stage_ranges
+            .iter_mut()
+            .find(|stage_range| stage_range.stage_flags == range.stage_flags)
+        {
+            Some(stage_range) => {
+                let end = std::cmp::max(
+                    stage_range.offset + stage_range.size,
+                    range.offset + range.size,
+                );
+                stage_range.offset = std::cmp::min(stage_range.offset, range.offset);
+                stage_range.size = end - stage_range.offset;
+            }
+            None => stage_ranges.push(range),
+        }
+    }
+
+    let mut merged_ranges: Vec<PushConstantRange> = vec![];
+
+    for range in stage_ranges.into_iter() {
+        match merged_ranges
+            .iter_mut()
+            .find(|merged| merged.offset == range.offset && merged.size == range.size)
+        {
+            Some(merged) => merged.stage_flags |= range.stage_flags,
+            None => merged_ranges.push(range),
+        }
+    }
+
+    merged_ranges.sort_by_key(|range| range.offset);
+    merged_ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk::{PushConstantRange, ShaderStageFlags};
+
+    #[test]
+    fn merge_ranges_test() {
+        let range = |stage_flags, offset, size| PushConstantRange {
+            stage_flags,
+            offset,
+            size,
+        };
+
+        let merged = super::merge_ranges(vec![
+            range(ShaderStageFlags::FRAGMENT, 64, 16),
+            range(ShaderStageFlags::VERTEX, 0, 128),
+            range(ShaderStageFlags::FRAGMENT, 128, 4),
+            range(ShaderStageFlags::GEOMETRY, 0, 128),
+        ]);
+
+        // No stage may appear in two ranges
+        for (i, first) in merged.iter().enumerate() {
+            for second in merged[i + 1..].iter() {
+                assert!((first.stage_flags & second.stage_flags).is_empty());
+            }
+        }
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(
+            (merged[0].offset, merged[0].size, merged[0].stage_flags),
+            (
+                0,
+                128,
+                ShaderStageFlags::VERTEX | ShaderStageFlags::GEOMETRY
+            )
+        );
+        assert_eq!(
+            (merged[1].offset, merged[1].size, merged[1].stage_flags),
+            (64, 68, ShaderStageFlags::FRAGMENT)
+        );
+    }
 }
- */
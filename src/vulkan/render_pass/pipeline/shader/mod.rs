@@ -1,14 +1,18 @@
 use ash::version::DeviceV1_0;
 use ash::vk::{
     PipelineShaderStageCreateInfo, ShaderModule, ShaderModuleCreateFlags, ShaderModuleCreateInfo,
-    ShaderStageFlags, StructureType,
+    StructureType,
 };
 use ash::Device;
 
 use std::ptr;
 
-use crate::render_sequence::render_pass::pipeline::shader::{Shader, ShaderStage};
-use std::ffi::CString;
+use super::conversion;
+use crate::render_sequence::render_pass::pipeline::shader::Shader;
+use std::ffi::CStr;
+
+/// Name of the entry point every engine shader is compiled with
+const ENTRY_POINT_NAME: &[u8] = b"main\0";
 
 pub struct VulkanShader {
     pub shader_module_: ShaderModule,
@@ -42,18 +46,20 @@ impl VulkanShader {
             s_type: ash::vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
             p_next: ptr::null(),
             flags: ash::vk::PipelineShaderStageCreateFlags::empty(),
-            stage: match self.shader_.stage_ {
-                ShaderStage::Vertex => ShaderStageFlags::VERTEX,
-                ShaderStage::Fragment => ShaderStageFlags::FRAGMENT,
-                ShaderStage::Compute => ShaderStageFlags::COMPUTE,
-                ShaderStage::Geometry => ShaderStageFlags::GEOMETRY,
-                _ => ShaderStageFlags::default(),
-            },
+            stage: conversion::shader_stage_to_vulkan_shader_stage(&self.shader_.stage_),
             module: self.shader_module_,
-            p_name: CString::new("main").unwrap().as_ptr(),
+            p_name: CStr::from_bytes_with_nul(ENTRY_POINT_NAME)
+                .expect("Invalid entry point name")
+                .as_ptr(),
             p_specialization_info: ptr::null(),
         }
     }
+
+    /// Destroys the shader module. Shader modules are only needed until the pipelines using them
+    /// have been created
+    pub fn destroy(&self, device: &Device) {
+        unsafe { device.destroy_shader_module(self.shader_module_, None) };
+    }
 }
 
 /*
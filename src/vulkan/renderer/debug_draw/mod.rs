@@ -0,0 +1,252 @@
+use std::mem::size_of;
+use std::os::raw::c_void;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use super::Renderer;
+use crate::geometry::camera::Camera;
+use crate::geometry::debug_draw::DebugDraw;
+use crate::geometry::vertex::Vertex;
+use crate::render_sequence::builtin;
+use crate::vulkan::buffer::Buffer;
+use crate::vulkan::physical_device::PhysicalDevice;
+use crate::vulkan::render_pass::pipeline::Pipeline;
+use crate::vulkan::render_pass::RenderPassContext;
+
+/// The maximum number of debug line vertices drawn per frame. Further lines are dropped
+const MAX_DEBUG_VERTICES: usize = 65536;
+
+/// The line pipelines of the current render pass's final subpass
+struct DebugPipelines {
+    depth_tested_: Pipeline,
+    overlay_: Pipeline,
+}
+
+/// Draws the lines of a DebugDraw in the final subpass of the renderer's render pass, after the
+/// subpass's own draws. Each swapchain image has a host visible vertex buffer the lines are
+/// written to every frame, holding the depth tested lines followed by the overlay lines
+pub struct DebugDrawPass {
+    /// built against the current render pass the first time there are lines to draw
+    pipelines_: Option<DebugPipelines>,
+    /// true once building the pipelines against the current render pass was attempted
+    built_: bool,
+    vertex_buffers_: Vec<Buffer>,
+    /// the number of depth tested and overlay vertices written for each swapchain image
+    vertex_counts_: Vec<(u32, u32)>,
+    /// true once dropped lines have been reported
+    overflow_reported_: bool,
+}
+
+impl DebugDrawPass {
+    /// Creates and maps the vertex buffer of every swapchain image
+    ///
+    /// # Arguments
+    ///
+    /// * 'instance' - the instance the physical device belongs to
+    ///
+    /// * 'device' - the logical device the buffers are created on
+    ///
+    /// * 'physical_device' - the physical device used to pick memory types
+    ///
+    /// * 'num_images' - the number of swapchain images
+    ///
+    pub fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &PhysicalDevice,
+        num_images: usize,
+    ) -> DebugDrawPass {
+        let size = (MAX_DEBUG_VERTICES * size_of::<Vertex>()) as u64;
+
+        let vertex_buffers = (0..num_images)
+            .map(|_| {
+                let mut vertex_buffer = Buffer::new(
+                    instance,
+                    device,
+                    physical_device,
+                    size,
+                    vk::BufferUsageFlags::VERTEX_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                );
+
+                unsafe { vertex_buffer.map(device, 0, size) };
+
+                vertex_buffer
+            })
+            .collect();
+
+        DebugDrawPass {
+            pipelines_: None,
+            built_: false,
+            vertex_buffers_: vertex_buffers,
+            vertex_counts_: vec![(0, 0); num_images],
+            overflow_reported_: false,
+        }
+    }
+
+    /// Destroys the pipelines built against the current render pass, so they are built again
+    /// against the next. The device must not be using them
+    pub fn reset(&mut self, device: &ash::Device) {
+        if let Some(pipelines) = self.pipelines_.take() {
+            pipelines.depth_tested_.destroy(device);
+            pipelines.overlay_.destroy(device);
+        }
+
+        self.built_ = false;
+    }
+
+    /// Writes the lines of a DebugDraw to the vertex buffer of a swapchain image, building the
+    /// pipelines first if there are lines to draw and they have not been built yet
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the pipelines are created on
+    ///
+    /// * 'image_index' - the swapchain image the frame is rendered to
+    ///
+    /// * 'debug_draw' - the lines of the frame
+    ///
+    /// * 'render_pass' - the render pass the lines are drawn in, with the description it was built
+    ///   from and the cache pipelines are built with
+    ///
+    pub fn update(
+        &mut self,
+        device: &ash::Device,
+        image_index: usize,
+        debug_draw: &DebugDraw,
+        render_pass: &RenderPassContext,
+    ) {
+        if !debug_draw.is_empty() && !self.built_ {
+            self.build_pipelines(device, render_pass);
+        }
+
+        let depth_tested = debug_draw.get_vertices(true);
+        let overlay = debug_draw.get_vertices(false);
+
+        if depth_tested.len() + overlay.len() > MAX_DEBUG_VERTICES && !self.overflow_reported_ {
+            println!(
+                "Dropping debug lines beyond the limit of {} vertices per frame",
+                MAX_DEBUG_VERTICES
+            );
+            self.overflow_reported_ = true;
+        }
+
+        // Both lists hold whole lines, so truncating them to the even limit keeps them whole
+        let depth_tested = &depth_tested[..depth_tested.len().min(MAX_DEBUG_VERTICES)];
+        let overlay = &overlay[..overlay.len().min(MAX_DEBUG_VERTICES - depth_tested.len())];
+
+        let vertex_buffer = &mut self.vertex_buffers_[image_index];
+
+        // The overlay lines follow the depth tested lines in the buffer
+        let vertices = [depth_tested, overlay].concat();
+
+        if !vertices.is_empty() {
+            unsafe {
+                vertex_buffer.copy_from_data(
+                    vertices.as_ptr() as *const c_void,
+                    (vertices.len() * size_of::<Vertex>()) as u64,
+                    0,
+                );
+            }
+        }
+
+        self.vertex_counts_[image_index] = (depth_tested.len() as u32, overlay.len() as u32);
+    }
+
+    /// Records the lines written for a swapchain image once for every camera. Must be recorded in
+    /// the final subpass of the render pass the pipelines were built against
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer belongs to
+    ///
+    /// * 'command_buffer' - the command buffer to record to
+    ///
+    /// * 'image_index' - the swapchain image the frame is rendered to
+    ///
+    /// * 'cameras' - every camera of the frame, with the viewport and scissor it draws to
+    ///
+    pub fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        cameras: &[(&Camera, vk::Viewport, vk::Rect2D)],
+    ) {
+        let pipelines = match self.pipelines_.as_ref() {
+            Some(pipelines) => pipelines,
+            None => return,
+        };
+
+        let (depth_tested_count, overlay_count) = self.vertex_counts_[image_index];
+
+        let draws = [
+            (&pipelines.depth_tested_, 0, depth_tested_count),
+            (&pipelines.overlay_, depth_tested_count, overlay_count),
+        ];
+
+        unsafe {
+            device.cmd_bind_vertex_buffers(
+                command_buffer,
+                0,
+                &[self.vertex_buffers_[image_index].buffer_handle_],
+                &[0],
+            );
+
+            for (camera, viewport, scissor) in cameras.iter() {
+                device.cmd_set_viewport(command_buffer, 0, &[*viewport]);
+                device.cmd_set_scissor(command_buffer, 0, &[*scissor]);
+
+                let mut push_constants = Renderer::mat4_to_bytes(camera.view_);
+                push_constants.append(&mut Renderer::mat4_to_bytes(camera.projection_));
+
+                for (pipeline, first_vertex, vertex_count) in draws.iter() {
+                    if *vertex_count == 0 {
+                        continue;
+                    }
+
+                    device.cmd_bind_pipeline(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        pipeline.pipeline_handle_,
+                    );
+                    device.cmd_push_constants(
+                        command_buffer,
+                        pipeline.layout_,
+                        pipeline.get_push_constant_stage_flags(0, push_constants.len() as u32),
+                        0,
+                        &push_constants,
+                    );
+                    device.cmd_draw(command_buffer, *vertex_count, 1, *first_vertex, 0);
+                }
+            }
+        }
+    }
+
+    /// Builds the line pipelines for the final subpass of a render pass. Lines are only drawn if
+    /// the final subpass writes nothing but the presented color, as their pipelines must write
+    /// every color attachment of the subpass
+    fn build_pipelines(&mut self, device: &ash::Device, render_pass: &RenderPassContext) {
+        self.built_ = true;
+
+        let build = |depth_test: bool| {
+            render_pass.build_overlay_pipeline(
+                device,
+                &builtin::debug_lines(depth_test),
+                "debug lines",
+            )
+        };
+
+        let depth_tested = match build(true) {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+
+        // The final subpass takes overlays, so the second pipeline is built as well
+        self.pipelines_ = Some(DebugPipelines {
+            depth_tested_: depth_tested,
+            overlay_: build(false).unwrap(),
+        });
+    }
+}
@@ -0,0 +1,104 @@
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use super::swapchain::Swapchain;
+use crate::render_sequence::render_pass::RenderPassDescription;
+use crate::vulkan::descriptor;
+use crate::vulkan::render_pass::pipeline::descriptor_set_layout;
+use crate::vulkan::render_pass::RenderPass;
+
+/// Descriptor sets binding the intermediate attachments of a render pass to the subpasses that
+/// read them as input attachments. Every swapchain image has its own intermediate attachments, so
+/// each subpass gets its own sets per swapchain image
+pub struct InputAttachmentSets {
+    descriptor_pools_: Vec<vk::DescriptorPool>,
+    /// indexed by subpass and then swapchain image. Holds a set for every set index of the
+    /// subpass's pipeline, or none if the subpass reads no input attachments
+    descriptor_sets_: Vec<Vec<Vec<vk::DescriptorSet>>>,
+}
+
+impl InputAttachmentSets {
+    /// Allocates and writes the input attachment descriptor sets of every subpass of a render
+    /// pass. The swapchain's framebuffers must have been created for the render pass
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the render pass was created on
+    ///
+    /// * 'render_pass' - the render pass whose subpasses read input attachments
+    ///
+    /// * 'description' - the description the render pass was built from
+    ///
+    /// * 'swapchain' - the swapchain owning the intermediate attachments
+    ///
+    pub fn new(
+        device: &ash::Device,
+        render_pass: &RenderPass,
+        description: &RenderPassDescription,
+        swapchain: &Swapchain,
+    ) -> InputAttachmentSets {
+        let num_images = swapchain.swapchain_images_.len();
+
+        let mut descriptor_pools = vec![];
+        let mut descriptor_sets = vec![];
+
+        for (subpass, pipeline) in description.get_pipelines().iter().enumerate() {
+            let input_attachments = pipeline.get_input_attachments();
+
+            if input_attachments.is_empty() {
+                descriptor_sets.push(vec![vec![]; num_images]);
+                continue;
+            }
+
+            // The pipeline was built from the same shaders, so its bindings are known to merge
+            let bindings = descriptor_set_layout::merge_bindings(&pipeline.shaders_)
+                .expect("Could not merge descriptor bindings");
+            let descriptor_pool = descriptor::create_pool(device, &bindings, num_images as u32);
+
+            let mut subpass_sets = vec![];
+
+            for image_index in 0..num_images {
+                let sets = descriptor::allocate_sets(
+                    device,
+                    descriptor_pool,
+                    &render_pass.get_pipeline(subpass).descriptor_set_layouts_,
+                );
+
+                for (set, binding, input_attachment_index) in input_attachments.iter() {
+                    descriptor::write_image(
+                        device,
+                        sets[*set as usize],
+                        *binding,
+                        vk::DescriptorType::INPUT_ATTACHMENT,
+                        swapchain
+                            .get_attachment_view(image_index, *input_attachment_index as usize),
+                        vk::Sampler::null(),
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    );
+                }
+
+                subpass_sets.push(sets);
+            }
+
+            descriptor_pools.push(descriptor_pool);
+            descriptor_sets.push(subpass_sets);
+        }
+
+        InputAttachmentSets {
+            descriptor_pools_: descriptor_pools,
+            descriptor_sets_: descriptor_sets,
+        }
+    }
+
+    /// Returns the descriptor sets a subpass binds, starting at set 0, when drawing to the given
+    /// swapchain image. Empty if the subpass reads no input attachments
+    pub fn get_descriptor_sets(&self, subpass: usize, image_index: u32) -> &Vec<vk::DescriptorSet> {
+        &self.descriptor_sets_[subpass][image_index as usize]
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        for descriptor_pool in self.descriptor_pools_.iter() {
+            unsafe { device.destroy_descriptor_pool(*descriptor_pool, None) };
+        }
+    }
+}
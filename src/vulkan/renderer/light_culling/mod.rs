@@ -0,0 +1,419 @@
+use std::mem::size_of;
+use std::os::raw::c_void;
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use cgmath::{conv, SquareMatrix};
+
+use super::scene_manager::SceneManager;
+use crate::geometry::camera::Camera;
+use crate::geometry::light::{self, Light};
+use crate::render_sequence::builtin;
+use crate::render_sequence::render_pass::pipeline::shader::ShaderDescription;
+use crate::vulkan::buffer::Buffer;
+use crate::vulkan::compute_manager::compute_pipeline::ComputePipeline;
+use crate::vulkan::descriptor;
+use crate::vulkan::physical_device::PhysicalDevice;
+use crate::vulkan::render_pass::pipeline::descriptor_set_layout;
+
+/// The binding of descriptor set SHADOW_SET at which pipelines declare the Clusters uniform block
+/// of lights.glsl
+pub const CLUSTER_UBO_BINDING: u32 = 3;
+/// The binding of the number of lights affecting each cluster
+pub const CLUSTER_LIGHT_COUNT_BINDING: u32 = 4;
+/// The binding of the light indices of each cluster
+pub const CLUSTER_LIGHT_INDEX_BINDING: u32 = 5;
+
+/// The descriptor set the light culling shader declares the bindings of lights.glsl in
+const CULLING_SET: usize = 1;
+/// Number of clusters each workgroup of the light culling shader culls
+const WORKGROUP_SIZE: u32 = 64;
+
+/// How light culling splits the view frustum into clusters: tiles across and down the screen and
+/// slices in depth, spaced exponentially between the camera's near and far planes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClusterGrid {
+    pub width_: u32,
+    pub height_: u32,
+    pub depth_: u32,
+    /// The maximum number of lights affecting a cluster. Further lights are ignored
+    pub max_lights_per_cluster_: u32,
+}
+
+impl ClusterGrid {
+    pub fn get_cluster_count(&self) -> u32 {
+        self.width_ * self.height_ * self.depth_
+    }
+}
+
+impl Default for ClusterGrid {
+    fn default() -> ClusterGrid {
+        ClusterGrid {
+            width_: 16,
+            height_: 9,
+            depth_: 24,
+            max_lights_per_cluster_: 128,
+        }
+    }
+}
+
+/// The Clusters uniform block of lights.glsl
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct ClusterUBO {
+    view_: [[f32; 4]; 4],
+    inverse_projection_: [[f32; 4]; 4],
+    viewport_: [f32; 4],
+    grid_: [u32; 4],
+    depth_: [f32; 4],
+}
+
+/// The cluster uniforms and light lists of one swapchain image, and the descriptor set the light
+/// culling shader writes the lists through
+struct ClusterFrame {
+    uniform_buffer_: Buffer,
+    light_counts_: Buffer,
+    light_indices_: Buffer,
+    descriptor_set_: vk::DescriptorSet,
+    /// true if lights were culled for the frame, so the culling shader is dispatched
+    culled_: bool,
+}
+
+/// Assigns the scene's lights to the clusters of the first camera's view frustum with a compute
+/// shader before the renderer's render pass, so that fragment shaders only consider the lights
+/// affecting their cluster. Provides the cluster light lists to pipelines declaring the cluster
+/// bindings of lights.glsl in descriptor set SHADOW_SET
+pub struct LightCulling {
+    grid_: ClusterGrid,
+    /// built from the light culling shader once a scene first has lights
+    pipeline_: Option<ComputePipeline>,
+    /// the bindings of the culling shader's descriptor set
+    bindings_: Vec<vk::DescriptorSetLayoutBinding>,
+    descriptor_pool_: vk::DescriptorPool,
+    frames_: Vec<ClusterFrame>,
+}
+
+impl LightCulling {
+    /// Creates the cluster buffers of every swapchain image, starting out without culled lights
+    ///
+    /// # Arguments
+    ///
+    /// * 'instance' - the instance the physical device belongs to
+    ///
+    /// * 'device' - the logical device the buffers are created on
+    ///
+    /// * 'physical_device' - the physical device used to pick memory types
+    ///
+    /// * 'grid' - how the view frustum is split into clusters
+    ///
+    /// * 'num_images' - the number of swapchain images
+    ///
+    pub fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &PhysicalDevice,
+        grid: ClusterGrid,
+        num_images: usize,
+    ) -> LightCulling {
+        let cluster_count = grid.get_cluster_count().max(1) as u64;
+        let index_count = cluster_count * grid.max_lights_per_cluster_.max(1) as u64;
+
+        let frames = (0..num_images)
+            .map(|_| {
+                let mut uniform_buffer = Buffer::new(
+                    instance,
+                    device,
+                    physical_device,
+                    size_of::<ClusterUBO>() as u64,
+                    vk::BufferUsageFlags::UNIFORM_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                );
+
+                unsafe {
+                    uniform_buffer.map(device, 0, size_of::<ClusterUBO>() as u64);
+                    uniform_buffer.copy_from_data(
+                        &ClusterUBO::default() as *const ClusterUBO as *const c_void,
+                        size_of::<ClusterUBO>() as u64,
+                        0,
+                    );
+                }
+
+                ClusterFrame {
+                    uniform_buffer_: uniform_buffer,
+                    light_counts_: Buffer::new(
+                        instance,
+                        device,
+                        physical_device,
+                        cluster_count * size_of::<u32>() as u64,
+                        vk::BufferUsageFlags::STORAGE_BUFFER,
+                        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                    ),
+                    light_indices_: Buffer::new(
+                        instance,
+                        device,
+                        physical_device,
+                        index_count * size_of::<u32>() as u64,
+                        vk::BufferUsageFlags::STORAGE_BUFFER,
+                        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                    ),
+                    descriptor_set_: vk::DescriptorSet::null(),
+                    culled_: false,
+                }
+            })
+            .collect();
+
+        LightCulling {
+            grid_: grid,
+            pipeline_: None,
+            bindings_: vec![],
+            descriptor_pool_: vk::DescriptorPool::null(),
+            frames_: frames,
+        }
+    }
+
+    pub fn get_grid(&self) -> ClusterGrid {
+        self.grid_
+    }
+
+    /// Uploads the clusters of a camera for a swapchain image, and builds the culling pipeline
+    /// the first time there are lights to cull. Lights are not culled if there are none or the
+    /// camera's projection is not an OpenGL style perspective projection, leaving fragment shaders
+    /// to consider every light
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the pipeline is created on
+    ///
+    /// * 'image_index' - the swapchain image the frame is rendered to
+    ///
+    /// * 'lights' - the lights of the scene
+    ///
+    /// * 'camera' - the camera whose view frustum is split into clusters
+    ///
+    /// * 'viewport' - the window space rectangle the camera draws to
+    ///
+    /// * 'pipeline_cache' - cache used to speed up building the culling pipeline
+    ///
+    pub fn update(
+        &mut self,
+        device: &ash::Device,
+        image_index: usize,
+        lights: &[Light],
+        camera: &Camera,
+        viewport: &vk::Viewport,
+        pipeline_cache: vk::PipelineCache,
+    ) {
+        let (near, far) = light::get_near_far(&camera.projection_);
+        let inverse_projection = camera.projection_.invert();
+
+        let mut cluster_ubo = ClusterUBO::default();
+
+        let culled = match inverse_projection {
+            Some(inverse_projection)
+                if !lights.is_empty()
+                    && self.grid_.get_cluster_count() > 0
+                    && near > 0.0
+                    && far > near =>
+            {
+                cluster_ubo = ClusterUBO {
+                    view_: conv::array4x4(camera.view_),
+                    inverse_projection_: conv::array4x4(inverse_projection),
+                    viewport_: [viewport.x, viewport.y, viewport.width, viewport.height],
+                    grid_: [
+                        self.grid_.width_,
+                        self.grid_.height_,
+                        self.grid_.depth_,
+                        self.grid_.max_lights_per_cluster_,
+                    ],
+                    depth_: [near, far, 0.0, 0.0],
+                };
+
+                true
+            }
+            _ => false,
+        };
+
+        if culled && self.pipeline_.is_none() {
+            self.build_pipeline(device, pipeline_cache);
+        }
+
+        let frame = &mut self.frames_[image_index];
+        frame.culled_ = culled;
+
+        unsafe {
+            frame.uniform_buffer_.copy_from_data(
+                &cluster_ubo as *const ClusterUBO as *const c_void,
+                size_of::<ClusterUBO>() as u64,
+                0,
+            );
+        }
+    }
+
+    /// Points the culling shader's descriptor set of a swapchain image at that image's lights and
+    /// cluster buffers. Does nothing until the culling pipeline has been built
+    pub fn write_culling_set(
+        &self,
+        device: &ash::Device,
+        scene_manager: &SceneManager,
+        image_index: usize,
+    ) {
+        if self.pipeline_.is_none() {
+            return;
+        }
+
+        let descriptor_set = self.frames_[image_index].descriptor_set_;
+
+        scene_manager.write_descriptor_set(device, image_index, descriptor_set, &self.bindings_);
+        self.write_descriptor_set(device, image_index, descriptor_set, &self.bindings_);
+    }
+
+    /// Points the cluster bindings of a descriptor set at a swapchain image's cluster uniforms and
+    /// light lists. Bindings whose declared type does not match lights.glsl are left untouched
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the descriptor set was allocated from
+    ///
+    /// * 'image_index' - the swapchain image whose clusters are bound
+    ///
+    /// * 'descriptor_set' - a descriptor set with the layout of set SHADOW_SET
+    ///
+    /// * 'bindings' - the bindings of the descriptor set's layout
+    ///
+    pub fn write_descriptor_set(
+        &self,
+        device: &ash::Device,
+        image_index: usize,
+        descriptor_set: vk::DescriptorSet,
+        bindings: &[vk::DescriptorSetLayoutBinding],
+    ) {
+        let frame = &self.frames_[image_index];
+
+        for binding in bindings.iter() {
+            let buffer = match (binding.binding, binding.descriptor_type) {
+                (CLUSTER_UBO_BINDING, vk::DescriptorType::UNIFORM_BUFFER) => &frame.uniform_buffer_,
+                (CLUSTER_LIGHT_COUNT_BINDING, vk::DescriptorType::STORAGE_BUFFER) => {
+                    &frame.light_counts_
+                }
+                (CLUSTER_LIGHT_INDEX_BINDING, vk::DescriptorType::STORAGE_BUFFER) => {
+                    &frame.light_indices_
+                }
+                _ => continue,
+            };
+
+            descriptor::write_buffer(
+                device,
+                descriptor_set,
+                binding.binding,
+                binding.descriptor_type,
+                buffer.buffer_handle_,
+                0,
+                vk::WHOLE_SIZE,
+            );
+        }
+    }
+
+    /// Records culling the lights of a swapchain image into its clusters, followed by a barrier
+    /// making the light lists visible to fragment shaders. Must be recorded outside of a render
+    /// pass. Records nothing if lights were not culled for the image
+    pub fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+    ) {
+        let frame = &self.frames_[image_index];
+
+        let pipeline = match self.pipeline_.as_ref() {
+            Some(pipeline) if frame.culled_ => pipeline,
+            _ => return,
+        };
+
+        let group_count = self.grid_.get_cluster_count().div_ceil(WORKGROUP_SIZE);
+
+        let memory_barrier = vk::MemoryBarrier {
+            s_type: vk::StructureType::MEMORY_BARRIER,
+            p_next: ptr::null(),
+            src_access_mask: vk::AccessFlags::SHADER_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ,
+        };
+
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                pipeline.pipeline_handle_,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                pipeline.layout_,
+                CULLING_SET as u32,
+                &[frame.descriptor_set_],
+                &[],
+            );
+            device.cmd_dispatch(command_buffer, group_count, 1, 1);
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[memory_barrier],
+                &[],
+                &[],
+            );
+        }
+    }
+
+    /// Destroys the culling pipeline and the cluster buffers. The device must not be using them
+    pub fn destroy(&mut self, device: &ash::Device) {
+        if let Some(pipeline) = self.pipeline_.take() {
+            pipeline.destroy(device);
+        }
+
+        unsafe {
+            device.destroy_descriptor_pool(self.descriptor_pool_, None);
+
+            for frame in self.frames_.iter() {
+                frame.uniform_buffer_.destroy(device);
+                frame.light_counts_.destroy(device);
+                frame.light_indices_.destroy(device);
+            }
+        }
+
+        self.frames_.clear();
+    }
+
+    /// Builds the culling pipeline and allocates its descriptor set for every swapchain image
+    fn build_pipeline(&mut self, device: &ash::Device, pipeline_cache: vk::PipelineCache) {
+        let shader_description = ShaderDescription::from_shader(&builtin::cluster_light_culling());
+
+        let pipeline = ComputePipeline::new(device, &shader_description, pipeline_cache)
+            .unwrap_or_else(|error| panic!("Could not build light culling pipeline: {}", error));
+
+        // The pipeline was built from the same shader, so its bindings are known to merge
+        let bindings = descriptor_set_layout::merge_bindings(&[shader_description])
+            .expect("Could not merge descriptor bindings")
+            .get(CULLING_SET)
+            .cloned()
+            .unwrap_or_default();
+
+        let descriptor_pool = descriptor::create_pool(
+            device,
+            std::slice::from_ref(&bindings),
+            self.frames_.len() as u32,
+        );
+        let layouts = vec![pipeline.descriptor_set_layouts_[CULLING_SET]; self.frames_.len()];
+        let descriptor_sets = descriptor::allocate_sets(device, descriptor_pool, &layouts);
+
+        for (frame, descriptor_set) in self.frames_.iter_mut().zip(descriptor_sets) {
+            frame.descriptor_set_ = descriptor_set;
+        }
+
+        self.pipeline_ = Some(pipeline);
+        self.bindings_ = bindings;
+        self.descriptor_pool_ = descriptor_pool;
+    }
+}
@@ -0,0 +1,501 @@
+use std::os::raw::c_void;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use super::light_culling::LightCulling;
+use super::scene_manager::SceneManager;
+use super::shadow::{ShadowPass, ShadowSets};
+use super::texture_sets::{TextureBinding, TextureSets};
+use crate::render_sequence::material::{MaterialDescription, MATERIAL_SET};
+use crate::render_sequence::render_pass::pipeline::shader::descriptor::DescriptorType;
+use crate::render_sequence::render_pass::pipeline::PipelineDescription;
+use crate::render_sequence::render_pass::RenderPassDescription;
+use crate::vulkan::buffer::Buffer;
+use crate::vulkan::descriptor;
+use crate::vulkan::physical_device::PhysicalDevice;
+use crate::vulkan::render_pass::pipeline::{descriptor_set_layout, Pipeline, PipelineError};
+use crate::vulkan::render_pass::RenderPass;
+
+/// The pipeline of a material and the descriptor sets drawing with it binds, built for the first
+/// subpass of a render pass
+struct MaterialPipeline {
+    /// the material's pipeline description, with its presented output matching the render pass
+    description_: PipelineDescription,
+    pipeline_: Pipeline,
+    descriptor_pool_: vk::DescriptorPool,
+    /// the descriptor set holding the material's parameters, or None if the pipeline declares no
+    /// bindings in set MATERIAL_SET
+    material_set_: Option<vk::DescriptorSet>,
+    shadow_sets_: ShadowSets,
+    texture_sets_: TextureSets,
+}
+
+/// A material of a renderer: its parameter values, uploaded to uniform buffers, and the pipeline
+/// instances using it are drawn with
+pub struct Material {
+    description_: MaterialDescription,
+    /// the textures bound to the combined image samplers of set MATERIAL_SET
+    textures_: Vec<TextureBinding>,
+    /// the binding and host visible buffer of each uniform block of set MATERIAL_SET
+    uniform_buffers_: Vec<(u32, Buffer)>,
+    pipeline_: Option<MaterialPipeline>,
+}
+
+impl Material {
+    /// Creates uniform buffers for the uniform blocks of a material's set MATERIAL_SET and fills
+    /// them with its parameter values. The material cannot be drawn with until its pipeline has
+    /// been built with build_pipeline
+    ///
+    /// # Arguments
+    ///
+    /// * 'instance' - the instance the physical device belongs to
+    ///
+    /// * 'device' - the logical device the buffers are created on
+    ///
+    /// * 'physical_device' - the physical device used to pick memory types
+    ///
+    /// * 'description' - the material's pipeline and parameter values
+    ///
+    /// * 'textures' - the texture of every combined image sampler of set MATERIAL_SET
+    ///
+    pub fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &PhysicalDevice,
+        description: &MaterialDescription,
+        textures: Vec<TextureBinding>,
+    ) -> Material {
+        let mut uniform_buffers = vec![];
+
+        for descriptor in description.get_descriptors().iter() {
+            match descriptor.descriptor_type_ {
+                DescriptorType::UniformBuffer => {
+                    let size = description.build_block_data(descriptor).len() as u64;
+
+                    let mut buffer = Buffer::new(
+                        instance,
+                        device,
+                        physical_device,
+                        size,
+                        vk::BufferUsageFlags::UNIFORM_BUFFER,
+                        vk::MemoryPropertyFlags::HOST_VISIBLE
+                            | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    );
+
+                    unsafe { buffer.map(device, 0, size) };
+
+                    uniform_buffers.push((descriptor.binding_, buffer));
+                }
+                DescriptorType::CombinedImageSampler => {}
+                _ => println!(
+                    "Material binding {} of set {} is neither a uniform block nor a sampler and \
+                     is left unwritten",
+                    descriptor.binding_, MATERIAL_SET
+                ),
+            }
+        }
+
+        let mut material = Material {
+            description_: description.clone(),
+            textures_: textures,
+            uniform_buffers_: uniform_buffers,
+            pipeline_: None,
+        };
+
+        material.write_uniform_buffers();
+        material
+    }
+
+    /// Builds the material's pipeline for the first subpass of a render pass, along with its
+    /// descriptor sets, replacing any pipeline built before. Returns an error if the pipeline
+    /// cannot be built, keeping the previous pipeline
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the render pass was created on
+    ///
+    /// * 'render_pass' - the render pass the material is drawn in
+    ///
+    /// * 'render_pass_description' - the description the render pass was built from
+    ///
+    /// * 'texture_bindings' - the textures bound to every pipeline of the renderer
+    ///
+    /// * 'num_images' - the number of swapchain images
+    ///
+    /// * 'pipeline_cache' - cache used to speed up pipeline compilation
+    ///
+    pub fn build_pipeline(
+        &mut self,
+        device: &ash::Device,
+        render_pass: &RenderPass,
+        render_pass_description: &RenderPassDescription,
+        texture_bindings: &[TextureBinding],
+        num_images: usize,
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<(), PipelineError> {
+        let pipeline =
+            self.create_pipeline(device, render_pass, render_pass_description, pipeline_cache)?;
+
+        self.set_pipeline(
+            device,
+            pipeline,
+            render_pass_description,
+            texture_bindings,
+            num_images,
+        );
+
+        Ok(())
+    }
+
+    /// Recompiles the GLSL sources of the material's pipeline and rebuilds it with create_pipeline
+    /// and set_pipeline. Returns an error, keeping the current pipeline, if a shader fails to
+    /// compile, the shaders no longer declare the material set the material's uniform buffers
+    /// were created for, or the pipeline cannot be built. The device must not be using the
+    /// current pipeline
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the render pass was created on
+    ///
+    /// * 'render_pass' - the render pass the material is drawn in
+    ///
+    /// * 'render_pass_description' - the description the render pass was built from
+    ///
+    /// * 'texture_bindings' - the textures bound to every pipeline of the renderer
+    ///
+    /// * 'num_images' - the number of swapchain images
+    ///
+    /// * 'pipeline_cache' - cache used to speed up pipeline compilation
+    ///
+    pub fn reload_pipeline(
+        &mut self,
+        device: &ash::Device,
+        render_pass: &RenderPass,
+        render_pass_description: &RenderPassDescription,
+        texture_bindings: &[TextureBinding],
+        num_images: usize,
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<(), String> {
+        let mut description = self.description_.clone();
+        description.pipeline_ = self.description_.pipeline_.recompile()?;
+
+        if description.get_descriptors() != self.description_.get_descriptors() {
+            return Err(format!(
+                "The bindings of set {} changed, so the material must be created again",
+                MATERIAL_SET
+            ));
+        }
+
+        let previous = std::mem::replace(&mut self.description_, description);
+
+        match self.create_pipeline(device, render_pass, render_pass_description, pipeline_cache) {
+            Ok(pipeline) => {
+                self.set_pipeline(
+                    device,
+                    pipeline,
+                    render_pass_description,
+                    texture_bindings,
+                    num_images,
+                );
+                Ok(())
+            }
+            Err(error) => {
+                self.description_ = previous;
+                Err(error.to_string())
+            }
+        }
+    }
+
+    /// Builds the material's pipeline for the first subpass of a render pass without using it, so
+    /// that the pipelines of several materials can be built before any is replaced. Returns an
+    /// error if the pipeline cannot be built
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the render pass was created on
+    ///
+    /// * 'render_pass' - the render pass the material is drawn in
+    ///
+    /// * 'render_pass_description' - the description the render pass was built from
+    ///
+    /// * 'pipeline_cache' - cache used to speed up pipeline compilation
+    ///
+    pub fn create_pipeline(
+        &self,
+        device: &ash::Device,
+        render_pass: &RenderPass,
+        render_pass_description: &RenderPassDescription,
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<Pipeline, PipelineError> {
+        Pipeline::from_pipeline_descriptor(
+            device,
+            &self.get_pipeline_description(render_pass_description),
+            render_pass.vk_render_pass_,
+            0,
+            &render_pass.multisample_,
+            &render_pass.features_,
+            pipeline_cache,
+        )
+    }
+
+    /// Replaces the material's pipeline with one created by create_pipeline, and creates its
+    /// descriptor sets. The device must not be using the previous pipeline
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the pipeline was created on
+    ///
+    /// * 'pipeline' - the pipeline built against the render pass the material is drawn in
+    ///
+    /// * 'render_pass_description' - the description the render pass was built from
+    ///
+    /// * 'texture_bindings' - the textures bound to every pipeline of the renderer
+    ///
+    /// * 'num_images' - the number of swapchain images
+    ///
+    pub fn set_pipeline(
+        &mut self,
+        device: &ash::Device,
+        pipeline: Pipeline,
+        render_pass_description: &RenderPassDescription,
+        texture_bindings: &[TextureBinding],
+        num_images: usize,
+    ) {
+        let pipeline_description = self.get_pipeline_description(render_pass_description);
+
+        self.destroy_pipeline(device);
+
+        let (shadow_sets, texture_sets) = {
+            let pipelines = [(&pipeline_description, &pipeline)];
+
+            (
+                ShadowSets::new(device, &pipelines, num_images),
+                TextureSets::new(device, &pipelines, texture_bindings),
+            )
+        };
+
+        // The pipeline was built from the same shaders, so its bindings are known to merge
+        let bindings = descriptor_set_layout::merge_bindings(&pipeline_description.shaders_)
+            .expect("Could not merge descriptor bindings")
+            .get(MATERIAL_SET as usize)
+            .cloned()
+            .unwrap_or_default();
+
+        let descriptor_pool = descriptor::create_pool(device, &[bindings], 1);
+        let material_set = match pipeline.descriptor_set_layouts_.get(MATERIAL_SET as usize) {
+            Some(layout) => descriptor::allocate_sets(device, descriptor_pool, &[*layout])
+                .first()
+                .cloned(),
+            None => None,
+        };
+
+        self.pipeline_ = Some(MaterialPipeline {
+            description_: pipeline_description,
+            pipeline_: pipeline,
+            descriptor_pool_: descriptor_pool,
+            material_set_: material_set,
+            shadow_sets_: shadow_sets,
+            texture_sets_: texture_sets,
+        });
+
+        self.write_material_set(device);
+    }
+
+    /// Replaces the material's parameter values and textures. The material's pipeline is kept, so
+    /// only the parameters of the description are used. The device must not be using the
+    /// material
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the material was created on
+    ///
+    /// * 'description' - the material with its new parameter values
+    ///
+    /// * 'textures' - the texture of every combined image sampler of set MATERIAL_SET
+    ///
+    pub fn set_parameters(
+        &mut self,
+        device: &ash::Device,
+        description: &MaterialDescription,
+        textures: Vec<TextureBinding>,
+    ) {
+        self.description_.parameters_ = description.parameters_.clone();
+        self.textures_ = textures;
+
+        self.write_uniform_buffers();
+        self.write_material_set(device);
+    }
+
+    /// Rebuilds the descriptor sets binding the renderer's textures to the material's pipeline.
+    /// The device must not be using them
+    pub fn set_texture_bindings(
+        &mut self,
+        device: &ash::Device,
+        texture_bindings: &[TextureBinding],
+    ) {
+        if let Some(material_pipeline) = self.pipeline_.as_mut() {
+            material_pipeline.texture_sets_.destroy(device);
+            material_pipeline.texture_sets_ = TextureSets::new(
+                device,
+                &[(
+                    &material_pipeline.description_,
+                    &material_pipeline.pipeline_,
+                )],
+                texture_bindings,
+            );
+        }
+    }
+
+    /// Points the material's shadow descriptor set of a swapchain image at that image's shadow
+    /// resources, lights and light clusters
+    pub fn write_shadow_sets(
+        &self,
+        device: &ash::Device,
+        shadow_pass: &ShadowPass,
+        scene_manager: &SceneManager,
+        light_culling: &LightCulling,
+        image_index: u32,
+    ) {
+        if let Some(material_pipeline) = self.pipeline_.as_ref() {
+            material_pipeline.shadow_sets_.write(
+                device,
+                shadow_pass,
+                scene_manager,
+                light_culling,
+                image_index,
+            );
+        }
+    }
+
+    pub fn get_description(&self) -> &MaterialDescription {
+        &self.description_
+    }
+
+    /// Returns the material's pipeline. Panics if it has not been built
+    pub fn get_pipeline(&self) -> &Pipeline {
+        &self
+            .pipeline_
+            .as_ref()
+            .expect("The material's pipeline has not been built")
+            .pipeline_
+    }
+
+    /// Returns the shadow descriptor set of a swapchain image, if the pipeline samples shadows,
+    /// and the set index and descriptor set of every texture and material set the pipeline binds
+    pub fn get_descriptor_sets(
+        &self,
+        image_index: u32,
+    ) -> (Option<vk::DescriptorSet>, Vec<(u32, vk::DescriptorSet)>) {
+        let material_pipeline = self
+            .pipeline_
+            .as_ref()
+            .expect("The material's pipeline has not been built");
+
+        let mut sets = material_pipeline
+            .texture_sets_
+            .get_descriptor_sets(0)
+            .clone();
+
+        if let Some(material_set) = material_pipeline.material_set_ {
+            sets.push((MATERIAL_SET, material_set));
+        }
+
+        (
+            material_pipeline
+                .shadow_sets_
+                .get_descriptor_set(0, image_index),
+            sets,
+        )
+    }
+
+    /// Destroys the material's pipeline and uniform buffers. The device must not be using them
+    pub fn destroy(&mut self, device: &ash::Device) {
+        self.destroy_pipeline(device);
+
+        for (_, buffer) in self.uniform_buffers_.iter() {
+            unsafe { buffer.destroy(device) };
+        }
+
+        self.uniform_buffers_.clear();
+    }
+
+    /// Returns the material's pipeline description as drawn in the first subpass of a render pass
+    fn get_pipeline_description(
+        &self,
+        render_pass_description: &RenderPassDescription,
+    ) -> PipelineDescription {
+        let mut pipeline_description = self.description_.pipeline_.clone();
+
+        // The material replaces the main pass pipeline, so it presents what the main pass does
+        if render_pass_description.subpasses_.is_empty() {
+            pipeline_description.set_presented_output();
+        }
+
+        pipeline_description
+    }
+
+    /// Destroys the material's pipeline and descriptor sets, if they have been built
+    fn destroy_pipeline(&mut self, device: &ash::Device) {
+        if let Some(material_pipeline) = self.pipeline_.take() {
+            material_pipeline.pipeline_.destroy(device);
+            material_pipeline.shadow_sets_.destroy(device);
+            material_pipeline.texture_sets_.destroy(device);
+
+            unsafe {
+                device.destroy_descriptor_pool(material_pipeline.descriptor_pool_, None);
+            }
+        }
+    }
+
+    /// Copies the parameter values of each uniform block into its buffer
+    fn write_uniform_buffers(&mut self) {
+        let descriptors = self.description_.get_descriptors();
+
+        for (binding, buffer) in self.uniform_buffers_.iter_mut() {
+            let descriptor = descriptors
+                .iter()
+                .find(|descriptor| descriptor.binding_ == *binding)
+                .unwrap();
+            let data = self.description_.build_block_data(descriptor);
+
+            unsafe { buffer.copy_from_data(data.as_ptr() as *const c_void, data.len() as u64, 0) };
+        }
+    }
+
+    /// Points the material set at the uniform buffers and textures of the material
+    fn write_material_set(&self, device: &ash::Device) {
+        let material_set = match self
+            .pipeline_
+            .as_ref()
+            .and_then(|material_pipeline| material_pipeline.material_set_)
+        {
+            Some(material_set) => material_set,
+            None => return,
+        };
+
+        for (binding, buffer) in self.uniform_buffers_.iter() {
+            descriptor::write_buffer(
+                device,
+                material_set,
+                *binding,
+                vk::DescriptorType::UNIFORM_BUFFER,
+                buffer.buffer_handle_,
+                0,
+                vk::WHOLE_SIZE,
+            );
+        }
+
+        for texture in self.textures_.iter() {
+            descriptor::write_image(
+                device,
+                material_set,
+                texture.binding_,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                texture.image_view_,
+                texture.sampler_,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            );
+        }
+    }
+}
@@ -1,48 +1,92 @@
+use std::path::PathBuf;
 use std::ptr;
 
 use ash::version::DeviceV1_0;
 
 use crate::vulkan::command;
 
+use super::super::geometry::camera::Camera;
+use super::super::geometry::debug_draw::DebugDraw;
 use super::super::geometry::palette::MeshID;
-use super::super::geometry::scene::Scene;
+use super::super::geometry::scene::{InstanceID, Scene};
+use super::super::geometry::sprite::SpriteBatch;
+use super::super::geometry::viewport::Viewport;
 use super::buffer;
 use super::geometry_manager;
 use super::physical_device;
 use super::surface;
+use super::texture_manager::TextureManager;
+use crate::render_sequence::material::{MaterialDescription, MaterialID, MATERIAL_SET};
+use crate::render_sequence::post_process::PostProcessChain;
 use crate::render_sequence::render_pass::RenderPassDescription;
+use crate::render_sequence::shader_watcher::ShaderWatcher;
 use crate::vulkan::render_pass::pipeline;
-use crate::vulkan::render_pass::pipeline::Pipeline;
+use crate::vulkan::render_pass::pipeline::{
+    Multisample, Pipeline, PipelineError, PipelineFeatures,
+};
 
 use super::pipeline::descriptor_set_layout;
-use super::render_pass::RenderPass;
+use super::render_pass::{RenderPass, RenderPassContext};
 
 use cgmath::conv;
-use cgmath::Matrix4;
+use cgmath::{Matrix4, SquareMatrix};
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::mem::size_of;
 
 use ash::vk::{DescriptorBufferInfo, DescriptorSetLayout};
 
+mod debug_draw;
+mod input_attachments;
 pub mod instance_manager;
+pub mod light_culling;
+mod material;
+mod occlusion;
+mod post_process;
+pub mod profiler;
 pub mod scene_manager;
+mod shadow;
+mod skybox;
+mod sprite_batch;
 mod swapchain;
+mod texture_sets;
 mod uniform_manager;
 
 const MAX_FRAMES_IN_FLIGHT: u32 = 2;
 const MAX_INSTANCES: u32 = 10;
-const MAX_LIGHTS: u32 = 100;
+const MAX_LIGHTS: u32 = 1024;
 
 pub struct VulkanInstance {
+    pub instance_id_: InstanceID,
     pub mesh_id_: MeshID,
+    pub transform_: Matrix4<f32>,
     pub descriptor_set_: ash::vk::DescriptorSet,
+    pub layer_mask_: u32,
+    pub material_id_: Option<MaterialID>,
+    /// true if the instance was hidden behind the scene in previous frames, so is not drawn. It
+    /// still casts shadows
+    pub occluded_: bool,
 }
 
 pub struct FrameData {
     pub vulkan_instances_: Vec<VulkanInstance>,
-    pub view_: Matrix4<f32>,
-    pub projection_: Matrix4<f32>,
+    pub cameras_: Vec<Camera>,
+}
+
+/// A pipeline with the descriptor sets its draws share
+struct PipelineBindings<'a> {
+    pipeline_: &'a Pipeline,
+    input_attachment_sets_: &'a [ash::vk::DescriptorSet],
+    shadow_set_: Option<ash::vk::DescriptorSet>,
+    sets_: &'a [(u32, ash::vk::DescriptorSet)],
+}
+
+/// A subpass of the render pass and the bindings of its pipeline
+struct SubpassDraw<'a> {
+    subpass_: usize,
+    /// Whether the subpass reads no vertex attributes and draws a triangle covering the viewport
+    fullscreen_: bool,
+    bindings_: PipelineBindings<'a>,
 }
 
 pub struct Renderer {
@@ -63,6 +107,55 @@ pub struct Renderer {
     instances_loaded_: bool,
     pipeline_: Option<Pipeline>,
     render_pass_: Option<RenderPass>,
+    /// input attachment descriptor sets of the current render pass's subpasses
+    input_attachment_sets_: Option<input_attachments::InputAttachmentSets>,
+    /// renders the shadow maps of the scene's shadow casting lights before the render pass
+    shadow_pass_: shadow::ShadowPass,
+    /// assigns the scene's lights to clusters of the first camera's view before the render pass
+    light_culling_: light_culling::LightCulling,
+    /// shadow and light descriptor sets of the current render pass's subpasses
+    shadow_sets_: Option<shadow::ShadowSets>,
+    /// textures bound to the combined image samplers of this renderer's pipelines
+    texture_bindings_: Vec<texture_sets::TextureBinding>,
+    /// texture descriptor sets of the current render pass's subpasses
+    texture_sets_: Option<texture_sets::TextureSets>,
+    /// materials instances can be drawn with in place of the main pass pipeline
+    materials_: HashMap<MaterialID, material::Material>,
+    curr_material_id_: MaterialID,
+    viewport_: Viewport,
+    /// the description the current render pass was built from, kept so it can be rebuilt
+    render_pass_description_: Option<RenderPassDescription>,
+    pipeline_cache_: ash::vk::PipelineCache,
+    /// watches the shader sources of the current render pass while hot reload is enabled
+    shader_watcher_: Option<ShaderWatcher>,
+    shader_hot_reload_: bool,
+    shader_reload_error_: Option<String>,
+    /// sample count and sample shading render passes are built with
+    multisample_: Multisample,
+    /// sample counts supported by both color and depth attachments of the physical device
+    supported_sample_counts_: ash::vk::SampleCountFlags,
+    sample_rate_shading_supported_: bool,
+    /// optional device features render passes build their pipelines with
+    pipeline_features_: PipelineFeatures,
+    /// post-processing chain applied to the scene image, which is then drawn to an HDR offscreen
+    /// target rather than the swapchain image
+    post_process_: Option<post_process::PostProcess>,
+    /// debug lines of the next frame
+    debug_draw_: DebugDraw,
+    /// draws the debug lines in the final subpass
+    debug_draw_pass_: debug_draw::DebugDrawPass,
+    /// sprites of the next frame
+    sprite_batch_: SpriteBatch,
+    /// draws the world sprites in the final subpass, before the debug lines
+    world_sprite_pass_: sprite_batch::SpriteBatchPass,
+    /// draws the other sprites in the final subpass, over the debug lines
+    sprite_batch_pass_: sprite_batch::SpriteBatchPass,
+    /// times the passes of each frame on the GPU
+    profiler_: profiler::GpuProfiler,
+    /// skips drawing instances hidden behind the scene in previous frames
+    occlusion_culling_: occlusion::OcclusionCulling,
+    /// draws the scene's environment in the final subpass, behind its geometry
+    skybox_pass_: skybox::SkyboxPass,
 }
 
 impl Renderer {
@@ -160,6 +253,56 @@ impl Renderer {
             );
         }
 
+        let shadow_pass = shadow::ShadowPass::new(
+            instance,
+            device,
+            physical_device,
+            swapchain.swapchain_images_.len(),
+        );
+
+        let light_culling = light_culling::LightCulling::new(
+            instance,
+            device,
+            physical_device,
+            light_culling::ClusterGrid::default(),
+            swapchain.swapchain_images_.len(),
+        );
+
+        let debug_draw_pass = debug_draw::DebugDrawPass::new(
+            instance,
+            device,
+            physical_device,
+            swapchain.swapchain_images_.len(),
+        );
+
+        let world_sprite_pass = sprite_batch::SpriteBatchPass::new(
+            instance,
+            device,
+            physical_device,
+            swapchain.swapchain_images_.len(),
+            true,
+        );
+
+        let sprite_batch_pass = sprite_batch::SpriteBatchPass::new(
+            instance,
+            device,
+            physical_device,
+            swapchain.swapchain_images_.len(),
+            false,
+        );
+
+        let skybox_pass = skybox::SkyboxPass::new(swapchain.swapchain_images_.len());
+
+        let profiler =
+            profiler::GpuProfiler::new(device, physical_device, MAX_FRAMES_IN_FLIGHT as usize);
+
+        let occlusion_culling = occlusion::OcclusionCulling::new(
+            instance,
+            device,
+            physical_device,
+            MAX_FRAMES_IN_FLIGHT as usize,
+        );
+
         let present_queue = device.get_device_queue(
             physical_device
                 .queue_family_indices_
@@ -186,6 +329,34 @@ impl Renderer {
             instances_loaded_: true,
             pipeline_: None,
             render_pass_: None,
+            input_attachment_sets_: None,
+            shadow_pass_: shadow_pass,
+            light_culling_: light_culling,
+            shadow_sets_: None,
+            texture_bindings_: vec![],
+            texture_sets_: None,
+            materials_: HashMap::new(),
+            curr_material_id_: 0,
+            viewport_: Viewport::full(),
+            render_pass_description_: None,
+            pipeline_cache_: ash::vk::PipelineCache::null(),
+            shader_watcher_: None,
+            shader_hot_reload_: false,
+            shader_reload_error_: None,
+            multisample_: Multisample::default(),
+            supported_sample_counts_: physical_device.get_supported_sample_counts(),
+            sample_rate_shading_supported_: physical_device.features_.sample_rate_shading
+                == ash::vk::TRUE,
+            pipeline_features_: PipelineFeatures::from_physical_device(physical_device),
+            post_process_: None,
+            debug_draw_: DebugDraw::new(),
+            debug_draw_pass_: debug_draw_pass,
+            sprite_batch_: SpriteBatch::new(),
+            world_sprite_pass_: world_sprite_pass,
+            sprite_batch_pass_: sprite_batch_pass,
+            profiler_: profiler,
+            occlusion_culling_: occlusion_culling,
+            skybox_pass_: skybox_pass,
         }
     }
     /*
@@ -200,8 +371,800 @@ impl Renderer {
 
         }
     */
-    pub fn set_render_pass(&mut self, device: &ash::Device, description: &RenderPassDescription) {
-       // let render_pass = RenderPass::from_description(device, description, None, None);
+    /// Builds the render pass and pipelines described by a RenderPassDescription, creates the
+    /// swapchain framebuffers for it, and makes it the render pass used by this renderer. Any
+    /// previously set render pass is destroyed
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the render pass is created on
+    ///
+    /// * 'description' - reflected description of the render pass and its pipelines
+    ///
+    /// * 'pipeline_cache' - cache used to speed up pipeline compilation
+    ///
+    pub fn set_render_pass(
+        &mut self,
+        device: &ash::Device,
+        description: &RenderPassDescription,
+        pipeline_cache: ash::vk::PipelineCache,
+    ) {
+        self.try_set_render_pass(device, description, pipeline_cache)
+            .unwrap_or_else(|error| panic!("Could not build render pass: {}", error));
+    }
+
+    /// Builds and sets a render pass like set_render_pass, but returns an error instead of
+    /// panicking if the pipelines cannot be built. The current render pass is kept on error
+    fn try_set_render_pass(
+        &mut self,
+        device: &ash::Device,
+        description: &RenderPassDescription,
+        pipeline_cache: ash::vk::PipelineCache,
+    ) -> Result<(), PipelineError> {
+        unsafe {
+            device
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+        }
+
+        let present = match self.post_process_ {
+            Some(_) => (
+                post_process::HDR_FORMAT,
+                ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+            None => (
+                self.swapchain_.swapchain_surface_format_,
+                ash::vk::ImageLayout::PRESENT_SRC_KHR,
+            ),
+        };
+
+        let render_pass = RenderPass::from_description(
+            device,
+            description,
+            present,
+            self.swapchain_.get_depth_format(),
+            self.multisample_,
+            self.pipeline_features_,
+            pipeline_cache,
+        )?;
+
+        // Every material pipeline is built against the new render pass before anything is
+        // destroyed, so the current render pass and pipelines are kept if any fails to build
+        let mut material_pipelines = vec![];
+
+        for (material_id, material) in self.materials_.iter() {
+            match material.create_pipeline(device, &render_pass, description, pipeline_cache) {
+                Ok(pipeline) => material_pipelines.push((*material_id, pipeline)),
+                Err(error) => {
+                    for (_, pipeline) in material_pipelines.iter() {
+                        pipeline.destroy(device);
+                    }
+                    render_pass.destroy(device);
+                    return Err(error);
+                }
+            }
+        }
+
+        if let Some(old_render_pass) = self.render_pass_.take() {
+            old_render_pass.destroy(device);
+        }
+
+        if let Some(old_input_attachment_sets) = self.input_attachment_sets_.take() {
+            old_input_attachment_sets.destroy(device);
+        }
+
+        if let Some(old_shadow_sets) = self.shadow_sets_.take() {
+            old_shadow_sets.destroy(device);
+        }
+
+        if let Some(old_texture_sets) = self.texture_sets_.take() {
+            old_texture_sets.destroy(device);
+        }
+
+        self.debug_draw_pass_.reset(device);
+        self.world_sprite_pass_.reset(device);
+        self.sprite_batch_pass_.reset(device);
+        self.occlusion_culling_.reset(device);
+        self.skybox_pass_.reset(device);
+
+        unsafe {
+            self.swapchain_.create_swapchain_framebuffers(
+                device,
+                render_pass.vk_render_pass_,
+                &render_pass.attachment_formats_,
+                render_pass.multisample_.samples_,
+                self.post_process_
+                    .as_ref()
+                    .map(|_| post_process::HDR_FORMAT),
+            );
+        }
+
+        if let Some(post_process) = self.post_process_.as_mut() {
+            post_process.create_framebuffers(device, &self.swapchain_);
+        }
+
+        self.input_attachment_sets_ = Some(input_attachments::InputAttachmentSets::new(
+            device,
+            &render_pass,
+            description,
+            &self.swapchain_,
+        ));
+
+        let shadow_sets = shadow::ShadowSets::new(
+            device,
+            &render_pass.get_pipelines(description),
+            self.swapchain_.swapchain_images_.len(),
+        );
+
+        for image_index in 0..self.swapchain_.swapchain_images_.len() {
+            shadow_sets.write(
+                device,
+                &self.shadow_pass_,
+                &self.scene_manager_,
+                &self.light_culling_,
+                image_index as u32,
+            );
+        }
+
+        self.shadow_sets_ = Some(shadow_sets);
+
+        self.texture_sets_ = Some(texture_sets::TextureSets::new(
+            device,
+            &render_pass.get_pipelines(description),
+            &self.texture_bindings_,
+        ));
+
+        for (material_id, pipeline) in material_pipelines {
+            let material = self.materials_.get_mut(&material_id).unwrap();
+
+            material.set_pipeline(
+                device,
+                pipeline,
+                description,
+                &self.texture_bindings_,
+                self.swapchain_.swapchain_images_.len(),
+            );
+
+            for image_index in 0..self.swapchain_.swapchain_images_.len() {
+                material.write_shadow_sets(
+                    device,
+                    &self.shadow_pass_,
+                    &self.scene_manager_,
+                    &self.light_culling_,
+                    image_index as u32,
+                );
+            }
+        }
+
+        let instance_descriptor_set_layout = *render_pass
+            .mainpass_
+            .descriptor_set_layouts_
+            .first()
+            .expect("The main pass pipeline must declare a descriptor set for instance uniforms");
+
+        self.create_descriptor_sets(device, instance_descriptor_set_layout);
+
+        self.pipeline_ = Some(render_pass.mainpass_.clone());
+        self.render_pass_ = Some(render_pass);
+        self.render_pass_description_ = Some(description.clone());
+        self.pipeline_cache_ = pipeline_cache;
+
+        self.watch_shader_sources();
+
+        Ok(())
+    }
+
+    /// Enables or disables shader hot reload. While enabled, the GLSL sources and includes of the
+    /// current render pass, of every material and of the post-processing passes are watched, and
+    /// the pipelines built from any that changed are rebuilt at the start of the next frame. If a
+    /// shader fails to compile or a pipeline cannot be built, the pipelines it belongs to are
+    /// kept and the error is reported
+    pub fn set_shader_hot_reload(&mut self, enabled: bool) {
+        self.shader_hot_reload_ = enabled;
+        self.shader_watcher_ = None;
+        self.watch_shader_sources();
+    }
+
+    /// Adds the shader sources of the render pass, every material and the post-processing passes
+    /// to the files watched for hot reload, if it is enabled
+    fn watch_shader_sources(&mut self) {
+        if !self.shader_hot_reload_ {
+            return;
+        }
+
+        let mut source_files = vec![];
+
+        if let Some(description) = self.render_pass_description_.as_ref() {
+            source_files.append(&mut description.get_source_files());
+        }
+
+        for material in self.materials_.values() {
+            source_files.append(&mut material.get_description().pipeline_.get_source_files());
+        }
+
+        if let Some(post_process) = self.post_process_.as_ref() {
+            source_files.append(&mut post_process.get_source_files());
+        }
+
+        self.shader_watcher_
+            .get_or_insert_with(ShaderWatcher::new)
+            .watch(source_files);
+    }
+
+    /// Returns the error of the last failed shader hot reload, or None if the last reload
+    /// succeeded
+    pub fn get_shader_reload_error(&self) -> Option<&String> {
+        self.shader_reload_error_.as_ref()
+    }
+
+    /// Rebuilds the pipelines whose shader sources changed, if hot reload is enabled
+    fn reload_changed_shaders(&mut self, device: &ash::Device) {
+        let changed_files = match self.shader_watcher_.as_mut() {
+            Some(shader_watcher) => shader_watcher.poll(),
+            None => return,
+        };
+
+        if changed_files.is_empty() {
+            return;
+        }
+
+        let result = self.reload_shaders(device, &changed_files);
+
+        self.shader_reload_error_ = match result {
+            Ok(()) => None,
+            Err(error) => {
+                println!("Shader hot reload failed, keeping previous pipelines: {}", error);
+                Some(error)
+            }
+        };
+    }
+
+    /// Recompiles and rebuilds the render pass, the materials and the post-processing passes whose
+    /// shaders were compiled from any of the changed files. Each keeps its current pipelines if it
+    /// cannot be rebuilt, and the errors of all that failed are returned
+    fn reload_shaders(
+        &mut self,
+        device: &ash::Device,
+        changed_files: &[PathBuf],
+    ) -> Result<(), String> {
+        let uses_changed_files = |source_files: Vec<PathBuf>| {
+            source_files.iter().any(|path| changed_files.contains(path))
+        };
+        let mut errors = vec![];
+
+        unsafe {
+            device
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+        }
+
+        if let Some(description) = self.render_pass_description_.clone() {
+            if uses_changed_files(description.get_source_files()) {
+                let result = description.recompile().and_then(|description| {
+                    self.try_set_render_pass(device, &description, self.pipeline_cache_)
+                        .map_err(|error| error.to_string())
+                });
+
+                if let Err(error) = result {
+                    errors.push(error);
+                }
+            }
+        }
+
+        if let (Some(render_pass), Some(render_pass_description)) = (
+            self.render_pass_.as_ref(),
+            self.render_pass_description_.as_ref(),
+        ) {
+            let num_images = self.swapchain_.swapchain_images_.len();
+
+            for material in self.materials_.values_mut() {
+                if !uses_changed_files(material.get_description().pipeline_.get_source_files()) {
+                    continue;
+                }
+
+                let result = material.reload_pipeline(
+                    device,
+                    render_pass,
+                    render_pass_description,
+                    &self.texture_bindings_,
+                    num_images,
+                    self.pipeline_cache_,
+                );
+
+                if let Err(error) = result {
+                    errors.push(error);
+                    continue;
+                }
+
+                for image_index in 0..num_images {
+                    material.write_shadow_sets(
+                        device,
+                        &self.shadow_pass_,
+                        &self.scene_manager_,
+                        &self.light_culling_,
+                        image_index as u32,
+                    );
+                }
+            }
+        }
+
+        if let Some(post_process) = self.post_process_.as_mut() {
+            let result = post_process.reload_shaders(
+                device,
+                &self.swapchain_,
+                changed_files,
+                self.pipeline_cache_,
+            );
+
+            if let Err(error) = result {
+                errors.push(error);
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors.join("; ")),
+        }
+    }
+
+    /// Sets the number of samples per pixel render passes are built with, clamped to the highest
+    /// count the device supports for both color and depth attachments. The current render pass is
+    /// rebuilt if the sample count changes. Returns the sample count in use
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the render pass is rebuilt on
+    ///
+    /// * 'samples' - the requested number of samples per pixel, 1 disables multisampling
+    ///
+    pub fn set_sample_count(&mut self, device: &ash::Device, samples: u32) -> u32 {
+        let samples = physical_device::clamp_sample_count(samples, self.supported_sample_counts_);
+
+        if samples != self.multisample_.samples_ {
+            self.multisample_.samples_ = samples;
+            self.rebuild_render_pass(device);
+        }
+
+        samples.as_raw()
+    }
+
+    /// Returns the number of samples per pixel render passes are built with
+    pub fn get_sample_count(&self) -> u32 {
+        self.multisample_.samples_.as_raw()
+    }
+
+    /// Enables shading a fraction of the samples of each pixel individually rather than once per
+    /// pixel, which also antialiases aliasing inside triangles. Only has an effect while
+    /// multisampling. Ignored if the device does not support sample rate shading
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the render pass is rebuilt on
+    ///
+    /// * 'min_sample_shading' - the minimum fraction of samples to shade, clamped to 0..1, or None
+    ///   to shade each pixel once
+    ///
+    pub fn set_sample_shading(&mut self, device: &ash::Device, min_sample_shading: Option<f32>) {
+        if min_sample_shading.is_some() && !self.sample_rate_shading_supported_ {
+            println!("Sample rate shading is not supported by this device, ignoring it");
+            return;
+        }
+
+        self.multisample_.min_sample_shading_ =
+            min_sample_shading.map(|fraction| fraction.clamp(0.0, 1.0));
+        self.rebuild_render_pass(device);
+    }
+
+    /// Changes how lights are split into clusters of the view frustum, recreating the cluster
+    /// buffers
+    ///
+    /// # Arguments
+    ///
+    /// * 'instance' - the instance the physical device belongs to
+    ///
+    /// * 'device' - the logical device the buffers are created on
+    ///
+    /// * 'physical_device' - the physical device used to pick memory types
+    ///
+    /// * 'grid' - the number of clusters across, down and in depth and the lights each can hold
+    ///
+    pub fn set_light_clusters(
+        &mut self,
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &physical_device::PhysicalDevice,
+        grid: light_culling::ClusterGrid,
+    ) {
+        unsafe {
+            device
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+        }
+
+        self.light_culling_.destroy(device);
+        self.light_culling_ = light_culling::LightCulling::new(
+            instance,
+            device,
+            physical_device,
+            grid,
+            self.swapchain_.swapchain_images_.len(),
+        );
+    }
+
+    /// Sets the post-processing chain applied to everything this renderer draws, or removes it.
+    /// While a chain is set, the render pass draws into an HDR offscreen target that the chain's
+    /// passes read, and the chain's final pass writes the swapchain image. The current render pass
+    /// is rebuilt. Panics if a pass of the chain cannot be built
+    ///
+    /// # Arguments
+    ///
+    /// * 'instance' - the instance the physical device belongs to
+    ///
+    /// * 'device' - the logical device the chain is created on
+    ///
+    /// * 'physical_device' - the physical device used to pick memory types
+    ///
+    /// * 'chain' - the effects to apply, or None to draw straight into the swapchain image
+    ///
+    pub fn set_post_process(
+        &mut self,
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &physical_device::PhysicalDevice,
+        chain: Option<&PostProcessChain>,
+    ) {
+        unsafe {
+            device
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+        }
+
+        if let Some(mut old_post_process) = self.post_process_.take() {
+            old_post_process.destroy(device);
+        }
+
+        if let Some(chain) = chain {
+            let mut post_process = post_process::PostProcess::new(
+                instance,
+                device,
+                physical_device,
+                chain,
+                self.swapchain_.swapchain_surface_format_,
+                self.pipeline_cache_,
+            )
+            .unwrap_or_else(|error| panic!("Could not build post-processing chain: {}", error));
+
+            if post_process.has_pending_uploads() {
+                self.submit_immediate(device, |command_buffer| {
+                    post_process.record_uploads(device, command_buffer)
+                });
+                post_process.finish_uploads(device);
+            }
+
+            self.post_process_ = Some(post_process);
+        }
+
+        self.rebuild_render_pass(device);
+        self.watch_shader_sources();
+    }
+
+    /// Binds a texture to the combined image sampler at the given set and binding of every
+    /// pipeline of this renderer declaring one there, replacing any texture bound to it before.
+    /// Set 0 holds instance uniforms, set 1 holds shadows and set MATERIAL_SET holds material
+    /// parameters, so none of them can be used
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the renderer was created on
+    ///
+    /// * 'set' - the descriptor set index of the sampler
+    ///
+    /// * 'binding' - the binding of the sampler within the set
+    ///
+    /// * 'image_view' - the view of the texture, which must be in shader read only layout
+    ///
+    /// * 'sampler' - the sampler the texture is looked up with
+    ///
+    pub fn bind_texture(
+        &mut self,
+        device: &ash::Device,
+        set: u32,
+        binding: u32,
+        image_view: ash::vk::ImageView,
+        sampler: ash::vk::Sampler,
+    ) {
+        assert!(
+            set != 0 && set as usize != shadow::SHADOW_SET && set != MATERIAL_SET,
+            "Textures cannot be bound to set {}, which is reserved",
+            set
+        );
+
+        self.texture_bindings_.retain(|texture_binding| {
+            (texture_binding.set_, texture_binding.binding_) != (set, binding)
+        });
+        self.texture_bindings_.push(texture_sets::TextureBinding {
+            set_: set,
+            binding_: binding,
+            image_view_: image_view,
+            sampler_: sampler,
+        });
+
+        let (render_pass, description) =
+            match (self.render_pass_.as_ref(), self.render_pass_description_.as_ref()) {
+                (Some(render_pass), Some(description)) => (render_pass, description),
+                _ => return,
+            };
+
+        unsafe {
+            device
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+        }
+
+        if let Some(old_texture_sets) = self.texture_sets_.take() {
+            old_texture_sets.destroy(device);
+        }
+
+        self.texture_sets_ = Some(texture_sets::TextureSets::new(
+            device,
+            &render_pass.get_pipelines(description),
+            &self.texture_bindings_,
+        ));
+
+        for material in self.materials_.values_mut() {
+            material.set_texture_bindings(device, &self.texture_bindings_);
+        }
+    }
+
+    /// Creates a material instances of scenes drawn by this renderer can be drawn with in place
+    /// of the main pass pipeline. Its pipeline is built for the first subpass of the current
+    /// render pass, and rebuilt whenever the render pass is. Parameters naming no uniform block
+    /// member or sampler of set MATERIAL_SET are reported. Returns a handle identifying the
+    /// material within this renderer, or an error if the pipeline cannot be built
+    ///
+    /// # Arguments
+    ///
+    /// * 'instance' - the instance the physical device belongs to
+    ///
+    /// * 'device' - the logical device the renderer was created on
+    ///
+    /// * 'physical_device' - the physical device used to pick memory types
+    ///
+    /// * 'description' - the material's pipeline and parameter values
+    ///
+    /// * 'textures' - the binding, image view and sampler of the texture of every combined image
+    ///   sampler of set MATERIAL_SET
+    ///
+    pub fn create_material(
+        &mut self,
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &physical_device::PhysicalDevice,
+        description: &MaterialDescription,
+        textures: &[(u32, ash::vk::ImageView, ash::vk::Sampler)],
+    ) -> Result<MaterialID, PipelineError> {
+        for message in description.find_mismatched_parameters() {
+            println!("{}", message);
+        }
+
+        let mut material = material::Material::new(
+            instance,
+            device,
+            physical_device,
+            description,
+            Renderer::material_texture_bindings(textures),
+        );
+
+        if let (Some(render_pass), Some(render_pass_description)) = (
+            self.render_pass_.as_ref(),
+            self.render_pass_description_.as_ref(),
+        ) {
+            let built = material.build_pipeline(
+                device,
+                render_pass,
+                render_pass_description,
+                &self.texture_bindings_,
+                self.swapchain_.swapchain_images_.len(),
+                self.pipeline_cache_,
+            );
+
+            if let Err(error) = built {
+                material.destroy(device);
+                return Err(error);
+            }
+
+            for image_index in 0..self.swapchain_.swapchain_images_.len() {
+                material.write_shadow_sets(
+                    device,
+                    &self.shadow_pass_,
+                    &self.scene_manager_,
+                    &self.light_culling_,
+                    image_index as u32,
+                );
+            }
+        }
+
+        let material_id = self.curr_material_id_;
+        self.materials_.insert(material_id, material);
+        self.curr_material_id_ += 1;
+
+        self.watch_shader_sources();
+
+        Ok(material_id)
+    }
+
+    /// Returns the description of a material, with its current parameter values. Panics if there
+    /// is no such material
+    pub fn get_material_description(&self, material_id: MaterialID) -> &MaterialDescription {
+        self.materials_
+            .get(&material_id)
+            .expect("No such material")
+            .get_description()
+    }
+
+    /// Replaces the parameter values and textures of a material once the device has finished
+    /// drawing with it. The material's pipeline is kept. Panics if there is no such material
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the renderer was created on
+    ///
+    /// * 'material_id' - handle of the material
+    ///
+    /// * 'description' - the material with its new parameter values
+    ///
+    /// * 'textures' - the binding, image view and sampler of the texture of every combined image
+    ///   sampler of set MATERIAL_SET
+    ///
+    pub fn set_material_parameters(
+        &mut self,
+        device: &ash::Device,
+        material_id: MaterialID,
+        description: &MaterialDescription,
+        textures: &[(u32, ash::vk::ImageView, ash::vk::Sampler)],
+    ) {
+        for message in description.find_mismatched_parameters() {
+            println!("{}", message);
+        }
+
+        unsafe {
+            device
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+        }
+
+        self.materials_
+            .get_mut(&material_id)
+            .expect("No such material")
+            .set_parameters(
+                device,
+                description,
+                Renderer::material_texture_bindings(textures),
+            );
+    }
+
+    /// Destroys a material once the device has finished drawing with it. Instances must no longer
+    /// use it. Panics if there is no such material
+    pub fn destroy_material(&mut self, device: &ash::Device, material_id: MaterialID) {
+        unsafe {
+            device
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+        }
+
+        self.materials_
+            .remove(&material_id)
+            .expect("No such material")
+            .destroy(device);
+    }
+
+    fn material_texture_bindings(
+        textures: &[(u32, ash::vk::ImageView, ash::vk::Sampler)],
+    ) -> Vec<texture_sets::TextureBinding> {
+        textures
+            .iter()
+            .map(
+                |(binding, image_view, sampler)| texture_sets::TextureBinding {
+                    set_: MATERIAL_SET,
+                    binding_: *binding,
+                    image_view_: *image_view,
+                    sampler_: *sampler,
+                },
+            )
+            .collect()
+    }
+
+    /// Records commands with the given function and submits them to the present queue, waiting
+    /// for them to complete. Must not be called while a frame is being recorded
+    fn submit_immediate<F>(&self, device: &ash::Device, record: F)
+    where
+        F: FnOnce(ash::vk::CommandBuffer),
+    {
+        // Every frame begins its command buffer again, so borrowing one in between is safe once
+        // the device is idle
+        let command_buffer = self.command_dispatch_.command_buffers_[0];
+
+        let command_buffer_begin_info = ash::vk::CommandBufferBeginInfo {
+            s_type: ash::vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+            p_next: ptr::null(),
+            flags: ash::vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+            p_inheritance_info: ptr::null(),
+        };
+
+        let submit_info = ash::vk::SubmitInfo {
+            s_type: ash::vk::StructureType::SUBMIT_INFO,
+            p_next: ptr::null(),
+            wait_semaphore_count: 0,
+            p_wait_semaphores: ptr::null(),
+            p_wait_dst_stage_mask: ptr::null(),
+            command_buffer_count: 1,
+            p_command_buffers: &command_buffer,
+            signal_semaphore_count: 0,
+            p_signal_semaphores: ptr::null(),
+        };
+
+        unsafe {
+            device
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+
+            device
+                .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+                .expect("Could not begin recording command buffer");
+
+            record(command_buffer);
+
+            device
+                .end_command_buffer(command_buffer)
+                .expect("Could not end recording command buffer");
+
+            device
+                .queue_submit(self.present_queue_, &[submit_info], ash::vk::Fence::null())
+                .expect("submit to queue failed");
+
+            device
+                .queue_wait_idle(self.present_queue_)
+                .expect("Could not wait for queue to become idle");
+        }
+    }
+
+    /// Rebuilds the current render pass from its description, picking up changed multisample
+    /// and post-processing settings. Does nothing if no render pass is set
+    fn rebuild_render_pass(&mut self, device: &ash::Device) {
+        if let Some(description) = self.render_pass_description_.clone() {
+            self.set_render_pass(device, &description, self.pipeline_cache_);
+        }
+    }
+
+    /// Restricts drawing to a rectangle of the swapchain image, for example to letterbox content
+    /// of a fixed aspect ratio. The rest of the image keeps the clear color
+    ///
+    /// # Arguments
+    ///
+    /// * 'viewport' - the rectangle to draw to, as fractions of the swapchain extent
+    ///
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport_ = viewport;
+    }
+
+    /// Returns the rectangle of the swapchain image this renderer draws to
+    pub fn get_viewport(&self) -> Viewport {
+        self.viewport_
+    }
+
+    /// Returns width / height of the swapchain images this renderer draws to
+    pub fn get_aspect_ratio(&self) -> f32 {
+        self.swapchain_.swapchain_extent_.width as f32
+            / self.swapchain_.swapchain_extent_.height as f32
+    }
+
+    /// Returns width / height of a camera viewport within this renderer's viewport, for building
+    /// the camera's projection
+    pub fn get_camera_aspect_ratio(&self, camera: &Camera) -> f32 {
+        camera.viewport_.within(&self.viewport_).get_aspect_ratio(
+            self.swapchain_.swapchain_extent_.width,
+            self.swapchain_.swapchain_extent_.height,
+        )
     }
 
     pub fn create_instance(&mut self, mesh_id: u64) -> u64 {
@@ -236,6 +1199,12 @@ impl Renderer {
         };
 
         self.descriptor_sets_ = unsafe {
+            device
+                .reset_descriptor_pool(
+                    self.descriptor_pool_,
+                    ash::vk::DescriptorPoolResetFlags::empty(),
+                )
+                .expect("Could not reset descriptor pool");
             device
                 .allocate_descriptor_sets(&descriptor_set_allocate_info)
                 .expect("Could not allocate descriptor sets")
@@ -285,7 +1254,19 @@ impl Renderer {
         &mut self,
         device: &ash::Device,
         geometry_manager: &geometry_manager::GeometryManager,
+        texture_manager: &TextureManager,
     ) {
+        // Rebuilding pipelines waits for the device to become idle, so do it between frames
+        self.reload_changed_shaders(device);
+
+        // Command buffers are recorded with the queries of the frame slot that was current, so
+        // one is never submitted again under another slot. Without a scene nothing is drawn, and
+        // the last presented image stays on screen
+        let scene = match self.scenes_.pop_front() {
+            Some(scene) => scene,
+            None => return,
+        };
+
         let wait_fences = [self.in_flight_fences_[self.current_frame_ as usize]];
 
         unsafe {
@@ -293,6 +1274,12 @@ impl Renderer {
                 .wait_for_fences(&wait_fences, true, std::u64::MAX)
                 .expect("Wait for fences failed");
 
+            // The frame's previous commands have completed, so their queries can be read
+            self.profiler_
+                .read_results(device, self.current_frame_ as usize);
+            self.occlusion_culling_
+                .read_results(device, self.current_frame_ as usize);
+
             let (image_index, ready) = self
                 .swapchain_
                 .acquire_next_image(self.image_available_sempahores_[self.current_frame_ as usize]);
@@ -302,27 +1289,115 @@ impl Renderer {
             let signal_semaphores =
                 [self.render_finished_semaphores_[self.current_frame_ as usize]];
 
-            let curr_scene = self.scenes_.pop_front();
+            if let Some(camera) = scene.get_cameras().first() {
+                self.shadow_pass_.update(
+                    device,
+                    image_index as usize,
+                    &scene.lights_,
+                    camera,
+                    self.pipeline_cache_,
+                );
 
-            match curr_scene {
-                Some(scene) => {
-                    let frame_data = FrameData {
-                        vulkan_instances_: self.process_scene(device, &scene, image_index),
-                        view_: scene.view_,
-                        projection_: scene.projection_,
-                    };
+                let (viewport, _) =
+                    self.build_viewport_and_scissor(&camera.viewport_.within(&self.viewport_));
 
-                    self.record_draw_commands_forward(
-                        device,
-                        geometry_manager,
-                        image_index,
-                        frame_data,
-                    );
-                }
+                self.light_culling_.update(
+                    device,
+                    image_index as usize,
+                    &scene.lights_,
+                    camera,
+                    &viewport,
+                    self.pipeline_cache_,
+                );
+            }
+
+            self.scene_manager_.update(image_index, &scene.lights_);
+            self.light_culling_.write_culling_set(
+                device,
+                &self.scene_manager_,
+                image_index as usize,
+            );
+
+            if let Some(shadow_sets) = self.shadow_sets_.as_ref() {
+                shadow_sets.write(
+                    device,
+                    &self.shadow_pass_,
+                    &self.scene_manager_,
+                    &self.light_culling_,
+                    image_index,
+                );
+            }
+
+            for material in self.materials_.values() {
+                material.write_shadow_sets(
+                    device,
+                    &self.shadow_pass_,
+                    &self.scene_manager_,
+                    &self.light_culling_,
+                    image_index,
+                );
+            }
 
-                None => {}
+            let render_pass = RenderPassContext {
+                render_pass_: self
+                    .render_pass_
+                    .as_ref()
+                    .expect("No render pass has been set for this renderer"),
+                description_: self.render_pass_description_.as_ref().unwrap(),
+                pipeline_cache_: self.pipeline_cache_,
             };
 
+            self.debug_draw_pass_.update(
+                device,
+                image_index as usize,
+                &self.debug_draw_,
+                &render_pass,
+            );
+            self.debug_draw_.clear();
+
+            for sprite_pass in
+                [&mut self.world_sprite_pass_, &mut self.sprite_batch_pass_].iter_mut()
+            {
+                sprite_pass.update(
+                    device,
+                    image_index as usize,
+                    &self.sprite_batch_,
+                    texture_manager,
+                    &render_pass,
+                );
+            }
+            self.sprite_batch_.clear();
+
+            self.skybox_pass_.update(
+                device,
+                image_index as usize,
+                scene.environment_,
+                texture_manager,
+                &render_pass,
+            );
+
+            let mut frame_data = FrameData {
+                vulkan_instances_: self.process_scene(device, &scene, image_index),
+                cameras_: scene.get_cameras(),
+            };
+
+            let render_pass = RenderPassContext {
+                render_pass_: self.render_pass_.as_ref().unwrap(),
+                description_: self.render_pass_description_.as_ref().unwrap(),
+                pipeline_cache_: self.pipeline_cache_,
+            };
+
+            self.occlusion_culling_.update(
+                device,
+                self.current_frame_ as usize,
+                &mut frame_data.vulkan_instances_,
+                geometry_manager,
+                &frame_data.cameras_,
+                &render_pass,
+            );
+
+            self.record_draw_commands_forward(device, geometry_manager, image_index, frame_data);
+
             let submit_infos = [ash::vk::SubmitInfo {
                 s_type: ash::vk::StructureType::SUBMIT_INFO,
                 p_next: ptr::null(),
@@ -357,6 +1432,50 @@ impl Renderer {
         }
     }
 
+    /// Returns the debug lines drawn over the next frame. They are cleared once the frame has been
+    /// drawn
+    pub fn get_mut_debug_draw(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw_
+    }
+
+    /// Returns the sprites drawn over the next frame. They are cleared once the frame has been
+    /// drawn, keeping the batch's camera
+    pub fn get_mut_sprite_batch(&mut self) -> &mut SpriteBatch {
+        &mut self.sprite_batch_
+    }
+
+    /// Returns the GPU time taken by each pass of the most recent frame whose timings are
+    /// available, a few frames behind the frame being drawn. Empty if the device cannot write
+    /// timestamps
+    pub fn last_frame_timings(&self) -> &profiler::FrameTimings {
+        self.profiler_.get_last_frame_timings()
+    }
+
+    /// Enables counting the vertex and fragment shader invocations of each top level pass of a
+    /// frame, reported with its timings. Ignored if the device does not support pipeline
+    /// statistics queries
+    pub fn set_pipeline_statistics(&mut self, enabled: bool) {
+        if !self.profiler_.set_statistics_enabled(enabled) {
+            println!("Pipeline statistics queries are not supported by this device, ignoring them");
+        }
+    }
+
+    pub fn get_pipeline_statistics(&self) -> bool {
+        self.profiler_.get_statistics_enabled()
+    }
+
+    /// Enables skipping instances whose bounding boxes were hidden behind the scene in previous
+    /// frames, tested with occlusion queries. Worthwhile for scenes where walls hide most
+    /// instances, such as interiors. Instances are drawn until they have been tested, so enabling
+    /// or disabling culling never hides an instance that should be seen
+    pub fn set_occlusion_culling(&mut self, enabled: bool) {
+        self.occlusion_culling_.set_enabled(enabled);
+    }
+
+    pub fn get_occlusion_culling(&self) -> bool {
+        self.occlusion_culling_.is_enabled()
+    }
+
     pub fn queue_scene(&mut self, scene: &Scene) {
         self.scenes_.push_back(scene.clone());
     }
@@ -368,7 +1487,10 @@ impl Renderer {
         image_index: u32,
     ) -> Vec<VulkanInstance> {
         let mut vulkan_instances = vec![];
-        let instances = scene.get_instances();
+        let (instance_ids, instances): (Vec<InstanceID>, Vec<_>) = scene
+            .iter_instances()
+            .map(|(instance_id, instance)| (*instance_id, *instance))
+            .unzip();
 
         let descriptor_buffer_infos = self
             .uniform_manager_
@@ -379,8 +1501,13 @@ impl Renderer {
 
         for (i, instance) in instances.iter().enumerate() {
             vulkan_instances.push(VulkanInstance {
+                instance_id_: instance_ids[i],
                 mesh_id_: instance.mesh_id_,
+                transform_: instance.transform_,
                 descriptor_set_: descriptor_sets[i],
+                layer_mask_: instance.layer_mask_,
+                material_id_: instance.material_id_,
+                occluded_: false,
             })
         }
 
@@ -522,6 +1649,256 @@ impl Renderer {
         bytes
     }
 
+    /// Converts a viewport given as fractions of the swapchain extent into a vulkan viewport and a
+    /// scissor rectangle covering the same pixels
+    fn build_viewport_and_scissor(
+        &self,
+        viewport: &Viewport,
+    ) -> (ash::vk::Viewport, ash::vk::Rect2D) {
+        let extent = self.swapchain_.swapchain_extent_;
+
+        let x = (viewport.x_ * extent.width as f32).round().max(0.0);
+        let y = (viewport.y_ * extent.height as f32).round().max(0.0);
+        let width = (viewport.width_ * extent.width as f32)
+            .round()
+            .min(extent.width as f32 - x)
+            .max(1.0);
+        let height = (viewport.height_ * extent.height as f32)
+            .round()
+            .min(extent.height as f32 - y)
+            .max(1.0);
+
+        let vk_viewport = ash::vk::Viewport {
+            x,
+            y,
+            width,
+            height,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+
+        let scissor = ash::vk::Rect2D {
+            offset: ash::vk::Offset2D {
+                x: x as i32,
+                y: y as i32,
+            },
+            extent: ash::vk::Extent2D {
+                width: width as u32,
+                height: height as u32,
+            },
+        };
+
+        (vk_viewport, scissor)
+    }
+
+    /// Binds a pipeline with the descriptor sets its draws share, and pushes a camera's view and
+    /// projection if the pipeline declares push constants
+    fn bind_pipeline(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        bindings: &PipelineBindings,
+        camera: &Camera,
+    ) {
+        let pipeline = bindings.pipeline_;
+
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                ash::vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline_handle_,
+            );
+
+            if !bindings.input_attachment_sets_.is_empty() {
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    ash::vk::PipelineBindPoint::GRAPHICS,
+                    pipeline.layout_,
+                    0,
+                    bindings.input_attachment_sets_,
+                    &[],
+                );
+            }
+
+            if let Some(shadow_set) = bindings.shadow_set_ {
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    ash::vk::PipelineBindPoint::GRAPHICS,
+                    pipeline.layout_,
+                    shadow::SHADOW_SET as u32,
+                    &[shadow_set],
+                    &[],
+                );
+            }
+
+            for (set, descriptor_set) in bindings.sets_.iter() {
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    ash::vk::PipelineBindPoint::GRAPHICS,
+                    pipeline.layout_,
+                    *set,
+                    &[*descriptor_set],
+                    &[],
+                );
+            }
+
+            if !pipeline.push_constant_ranges_.is_empty() {
+                let mut push_constant_vec = vec![];
+
+                let mut view_bytes = Renderer::mat4_to_bytes(camera.view_);
+
+                let mut projection_bytes = Renderer::mat4_to_bytes(camera.projection_);
+
+                push_constant_vec.append(&mut view_bytes);
+                push_constant_vec.append(&mut projection_bytes);
+
+                device.cmd_push_constants(
+                    command_buffer,
+                    pipeline.layout_,
+                    pipeline.get_push_constant_stage_flags(0, push_constant_vec.len() as u32),
+                    0,
+                    push_constant_vec.as_slice(),
+                );
+            }
+        }
+    }
+
+    /// Records the draws of one subpass for every camera. Subpasses reading vertex attributes draw
+    /// the instances each camera sees, while fullscreen subpasses generate a single triangle
+    /// covering each camera's viewport. In the first subpass, instances with a material are drawn
+    /// with the material's pipeline, grouped so that each pipeline is bound once per camera
+    fn record_subpass_draws(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        geometry_manager: &geometry_manager::GeometryManager,
+        image_index: u32,
+        draw: &SubpassDraw,
+        frame_data: &FrameData,
+    ) {
+        let subpass = draw.subpass_;
+        let fullscreen = draw.fullscreen_;
+
+        let camera_viewports: Vec<Viewport> = frame_data
+            .cameras_
+            .iter()
+            .map(|camera| camera.viewport_.within(&self.viewport_))
+            .collect();
+
+        for (index, camera) in frame_data.cameras_.iter().enumerate() {
+            let (viewport, scissor) = self.build_viewport_and_scissor(&camera_viewports[index]);
+
+            unsafe {
+                device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+                device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+            }
+
+            // Cameras share the depth buffer, so depth an earlier camera wrote where this one draws
+            // is cleared before this camera's instances are tested against it. Later subpasses see
+            // the depth of the last camera drawn over each pixel
+            let overlaps_earlier = camera_viewports[..index]
+                .iter()
+                .any(|earlier| earlier.overlaps(&camera_viewports[index]));
+
+            if subpass == 0 && !fullscreen && overlaps_earlier {
+                Renderer::clear_depth(device, command_buffer, scissor);
+            }
+
+            self.bind_pipeline(device, command_buffer, &draw.bindings_, camera);
+
+            if fullscreen {
+                unsafe { device.cmd_draw(command_buffer, 3, 1, 0, 0) };
+                continue;
+            }
+
+            let mut vulkan_instances: Vec<&VulkanInstance> = frame_data
+                .vulkan_instances_
+                .iter()
+                .filter(|vulkan_instance| {
+                    !vulkan_instance.occluded_ && camera.sees_layers(vulkan_instance.layer_mask_)
+                })
+                .collect();
+
+            // Instances without a material sort first, so they are drawn while the subpass's own
+            // pipeline is still bound
+            vulkan_instances.sort_by_key(|vulkan_instance| vulkan_instance.material_id_);
+
+            let mut bound_material = None;
+
+            for vulkan_instance in vulkan_instances {
+                let layout = match vulkan_instance.material_id_ {
+                    Some(material_id) if subpass == 0 => {
+                        let material = self.materials_.get(&material_id).expect("No such material");
+
+                        if bound_material != Some(material_id) {
+                            let (material_shadow_set, material_sets) =
+                                material.get_descriptor_sets(image_index);
+
+                            let material_bindings = PipelineBindings {
+                                pipeline_: material.get_pipeline(),
+                                input_attachment_sets_: &[],
+                                shadow_set_: material_shadow_set,
+                                sets_: &material_sets,
+                            };
+
+                            self.bind_pipeline(device, command_buffer, &material_bindings, camera);
+                            bound_material = Some(material_id);
+                        }
+
+                        material.get_pipeline().layout_
+                    }
+                    _ => draw.bindings_.pipeline_.layout_,
+                };
+
+                let mesh_location = geometry_manager.get_mesh_location(&vulkan_instance.mesh_id_);
+
+                unsafe {
+                    device.cmd_bind_descriptor_sets(
+                        command_buffer,
+                        ash::vk::PipelineBindPoint::GRAPHICS,
+                        layout,
+                        0,
+                        &[vulkan_instance.descriptor_set_],
+                        &[],
+                    );
+                    device.cmd_draw_indexed(
+                        command_buffer,
+                        mesh_location.index_count_ as u32,
+                        1,
+                        mesh_location.index_offset_ as u32,
+                        mesh_location.vertex_offset_ as i32,
+                        0,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Clears the depth attachment of the current subpass to the far plane inside a rectangle
+    fn clear_depth(
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        rect: ash::vk::Rect2D,
+    ) {
+        let clear_attachment = ash::vk::ClearAttachment {
+            aspect_mask: ash::vk::ImageAspectFlags::DEPTH,
+            color_attachment: 0,
+            clear_value: ash::vk::ClearValue {
+                depth_stencil: ash::vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        };
+        let clear_rect = ash::vk::ClearRect {
+            rect,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+        unsafe { device.cmd_clear_attachments(command_buffer, &[clear_attachment], &[clear_rect]) };
+    }
+
     fn record_draw_commands_forward(
         &mut self,
         device: &ash::Device,
@@ -543,26 +1920,26 @@ impl Renderer {
                 .expect("could not begin recording command buffer");
         }
 
-        let clear_values = [
-            ash::vk::ClearValue {
-                color: ash::vk::ClearColorValue {
-                    float32: [0.0, 0.0, 0.0, 1.0],
-                },
-            },
-            ash::vk::ClearValue {
-                depth_stencil: ash::vk::ClearDepthStencilValue {
-                    depth: 1.0,
-                    stencil: 0,
-                },
-            },
-        ];
+        self.profiler_
+            .begin_frame(device, command_buffer, self.current_frame_ as usize);
+        self.occlusion_culling_
+            .reset_queries(device, command_buffer, self.current_frame_ as usize);
+
+        let render_pass = self
+            .render_pass_
+            .as_ref()
+            .expect("No render pass has been set for this renderer");
+        let render_pass_description = self.render_pass_description_.as_ref().unwrap();
+        let input_attachment_sets = self.input_attachment_sets_.as_ref().unwrap();
+        let shadow_sets = self.shadow_sets_.as_ref().unwrap();
+        let texture_sets = self.texture_sets_.as_ref().unwrap();
 
-        let render_pass = self.render_pass_.as_ref().unwrap().vk_render_pass_;
+        let clear_values = render_pass.build_clear_values();
 
         let render_pass_begin_info = ash::vk::RenderPassBeginInfo {
             s_type: ash::vk::StructureType::RENDER_PASS_BEGIN_INFO,
             p_next: ptr::null(),
-            render_pass: render_pass,
+            render_pass: render_pass.vk_render_pass_,
             framebuffer: self.swapchain_.swapchain_framebuffers_[image_index as usize],
             render_area: ash::vk::Rect2D {
                 offset: ash::vk::Offset2D { x: 0, y: 0 },
@@ -572,70 +1949,170 @@ impl Renderer {
             p_clear_values: clear_values.as_ptr(),
         };
         unsafe {
+            let vertex_buffers = [geometry_manager.vertex_device_buffer_.buffer_handle_];
+            let offsets = [0_u64];
+
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &vertex_buffers, &offsets);
+
+            device.cmd_bind_index_buffer(
+                command_buffer,
+                geometry_manager.index_device_buffer_.buffer_handle_,
+                0,
+                ash::vk::IndexType::UINT32,
+            );
+
+            // Shadow maps are rendered in their own render passes, before the render pass that
+            // samples them begins
+            self.profiler_
+                .begin_scope(device, command_buffer, "shadows");
+            self.shadow_pass_.record(
+                device,
+                command_buffer,
+                image_index as usize,
+                geometry_manager,
+                &frame_data.vulkan_instances_,
+            );
+            self.profiler_.end_scope(device, command_buffer);
+
+            self.profiler_
+                .begin_scope(device, command_buffer, "light culling");
+            self.light_culling_
+                .record(device, command_buffer, image_index as usize);
+            self.profiler_.end_scope(device, command_buffer);
+
+            self.profiler_
+                .begin_scope(device, command_buffer, "render pass");
             device.cmd_begin_render_pass(
                 command_buffer,
                 &render_pass_begin_info,
                 ash::vk::SubpassContents::INLINE,
             );
 
-            let mut push_constant_vec = vec![];
-
-            let mut view_bytes = Renderer::mat4_to_bytes(frame_data.view_);
+            for (subpass, pipeline_description) in
+                render_pass_description.get_pipelines().iter().enumerate()
+            {
+                if subpass > 0 {
+                    device.cmd_next_subpass(command_buffer, ash::vk::SubpassContents::INLINE);
+                }
 
-            let mut projection_bytes = Renderer::mat4_to_bytes(frame_data.projection_);
+                self.profiler_
+                    .begin_scope(device, command_buffer, &format!("subpass {}", subpass));
+                let draw = SubpassDraw {
+                    subpass_: subpass,
+                    fullscreen_: !pipeline_description.has_vertex_input(),
+                    bindings_: PipelineBindings {
+                        pipeline_: render_pass.get_pipeline(subpass),
+                        input_attachment_sets_: input_attachment_sets
+                            .get_descriptor_sets(subpass, image_index),
+                        shadow_set_: shadow_sets.get_descriptor_set(subpass, image_index),
+                        sets_: texture_sets.get_descriptor_sets(subpass),
+                    },
+                };
+                self.record_subpass_draws(
+                    device,
+                    command_buffer,
+                    geometry_manager,
+                    image_index,
+                    &draw,
+                    &frame_data,
+                );
+                self.profiler_.end_scope(device, command_buffer);
+            }
 
-            push_constant_vec.append(&mut view_bytes);
-            push_constant_vec.append(&mut projection_bytes);
+            //device.cmd_bind_descriptor_sets(command_buffer, ash::vk::PipelineBindPoint::GRAPHICS,)
 
-            device.cmd_push_constants(
+            let scene_cameras: Vec<_> = frame_data
+                .cameras_
+                .iter()
+                .map(|camera| {
+                    let (viewport, scissor) =
+                        self.build_viewport_and_scissor(&camera.viewport_.within(&self.viewport_));
+                    (camera, viewport, scissor)
+                })
+                .collect();
+
+            // The skybox fills the pixels the subpasses left at the far plane
+            self.profiler_.begin_scope(device, command_buffer, "skybox");
+            self.skybox_pass_
+                .record(device, command_buffer, image_index as usize, &scene_cameras);
+            self.profiler_.end_scope(device, command_buffer);
+
+            // Boxes are tested against the depth of everything the subpasses drew
+            self.profiler_
+                .begin_scope(device, command_buffer, "occlusion queries");
+            self.occlusion_culling_.record(
+                device,
                 command_buffer,
-                self.pipeline_.unwrap().layout_,
-                ash::vk::ShaderStageFlags::VERTEX,
-                0,
-                push_constant_vec.as_slice(),
+                self.current_frame_ as usize,
+                &scene_cameras,
             );
+            self.profiler_.end_scope(device, command_buffer);
 
-            device.cmd_bind_pipeline(
+            self.profiler_
+                .begin_scope(device, command_buffer, "world sprites");
+            self.world_sprite_pass_.record(
+                device,
                 command_buffer,
-                ash::vk::PipelineBindPoint::GRAPHICS,
-                self.pipeline_.unwrap().pipeline_handle_,
+                image_index as usize,
+                &scene_cameras,
             );
+            self.profiler_.end_scope(device, command_buffer);
 
-            let vertex_buffers = [geometry_manager.vertex_device_buffer_.buffer_handle_];
-            let offsets = [0_u64];
-
-            device.cmd_bind_vertex_buffers(command_buffer, 0, &vertex_buffers, &offsets);
+            self.profiler_
+                .begin_scope(device, command_buffer, "debug lines");
+            self.debug_draw_pass_.record(
+                device,
+                command_buffer,
+                image_index as usize,
+                &scene_cameras,
+            );
+            self.profiler_.end_scope(device, command_buffer);
+
+            // Sprites without a camera are drawn in pixels of the renderer's viewport, with the
+            // origin at its top left
+            let (sprite_viewport, sprite_scissor) = self.build_viewport_and_scissor(
+                &self
+                    .sprite_batch_
+                    .get_camera()
+                    .map_or(self.viewport_, |camera| {
+                        camera.viewport_.within(&self.viewport_)
+                    }),
+            );
+            let sprite_camera = match self.sprite_batch_.get_camera() {
+                Some(camera) => *camera,
+                None => Camera::new(
+                    Matrix4::identity(),
+                    cgmath::ortho(
+                        0.0,
+                        sprite_viewport.width,
+                        0.0,
+                        sprite_viewport.height,
+                        -1.0,
+                        1.0,
+                    ),
+                ),
+            };
 
-            device.cmd_bind_index_buffer(
+            self.profiler_
+                .begin_scope(device, command_buffer, "sprites");
+            self.sprite_batch_pass_.record(
+                device,
                 command_buffer,
-                geometry_manager.index_device_buffer_.buffer_handle_,
-                0,
-                ash::vk::IndexType::UINT32,
+                image_index as usize,
+                &[(&sprite_camera, sprite_viewport, sprite_scissor)],
             );
+            self.profiler_.end_scope(device, command_buffer);
 
-            for vulkan_instance in frame_data.vulkan_instances_.iter() {
-                let mesh_location = geometry_manager.get_mesh_location(&vulkan_instance.mesh_id_);
+            device.cmd_end_render_pass(command_buffer);
+            self.profiler_.end_scope(device, command_buffer);
 
-                device.cmd_bind_descriptor_sets(
-                    command_buffer,
-                    ash::vk::PipelineBindPoint::GRAPHICS,
-                    self.pipeline_.unwrap().layout_,
-                    0,
-                    &[vulkan_instance.descriptor_set_],
-                    &[],
-                );
-                device.cmd_draw_indexed(
-                    command_buffer,
-                    mesh_location.index_count_ as u32,
-                    1,
-                    mesh_location.index_offset_ as u32,
-                    mesh_location.vertex_offset_ as i32,
-                    0,
-                );
+            if let Some(post_process) = self.post_process_.as_ref() {
+                self.profiler_
+                    .begin_scope(device, command_buffer, "post process");
+                post_process.record(device, command_buffer, image_index as usize);
+                self.profiler_.end_scope(device, command_buffer);
             }
-            //device.cmd_bind_descriptor_sets(command_buffer, ash::vk::PipelineBindPoint::GRAPHICS,)
 
-            device.cmd_end_render_pass(command_buffer);
             device
                 .end_command_buffer(command_buffer)
                 .expect("Could not end recording command buffer");
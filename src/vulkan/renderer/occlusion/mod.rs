@@ -0,0 +1,562 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::os::raw::c_void;
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use cgmath::{EuclideanSpace, Matrix4, Point3, SquareMatrix, Transform, Vector3};
+
+use super::{Renderer, VulkanInstance};
+use crate::geometry::camera::Camera;
+use crate::geometry::scene::InstanceID;
+use crate::geometry::vertex::Vertex;
+use crate::render_sequence::builtin;
+use crate::vulkan::buffer::Buffer;
+use crate::vulkan::geometry_manager::GeometryManager;
+use crate::vulkan::physical_device::PhysicalDevice;
+use crate::vulkan::render_pass::pipeline::Pipeline;
+use crate::vulkan::render_pass::RenderPassContext;
+
+/// The maximum number of instances tested per frame. Further instances are always drawn
+const MAX_OCCLUSION_QUERIES: usize = 4096;
+/// Vertices of each proxy box, as a triangle list
+const PROXY_VERTICES: usize = 36;
+/// Fraction of its size each proxy box grows by on every side, so instances coming into view
+/// from behind an occluder are drawn a little before they show
+const PROXY_MARGIN: f32 = 0.05;
+/// Consecutive frames an instance's proxy must be hidden before the instance is hidden, so a
+/// single frame's result cannot make it flicker
+const OCCLUDED_FRAMES_TO_HIDE: u32 = 2;
+
+/// The corners of each face of a box, two triangles per face, for corners indexed by the bits of
+/// their x, y and z coordinates being at the maximum
+#[rustfmt::skip]
+const BOX_TRIANGLES: [usize; PROXY_VERTICES] = [
+    0, 2, 3, 0, 3, 1,
+    4, 5, 7, 4, 7, 6,
+    0, 1, 5, 0, 5, 4,
+    2, 6, 7, 2, 7, 3,
+    0, 4, 6, 0, 6, 2,
+    1, 3, 7, 1, 7, 5,
+];
+
+/// The proxies and queries of a frame in flight
+struct OcclusionFrame {
+    vertex_buffer_: Buffer,
+    query_pool_: vk::QueryPool,
+    /// the instance each query tests, with its layer mask
+    tested_: Vec<(InstanceID, u32)>,
+    /// true if queries were recorded whose results have not been read yet
+    recorded_: bool,
+}
+
+/// Hides instances whose bounding boxes were hidden behind the scene in previous frames. Every
+/// frame, a box around each instance is drawn in the final subpass, after the scene, inside an
+/// occlusion query counting its fragments that pass the depth test. Results are read once the
+/// frame's fence has signalled, so they decide the visibility of a frame a few frames later.
+/// Instances without results, near the camera or only recently hidden are drawn, so objects
+/// coming into view do not pop in. Shadows are still cast by hidden instances
+pub struct OcclusionCulling {
+    enabled_: bool,
+    /// built against the current render pass the first time culling is enabled with instances
+    pipeline_: Option<Pipeline>,
+    /// true once building the pipeline against the current render pass was attempted
+    built_: bool,
+    frames_: Vec<OcclusionFrame>,
+    /// the number of consecutive frames each tested instance's box has been hidden
+    occluded_frames_: HashMap<InstanceID, u32>,
+    /// true once untested instances have been reported
+    overflow_reported_: bool,
+}
+
+impl OcclusionCulling {
+    /// Creates and maps the proxy vertex buffer and creates the query pool of every frame in
+    /// flight. Culling starts disabled
+    ///
+    /// # Arguments
+    ///
+    /// * 'instance' - the instance the physical device belongs to
+    ///
+    /// * 'device' - the logical device the buffers and query pools are created on
+    ///
+    /// * 'physical_device' - the physical device used to pick memory types
+    ///
+    /// * 'num_frames' - the number of frames in flight
+    ///
+    pub fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &PhysicalDevice,
+        num_frames: usize,
+    ) -> OcclusionCulling {
+        let size = (MAX_OCCLUSION_QUERIES * PROXY_VERTICES * size_of::<Vertex>()) as u64;
+
+        let query_pool_create_info = vk::QueryPoolCreateInfo {
+            s_type: vk::StructureType::QUERY_POOL_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::QueryPoolCreateFlags::empty(),
+            query_type: vk::QueryType::OCCLUSION,
+            query_count: MAX_OCCLUSION_QUERIES as u32,
+            pipeline_statistics: vk::QueryPipelineStatisticFlags::empty(),
+        };
+
+        let frames = (0..num_frames)
+            .map(|_| {
+                let mut vertex_buffer = Buffer::new(
+                    instance,
+                    device,
+                    physical_device,
+                    size,
+                    vk::BufferUsageFlags::VERTEX_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                );
+
+                unsafe { vertex_buffer.map(device, 0, size) };
+
+                let query_pool = unsafe {
+                    device
+                        .create_query_pool(&query_pool_create_info, None)
+                        .expect("Could not create query pool")
+                };
+
+                OcclusionFrame {
+                    vertex_buffer_: vertex_buffer,
+                    query_pool_: query_pool,
+                    tested_: vec![],
+                    recorded_: false,
+                }
+            })
+            .collect();
+
+        OcclusionCulling {
+            enabled_: false,
+            pipeline_: None,
+            built_: false,
+            frames_: frames,
+            occluded_frames_: HashMap::new(),
+            overflow_reported_: false,
+        }
+    }
+
+    /// Destroys the pipeline built against the current render pass, so it is built again against
+    /// the next. The device must not be using it
+    pub fn reset(&mut self, device: &ash::Device) {
+        if let Some(pipeline) = self.pipeline_.take() {
+            pipeline.destroy(device);
+        }
+
+        for frame in self.frames_.iter_mut() {
+            frame.tested_.clear();
+        }
+
+        self.built_ = false;
+    }
+
+    /// Enables or disables culling. Instances are drawn until their boxes have been tested, so
+    /// previous results are forgotten either way
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled_ = enabled;
+        self.occluded_frames_.clear();
+
+        for frame in self.frames_.iter_mut() {
+            frame.recorded_ = false;
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_
+    }
+
+    /// Reads the results of the queries recorded for a frame in flight, updating how long each
+    /// tested instance has been hidden. Must only be called once the frame's fence has signalled.
+    /// Results are ignored if they are not available
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the query pools were created on
+    ///
+    /// * 'frame' - the frame in flight to read
+    ///
+    pub fn read_results(&mut self, device: &ash::Device, frame: usize) {
+        let frame = &mut self.frames_[frame];
+
+        if !self.enabled_ || !frame.recorded_ || frame.tested_.is_empty() {
+            return;
+        }
+
+        frame.recorded_ = false;
+
+        let previous = &self.occluded_frames_;
+        let mut samples = vec![0_u64; frame.tested_.len()];
+
+        let result = unsafe {
+            device.get_query_pool_results(
+                frame.query_pool_,
+                0,
+                samples.len() as u32,
+                &mut samples,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        };
+
+        if result.is_err() {
+            return;
+        }
+
+        // Instances that were not tested are forgotten, so they are drawn until tested again
+        let occluded_frames = frame
+            .tested_
+            .iter()
+            .zip(samples)
+            .map(|((instance_id, _), samples)| {
+                let occluded_frames = if samples > 0 {
+                    0
+                } else {
+                    previous.get(instance_id).map_or(1, |frames| frames + 1)
+                };
+
+                (*instance_id, occluded_frames)
+            })
+            .collect();
+
+        self.occluded_frames_ = occluded_frames;
+    }
+
+    /// Marks the instances hidden in previous frames as occluded and writes the boxes of every
+    /// instance to test to the vertex buffer of a frame in flight, building the pipeline first if
+    /// it has not been built yet. Instances whose box holds a camera are never occluded, as the
+    /// camera's near plane cuts into the box, and neither are instances seen by a camera a later
+    /// camera draws over
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the pipeline is created on
+    ///
+    /// * 'frame' - the frame in flight being recorded
+    ///
+    /// * 'vulkan_instances' - the instances of the frame
+    ///
+    /// * 'geometry_manager' - holds the bounds of the instances' meshes
+    ///
+    /// * 'cameras' - the cameras of the frame
+    ///
+    /// * 'render_pass' - the render pass the boxes are drawn in, with the description it was built
+    ///   from and the cache pipelines are built with
+    ///
+    pub fn update(
+        &mut self,
+        device: &ash::Device,
+        frame: usize,
+        vulkan_instances: &mut [VulkanInstance],
+        geometry_manager: &GeometryManager,
+        cameras: &[Camera],
+        render_pass: &RenderPassContext,
+    ) {
+        self.frames_[frame].tested_.clear();
+
+        if !self.enabled_ || vulkan_instances.is_empty() {
+            return;
+        }
+
+        if !self.built_ {
+            self.build_pipeline(device, render_pass);
+        }
+
+        if self.pipeline_.is_none() {
+            return;
+        }
+
+        let eyes: Vec<Point3<f32>> = cameras
+            .iter()
+            .filter_map(|camera| camera.view_.invert())
+            .map(|inverse_view| inverse_view.transform_point(Point3::origin()))
+            .collect();
+
+        // Each camera clears the depth earlier cameras wrote where it draws, so the depth buffer
+        // only holds a camera's depth where no later camera overlaps it. Instances seen by
+        // overlapped cameras cannot be tested
+        let overlapped_layers = cameras
+            .iter()
+            .enumerate()
+            .filter(|(index, camera)| {
+                cameras[index + 1..]
+                    .iter()
+                    .any(|later| later.viewport_.overlaps(&camera.viewport_))
+            })
+            .fold(0, |layer_mask, (_, camera)| layer_mask | camera.layer_mask_);
+
+        if vulkan_instances.len() > MAX_OCCLUSION_QUERIES && !self.overflow_reported_ {
+            println!(
+                "Not culling instances beyond the limit of {} occlusion queries per frame",
+                MAX_OCCLUSION_QUERIES
+            );
+            self.overflow_reported_ = true;
+        }
+
+        let frame = &mut self.frames_[frame];
+        let mut vertices = Vec::with_capacity(MAX_OCCLUSION_QUERIES * PROXY_VERTICES);
+
+        for vulkan_instance in vulkan_instances.iter_mut().take(MAX_OCCLUSION_QUERIES) {
+            let bounds = geometry_manager
+                .get_mesh_location(&vulkan_instance.mesh_id_)
+                .bounds_;
+            let (min, max) = OcclusionCulling::proxy_bounds(&vulkan_instance.transform_, bounds);
+
+            let holds_camera = eyes
+                .iter()
+                .any(|eye| (0..3).all(|axis| eye[axis] >= min[axis] && eye[axis] <= max[axis]));
+
+            if holds_camera || vulkan_instance.layer_mask_ & overlapped_layers != 0 {
+                self.occluded_frames_.remove(&vulkan_instance.instance_id_);
+                continue;
+            }
+
+            vulkan_instance.occluded_ = self
+                .occluded_frames_
+                .get(&vulkan_instance.instance_id_)
+                .is_some_and(|frames| *frames >= OCCLUDED_FRAMES_TO_HIDE);
+
+            for corner in BOX_TRIANGLES.iter() {
+                vertices.push(Vertex {
+                    position_: [
+                        if corner & 1 == 0 { min.x } else { max.x },
+                        if corner & 2 == 0 { min.y } else { max.y },
+                        if corner & 4 == 0 { min.z } else { max.z },
+                    ],
+                    normal_: [0.0, 0.0, 0.0],
+                    color_: [0.0, 0.0, 0.0],
+                    uv_: [0.0, 0.0],
+                });
+            }
+
+            frame
+                .tested_
+                .push((vulkan_instance.instance_id_, vulkan_instance.layer_mask_));
+        }
+
+        if !vertices.is_empty() {
+            unsafe {
+                frame.vertex_buffer_.copy_from_data(
+                    vertices.as_ptr() as *const c_void,
+                    (vertices.len() * size_of::<Vertex>()) as u64,
+                    0,
+                );
+            }
+        }
+    }
+
+    /// Resets the queries of a frame in flight before they are recorded. Must be recorded outside
+    /// of render passes
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer belongs to
+    ///
+    /// * 'command_buffer' - the command buffer the frame is recorded to
+    ///
+    /// * 'frame' - the frame in flight being recorded
+    ///
+    pub fn reset_queries(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        frame: usize,
+    ) {
+        let frame = &self.frames_[frame];
+
+        if frame.tested_.is_empty() {
+            return;
+        }
+
+        unsafe {
+            device.cmd_reset_query_pool(
+                command_buffer,
+                frame.query_pool_,
+                0,
+                frame.tested_.len() as u32,
+            );
+        }
+    }
+
+    /// Records the box of every tested instance inside its occlusion query, once for every camera
+    /// that sees the instance's layers. Must be recorded in the final subpass of the render pass
+    /// the pipeline was built against, after the scene's draws
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer belongs to
+    ///
+    /// * 'command_buffer' - the command buffer to record to
+    ///
+    /// * 'frame' - the frame in flight being recorded
+    ///
+    /// * 'cameras' - every camera of the frame, with the viewport and scissor it draws to
+    ///
+    pub fn record(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        frame: usize,
+        cameras: &[(&Camera, vk::Viewport, vk::Rect2D)],
+    ) {
+        let pipeline = match self.pipeline_.as_ref() {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+
+        let frame = &mut self.frames_[frame];
+
+        if frame.tested_.is_empty() {
+            return;
+        }
+
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline_handle_,
+            );
+            device.cmd_bind_vertex_buffers(
+                command_buffer,
+                0,
+                &[frame.vertex_buffer_.buffer_handle_],
+                &[0],
+            );
+
+            // With a single camera, its state is set once for every query
+            let per_camera_state = cameras.len() > 1;
+
+            if let (false, Some((camera, viewport, scissor))) = (per_camera_state, cameras.first())
+            {
+                device.cmd_set_viewport(command_buffer, 0, &[*viewport]);
+                device.cmd_set_scissor(command_buffer, 0, &[*scissor]);
+                OcclusionCulling::push_camera(device, command_buffer, pipeline, camera);
+            }
+
+            for (query, (_, layer_mask)) in frame.tested_.iter().enumerate() {
+                device.cmd_begin_query(
+                    command_buffer,
+                    frame.query_pool_,
+                    query as u32,
+                    vk::QueryControlFlags::empty(),
+                );
+
+                // The query counts the fragments of every camera, so the instance is drawn if any
+                // camera sees it
+                for (camera, viewport, scissor) in cameras.iter() {
+                    if !camera.sees_layers(*layer_mask) {
+                        continue;
+                    }
+
+                    if per_camera_state {
+                        device.cmd_set_viewport(command_buffer, 0, &[*viewport]);
+                        device.cmd_set_scissor(command_buffer, 0, &[*scissor]);
+                        OcclusionCulling::push_camera(device, command_buffer, pipeline, camera);
+                    }
+
+                    device.cmd_draw(
+                        command_buffer,
+                        PROXY_VERTICES as u32,
+                        1,
+                        (query * PROXY_VERTICES) as u32,
+                        0,
+                    );
+                }
+
+                device.cmd_end_query(command_buffer, frame.query_pool_, query as u32);
+            }
+        }
+
+        frame.recorded_ = true;
+    }
+
+    /// Pushes the view and projection of a camera to the proxy pipeline
+    fn push_camera(
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        pipeline: &Pipeline,
+        camera: &Camera,
+    ) {
+        let mut push_constants = Renderer::mat4_to_bytes(camera.view_);
+        push_constants.append(&mut Renderer::mat4_to_bytes(camera.projection_));
+
+        unsafe {
+            device.cmd_push_constants(
+                command_buffer,
+                pipeline.layout_,
+                pipeline.get_push_constant_stage_flags(0, push_constants.len() as u32),
+                0,
+                &push_constants,
+            );
+        }
+    }
+
+    /// Returns the minimum and maximum corners of the world space box bounding a mesh's bounds
+    /// once transformed, grown by PROXY_MARGIN
+    fn proxy_bounds(
+        transform: &Matrix4<f32>,
+        bounds: (Point3<f32>, Point3<f32>),
+    ) -> (Point3<f32>, Point3<f32>) {
+        let (local_min, local_max) = bounds;
+
+        let corners = (0..8).map(|corner| {
+            transform.transform_point(Point3::new(
+                if corner & 1 == 0 {
+                    local_min.x
+                } else {
+                    local_max.x
+                },
+                if corner & 2 == 0 {
+                    local_min.y
+                } else {
+                    local_max.y
+                },
+                if corner & 4 == 0 {
+                    local_min.z
+                } else {
+                    local_max.z
+                },
+            ))
+        });
+
+        let infinity = f32::INFINITY;
+        let (min, max) = corners.fold(
+            (
+                Point3::new(infinity, infinity, infinity),
+                Point3::new(-infinity, -infinity, -infinity),
+            ),
+            |(min, max), corner| {
+                (
+                    Point3::new(
+                        min.x.min(corner.x),
+                        min.y.min(corner.y),
+                        min.z.min(corner.z),
+                    ),
+                    Point3::new(
+                        max.x.max(corner.x),
+                        max.y.max(corner.y),
+                        max.z.max(corner.z),
+                    ),
+                )
+            },
+        );
+
+        let margin: Vector3<f32> = (max - min) * PROXY_MARGIN;
+
+        (min - margin, max + margin)
+    }
+
+    /// Builds the proxy pipeline for the final subpass of a render pass. Instances are only culled
+    /// if the final subpass writes nothing but the presented color, as the pipeline must match
+    /// every color attachment of the subpass
+    fn build_pipeline(&mut self, device: &ash::Device, render_pass: &RenderPassContext) {
+        self.built_ = true;
+
+        self.pipeline_ = render_pass.build_overlay_pipeline(
+            device,
+            &builtin::occlusion_proxies(),
+            "occlusion proxies",
+        );
+    }
+}
@@ -0,0 +1,716 @@
+use std::path::PathBuf;
+use std::ptr;
+
+use ash::version::{DeviceV1_0, InstanceV1_0};
+use ash::vk;
+
+use super::swapchain::attachment::Attachment;
+use super::swapchain::Swapchain;
+use crate::render_sequence::post_process::{
+    ColorLut, PostImage, PostPassDescription, PostProcessChain,
+};
+use crate::render_sequence::render_pass::pipeline::PipelineDescription;
+use crate::vulkan::descriptor;
+use crate::vulkan::physical_device::PhysicalDevice;
+use crate::vulkan::render_pass::pipeline::{
+    descriptor_set_layout, Multisample, Pipeline, PipelineError, PipelineFeatures,
+};
+use crate::vulkan::texture_manager::texture::{Texture, TextureDescription};
+
+/// The format of the scene image and of the images post-processing passes write before the blit
+pub const HDR_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+/// The images, framebuffers and descriptor sets the chain uses when drawing to one swapchain image
+struct PostFrame {
+    images_: Vec<(PostImage, Attachment)>,
+    /// one per pass
+    framebuffers_: Vec<vk::Framebuffer>,
+    /// one per pass, null for passes that sample no images
+    descriptor_sets_: Vec<vk::DescriptorSet>,
+}
+
+/// Runs a renderer's post-processing chain. The renderer's render pass draws the scene into an HDR
+/// offscreen target, which the chain's fullscreen passes then read one after another, each writing
+/// an image the next one samples, until the final pass blits into the swapchain image
+pub struct PostProcess {
+    passes_: Vec<PostPassDescription>,
+    /// one per pass
+    pipelines_: Vec<Pipeline>,
+    /// render pass of the passes writing HDR images
+    hdr_render_pass_: vk::RenderPass,
+    /// render pass of the final blit into the swapchain image
+    present_render_pass_: vk::RenderPass,
+    features_: PipelineFeatures,
+    sampler_: vk::Sampler,
+    color_luts_: Vec<Texture>,
+    descriptor_pool_: vk::DescriptorPool,
+    frames_: Vec<PostFrame>,
+    extent_: vk::Extent2D,
+    memory_properties_: vk::PhysicalDeviceMemoryProperties,
+}
+
+impl PostProcess {
+    /// Builds the render passes and pipelines of a post-processing chain, and stages the chain's
+    /// color lookup tables. Framebuffers are created separately, once the swapchain's offscreen
+    /// targets exist. Returns an error if a pass's pipeline cannot be built
+    ///
+    /// # Arguments
+    ///
+    /// * 'instance' - the instance the physical device belongs to
+    ///
+    /// * 'device' - the logical device the chain is created on
+    ///
+    /// * 'physical_device' - the physical device used to pick memory types
+    ///
+    /// * 'chain' - the effects to apply
+    ///
+    /// * 'surface_format' - the format of the swapchain images the chain ends in
+    ///
+    /// * 'pipeline_cache' - cache used to speed up pipeline compilation
+    ///
+    pub fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &PhysicalDevice,
+        chain: &PostProcessChain,
+        surface_format: vk::Format,
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<PostProcess, PipelineError> {
+        let passes = chain.build_passes();
+
+        let hdr_render_pass = PostProcess::create_render_pass(
+            device,
+            HDR_FORMAT,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+        let present_render_pass = PostProcess::create_render_pass(
+            device,
+            surface_format,
+            vk::ImageLayout::PRESENT_SRC_KHR,
+        );
+
+        let features = PipelineFeatures::from_physical_device(physical_device);
+        let mut pipelines: Vec<Pipeline> = vec![];
+
+        for pass in passes.iter() {
+            let render_pass = match pass.output_ {
+                PostImage::Swapchain => present_render_pass,
+                _ => hdr_render_pass,
+            };
+
+            match Pipeline::from_pipeline_descriptor(
+                device,
+                &pass.pipeline_,
+                render_pass,
+                0,
+                &Multisample::default(),
+                &features,
+                pipeline_cache,
+            ) {
+                Ok(pipeline) => pipelines.push(pipeline),
+                Err(error) => {
+                    for pipeline in pipelines.iter() {
+                        pipeline.destroy(device);
+                    }
+                    unsafe {
+                        device.destroy_render_pass(hdr_render_pass, None);
+                        device.destroy_render_pass(present_render_pass, None);
+                    }
+                    return Err(error);
+                }
+            }
+        }
+
+        let color_luts = chain
+            .get_color_luts()
+            .iter()
+            .map(|lut| PostProcess::create_color_lut(instance, device, physical_device, lut))
+            .collect();
+
+        Ok(PostProcess {
+            passes_: passes,
+            pipelines_: pipelines,
+            hdr_render_pass_: hdr_render_pass,
+            present_render_pass_: present_render_pass,
+            features_: features,
+            sampler_: PostProcess::create_sampler(device),
+            color_luts_: color_luts,
+            descriptor_pool_: vk::DescriptorPool::null(),
+            frames_: vec![],
+            extent_: vk::Extent2D {
+                width: 0,
+                height: 0,
+            },
+            memory_properties_: unsafe {
+                instance
+                    .get_physical_device_memory_properties(physical_device.physical_device_handle_)
+            },
+        })
+    }
+
+    /// Returns the GLSL files, including #included files, the shaders of the chain's passes were
+    /// compiled from
+    pub fn get_source_files(&self) -> Vec<PathBuf> {
+        let mut source_files: Vec<PathBuf> = self
+            .passes_
+            .iter()
+            .flat_map(|pass| pass.pipeline_.get_source_files())
+            .collect();
+
+        source_files.sort();
+        source_files.dedup();
+        source_files
+    }
+
+    /// Recompiles the passes whose shaders were compiled from any of the changed files, rebuilds
+    /// their pipelines and creates the framebuffers and descriptor sets again. Returns an error,
+    /// keeping every current pipeline, if a shader fails to compile or a pipeline cannot be
+    /// built. The device must not be using the chain
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the chain was created on
+    ///
+    /// * 'swapchain' - the swapchain whose images the chain draws to
+    ///
+    /// * 'changed_files' - the GLSL files that changed
+    ///
+    /// * 'pipeline_cache' - cache used to speed up pipeline compilation
+    ///
+    pub fn reload_shaders(
+        &mut self,
+        device: &ash::Device,
+        swapchain: &Swapchain,
+        changed_files: &[PathBuf],
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<(), String> {
+        let mut reloaded = vec![];
+
+        for (pass_index, pass) in self.passes_.iter().enumerate() {
+            let changed = pass
+                .pipeline_
+                .get_source_files()
+                .iter()
+                .any(|path| changed_files.contains(path));
+
+            if !changed {
+                continue;
+            }
+
+            let result = pass.pipeline_.recompile().and_then(|description| {
+                self.create_pipeline(device, &description, pass.output_, pipeline_cache)
+                    .map(|pipeline| (pass_index, description, pipeline))
+                    .map_err(|error| error.to_string())
+            });
+
+            match result {
+                Ok(pass_pipeline) => reloaded.push(pass_pipeline),
+                Err(error) => {
+                    for (_, _, pipeline) in reloaded.iter() {
+                        pipeline.destroy(device);
+                    }
+                    return Err(error);
+                }
+            }
+        }
+
+        if reloaded.is_empty() {
+            return Ok(());
+        }
+
+        for (pass_index, description, pipeline) in reloaded {
+            self.pipelines_[pass_index].destroy(device);
+            self.pipelines_[pass_index] = pipeline;
+            self.passes_[pass_index].pipeline_ = description;
+        }
+
+        // The descriptor set layouts of the new pipelines may differ, so every set is allocated
+        // again
+        self.create_framebuffers(device, swapchain);
+
+        Ok(())
+    }
+
+    /// Returns true if color lookup tables still have to be uploaded with record_uploads
+    pub fn has_pending_uploads(&self) -> bool {
+        self.color_luts_.iter().any(|lut| lut.has_pending_upload())
+    }
+
+    /// Records uploading the staged color lookup tables. Once the commands have completed,
+    /// finish_uploads must be called to free the staging memory
+    pub fn record_uploads(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        for lut in self.color_luts_.iter() {
+            lut.record_upload(device, command_buffer);
+        }
+    }
+
+    /// Frees the staging memory of uploaded color lookup tables
+    pub fn finish_uploads(&mut self, device: &ash::Device) {
+        for lut in self.color_luts_.iter_mut() {
+            lut.finish_upload(device);
+        }
+    }
+
+    /// Creates the images each pass writes, a framebuffer for every pass and the descriptor sets
+    /// binding each pass's inputs, for every swapchain image. Any previously created ones are
+    /// destroyed. The swapchain's framebuffers must present to offscreen targets
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the chain was created on
+    ///
+    /// * 'swapchain' - the swapchain whose images the chain draws to
+    ///
+    pub fn create_framebuffers(&mut self, device: &ash::Device, swapchain: &Swapchain) {
+        self.destroy_framebuffers(device);
+
+        self.extent_ = swapchain.swapchain_extent_;
+
+        let num_images = swapchain.swapchain_images_.len();
+
+        // The pipelines were built from the same shaders, so their bindings are known to merge
+        let pass_bindings: Vec<Vec<vk::DescriptorSetLayoutBinding>> = self
+            .passes_
+            .iter()
+            .map(|pass| {
+                descriptor_set_layout::merge_bindings(&pass.pipeline_.shaders_)
+                    .expect("Could not merge descriptor bindings")
+                    .first()
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        self.descriptor_pool_ = descriptor::create_pool(device, &pass_bindings, num_images as u32);
+
+        for image_index in 0..num_images {
+            let mut images: Vec<(PostImage, Attachment)> = vec![];
+
+            for pass in self.passes_.iter() {
+                let output = pass.output_;
+
+                if output == PostImage::Swapchain
+                    || images.iter().any(|(image, _)| *image == output)
+                {
+                    continue;
+                }
+
+                images.push((
+                    output,
+                    Attachment::new(
+                        device,
+                        &self.memory_properties_,
+                        self.get_image_extent(output),
+                        HDR_FORMAT,
+                        vk::SampleCountFlags::TYPE_1,
+                    ),
+                ));
+            }
+
+            let mut frame = PostFrame {
+                images_: images,
+                framebuffers_: vec![],
+                descriptor_sets_: vec![],
+            };
+
+            for (pass_index, pass) in self.passes_.iter().enumerate() {
+                let output_view = self.get_image_view(&frame, swapchain, image_index, pass.output_);
+                let extent = self.get_image_extent(pass.output_);
+
+                let framebuffer_create_info = vk::FramebufferCreateInfo {
+                    s_type: vk::StructureType::FRAMEBUFFER_CREATE_INFO,
+                    p_next: ptr::null(),
+                    flags: vk::FramebufferCreateFlags::empty(),
+                    render_pass: match pass.output_ {
+                        PostImage::Swapchain => self.present_render_pass_,
+                        _ => self.hdr_render_pass_,
+                    },
+                    attachment_count: 1,
+                    p_attachments: &output_view,
+                    width: extent.width,
+                    height: extent.height,
+                    layers: 1,
+                };
+
+                frame.framebuffers_.push(unsafe {
+                    device
+                        .create_framebuffer(&framebuffer_create_info, None)
+                        .expect("Could not create post-processing framebuffer")
+                });
+
+                let bindings = &pass_bindings[pass_index];
+                let pipeline = &self.pipelines_[pass_index];
+
+                let descriptor_set = match pipeline.descriptor_set_layouts_.first() {
+                    Some(layout) if !bindings.is_empty() => {
+                        descriptor::allocate_sets(device, self.descriptor_pool_, &[*layout])[0]
+                    }
+                    _ => vk::DescriptorSet::null(),
+                };
+
+                // Binding i samples the pass's i-th input. Bindings beyond the inputs are left
+                // unwritten, so custom shaders must only declare the ones they read
+                for binding in bindings.iter() {
+                    if let Some(input) = pass.inputs_.get(binding.binding as usize) {
+                        descriptor::write_image(
+                            device,
+                            descriptor_set,
+                            binding.binding,
+                            binding.descriptor_type,
+                            self.get_image_view(&frame, swapchain, image_index, *input),
+                            self.sampler_,
+                            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        );
+                    }
+                }
+
+                frame.descriptor_sets_.push(descriptor_set);
+            }
+
+            self.frames_.push(frame);
+        }
+    }
+
+    /// Records every pass of the chain for a swapchain image. Must be recorded after the render
+    /// pass drawing the scene into the swapchain image's offscreen target
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer was allocated from
+    ///
+    /// * 'command_buffer' - the command buffer to record to
+    ///
+    /// * 'image_index' - the swapchain image the chain ends in
+    ///
+    pub fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+    ) {
+        let frame = &self.frames_[image_index];
+
+        for (pass_index, pass) in self.passes_.iter().enumerate() {
+            let pipeline = &self.pipelines_[pass_index];
+            let extent = self.get_image_extent(pass.output_);
+            let input_extent = pass
+                .inputs_
+                .first()
+                .map_or(extent, |input| self.get_image_extent(*input));
+
+            let render_pass_begin_info = vk::RenderPassBeginInfo {
+                s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
+                p_next: ptr::null(),
+                render_pass: match pass.output_ {
+                    PostImage::Swapchain => self.present_render_pass_,
+                    _ => self.hdr_render_pass_,
+                },
+                framebuffer: frame.framebuffers_[pass_index],
+                render_area: vk::Rect2D {
+                    offset: vk::Offset2D { x: 0, y: 0 },
+                    extent,
+                },
+                clear_value_count: 0,
+                p_clear_values: ptr::null(),
+            };
+
+            // The parameters followed by the texel size of the first input, cut to the size of
+            // the push constant block the pass's shaders declare
+            let mut push_constants = vec![];
+
+            for value in pass.parameters_.iter().chain(
+                [
+                    1.0 / input_extent.width as f32,
+                    1.0 / input_extent.height as f32,
+                ]
+                .iter(),
+            ) {
+                push_constants.extend_from_slice(&value.to_le_bytes());
+            }
+
+            let push_constant_size = pipeline
+                .push_constant_ranges_
+                .iter()
+                .map(|range| range.offset + range.size)
+                .max()
+                .unwrap_or(0)
+                .min(push_constants.len() as u32);
+
+            unsafe {
+                device.cmd_begin_render_pass(
+                    command_buffer,
+                    &render_pass_begin_info,
+                    vk::SubpassContents::INLINE,
+                );
+
+                device.cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline.pipeline_handle_,
+                );
+
+                device.cmd_set_viewport(
+                    command_buffer,
+                    0,
+                    &[vk::Viewport {
+                        x: 0.0,
+                        y: 0.0,
+                        width: extent.width as f32,
+                        height: extent.height as f32,
+                        min_depth: 0.0,
+                        max_depth: 1.0,
+                    }],
+                );
+                device.cmd_set_scissor(
+                    command_buffer,
+                    0,
+                    &[vk::Rect2D {
+                        offset: vk::Offset2D { x: 0, y: 0 },
+                        extent,
+                    }],
+                );
+
+                if frame.descriptor_sets_[pass_index] != vk::DescriptorSet::null() {
+                    device.cmd_bind_descriptor_sets(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        pipeline.layout_,
+                        0,
+                        &[frame.descriptor_sets_[pass_index]],
+                        &[],
+                    );
+                }
+
+                if push_constant_size > 0 {
+                    device.cmd_push_constants(
+                        command_buffer,
+                        pipeline.layout_,
+                        pipeline.get_push_constant_stage_flags(0, push_constant_size),
+                        0,
+                        &push_constants[..push_constant_size as usize],
+                    );
+                }
+
+                device.cmd_draw(command_buffer, 3, 1, 0, 0);
+                device.cmd_end_render_pass(command_buffer);
+            }
+        }
+    }
+
+    /// Destroys the chain's render passes, pipelines, images and framebuffers
+    pub fn destroy(&mut self, device: &ash::Device) {
+        self.destroy_framebuffers(device);
+
+        for pipeline in self.pipelines_.iter() {
+            pipeline.destroy(device);
+        }
+
+        for lut in self.color_luts_.iter_mut() {
+            lut.destroy(device);
+        }
+
+        unsafe {
+            device.destroy_sampler(self.sampler_, None);
+            device.destroy_render_pass(self.hdr_render_pass_, None);
+            device.destroy_render_pass(self.present_render_pass_, None);
+        }
+    }
+
+    fn destroy_framebuffers(&mut self, device: &ash::Device) {
+        for frame in self.frames_.drain(..) {
+            for framebuffer in frame.framebuffers_.iter() {
+                unsafe { device.destroy_framebuffer(*framebuffer, None) };
+            }
+
+            for (_, image) in frame.images_.iter() {
+                image.destroy(device);
+            }
+        }
+
+        if self.descriptor_pool_ != vk::DescriptorPool::null() {
+            unsafe { device.destroy_descriptor_pool(self.descriptor_pool_, None) };
+            self.descriptor_pool_ = vk::DescriptorPool::null();
+        }
+    }
+
+    /// Builds the pipeline of a pass drawing to the given image
+    fn create_pipeline(
+        &self,
+        device: &ash::Device,
+        description: &PipelineDescription,
+        output: PostImage,
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<Pipeline, PipelineError> {
+        let render_pass = match output {
+            PostImage::Swapchain => self.present_render_pass_,
+            _ => self.hdr_render_pass_,
+        };
+
+        Pipeline::from_pipeline_descriptor(
+            device,
+            description,
+            render_pass,
+            0,
+            &Multisample::default(),
+            &self.features_,
+            pipeline_cache,
+        )
+    }
+
+    /// Returns the size of an image of the chain. Bloom is computed at half resolution
+    fn get_image_extent(&self, image: PostImage) -> vk::Extent2D {
+        match image {
+            PostImage::Bloom(_) => vk::Extent2D {
+                width: (self.extent_.width / 2).max(1),
+                height: (self.extent_.height / 2).max(1),
+            },
+            _ => self.extent_,
+        }
+    }
+
+    fn get_image_view(
+        &self,
+        frame: &PostFrame,
+        swapchain: &Swapchain,
+        image_index: usize,
+        image: PostImage,
+    ) -> vk::ImageView {
+        match image {
+            PostImage::Scene => swapchain
+                .get_offscreen_target_view(image_index)
+                .expect("Post-processing requires the scene to be drawn to an offscreen target"),
+            PostImage::Swapchain => swapchain.get_image_view(image_index),
+            PostImage::ColorLut(index) => self.color_luts_[index].image_view_,
+            _ => {
+                frame
+                    .images_
+                    .iter()
+                    .find(|(chain_image, _)| *chain_image == image)
+                    .expect("A post-processing pass reads an image no earlier pass writes")
+                    .1
+                    .image_view_
+            }
+        }
+    }
+
+    /// Creates a render pass writing a single color attachment, which every pass overwrites
+    /// completely. It waits for earlier passes writing the images it samples, and for earlier
+    /// passes still sampling the image it writes
+    fn create_render_pass(
+        device: &ash::Device,
+        format: vk::Format,
+        final_layout: vk::ImageLayout,
+    ) -> vk::RenderPass {
+        let attachment = vk::AttachmentDescription {
+            flags: vk::AttachmentDescriptionFlags::empty(),
+            format,
+            samples: vk::SampleCountFlags::TYPE_1,
+            load_op: vk::AttachmentLoadOp::DONT_CARE,
+            store_op: vk::AttachmentStoreOp::STORE,
+            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            final_layout,
+        };
+
+        let color_attachment_reference = vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        };
+
+        let subpass = vk::SubpassDescription {
+            flags: vk::SubpassDescriptionFlags::empty(),
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            input_attachment_count: 0,
+            p_input_attachments: ptr::null(),
+            color_attachment_count: 1,
+            p_color_attachments: &color_attachment_reference,
+            p_resolve_attachments: ptr::null(),
+            p_depth_stencil_attachment: ptr::null(),
+            preserve_attachment_count: 0,
+            p_preserve_attachments: ptr::null(),
+        };
+
+        let subpass_dependency = vk::SubpassDependency {
+            src_subpass: vk::SUBPASS_EXTERNAL,
+            dst_subpass: 0,
+            src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags::FRAGMENT_SHADER,
+            dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER
+                | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dependency_flags: vk::DependencyFlags::empty(),
+        };
+
+        let render_pass_create_info = vk::RenderPassCreateInfo {
+            s_type: vk::StructureType::RENDER_PASS_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::RenderPassCreateFlags::empty(),
+            attachment_count: 1,
+            p_attachments: &attachment,
+            subpass_count: 1,
+            p_subpasses: &subpass,
+            dependency_count: 1,
+            p_dependencies: &subpass_dependency,
+        };
+
+        unsafe {
+            device
+                .create_render_pass(&render_pass_create_info, None)
+                .expect("Could not create post-processing render pass")
+        }
+    }
+
+    /// Creates the 3D texture of a color lookup table, with its texels staged for upload
+    fn create_color_lut(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &PhysicalDevice,
+        lut: &ColorLut,
+    ) -> Texture {
+        let texels: Vec<u8> = lut.texels_.iter().flatten().copied().collect();
+
+        let description = TextureDescription::new(
+            vk::Extent3D {
+                width: lut.size_,
+                height: lut.size_,
+                depth: lut.size_,
+            },
+            vk::Format::R8G8B8A8_UNORM,
+            vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        );
+
+        Texture::new(instance, device, physical_device, &description, &[texels])
+    }
+
+    /// Creates a bilinear sampler clamping lookups to the edge of the image
+    fn create_sampler(device: &ash::Device) -> vk::Sampler {
+        let sampler_create_info = vk::SamplerCreateInfo {
+            s_type: vk::StructureType::SAMPLER_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::SamplerCreateFlags::empty(),
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+            address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            mip_lod_bias: 0.0,
+            anisotropy_enable: vk::FALSE,
+            max_anisotropy: 1.0,
+            compare_enable: vk::FALSE,
+            compare_op: vk::CompareOp::ALWAYS,
+            min_lod: 0.0,
+            max_lod: 0.0,
+            border_color: vk::BorderColor::FLOAT_OPAQUE_BLACK,
+            unnormalized_coordinates: vk::FALSE,
+        };
+
+        unsafe {
+            device
+                .create_sampler(&sampler_create_info, None)
+                .expect("Could not create post-processing sampler")
+        }
+    }
+}
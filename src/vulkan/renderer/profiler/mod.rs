@@ -0,0 +1,475 @@
+use std::fmt;
+use std::mem::size_of;
+use std::ptr;
+use std::time::Duration;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use crate::vulkan::physical_device::PhysicalDevice;
+
+/// The maximum number of scopes timed per frame. Further scopes are not timed
+const MAX_SCOPES: u32 = 64;
+/// Timestamps written per scope, at its beginning and its end
+const TIMESTAMPS_PER_SCOPE: u32 = 2;
+/// Statistics counted for top level scopes while pipeline statistics are enabled. Results are
+/// written in the order of the flags' bits
+const STATISTICS: vk::QueryPipelineStatisticFlags = vk::QueryPipelineStatisticFlags::from_raw(
+    vk::QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS.as_raw()
+        | vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS.as_raw(),
+);
+/// Number of flags in STATISTICS
+const STATISTIC_COUNT: usize = 2;
+
+/// Shader invocations counted while a scope was recorded
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PipelineStatistics {
+    pub vertex_invocations_: u64,
+    pub fragment_invocations_: u64,
+}
+
+/// The GPU time taken by a named scope of a frame's commands
+#[derive(Clone, Debug)]
+pub struct ScopeTiming {
+    pub name_: String,
+    /// Number of scopes this scope is nested in
+    pub depth_: u32,
+    pub duration_: Duration,
+    /// Shader invocations of top level scopes, if pipeline statistics were enabled
+    pub statistics_: Option<PipelineStatistics>,
+}
+
+/// GPU timings of the scopes of a frame, in the order they were recorded
+#[derive(Clone, Debug, Default)]
+pub struct FrameTimings {
+    pub scopes_: Vec<ScopeTiming>,
+    /// The time from the beginning of the first scope to the end of the last
+    pub total_: Duration,
+}
+
+impl fmt::Display for FrameTimings {
+    /// Writes a report of the frame, a line per scope indented by its depth
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "GPU frame: {:.3} ms", milliseconds(self.total_))?;
+
+        for scope in self.scopes_.iter() {
+            write!(
+                f,
+                "{:indent$}{}: {:.3} ms",
+                "",
+                scope.name_,
+                milliseconds(scope.duration_),
+                indent = 2 * (scope.depth_ as usize + 1)
+            )?;
+
+            if let Some(statistics) = scope.statistics_ {
+                write!(
+                    f,
+                    " ({} vertex, {} fragment invocations)",
+                    statistics.vertex_invocations_, statistics.fragment_invocations_
+                )?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A scope recorded to a frame's command buffer. Its timestamps are the queries at twice its index
+/// and the one after
+struct RecordedScope {
+    name_: String,
+    depth_: u32,
+    /// the pipeline statistics query counting the scope's invocations
+    statistics_query_: Option<u32>,
+}
+
+/// The query pools of a frame in flight, and the scopes recorded to them
+struct FrameQueries {
+    timestamp_pool_: vk::QueryPool,
+    statistics_pool_: Option<vk::QueryPool>,
+    scopes_: Vec<RecordedScope>,
+    /// true if queries were recorded whose results have not been read yet
+    recorded_: bool,
+}
+
+/// Times named scopes of each frame's commands with timestamp queries, and optionally counts their
+/// shader invocations with pipeline statistics queries. Each frame in flight has its own query
+/// pools, read once the frame's fence has signalled, so results lag the recorded frame by the
+/// number of frames in flight
+pub struct GpuProfiler {
+    /// nanoseconds per timestamp tick
+    timestamp_period_: f32,
+    /// false if the device cannot write timestamps on graphics and compute queues
+    supported_: bool,
+    statistics_enabled_: bool,
+    frames_: Vec<FrameQueries>,
+    /// the frame in flight scopes are currently recorded for
+    current_frame_: usize,
+    /// the scopes begun and not yet ended, or None for those beyond MAX_SCOPES
+    open_scopes_: Vec<Option<usize>>,
+    last_frame_timings_: FrameTimings,
+    /// true once scopes beyond MAX_SCOPES have been reported
+    overflow_reported_: bool,
+}
+
+impl GpuProfiler {
+    /// Creates the query pools of every frame in flight. Pipeline statistics pools are only
+    /// created if the device supports pipeline statistics queries
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the query pools are created on
+    ///
+    /// * 'physical_device' - the physical device whose timestamp support and period are used
+    ///
+    /// * 'num_frames' - the number of frames in flight
+    ///
+    pub fn new(
+        device: &ash::Device,
+        physical_device: &PhysicalDevice,
+        num_frames: usize,
+    ) -> GpuProfiler {
+        let supported = physical_device
+            .properties_
+            .limits
+            .timestamp_compute_and_graphics
+            == vk::TRUE;
+        let statistics_supported = physical_device.features_.pipeline_statistics_query == vk::TRUE;
+
+        let frames = (0..num_frames)
+            .map(|_| FrameQueries {
+                timestamp_pool_: GpuProfiler::create_pool(
+                    device,
+                    vk::QueryType::TIMESTAMP,
+                    vk::QueryPipelineStatisticFlags::empty(),
+                    MAX_SCOPES * TIMESTAMPS_PER_SCOPE,
+                ),
+                statistics_pool_: if statistics_supported {
+                    Some(GpuProfiler::create_pool(
+                        device,
+                        vk::QueryType::PIPELINE_STATISTICS,
+                        STATISTICS,
+                        MAX_SCOPES,
+                    ))
+                } else {
+                    None
+                },
+                scopes_: vec![],
+                recorded_: false,
+            })
+            .collect();
+
+        GpuProfiler {
+            timestamp_period_: physical_device.properties_.limits.timestamp_period,
+            supported_: supported,
+            statistics_enabled_: false,
+            frames_: frames,
+            current_frame_: 0,
+            open_scopes_: vec![],
+            last_frame_timings_: FrameTimings::default(),
+            overflow_reported_: false,
+        }
+    }
+
+    /// Enables counting the shader invocations of top level scopes. Returns false, leaving them
+    /// disabled, if the device does not support pipeline statistics queries
+    pub fn set_statistics_enabled(&mut self, enabled: bool) -> bool {
+        let supported = self
+            .frames_
+            .first()
+            .is_some_and(|frame| frame.statistics_pool_.is_some());
+
+        self.statistics_enabled_ = enabled && supported;
+        !enabled || supported
+    }
+
+    pub fn get_statistics_enabled(&self) -> bool {
+        self.statistics_enabled_
+    }
+
+    /// Returns the timings of the most recent frame whose results have been read
+    pub fn get_last_frame_timings(&self) -> &FrameTimings {
+        &self.last_frame_timings_
+    }
+
+    /// Reads the results of the queries recorded for a frame in flight into the last frame
+    /// timings. Must only be called once the frame's fence has signalled. The previous timings
+    /// are kept if no queries were recorded or their results are not available
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the query pools were created on
+    ///
+    /// * 'frame' - the frame in flight to read
+    ///
+    pub fn read_results(&mut self, device: &ash::Device, frame: usize) {
+        let timestamp_period = f64::from(self.timestamp_period_);
+        let frame = &mut self.frames_[frame];
+
+        if !frame.recorded_ || frame.scopes_.is_empty() {
+            return;
+        }
+
+        let mut timestamps = vec![0_u64; frame.scopes_.len() * TIMESTAMPS_PER_SCOPE as usize];
+
+        let timestamps_read = unsafe {
+            device.get_query_pool_results(
+                frame.timestamp_pool_,
+                0,
+                timestamps.len() as u32,
+                &mut timestamps,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        };
+
+        if timestamps_read.is_err() {
+            return;
+        }
+
+        let statistics_count = frame
+            .scopes_
+            .iter()
+            .filter_map(|scope| scope.statistics_query_)
+            .max()
+            .map_or(0, |query| query as usize + 1);
+
+        let mut statistics = vec![[0_u64; STATISTIC_COUNT]; statistics_count];
+
+        // Each statistics query writes several values, which get_query_pool_results cannot
+        // express, so the results are read with the raw entry point
+        if let (Some(statistics_pool), true) = (frame.statistics_pool_, statistics_count > 0) {
+            let result = unsafe {
+                device.fp_v1_0().get_query_pool_results(
+                    device.handle(),
+                    statistics_pool,
+                    0,
+                    statistics_count as u32,
+                    statistics_count * size_of::<[u64; STATISTIC_COUNT]>(),
+                    statistics.as_mut_ptr() as *mut _,
+                    size_of::<[u64; STATISTIC_COUNT]>() as u64,
+                    vk::QueryResultFlags::TYPE_64,
+                )
+            };
+
+            if result != vk::Result::SUCCESS {
+                return;
+            }
+        }
+
+        let ticks_to_duration =
+            |ticks: u64| Duration::from_secs_f64(ticks as f64 * timestamp_period * 1e-9);
+
+        let scopes = frame
+            .scopes_
+            .iter()
+            .zip(timestamps.chunks(TIMESTAMPS_PER_SCOPE as usize))
+            .map(|(scope, ticks)| ScopeTiming {
+                name_: scope.name_.clone(),
+                depth_: scope.depth_,
+                duration_: ticks_to_duration(ticks[1].saturating_sub(ticks[0])),
+                statistics_: scope.statistics_query_.map(|query| {
+                    let [vertex, fragment] = statistics[query as usize];
+
+                    PipelineStatistics {
+                        vertex_invocations_: vertex,
+                        fragment_invocations_: fragment,
+                    }
+                }),
+            })
+            .collect();
+
+        let first = timestamps.iter().step_by(2).min().copied().unwrap_or(0);
+        let last = timestamps
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .max()
+            .copied()
+            .unwrap_or(0);
+
+        self.last_frame_timings_ = FrameTimings {
+            scopes_: scopes,
+            total_: ticks_to_duration(last.saturating_sub(first)),
+        };
+        frame.recorded_ = false;
+    }
+
+    /// Resets the query pools of a frame in flight, so the scopes of the frame can be recorded.
+    /// Must be recorded outside of render passes, before any scope of the frame
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer belongs to
+    ///
+    /// * 'command_buffer' - the command buffer the frame is recorded to
+    ///
+    /// * 'frame' - the frame in flight being recorded
+    ///
+    pub fn begin_frame(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        frame: usize,
+    ) {
+        self.current_frame_ = frame;
+        self.open_scopes_.clear();
+
+        let frame = &mut self.frames_[frame];
+        frame.scopes_.clear();
+
+        if !self.supported_ {
+            return;
+        }
+
+        unsafe {
+            device.cmd_reset_query_pool(
+                command_buffer,
+                frame.timestamp_pool_,
+                0,
+                MAX_SCOPES * TIMESTAMPS_PER_SCOPE,
+            );
+
+            if let Some(statistics_pool) = frame.statistics_pool_ {
+                device.cmd_reset_query_pool(command_buffer, statistics_pool, 0, MAX_SCOPES);
+            }
+        }
+
+        frame.recorded_ = true;
+    }
+
+    /// Begins timing a named scope, nested in the scopes begun and not yet ended. Top level scopes
+    /// also count shader invocations while pipeline statistics are enabled, so must begin and end
+    /// outside of render passes
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer belongs to
+    ///
+    /// * 'command_buffer' - the command buffer the frame is recorded to
+    ///
+    /// * 'name' - the name the scope is reported with
+    ///
+    pub fn begin_scope(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        name: &str,
+    ) {
+        let frame = &mut self.frames_[self.current_frame_];
+
+        if !self.supported_ || frame.scopes_.len() >= MAX_SCOPES as usize {
+            if self.supported_ && !self.overflow_reported_ {
+                println!(
+                    "Not timing GPU scopes beyond the limit of {} per frame",
+                    MAX_SCOPES
+                );
+                self.overflow_reported_ = true;
+            }
+
+            self.open_scopes_.push(None);
+            return;
+        }
+
+        let index = frame.scopes_.len();
+        let depth = self.open_scopes_.len() as u32;
+
+        let statistics_query = match frame.statistics_pool_ {
+            Some(_) if self.statistics_enabled_ && depth == 0 => Some(
+                frame
+                    .scopes_
+                    .iter()
+                    .filter(|scope| scope.statistics_query_.is_some())
+                    .count() as u32,
+            ),
+            _ => None,
+        };
+
+        unsafe {
+            device.cmd_write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                frame.timestamp_pool_,
+                index as u32 * TIMESTAMPS_PER_SCOPE,
+            );
+
+            if let (Some(statistics_pool), Some(query)) = (frame.statistics_pool_, statistics_query)
+            {
+                device.cmd_begin_query(
+                    command_buffer,
+                    statistics_pool,
+                    query,
+                    vk::QueryControlFlags::empty(),
+                );
+            }
+        }
+
+        frame.scopes_.push(RecordedScope {
+            name_: name.to_string(),
+            depth_: depth,
+            statistics_query_: statistics_query,
+        });
+        self.open_scopes_.push(Some(index));
+    }
+
+    /// Ends the most recently begun scope that has not been ended
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer belongs to
+    ///
+    /// * 'command_buffer' - the command buffer the frame is recorded to
+    ///
+    pub fn end_scope(&mut self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        let index = match self.open_scopes_.pop() {
+            Some(Some(index)) => index,
+            _ => return,
+        };
+
+        let frame = &self.frames_[self.current_frame_];
+        let scope = &frame.scopes_[index];
+
+        unsafe {
+            if let (Some(statistics_pool), Some(query)) =
+                (frame.statistics_pool_, scope.statistics_query_)
+            {
+                device.cmd_end_query(command_buffer, statistics_pool, query);
+            }
+
+            device.cmd_write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                frame.timestamp_pool_,
+                index as u32 * TIMESTAMPS_PER_SCOPE + 1,
+            );
+        }
+    }
+
+    fn create_pool(
+        device: &ash::Device,
+        query_type: vk::QueryType,
+        pipeline_statistics: vk::QueryPipelineStatisticFlags,
+        query_count: u32,
+    ) -> vk::QueryPool {
+        let create_info = vk::QueryPoolCreateInfo {
+            s_type: vk::StructureType::QUERY_POOL_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::QueryPoolCreateFlags::empty(),
+            query_type,
+            query_count,
+            pipeline_statistics,
+        };
+
+        unsafe {
+            device
+                .create_query_pool(&create_info, None)
+                .expect("Could not create query pool")
+        }
+    }
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
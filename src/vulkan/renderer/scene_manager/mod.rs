@@ -1,25 +1,78 @@
 use super::buffer::Buffer;
+use crate::geometry::light::{Light, LightType};
+use crate::vulkan::descriptor;
 use crate::vulkan::physical_device::PhysicalDevice;
-use ash::vk::DescriptorBufferInfo;
-use cgmath::{Vector4, Zero};
-use std::collections::HashMap;
+use ash::vk;
 use std::mem::size_of;
 use std::os::raw::c_void;
 
+/// The binding of descriptor set SHADOW_SET at which pipelines declare the Lights storage buffer of
+/// lights.glsl
+pub const LIGHT_BINDING: u32 = 2;
+
+/// Offset of the lights array in the Lights storage buffer, following the light count padded to
+/// the alignment of a vec4
+const LIGHTS_OFFSET: usize = 16;
+
+/// A light as laid out in the Lights storage buffer of lights.glsl
 #[repr(C)]
-pub struct LightUBO {
-    pub position_: Vector4<f32>,
-    pub color_: Vector4<f32>,
-    /*
-        pub constant_attenuation_: f32,
-        pub linear_attenuation_: f32,
-        pub quadratic_attenuation_: f32,
-    */
+#[derive(Clone, Copy, Default)]
+struct LightUBO {
+    position_: [f32; 4],
+    direction_: [f32; 4],
+    color_: [f32; 4],
+    attenuation_: [f32; 4],
+    cone_: [f32; 4],
 }
 
+impl LightUBO {
+    fn from_light(light: &Light) -> LightUBO {
+        let light_type = match light.light_type_ {
+            LightType::Directional => 0.0,
+            LightType::Spot => 1.0,
+            LightType::Point => 2.0,
+        };
+
+        LightUBO {
+            position_: [
+                light.position_.x,
+                light.position_.y,
+                light.position_.z,
+                light_type,
+            ],
+            direction_: [
+                light.direction_.x,
+                light.direction_.y,
+                light.direction_.z,
+                light.range_,
+            ],
+            color_: [
+                light.color_.x,
+                light.color_.y,
+                light.color_.z,
+                light.intensity_,
+            ],
+            attenuation_: [
+                light.attenuation_.constant_,
+                light.attenuation_.linear_,
+                light.attenuation_.quadratic_,
+                0.0,
+            ],
+            cone_: [
+                (light.inner_cone_angle_.0 / 2.0).cos(),
+                (light.cone_angle_.0 / 2.0).cos(),
+                0.0,
+                0.0,
+            ],
+        }
+    }
+}
+
+/// Uploads the lights of each frame's scene to a storage buffer per swapchain image, bound to
+/// pipelines declaring the Lights block of lights.glsl
 pub struct SceneManager {
-    lights_: HashMap<u64, LightUBO>,
-    uniform_buffers_: Vec<Buffer>,
+    max_lights_: u64,
+    storage_buffers_: Vec<Buffer>,
 }
 
 impl SceneManager {
@@ -30,7 +83,7 @@ impl SceneManager {
         max_lights: u64,
         num_frames: usize,
     ) -> SceneManager {
-        let buffer_size = size_of::<LightUBO>() as u64 * max_lights;
+        let buffer_size = LIGHTS_OFFSET as u64 + size_of::<LightUBO>() as u64 * max_lights;
 
         let mut buffers = vec![];
 
@@ -40,59 +93,104 @@ impl SceneManager {
                 device,
                 physical_device,
                 buffer_size,
-                ash::vk::BufferUsageFlags::UNIFORM_BUFFER,
+                ash::vk::BufferUsageFlags::STORAGE_BUFFER,
                 ash::vk::MemoryPropertyFlags::HOST_VISIBLE
                     | ash::vk::MemoryPropertyFlags::HOST_COHERENT,
             );
 
-            unsafe { buffer.map(device, 0, buffer_size) };
+            // Start out without lights, until the first scene is processed
+            let light_count = [0_u8; LIGHTS_OFFSET];
+
+            unsafe {
+                buffer.map(device, 0, buffer_size);
+                buffer.copy_from_data(
+                    light_count.as_ptr() as *const c_void,
+                    LIGHTS_OFFSET as u64,
+                    0,
+                );
+            }
 
             buffers.push(buffer);
         }
 
         SceneManager {
-            lights_: HashMap::new(),
-            uniform_buffers_: buffers,
+            max_lights_: max_lights,
+            storage_buffers_: buffers,
         }
     }
 
-    pub fn create_light(&mut self) -> u64 {
-        let light_id = self.lights_.len();
-        let light = LightUBO {
-            position_: Vector4::zero(),
-            color_: Vector4::zero(),
-            /*
-            constant_attenuation_: 0.0,
-            linear_attenuation_: 0.0,
-            quadratic_attenuation_: 0.0,
-            */
-        };
-        self.lights_.insert(light_id as u64, light);
-        light_id as u64
-    }
+    /// Copies the light count and the lights of a scene into the storage buffer of a swapchain
+    /// image. Lights beyond the maximum are ignored
+    ///
+    /// # Arguments
+    ///
+    /// * 'image_index' - the swapchain image the frame is rendered to
+    ///
+    /// * 'lights' - the lights of the scene
+    ///
+    pub fn update(&mut self, image_index: u32, lights: &[Light]) {
+        let lights = &lights[..lights.len().min(self.max_lights_ as usize)];
 
-    pub fn get_mut_light(&mut self, light_id: u64) -> &mut LightUBO {
-        self.lights_.get_mut(&light_id).expect("No such light")
-    }
+        let mut data = vec![0; LIGHTS_OFFSET + size_of::<LightUBO>() * lights.len()];
+        data[..4].copy_from_slice(&(lights.len() as u32).to_le_bytes());
 
-    pub fn update(&mut self, curr_frame: u32) {
-        for (key, light) in self.lights_.iter_mut() {
-            let data_ptr: *mut c_void = light as *mut _ as *mut c_void;
-            unsafe {
-                self.uniform_buffers_[curr_frame as usize].copy_from_data(
-                    data_ptr,
-                    size_of::<LightUBO>() as u64,
-                    size_of::<LightUBO>() as u64 * key,
+        for (index, light) in lights.iter().enumerate() {
+            let light_ubo = LightUBO::from_light(light);
+            let offset = LIGHTS_OFFSET + size_of::<LightUBO>() * index;
+            let light_bytes = unsafe {
+                std::slice::from_raw_parts(
+                    &light_ubo as *const LightUBO as *const u8,
+                    size_of::<LightUBO>(),
                 )
             };
+
+            data[offset..offset + light_bytes.len()].copy_from_slice(light_bytes);
         }
+
+        unsafe {
+            self.storage_buffers_[image_index as usize].copy_from_data(
+                data.as_ptr() as *const c_void,
+                data.len() as u64,
+                0,
+            )
+        };
     }
 
-    pub fn get_descriptor_buffer_info(&self) -> DescriptorBufferInfo {
-        DescriptorBufferInfo {
-            buffer: self.uniform_buffers_[0].buffer_handle_,
-            offset: 0,
-            range: size_of::<LightUBO>() as u64 * self.lights_.len() as u64,
+    /// Points the Lights binding of a descriptor set at a swapchain image's light buffer. Does
+    /// nothing if the set does not declare it as a storage buffer
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the descriptor set was allocated from
+    ///
+    /// * 'image_index' - the swapchain image whose lights are bound
+    ///
+    /// * 'descriptor_set' - a descriptor set with the layout of set SHADOW_SET
+    ///
+    /// * 'bindings' - the bindings of the descriptor set's layout
+    ///
+    pub fn write_descriptor_set(
+        &self,
+        device: &ash::Device,
+        image_index: usize,
+        descriptor_set: vk::DescriptorSet,
+        bindings: &[vk::DescriptorSetLayoutBinding],
+    ) {
+        let declared = bindings.iter().any(|binding| {
+            binding.binding == LIGHT_BINDING
+                && binding.descriptor_type == vk::DescriptorType::STORAGE_BUFFER
+        });
+
+        if declared {
+            descriptor::write_buffer(
+                device,
+                descriptor_set,
+                LIGHT_BINDING,
+                vk::DescriptorType::STORAGE_BUFFER,
+                self.storage_buffers_[image_index].buffer_handle_,
+                0,
+                vk::WHOLE_SIZE,
+            );
         }
     }
 }
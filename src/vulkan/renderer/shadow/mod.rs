@@ -0,0 +1,900 @@
+use std::mem::size_of;
+use std::os::raw::c_void;
+use std::ptr;
+
+use ash::version::{DeviceV1_0, InstanceV1_0};
+use ash::vk;
+use cgmath::conv;
+
+use super::light_culling::LightCulling;
+use super::scene_manager::SceneManager;
+use super::{Renderer, VulkanInstance};
+use crate::geometry::camera::Camera;
+use crate::geometry::light::{Light, LightType, MAX_CASCADES};
+use crate::render_sequence::builtin;
+use crate::render_sequence::render_pass::pipeline::PipelineDescription;
+use crate::vulkan::buffer::Buffer;
+use crate::vulkan::descriptor;
+use crate::vulkan::geometry_manager::GeometryManager;
+use crate::vulkan::physical_device::{self, PhysicalDevice};
+use crate::vulkan::render_pass::pipeline::{
+    descriptor_set_layout, Multisample, Pipeline, PipelineFeatures,
+};
+
+/// The maximum number of shadow casting lights per frame. Further shadow casting lights are ignored
+pub const MAX_SHADOWED_LIGHTS: usize = 4;
+
+/// The descriptor set index at which pipelines declare the shadow bindings of shadows.glsl and the
+/// light and cluster bindings of lights.glsl
+pub const SHADOW_SET: usize = 1;
+
+const SHADOW_UBO_BINDING: u32 = 0;
+const SHADOW_MAP_BINDING: u32 = 1;
+
+/// A shadowed light as laid out in the Shadows uniform block of shadows.glsl
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct ShadowedLightUBO {
+    view_projections_: [[[f32; 4]; 4]; MAX_CASCADES],
+    cascade_splits_: [f32; 4],
+    position_: [f32; 4],
+    direction_: [f32; 4],
+    color_: [f32; 4],
+    params_: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ShadowUBO {
+    lights_: [ShadowedLightUBO; MAX_SHADOWED_LIGHTS],
+    count_: [u32; 4],
+}
+
+/// A depth image with one layer per cascade, a framebuffer to render each layer and a view to
+/// sample all layers as a sampler2DArrayShadow
+struct ShadowMap {
+    image_: vk::Image,
+    device_memory_: vk::DeviceMemory,
+    array_view_: vk::ImageView,
+    layer_views_: Vec<vk::ImageView>,
+    framebuffers_: Vec<vk::Framebuffer>,
+    resolution_: u32,
+}
+
+impl ShadowMap {
+    fn new(
+        device: &ash::Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        render_pass: vk::RenderPass,
+        format: vk::Format,
+        resolution: u32,
+        layers: u32,
+    ) -> ShadowMap {
+        let image_create_info = vk::ImageCreateInfo {
+            s_type: vk::StructureType::IMAGE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::ImageCreateFlags::empty(),
+            image_type: vk::ImageType::TYPE_2D,
+            format,
+            extent: vk::Extent3D {
+                width: resolution,
+                height: resolution,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: layers,
+            samples: vk::SampleCountFlags::TYPE_1,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 0,
+            p_queue_family_indices: ptr::null(),
+            initial_layout: vk::ImageLayout::UNDEFINED,
+        };
+
+        let image = unsafe {
+            device
+                .create_image(&image_create_info, None)
+                .expect("Could not create shadow map image")
+        };
+
+        let memory_requirements = unsafe { device.get_image_memory_requirements(image) };
+
+        let memory_allocate_info = vk::MemoryAllocateInfo {
+            s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
+            p_next: ptr::null(),
+            allocation_size: memory_requirements.size,
+            memory_type_index: physical_device::find_memory_type_index(
+                memory_properties,
+                memory_requirements.memory_type_bits,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )
+            .expect("could not find suitable memory type"),
+        };
+
+        let device_memory = unsafe {
+            device
+                .allocate_memory(&memory_allocate_info, None)
+                .expect("Could not allocate memory for shadow map")
+        };
+
+        unsafe {
+            device
+                .bind_image_memory(image, device_memory, 0)
+                .expect("Could not bind shadow map memory");
+        }
+
+        let create_view =
+            |view_type: vk::ImageViewType, base_array_layer: u32, layer_count: u32| {
+                let image_view_create_info = vk::ImageViewCreateInfo {
+                    s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
+                    p_next: ptr::null(),
+                    flags: vk::ImageViewCreateFlags::empty(),
+                    image,
+                    view_type,
+                    format,
+                    components: vk::ComponentMapping {
+                        r: vk::ComponentSwizzle::IDENTITY,
+                        g: vk::ComponentSwizzle::IDENTITY,
+                        b: vk::ComponentSwizzle::IDENTITY,
+                        a: vk::ComponentSwizzle::IDENTITY,
+                    },
+                    subresource_range: vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::DEPTH,
+                        base_mip_level: 0,
+                        level_count: 1,
+                        base_array_layer,
+                        layer_count,
+                    },
+                };
+
+                unsafe {
+                    device
+                        .create_image_view(&image_view_create_info, None)
+                        .expect("Could not create shadow map view")
+                }
+            };
+
+        let array_view = create_view(vk::ImageViewType::TYPE_2D_ARRAY, 0, layers);
+        let layer_views: Vec<vk::ImageView> = (0..layers)
+            .map(|layer| create_view(vk::ImageViewType::TYPE_2D, layer, 1))
+            .collect();
+
+        let framebuffers = layer_views
+            .iter()
+            .map(|layer_view| {
+                let framebuffer_create_info = vk::FramebufferCreateInfo {
+                    s_type: vk::StructureType::FRAMEBUFFER_CREATE_INFO,
+                    p_next: ptr::null(),
+                    flags: vk::FramebufferCreateFlags::empty(),
+                    render_pass,
+                    attachment_count: 1,
+                    p_attachments: layer_view,
+                    width: resolution,
+                    height: resolution,
+                    layers: 1,
+                };
+
+                unsafe {
+                    device
+                        .create_framebuffer(&framebuffer_create_info, None)
+                        .expect("Could not create shadow map framebuffer")
+                }
+            })
+            .collect();
+
+        ShadowMap {
+            image_: image,
+            device_memory_: device_memory,
+            array_view_: array_view,
+            layer_views_: layer_views,
+            framebuffers_: framebuffers,
+            resolution_: resolution,
+        }
+    }
+
+    fn get_layer_count(&self) -> u32 {
+        self.layer_views_.len() as u32
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            for framebuffer in self.framebuffers_.iter() {
+                device.destroy_framebuffer(*framebuffer, None);
+            }
+
+            for layer_view in self.layer_views_.iter() {
+                device.destroy_image_view(*layer_view, None);
+            }
+
+            device.destroy_image_view(self.array_view_, None);
+            device.destroy_image(self.image_, None);
+            device.free_memory(self.device_memory_, None);
+        }
+    }
+}
+
+/// The shadow resources of one swapchain image
+struct ShadowFrame {
+    maps_: Vec<ShadowMap>,
+    /// a cleared map bound to the shadow map array elements no light uses
+    empty_map_: ShadowMap,
+    uniform_buffer_: Buffer,
+    /// the shadow casting lights of the frame and the view projection of each of their maps
+    lights_: Vec<(Light, Vec<cgmath::Matrix4<f32>>)>,
+}
+
+/// Renders a depth map from every shadow casting light of a scene before the renderer's render
+/// pass, and provides the maps and light matrices to pipelines declaring the bindings of
+/// shadows.glsl in descriptor set SHADOW_SET
+pub struct ShadowPass {
+    render_pass_: vk::RenderPass,
+    /// built from the shadow depth shader once a scene first has a shadow casting light
+    pipeline_: Option<Pipeline>,
+    sampler_: vk::Sampler,
+    format_: vk::Format,
+    features_: PipelineFeatures,
+    memory_properties_: vk::PhysicalDeviceMemoryProperties,
+    frames_: Vec<ShadowFrame>,
+}
+
+impl ShadowPass {
+    /// Creates the shadow render pass, the depth comparison sampler and the shadow resources of
+    /// every swapchain image
+    ///
+    /// # Arguments
+    ///
+    /// * 'instance' - the instance the physical device belongs to
+    ///
+    /// * 'device' - the logical device the resources are created on
+    ///
+    /// * 'physical_device' - the physical device used to pick a depth format and memory types
+    ///
+    /// * 'num_images' - the number of swapchain images of the renderer
+    ///
+    pub fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &PhysicalDevice,
+        num_images: usize,
+    ) -> ShadowPass {
+        let format = physical_device
+            .get_supported_format(
+                instance,
+                &[vk::Format::D32_SFLOAT, vk::Format::D16_UNORM],
+                vk::ImageTiling::OPTIMAL,
+                vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+                    | vk::FormatFeatureFlags::SAMPLED_IMAGE,
+            )
+            .expect("Could not find a depth format that can be sampled");
+
+        let memory_properties = unsafe {
+            instance.get_physical_device_memory_properties(physical_device.physical_device_handle_)
+        };
+
+        let render_pass = ShadowPass::create_render_pass(device, format);
+        let sampler = ShadowPass::create_sampler(device);
+
+        let frames = (0..num_images)
+            .map(|_| {
+                let buffer_size = size_of::<ShadowUBO>() as u64;
+
+                let mut uniform_buffer = Buffer::new(
+                    instance,
+                    device,
+                    physical_device,
+                    buffer_size,
+                    vk::BufferUsageFlags::UNIFORM_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                );
+
+                // Start out without shadowed lights, until the first scene is processed
+                unsafe {
+                    uniform_buffer.map(device, 0, buffer_size);
+                    uniform_buffer.copy_from_data(
+                        &ShadowUBO::default() as *const ShadowUBO as *const c_void,
+                        buffer_size,
+                        0,
+                    );
+                }
+
+                ShadowFrame {
+                    maps_: vec![],
+                    empty_map_: ShadowMap::new(
+                        device,
+                        &memory_properties,
+                        render_pass,
+                        format,
+                        1,
+                        1,
+                    ),
+                    uniform_buffer_: uniform_buffer,
+                    lights_: vec![],
+                }
+            })
+            .collect();
+
+        ShadowPass {
+            render_pass_: render_pass,
+            pipeline_: None,
+            sampler_: sampler,
+            format_: format,
+            features_: PipelineFeatures::from_physical_device(physical_device),
+            memory_properties_: memory_properties,
+            frames_: frames,
+        }
+    }
+
+    /// Computes the shadow projections of the scene's shadow casting lights for a swapchain image,
+    /// uploads them and makes sure every light has a shadow map of the right size
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the shadow maps are created on
+    ///
+    /// * 'image_index' - the swapchain image the frame is rendered to
+    ///
+    /// * 'lights' - the lights of the scene
+    ///
+    /// * 'camera' - the camera directional light cascades are fitted to
+    ///
+    /// * 'pipeline_cache' - cache used to speed up building the shadow pipeline
+    ///
+    pub fn update(
+        &mut self,
+        device: &ash::Device,
+        image_index: usize,
+        lights: &[Light],
+        camera: &Camera,
+        pipeline_cache: vk::PipelineCache,
+    ) {
+        let shadowed_lights: Vec<Light> = lights
+            .iter()
+            .filter(|light| light.get_shadow_map_count() > 0)
+            .take(MAX_SHADOWED_LIGHTS)
+            .cloned()
+            .collect();
+
+        if !shadowed_lights.is_empty() && self.pipeline_.is_none() {
+            self.pipeline_ = Some(
+                Pipeline::from_pipeline_descriptor(
+                    device,
+                    &builtin::shadow_depth(),
+                    self.render_pass_,
+                    0,
+                    &Multisample::default(),
+                    &self.features_,
+                    pipeline_cache,
+                )
+                .unwrap_or_else(|error| panic!("Could not build shadow pipeline: {}", error)),
+            );
+        }
+
+        self.resize_maps(device, image_index, &shadowed_lights);
+
+        let mut shadow_ubo = ShadowUBO::default();
+        let frame = &mut self.frames_[image_index];
+
+        frame.lights_ = shadowed_lights
+            .iter()
+            .map(|light| (*light, light.get_shadow_view_projections(camera).0))
+            .collect();
+
+        for (index, light) in shadowed_lights.iter().enumerate() {
+            let shadow = light.shadow_.unwrap();
+            let (view_projections, splits) = light.get_shadow_view_projections(camera);
+            let light_ubo = &mut shadow_ubo.lights_[index];
+
+            for (cascade, view_projection) in view_projections.iter().enumerate() {
+                light_ubo.view_projections_[cascade] = conv::array4x4(*view_projection);
+                light_ubo.cascade_splits_[cascade] = splits[cascade];
+            }
+
+            light_ubo.position_ = [
+                light.position_.x,
+                light.position_.y,
+                light.position_.z,
+                match light.light_type_ {
+                    LightType::Directional => 0.0,
+                    LightType::Spot => 1.0,
+                    LightType::Point => 2.0,
+                },
+            ];
+            light_ubo.direction_ = [
+                light.direction_.x,
+                light.direction_.y,
+                light.direction_.z,
+                (light.cone_angle_.0 / 2.0).cos(),
+            ];
+            let color = light.color_ * light.intensity_;
+            light_ubo.color_ = [color.x, color.y, color.z, light.range_];
+            light_ubo.params_ = [
+                view_projections.len() as f32,
+                shadow.pcf_radius_ as f32,
+                1.0 / shadow.resolution_ as f32,
+                0.0,
+            ];
+        }
+
+        shadow_ubo.count_[0] = shadowed_lights.len() as u32;
+
+        unsafe {
+            frame.uniform_buffer_.copy_from_data(
+                &shadow_ubo as *const ShadowUBO as *const c_void,
+                size_of::<ShadowUBO>() as u64,
+                0,
+            );
+        }
+    }
+
+    /// Points the shadow bindings of a descriptor set at a swapchain image's shadow uniforms and
+    /// maps. Bindings whose declared type does not match shadows.glsl are left untouched
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the descriptor set was allocated from
+    ///
+    /// * 'image_index' - the swapchain image whose shadow resources are bound
+    ///
+    /// * 'descriptor_set' - a descriptor set with the layout of set SHADOW_SET
+    ///
+    /// * 'bindings' - the bindings of the descriptor set's layout
+    ///
+    pub fn write_descriptor_set(
+        &self,
+        device: &ash::Device,
+        image_index: usize,
+        descriptor_set: vk::DescriptorSet,
+        bindings: &[vk::DescriptorSetLayoutBinding],
+    ) {
+        let frame = &self.frames_[image_index];
+
+        for binding in bindings.iter() {
+            match (binding.binding, binding.descriptor_type) {
+                (SHADOW_UBO_BINDING, vk::DescriptorType::UNIFORM_BUFFER) => {
+                    descriptor::write_buffer(
+                        device,
+                        descriptor_set,
+                        binding.binding,
+                        binding.descriptor_type,
+                        frame.uniform_buffer_.buffer_handle_,
+                        0,
+                        size_of::<ShadowUBO>() as u64,
+                    );
+                }
+                (SHADOW_MAP_BINDING, vk::DescriptorType::COMBINED_IMAGE_SAMPLER) => {
+                    let image_views: Vec<vk::ImageView> = (0..binding.descriptor_count as usize)
+                        .map(|index| match frame.maps_.get(index) {
+                            Some(map) if index < frame.lights_.len() => map.array_view_,
+                            _ => frame.empty_map_.array_view_,
+                        })
+                        .collect();
+
+                    descriptor::write_images(
+                        device,
+                        descriptor_set,
+                        binding.binding,
+                        binding.descriptor_type,
+                        &image_views,
+                        self.sampler_,
+                        vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Records rendering the depth of every instance into each shadow map of a swapchain image.
+    /// Must be recorded outside of a render pass, with the geometry manager's vertex and index
+    /// buffers bound
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer was allocated from
+    ///
+    /// * 'command_buffer' - the command buffer to record to
+    ///
+    /// * 'image_index' - the swapchain image whose shadow maps are rendered
+    ///
+    /// * 'geometry_manager' - the geometry manager holding the instances' meshes
+    ///
+    /// * 'vulkan_instances' - the instances casting shadows
+    ///
+    pub fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        geometry_manager: &GeometryManager,
+        vulkan_instances: &[VulkanInstance],
+    ) {
+        let frame = &self.frames_[image_index];
+
+        // The empty map only needs clearing, so that sampling it reports no shadow
+        self.begin_render_pass(device, command_buffer, &frame.empty_map_, 0);
+        unsafe { device.cmd_end_render_pass(command_buffer) };
+
+        let pipeline = match self.pipeline_.as_ref() {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+
+        for ((light, view_projections), map) in frame.lights_.iter().zip(frame.maps_.iter()) {
+            let shadow = light.shadow_.unwrap();
+
+            for (layer, view_projection) in view_projections.iter().enumerate() {
+                self.begin_render_pass(device, command_buffer, map, layer);
+
+                unsafe {
+                    device.cmd_bind_pipeline(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        pipeline.pipeline_handle_,
+                    );
+
+                    device.cmd_set_viewport(
+                        command_buffer,
+                        0,
+                        &[vk::Viewport {
+                            x: 0.0,
+                            y: 0.0,
+                            width: map.resolution_ as f32,
+                            height: map.resolution_ as f32,
+                            min_depth: 0.0,
+                            max_depth: 1.0,
+                        }],
+                    );
+                    device.cmd_set_scissor(
+                        command_buffer,
+                        0,
+                        &[vk::Rect2D {
+                            offset: vk::Offset2D { x: 0, y: 0 },
+                            extent: vk::Extent2D {
+                                width: map.resolution_,
+                                height: map.resolution_,
+                            },
+                        }],
+                    );
+                    device.cmd_set_depth_bias(
+                        command_buffer,
+                        shadow.constant_bias_,
+                        0.0,
+                        shadow.slope_bias_,
+                    );
+
+                    let view_projection_bytes = Renderer::mat4_to_bytes(*view_projection);
+
+                    device.cmd_push_constants(
+                        command_buffer,
+                        pipeline.layout_,
+                        pipeline
+                            .get_push_constant_stage_flags(0, view_projection_bytes.len() as u32),
+                        0,
+                        view_projection_bytes.as_slice(),
+                    );
+
+                    for vulkan_instance in vulkan_instances.iter() {
+                        let mesh_location =
+                            geometry_manager.get_mesh_location(&vulkan_instance.mesh_id_);
+
+                        device.cmd_bind_descriptor_sets(
+                            command_buffer,
+                            vk::PipelineBindPoint::GRAPHICS,
+                            pipeline.layout_,
+                            0,
+                            &[vulkan_instance.descriptor_set_],
+                            &[],
+                        );
+                        device.cmd_draw_indexed(
+                            command_buffer,
+                            mesh_location.index_count_ as u32,
+                            1,
+                            mesh_location.index_offset_ as u32,
+                            mesh_location.vertex_offset_ as i32,
+                            0,
+                        );
+                    }
+
+                    device.cmd_end_render_pass(command_buffer);
+                }
+            }
+        }
+    }
+
+    /// Recreates the shadow maps of a swapchain image whose resolution or cascade count no longer
+    /// matches its light. Waits for the device to become idle before destroying any map
+    fn resize_maps(&mut self, device: &ash::Device, image_index: usize, lights: &[Light]) {
+        let frame = &mut self.frames_[image_index];
+
+        let outdated = frame.maps_.len() < lights.len()
+            || lights.iter().zip(frame.maps_.iter()).any(|(light, map)| {
+                map.resolution_ != light.shadow_.unwrap().resolution_
+                    || map.get_layer_count() != light.get_shadow_map_count()
+            });
+
+        if !outdated {
+            return;
+        }
+
+        unsafe {
+            device
+                .device_wait_idle()
+                .expect("Could not wait for device to become idle");
+        }
+
+        for map in frame.maps_.drain(..) {
+            map.destroy(device);
+        }
+
+        for light in lights.iter() {
+            frame.maps_.push(ShadowMap::new(
+                device,
+                &self.memory_properties_,
+                self.render_pass_,
+                self.format_,
+                light.shadow_.unwrap().resolution_,
+                light.get_shadow_map_count(),
+            ));
+        }
+    }
+
+    fn begin_render_pass(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        map: &ShadowMap,
+        layer: usize,
+    ) {
+        let clear_values = [vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue {
+                depth: 1.0,
+                stencil: 0,
+            },
+        }];
+
+        let render_pass_begin_info = vk::RenderPassBeginInfo {
+            s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
+            p_next: ptr::null(),
+            render_pass: self.render_pass_,
+            framebuffer: map.framebuffers_[layer],
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: vk::Extent2D {
+                    width: map.resolution_,
+                    height: map.resolution_,
+                },
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+        };
+
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &render_pass_begin_info,
+                vk::SubpassContents::INLINE,
+            );
+        }
+    }
+
+    /// Creates a render pass with a single depth attachment, which is left ready to be sampled by
+    /// fragment shaders of the following render pass
+    fn create_render_pass(device: &ash::Device, format: vk::Format) -> vk::RenderPass {
+        let attachment = vk::AttachmentDescription {
+            flags: vk::AttachmentDescriptionFlags::empty(),
+            format,
+            samples: vk::SampleCountFlags::TYPE_1,
+            load_op: vk::AttachmentLoadOp::CLEAR,
+            store_op: vk::AttachmentStoreOp::STORE,
+            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            final_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+        };
+
+        let depth_attachment_reference = vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+
+        let subpass = vk::SubpassDescription {
+            flags: vk::SubpassDescriptionFlags::empty(),
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            input_attachment_count: 0,
+            p_input_attachments: ptr::null(),
+            color_attachment_count: 0,
+            p_color_attachments: ptr::null(),
+            p_resolve_attachments: ptr::null(),
+            p_depth_stencil_attachment: &depth_attachment_reference,
+            preserve_attachment_count: 0,
+            p_preserve_attachments: ptr::null(),
+        };
+
+        // Wait for the previous frame's shadow reads before clearing, and make the depth writes
+        // visible to the fragment shaders sampling the map afterwards
+        let subpass_dependencies = [
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_stage_mask: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                src_access_mask: vk::AccessFlags::SHADER_READ,
+                dst_access_mask: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::BY_REGION,
+            },
+            vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: vk::SUBPASS_EXTERNAL,
+                src_stage_mask: vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::SHADER_READ,
+                dependency_flags: vk::DependencyFlags::BY_REGION,
+            },
+        ];
+
+        let render_pass_create_info = vk::RenderPassCreateInfo {
+            s_type: vk::StructureType::RENDER_PASS_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::RenderPassCreateFlags::empty(),
+            attachment_count: 1,
+            p_attachments: &attachment,
+            subpass_count: 1,
+            p_subpasses: &subpass,
+            dependency_count: subpass_dependencies.len() as u32,
+            p_dependencies: subpass_dependencies.as_ptr(),
+        };
+
+        unsafe {
+            device
+                .create_render_pass(&render_pass_create_info, None)
+                .expect("Could not create shadow render pass")
+        }
+    }
+
+    /// Creates a sampler comparing against the shadow map's depth with linear filtering, which
+    /// gives bilinear percentage closer filtering for free. Lookups outside the map are lit
+    fn create_sampler(device: &ash::Device) -> vk::Sampler {
+        let sampler_create_info = vk::SamplerCreateInfo {
+            s_type: vk::StructureType::SAMPLER_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::SamplerCreateFlags::empty(),
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+            address_mode_u: vk::SamplerAddressMode::CLAMP_TO_BORDER,
+            address_mode_v: vk::SamplerAddressMode::CLAMP_TO_BORDER,
+            address_mode_w: vk::SamplerAddressMode::CLAMP_TO_BORDER,
+            mip_lod_bias: 0.0,
+            anisotropy_enable: vk::FALSE,
+            max_anisotropy: 1.0,
+            compare_enable: vk::TRUE,
+            compare_op: vk::CompareOp::LESS_OR_EQUAL,
+            min_lod: 0.0,
+            max_lod: 0.0,
+            border_color: vk::BorderColor::FLOAT_OPAQUE_WHITE,
+            unnormalized_coordinates: vk::FALSE,
+        };
+
+        unsafe {
+            device
+                .create_sampler(&sampler_create_info, None)
+                .expect("Could not create shadow sampler")
+        }
+    }
+}
+
+/// Descriptor sets binding the shadow resources, lights and light clusters to pipelines declaring
+/// descriptor set SHADOW_SET, one set per pipeline and swapchain image
+pub struct ShadowSets {
+    descriptor_pools_: Vec<vk::DescriptorPool>,
+    /// indexed by pipeline and then swapchain image, empty for pipelines without shadow bindings
+    descriptor_sets_: Vec<Vec<vk::DescriptorSet>>,
+    /// the bindings of set SHADOW_SET of each pipeline
+    bindings_: Vec<Vec<vk::DescriptorSetLayoutBinding>>,
+}
+
+impl ShadowSets {
+    /// Allocates shadow descriptor sets for every pipeline declaring them, such as the pipelines
+    /// of a render pass's subpasses in subpass order
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the pipelines were created on
+    ///
+    /// * 'pipelines' - each pipeline together with the description it was built from
+    ///
+    /// * 'num_images' - the number of swapchain images
+    ///
+    pub fn new(
+        device: &ash::Device,
+        pipelines: &[(&PipelineDescription, &Pipeline)],
+        num_images: usize,
+    ) -> ShadowSets {
+        let mut descriptor_pools = vec![];
+        let mut descriptor_sets = vec![];
+        let mut pipeline_bindings = vec![];
+
+        for (pipeline_description, pipeline) in pipelines.iter() {
+            // The pipeline was built from the same shaders, so its bindings are known to merge
+            let bindings = descriptor_set_layout::merge_bindings(&pipeline_description.shaders_)
+                .expect("Could not merge descriptor bindings")
+                .get(SHADOW_SET)
+                .cloned()
+                .unwrap_or_default();
+
+            if bindings.is_empty() {
+                descriptor_sets.push(vec![]);
+                pipeline_bindings.push(bindings);
+                continue;
+            }
+
+            let descriptor_pool =
+                descriptor::create_pool(device, std::slice::from_ref(&bindings), num_images as u32);
+            let layouts = vec![pipeline.descriptor_set_layouts_[SHADOW_SET]; num_images];
+
+            descriptor_sets.push(descriptor::allocate_sets(device, descriptor_pool, &layouts));
+            descriptor_pools.push(descriptor_pool);
+            pipeline_bindings.push(bindings);
+        }
+
+        ShadowSets {
+            descriptor_pools_: descriptor_pools,
+            descriptor_sets_: descriptor_sets,
+            bindings_: pipeline_bindings,
+        }
+    }
+
+    /// Returns the shadow descriptor set of a pipeline for a swapchain image, or None if the
+    /// pipeline does not sample shadows
+    pub fn get_descriptor_set(
+        &self,
+        pipeline: usize,
+        image_index: u32,
+    ) -> Option<vk::DescriptorSet> {
+        self.descriptor_sets_[pipeline]
+            .get(image_index as usize)
+            .cloned()
+    }
+
+    /// Points every pipeline's shadow descriptor set of a swapchain image at that image's shadow
+    /// resources, lights and light clusters
+    pub fn write(
+        &self,
+        device: &ash::Device,
+        shadow_pass: &ShadowPass,
+        scene_manager: &SceneManager,
+        light_culling: &LightCulling,
+        image_index: u32,
+    ) {
+        for (sets, bindings) in self.descriptor_sets_.iter().zip(self.bindings_.iter()) {
+            if let Some(descriptor_set) = sets.get(image_index as usize) {
+                shadow_pass.write_descriptor_set(
+                    device,
+                    image_index as usize,
+                    *descriptor_set,
+                    bindings,
+                );
+                scene_manager.write_descriptor_set(
+                    device,
+                    image_index as usize,
+                    *descriptor_set,
+                    bindings,
+                );
+                light_culling.write_descriptor_set(
+                    device,
+                    image_index as usize,
+                    *descriptor_set,
+                    bindings,
+                );
+            }
+        }
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        for descriptor_pool in self.descriptor_pools_.iter() {
+            unsafe { device.destroy_descriptor_pool(*descriptor_pool, None) };
+        }
+    }
+}
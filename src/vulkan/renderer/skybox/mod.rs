@@ -0,0 +1,223 @@
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use super::Renderer;
+use crate::geometry::camera::Camera;
+use crate::render_sequence::builtin;
+use crate::vulkan::descriptor;
+use crate::vulkan::render_pass::pipeline::{descriptor_set_layout, Pipeline};
+use crate::vulkan::render_pass::RenderPassContext;
+use crate::vulkan::texture_manager::{TextureID, TextureManager};
+
+/// The descriptor set the skybox shader reads its cube map from
+const SKYBOX_TEXTURE_SET: usize = 0;
+/// Vertices of the triangle covering the screen
+const SKYBOX_VERTICES: u32 = 3;
+
+/// The skybox pipeline of the current render pass's final subpass, with the descriptor set each
+/// swapchain image binds its environment with
+struct SkyboxPipeline {
+    pipeline_: Pipeline,
+    descriptor_pool_: vk::DescriptorPool,
+    descriptor_sets_: Vec<vk::DescriptorSet>,
+}
+
+/// Draws the scene's environment cube map behind everything else in the final subpass of the
+/// renderer's render pass, right after its opaque geometry. Only pixels left at the cleared far
+/// depth are covered
+pub struct SkyboxPass {
+    /// built against the current render pass the first time a scene has an environment
+    pipeline_: Option<SkyboxPipeline>,
+    /// true once building the pipeline against the current render pass was attempted
+    built_: bool,
+    /// the environment written to the descriptor set of each swapchain image, if any
+    environments_: Vec<Option<TextureID>>,
+}
+
+impl SkyboxPass {
+    /// Creates a skybox pass drawing nothing until a scene has an environment
+    ///
+    /// # Arguments
+    ///
+    /// * 'num_images' - the number of swapchain images
+    ///
+    pub fn new(num_images: usize) -> SkyboxPass {
+        SkyboxPass {
+            pipeline_: None,
+            built_: false,
+            environments_: vec![None; num_images],
+        }
+    }
+
+    /// Destroys the pipeline built against the current render pass, along with its descriptor
+    /// sets, so it is built again against the next. The device must not be using them
+    pub fn reset(&mut self, device: &ash::Device) {
+        if let Some(pipeline) = self.pipeline_.take() {
+            pipeline.pipeline_.destroy(device);
+
+            unsafe { device.destroy_descriptor_pool(pipeline.descriptor_pool_, None) };
+        }
+
+        for environment in self.environments_.iter_mut() {
+            *environment = None;
+        }
+
+        self.built_ = false;
+    }
+
+    /// Writes the environment of a frame to the descriptor set of a swapchain image, building the
+    /// pipeline first if there is an environment and it has not been built yet
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the pipeline is created on
+    ///
+    /// * 'image_index' - the swapchain image the frame is rendered to
+    ///
+    /// * 'environment' - the cube map drawn behind the scene, or None to draw nothing
+    ///
+    /// * 'texture_manager' - owns the environment's texture
+    ///
+    /// * 'render_pass' - the render pass the skybox is drawn in, with the description it was built
+    ///   from and the cache pipelines are built with
+    ///
+    pub fn update(
+        &mut self,
+        device: &ash::Device,
+        image_index: usize,
+        environment: Option<TextureID>,
+        texture_manager: &TextureManager,
+        render_pass: &RenderPassContext,
+    ) {
+        if environment.is_some() && !self.built_ {
+            self.build_pipeline(device, render_pass);
+        }
+
+        let pipeline = match self.pipeline_.as_ref() {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+
+        // The image's previous frame has completed, so its set can be rewritten when the
+        // environment changes
+        if environment == self.environments_[image_index] {
+            return;
+        }
+
+        self.environments_[image_index] = environment;
+
+        if let Some(environment) = environment {
+            let texture = texture_manager.get_texture(environment);
+
+            descriptor::write_image(
+                device,
+                pipeline.descriptor_sets_[image_index],
+                0,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                texture.image_view_,
+                texture.sampler_,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            );
+        }
+    }
+
+    /// Records the skybox of a swapchain image once for every camera. Must be recorded in the
+    /// final subpass of the render pass the pipeline was built against
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer belongs to
+    ///
+    /// * 'command_buffer' - the command buffer to record to
+    ///
+    /// * 'image_index' - the swapchain image the frame is rendered to
+    ///
+    /// * 'cameras' - the cameras the skybox is drawn with, with the viewport and scissor each
+    ///   draws to
+    ///
+    pub fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        cameras: &[(&Camera, vk::Viewport, vk::Rect2D)],
+    ) {
+        let pipeline = match self.pipeline_.as_ref() {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+
+        if self.environments_[image_index].is_none() {
+            return;
+        }
+
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline_.pipeline_handle_,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline_.layout_,
+                SKYBOX_TEXTURE_SET as u32,
+                &[pipeline.descriptor_sets_[image_index]],
+                &[],
+            );
+
+            for (camera, viewport, scissor) in cameras.iter() {
+                device.cmd_set_viewport(command_buffer, 0, &[*viewport]);
+                device.cmd_set_scissor(command_buffer, 0, &[*scissor]);
+
+                let mut push_constants = Renderer::mat4_to_bytes(camera.view_);
+                push_constants.append(&mut Renderer::mat4_to_bytes(camera.projection_));
+
+                device.cmd_push_constants(
+                    command_buffer,
+                    pipeline.pipeline_.layout_,
+                    pipeline
+                        .pipeline_
+                        .get_push_constant_stage_flags(0, push_constants.len() as u32),
+                    0,
+                    &push_constants,
+                );
+                device.cmd_draw(command_buffer, SKYBOX_VERTICES, 1, 0, 0);
+            }
+        }
+    }
+
+    /// Builds the skybox pipeline for the final subpass of a render pass. The skybox is only drawn
+    /// if the final subpass writes nothing but the presented color, as its pipeline must write
+    /// every color attachment of the subpass
+    fn build_pipeline(&mut self, device: &ash::Device, render_pass: &RenderPassContext) {
+        self.built_ = true;
+
+        let description = builtin::skybox();
+
+        let built = render_pass.build_overlay_pipeline(device, &description, "the skybox");
+
+        let pipeline = match built {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+
+        // The pipeline was built from the same shaders, so its bindings are known to merge
+        let bindings = descriptor_set_layout::merge_bindings(&description.shaders_)
+            .expect("Could not merge descriptor bindings")
+            .get(SKYBOX_TEXTURE_SET)
+            .cloned()
+            .unwrap_or_default();
+
+        let num_images = self.environments_.len();
+        let descriptor_pool = descriptor::create_pool(device, &[bindings], num_images as u32);
+        let layouts = vec![pipeline.descriptor_set_layouts_[SKYBOX_TEXTURE_SET]; num_images];
+        let descriptor_sets = descriptor::allocate_sets(device, descriptor_pool, &layouts);
+
+        self.pipeline_ = Some(SkyboxPipeline {
+            pipeline_: pipeline,
+            descriptor_pool_: descriptor_pool,
+            descriptor_sets_: descriptor_sets,
+        });
+    }
+}
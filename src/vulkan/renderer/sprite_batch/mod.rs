@@ -0,0 +1,340 @@
+use std::mem::size_of;
+use std::os::raw::c_void;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use super::Renderer;
+use crate::geometry::camera::Camera;
+use crate::geometry::sprite::{SpriteBatch, SpriteDraw};
+use crate::geometry::vertex::Vertex;
+use crate::render_sequence::builtin;
+use crate::vulkan::buffer::Buffer;
+use crate::vulkan::descriptor;
+use crate::vulkan::physical_device::PhysicalDevice;
+use crate::vulkan::render_pass::pipeline::{descriptor_set_layout, Pipeline};
+use crate::vulkan::render_pass::RenderPassContext;
+use crate::vulkan::texture_manager::TextureManager;
+
+/// The maximum number of sprites drawn per frame. Further sprites are dropped
+const MAX_SPRITES: usize = 16384;
+/// The maximum number of texture runs drawn per frame. Further runs are dropped
+const MAX_SPRITE_DRAWS: usize = 256;
+/// Vertices of each sprite's quad
+const SPRITE_VERTICES: usize = 6;
+/// The descriptor set the sprite shader reads its texture from
+const SPRITE_TEXTURE_SET: usize = 0;
+
+/// The sprite pipeline of the current render pass's final subpass, with a descriptor pool per
+/// swapchain image the texture sets of that image's draws are allocated from every frame
+struct SpritePipeline {
+    pipeline_: Pipeline,
+    descriptor_pools_: Vec<vk::DescriptorPool>,
+}
+
+/// The sprites written for a swapchain image
+struct SpriteFrame {
+    vertex_buffer_: Buffer,
+    /// each run of sprites sharing a texture, with the descriptor set of the texture
+    draws_: Vec<(SpriteDraw, vk::DescriptorSet)>,
+}
+
+/// Draws the sprites of a SpriteBatch in the final subpass of the renderer's render pass, after
+/// the debug lines. Each swapchain image has a host visible vertex buffer the sprites are written
+/// to every frame
+pub struct SpriteBatchPass {
+    /// whether the pass draws the batch's world sprites, depth tested, rather than the others
+    world_: bool,
+    /// built against the current render pass the first time there are sprites to draw
+    pipeline_: Option<SpritePipeline>,
+    /// true once building the pipeline against the current render pass was attempted
+    built_: bool,
+    frames_: Vec<SpriteFrame>,
+    /// true once dropped sprites have been reported
+    overflow_reported_: bool,
+}
+
+impl SpriteBatchPass {
+    /// Creates and maps the vertex buffer of every swapchain image
+    ///
+    /// # Arguments
+    ///
+    /// * 'instance' - the instance the physical device belongs to
+    ///
+    /// * 'device' - the logical device the buffers are created on
+    ///
+    /// * 'physical_device' - the physical device used to pick memory types
+    ///
+    /// * 'num_images' - the number of swapchain images
+    ///
+    /// * 'world' - whether the pass draws world sprites rather than the others
+    ///
+    pub fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &PhysicalDevice,
+        num_images: usize,
+        world: bool,
+    ) -> SpriteBatchPass {
+        let size = (MAX_SPRITES * SPRITE_VERTICES * size_of::<Vertex>()) as u64;
+
+        let frames = (0..num_images)
+            .map(|_| {
+                let mut vertex_buffer = Buffer::new(
+                    instance,
+                    device,
+                    physical_device,
+                    size,
+                    vk::BufferUsageFlags::VERTEX_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                );
+
+                unsafe { vertex_buffer.map(device, 0, size) };
+
+                SpriteFrame {
+                    vertex_buffer_: vertex_buffer,
+                    draws_: vec![],
+                }
+            })
+            .collect();
+
+        SpriteBatchPass {
+            world_: world,
+            pipeline_: None,
+            built_: false,
+            frames_: frames,
+            overflow_reported_: false,
+        }
+    }
+
+    /// Destroys the pipeline built against the current render pass, along with the texture sets
+    /// allocated for it, so it is built again against the next. The device must not be using them
+    pub fn reset(&mut self, device: &ash::Device) {
+        if let Some(pipeline) = self.pipeline_.take() {
+            pipeline.pipeline_.destroy(device);
+
+            for descriptor_pool in pipeline.descriptor_pools_.iter() {
+                unsafe { device.destroy_descriptor_pool(*descriptor_pool, None) };
+            }
+        }
+
+        for frame in self.frames_.iter_mut() {
+            frame.draws_.clear();
+        }
+
+        self.built_ = false;
+    }
+
+    /// Writes the sprites of a SpriteBatch to the vertex buffer of a swapchain image and the
+    /// textures of its draws to descriptor sets, building the pipeline first if there are sprites
+    /// to draw and it has not been built yet
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the pipeline is created on
+    ///
+    /// * 'image_index' - the swapchain image the frame is rendered to
+    ///
+    /// * 'sprite_batch' - the sprites of the frame
+    ///
+    /// * 'texture_manager' - owns the textures the sprites are drawn with
+    ///
+    /// * 'render_pass' - the render pass the sprites are drawn in, with the description it was
+    ///   built from and the cache pipelines are built with
+    ///
+    pub fn update(
+        &mut self,
+        device: &ash::Device,
+        image_index: usize,
+        sprite_batch: &SpriteBatch,
+        texture_manager: &TextureManager,
+        render_pass: &RenderPassContext,
+    ) {
+        let (vertices, mut draws) = sprite_batch.build_draws(self.world_);
+
+        if !draws.is_empty() && !self.built_ {
+            self.build_pipeline(device, render_pass);
+        }
+
+        let frame = &mut self.frames_[image_index];
+        frame.draws_.clear();
+
+        let pipeline = match self.pipeline_.as_ref() {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+
+        let max_vertices = (MAX_SPRITES * SPRITE_VERTICES) as u32;
+        draws.truncate(MAX_SPRITE_DRAWS);
+        draws.retain(|draw| draw.first_vertex_ < max_vertices);
+
+        if let Some(last) = draws.last_mut() {
+            last.vertex_count_ = last.vertex_count_.min(max_vertices - last.first_vertex_);
+        }
+
+        let vertex_count = draws
+            .last()
+            .map_or(0, |draw| draw.first_vertex_ + draw.vertex_count_);
+
+        if vertex_count < vertices.len() as u32 && !self.overflow_reported_ {
+            println!(
+                "Dropping sprites beyond the limit of {} sprites in {} texture runs per frame",
+                MAX_SPRITES, MAX_SPRITE_DRAWS
+            );
+            self.overflow_reported_ = true;
+        }
+
+        if vertex_count > 0 {
+            unsafe {
+                frame.vertex_buffer_.copy_from_data(
+                    vertices.as_ptr() as *const c_void,
+                    vertex_count as u64 * size_of::<Vertex>() as u64,
+                    0,
+                );
+            }
+        }
+
+        // The sets of the image's previous frame are no longer in use once its fence has
+        // signalled, so they are freed together
+        let descriptor_pool = pipeline.descriptor_pools_[image_index];
+
+        unsafe {
+            device
+                .reset_descriptor_pool(descriptor_pool, vk::DescriptorPoolResetFlags::empty())
+                .expect("Could not reset descriptor pool");
+        }
+
+        let layouts =
+            vec![pipeline.pipeline_.descriptor_set_layouts_[SPRITE_TEXTURE_SET]; draws.len()];
+        let descriptor_sets = descriptor::allocate_sets(device, descriptor_pool, &layouts);
+
+        for (draw, descriptor_set) in draws.into_iter().zip(descriptor_sets) {
+            let texture = texture_manager.get_texture(draw.texture_);
+
+            descriptor::write_image(
+                device,
+                descriptor_set,
+                0,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                texture.image_view_,
+                texture.sampler_,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            );
+
+            frame.draws_.push((draw, descriptor_set));
+        }
+    }
+
+    /// Records the sprites written for a swapchain image once for every camera. Must be recorded
+    /// in the final subpass of the render pass the pipeline was built against
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the command buffer belongs to
+    ///
+    /// * 'command_buffer' - the command buffer to record to
+    ///
+    /// * 'image_index' - the swapchain image the frame is rendered to
+    ///
+    /// * 'cameras' - the cameras the sprites are drawn with, with the viewport and scissor each
+    ///   draws to
+    ///
+    pub fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        cameras: &[(&Camera, vk::Viewport, vk::Rect2D)],
+    ) {
+        let pipeline = match self.pipeline_.as_ref() {
+            Some(pipeline) => &pipeline.pipeline_,
+            None => return,
+        };
+
+        let frame = &self.frames_[image_index];
+
+        if frame.draws_.is_empty() {
+            return;
+        }
+
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline_handle_,
+            );
+            device.cmd_bind_vertex_buffers(
+                command_buffer,
+                0,
+                &[frame.vertex_buffer_.buffer_handle_],
+                &[0],
+            );
+
+            for (camera, viewport, scissor) in cameras.iter() {
+                device.cmd_set_viewport(command_buffer, 0, &[*viewport]);
+                device.cmd_set_scissor(command_buffer, 0, &[*scissor]);
+
+                let mut push_constants = Renderer::mat4_to_bytes(camera.view_);
+                push_constants.append(&mut Renderer::mat4_to_bytes(camera.projection_));
+
+                device.cmd_push_constants(
+                    command_buffer,
+                    pipeline.layout_,
+                    pipeline.get_push_constant_stage_flags(0, push_constants.len() as u32),
+                    0,
+                    &push_constants,
+                );
+
+                for (draw, descriptor_set) in frame.draws_.iter() {
+                    device.cmd_bind_descriptor_sets(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        pipeline.layout_,
+                        SPRITE_TEXTURE_SET as u32,
+                        &[*descriptor_set],
+                        &[],
+                    );
+                    device.cmd_draw(command_buffer, draw.vertex_count_, 1, draw.first_vertex_, 0);
+                }
+            }
+        }
+    }
+
+    /// Builds the sprite pipeline for the final subpass of a render pass. Sprites are only drawn
+    /// if the final subpass writes nothing but the presented color, as their pipeline must write
+    /// every color attachment of the subpass
+    fn build_pipeline(&mut self, device: &ash::Device, render_pass: &RenderPassContext) {
+        self.built_ = true;
+
+        let description = builtin::sprites(self.world_);
+
+        let pipeline = match render_pass.build_overlay_pipeline(device, &description, "sprites") {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+
+        // The pipeline was built from the same shaders, so its bindings are known to merge
+        let bindings = descriptor_set_layout::merge_bindings(&description.shaders_)
+            .expect("Could not merge descriptor bindings")
+            .get(SPRITE_TEXTURE_SET)
+            .cloned()
+            .unwrap_or_default();
+
+        let descriptor_pools = self
+            .frames_
+            .iter()
+            .map(|_| {
+                descriptor::create_pool(
+                    device,
+                    std::slice::from_ref(&bindings),
+                    MAX_SPRITE_DRAWS as u32,
+                )
+            })
+            .collect();
+
+        self.pipeline_ = Some(SpritePipeline {
+            pipeline_: pipeline,
+            descriptor_pools_: descriptor_pools,
+        });
+    }
+}
@@ -0,0 +1,142 @@
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use crate::vulkan::physical_device;
+
+/// A color image written by one subpass and read by later subpasses of the same render pass as an
+/// input attachment, or a multisampled color image resolved at the end of a subpass. Single sampled
+/// images can also be sampled by later render passes, such as post-processing passes
+pub struct Attachment {
+    image_: vk::Image,
+    pub image_view_: vk::ImageView,
+    device_memory_: vk::DeviceMemory,
+}
+
+impl Attachment {
+    /// Creates a color attachment image with a view covering it
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the image is created on
+    ///
+    /// * 'memory_properties' - memory properties of the device's physical device
+    ///
+    /// * 'extent' - size of the image, matching the framebuffer it is attached to
+    ///
+    /// * 'format' - format of the image
+    ///
+    /// * 'samples' - the number of samples per pixel. Multisampled images are only rendered to and
+    ///   resolved, so they cannot be read as input attachments or sampled
+    ///
+    pub fn new(
+        device: &ash::Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        samples: vk::SampleCountFlags,
+    ) -> Attachment {
+        let image_create_info = vk::ImageCreateInfo {
+            s_type: vk::StructureType::IMAGE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::ImageCreateFlags::empty(),
+            image_type: vk::ImageType::TYPE_2D,
+            format,
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: if samples == vk::SampleCountFlags::TYPE_1 {
+                vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::INPUT_ATTACHMENT
+                    | vk::ImageUsageFlags::SAMPLED
+            } else {
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT
+            },
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 0,
+            p_queue_family_indices: ptr::null(),
+            initial_layout: vk::ImageLayout::UNDEFINED,
+        };
+
+        let image = unsafe {
+            device
+                .create_image(&image_create_info, None)
+                .expect("Could not create attachment image")
+        };
+
+        let memory_requirements = unsafe { device.get_image_memory_requirements(image) };
+
+        let memory_allocate_info = vk::MemoryAllocateInfo {
+            s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
+            p_next: ptr::null(),
+            allocation_size: memory_requirements.size,
+            memory_type_index: physical_device::find_memory_type_index(
+                memory_properties,
+                memory_requirements.memory_type_bits,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )
+            .expect("could not find suitable memory type"),
+        };
+
+        let device_memory = unsafe {
+            device
+                .allocate_memory(&memory_allocate_info, None)
+                .expect("Could not allocate memory for attachment image")
+        };
+
+        unsafe {
+            device
+                .bind_image_memory(image, device_memory, 0)
+                .expect("Could not bind image memory");
+        }
+
+        let image_view_create_info = vk::ImageViewCreateInfo {
+            s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::ImageViewCreateFlags::empty(),
+            image,
+            view_type: vk::ImageViewType::TYPE_2D,
+            format,
+            components: vk::ComponentMapping {
+                r: vk::ComponentSwizzle::IDENTITY,
+                g: vk::ComponentSwizzle::IDENTITY,
+                b: vk::ComponentSwizzle::IDENTITY,
+                a: vk::ComponentSwizzle::IDENTITY,
+            },
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+        };
+
+        let image_view = unsafe {
+            device
+                .create_image_view(&image_view_create_info, None)
+                .expect("could not create attachment image view")
+        };
+
+        Attachment {
+            image_: image,
+            image_view_: image_view,
+            device_memory_: device_memory,
+        }
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_image_view(self.image_view_, None);
+            device.destroy_image(self.image_, None);
+            device.free_memory(self.device_memory_, None);
+        }
+    }
+}
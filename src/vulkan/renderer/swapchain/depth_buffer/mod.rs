@@ -1,12 +1,14 @@
 use crate::vulkan::physical_device;
 use std::ptr;
 
-use ash::version::DeviceV1_0;
+use ash::version::{DeviceV1_0, InstanceV1_0};
 
 pub struct DepthBuffer {
     image_: ash::vk::Image,
     pub image_view_: ash::vk::ImageView,
     device_memory_: ash::vk::DeviceMemory,
+    pub format_: ash::vk::Format,
+    pub samples_: ash::vk::SampleCountFlags,
 }
 
 impl DepthBuffer {
@@ -20,15 +22,50 @@ impl DepthBuffer {
             .get_supported_format(
                 instance,
                 &[
-                    ash::vk::Format::D32_SFLOAT,
                     ash::vk::Format::D32_SFLOAT_S8_UINT,
                     ash::vk::Format::D24_UNORM_S8_UINT,
+                    ash::vk::Format::D32_SFLOAT,
                 ],
                 ash::vk::ImageTiling::OPTIMAL,
                 ash::vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
             )
             .expect("Could not find supported format");
 
+        let memory_properties = unsafe {
+            instance.get_physical_device_memory_properties(physical_device.physical_device_handle_)
+        };
+
+        DepthBuffer::with_samples(
+            device,
+            &memory_properties,
+            extent,
+            format,
+            ash::vk::SampleCountFlags::TYPE_1,
+        )
+    }
+
+    /// Creates a depth buffer of a known format with the given number of samples per pixel, which
+    /// must match the sample count of the render pass it is used in
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the image is created on
+    ///
+    /// * 'memory_properties' - memory properties of the device's physical device
+    ///
+    /// * 'extent' - size of the image, matching the framebuffer it is attached to
+    ///
+    /// * 'format' - a supported depth format
+    ///
+    /// * 'samples' - the number of samples per pixel
+    ///
+    pub fn with_samples(
+        device: &ash::Device,
+        memory_properties: &ash::vk::PhysicalDeviceMemoryProperties,
+        extent: ash::vk::Extent2D,
+        format: ash::vk::Format,
+        samples: ash::vk::SampleCountFlags,
+    ) -> DepthBuffer {
         let image_create_info = ash::vk::ImageCreateInfo {
             s_type: ash::vk::StructureType::IMAGE_CREATE_INFO,
             p_next: ptr::null(),
@@ -42,7 +79,7 @@ impl DepthBuffer {
             },
             mip_levels: 1,
             array_layers: 1,
-            samples: ash::vk::SampleCountFlags::TYPE_1,
+            samples,
             tiling: ash::vk::ImageTiling::OPTIMAL,
             usage: ash::vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
             sharing_mode: ash::vk::SharingMode::EXCLUSIVE,
@@ -63,13 +100,12 @@ impl DepthBuffer {
             s_type: ash::vk::StructureType::MEMORY_ALLOCATE_INFO,
             p_next: ptr::null(),
             allocation_size: memory_requirements.size,
-            memory_type_index: physical_device
-                .get_suitable_memory_type_index(
-                    instance,
-                    memory_requirements.memory_type_bits,
-                    ash::vk::MemoryPropertyFlags::DEVICE_LOCAL,
-                )
-                .expect("could not find suitable memory type"),
+            memory_type_index: physical_device::find_memory_type_index(
+                memory_properties,
+                memory_requirements.memory_type_bits,
+                ash::vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )
+            .expect("could not find suitable memory type"),
         };
 
         let device_memory = unsafe {
@@ -83,6 +119,14 @@ impl DepthBuffer {
                 .bind_image_memory(image, device_memory, 0)
                 .expect("Could not bind image memory");
         }
+
+        // Pipelines may enable stencil tests, so prefer formats with a stencil aspect
+        let aspect_mask = if DepthBuffer::has_stencil(format) {
+            ash::vk::ImageAspectFlags::DEPTH | ash::vk::ImageAspectFlags::STENCIL
+        } else {
+            ash::vk::ImageAspectFlags::DEPTH
+        };
+
         let image_view_create_info = ash::vk::ImageViewCreateInfo {
             s_type: ash::vk::StructureType::IMAGE_VIEW_CREATE_INFO,
             p_next: ptr::null(),
@@ -97,7 +141,7 @@ impl DepthBuffer {
                 a: ash::vk::ComponentSwizzle::IDENTITY,
             },
             subresource_range: ash::vk::ImageSubresourceRange {
-                aspect_mask: ash::vk::ImageAspectFlags::DEPTH,
+                aspect_mask,
                 base_mip_level: 0,
                 level_count: 1,
                 base_array_layer: 0,
@@ -114,6 +158,21 @@ impl DepthBuffer {
             image_: image,
             image_view_: image_view,
             device_memory_: device_memory,
+            format_: format,
+            samples_: samples,
+        }
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_image_view(self.image_view_, None);
+            device.destroy_image(self.image_, None);
+            device.free_memory(self.device_memory_, None);
         }
     }
+
+    pub fn has_stencil(format: ash::vk::Format) -> bool {
+        format == ash::vk::Format::D32_SFLOAT_S8_UINT
+            || format == ash::vk::Format::D24_UNORM_S8_UINT
+    }
 }
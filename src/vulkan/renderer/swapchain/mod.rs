@@ -1,11 +1,12 @@
 use std::ptr;
 
-use ash::version::DeviceV1_0;
+use ash::version::{DeviceV1_0, InstanceV1_0};
 use ash::vk;
 
 use super::super::physical_device;
 use super::super::surface;
 
+pub mod attachment;
 mod depth_buffer;
 
 pub struct Swapchain {
@@ -13,6 +14,14 @@ pub struct Swapchain {
     swapchain_handle_: ash::vk::SwapchainKHR,
     pub swapchain_images_: Vec<SwapchainImage>,
     depth_buffer_: depth_buffer::DepthBuffer,
+    /// intermediate attachments of the current render pass, one set per swapchain image. When
+    /// multisampled, each set is followed by the multisampled copies of the swapchain image and
+    /// the intermediate attachments
+    attachments_: Vec<Vec<attachment::Attachment>>,
+    /// images the render pass presents to instead of the swapchain images while post-processing,
+    /// one per swapchain image
+    offscreen_targets_: Vec<attachment::Attachment>,
+    memory_properties_: ash::vk::PhysicalDeviceMemoryProperties,
     pub swapchain_framebuffers_: Vec<ash::vk::Framebuffer>,
     pub swapchain_extent_: ash::vk::Extent2D,
     pub swapchain_surface_format_: ash::vk::Format,
@@ -114,24 +123,137 @@ impl Swapchain {
         let depth_buffer =
             depth_buffer::DepthBuffer::new(instance, device, physical_device, extent);
 
+        let memory_properties =
+            instance.get_physical_device_memory_properties(physical_device.physical_device_handle_);
+
         Swapchain {
             swapchain_loader_: swapchain_loader,
             swapchain_handle_: swapchain_handle,
             swapchain_images_: swapchain_images,
             depth_buffer_: depth_buffer,
+            attachments_: vec![],
+            offscreen_targets_: vec![],
+            memory_properties_: memory_properties,
             swapchain_framebuffers_: vec![],
             swapchain_extent_: extent,
             swapchain_surface_format_: surface_format.format,
         }
     }
 
+    pub fn get_depth_format(&self) -> ash::vk::Format {
+        self.depth_buffer_.format_
+    }
+
+    /// Returns the view of an intermediate attachment created for the given swapchain image
+    pub fn get_attachment_view(&self, image_index: usize, attachment: usize) -> ash::vk::ImageView {
+        self.attachments_[image_index][attachment].image_view_
+    }
+
+    /// Returns the view of a swapchain image
+    pub fn get_image_view(&self, image_index: usize) -> ash::vk::ImageView {
+        self.swapchain_images_[image_index].view_
+    }
+
+    /// Returns the view of the offscreen target created for the given swapchain image, or None if
+    /// the framebuffers present to the swapchain images
+    pub fn get_offscreen_target_view(&self, image_index: usize) -> Option<ash::vk::ImageView> {
+        self.offscreen_targets_
+            .get(image_index)
+            .map(|target| target.image_view_)
+    }
+
+    /// Creates a framebuffer for each swapchain image. Framebuffer attachments are the swapchain
+    /// image, or an offscreen target if an offscreen format is given, an image for each
+    /// intermediate attachment and then the depth buffer. When multisampled they are followed by
+    /// multisampled images of the presented image and each intermediate attachment, and the depth
+    /// buffer is recreated with the same sample count
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the framebuffers are created on
+    ///
+    /// * 'render_pass' - the render pass the framebuffers are compatible with
+    ///
+    /// * 'attachment_formats' - formats of the render pass's intermediate attachments
+    ///
+    /// * 'samples' - the sample count of the render pass
+    ///
+    /// * 'offscreen_format' - the format of the offscreen targets to present to, or None to present
+    ///   to the swapchain images
+    ///
     pub unsafe fn create_swapchain_framebuffers(
         &mut self,
         device: &ash::Device,
         render_pass: ash::vk::RenderPass,
+        attachment_formats: &[ash::vk::Format],
+        samples: ash::vk::SampleCountFlags,
+        offscreen_format: Option<ash::vk::Format>,
     ) {
-        for image in self.swapchain_images_.iter() {
-            let attachments = [image.view_, self.depth_buffer_.image_view_];
+        self.destroy_swapchain_framebuffers(device);
+
+        if self.depth_buffer_.samples_ != samples {
+            self.depth_buffer_.destroy(device);
+            self.depth_buffer_ = depth_buffer::DepthBuffer::with_samples(
+                device,
+                &self.memory_properties_,
+                self.swapchain_extent_,
+                self.depth_buffer_.format_,
+                samples,
+            );
+        }
+
+        let mut image_formats: Vec<(ash::vk::Format, ash::vk::SampleCountFlags)> =
+            attachment_formats
+                .iter()
+                .map(|format| (*format, ash::vk::SampleCountFlags::TYPE_1))
+                .collect();
+
+        let presented_format = offscreen_format.unwrap_or(self.swapchain_surface_format_);
+
+        if samples != ash::vk::SampleCountFlags::TYPE_1 {
+            image_formats.push((presented_format, samples));
+            image_formats.extend(attachment_formats.iter().map(|format| (*format, samples)));
+        }
+
+        if let Some(format) = offscreen_format {
+            for _ in self.swapchain_images_.iter() {
+                self.offscreen_targets_.push(attachment::Attachment::new(
+                    device,
+                    &self.memory_properties_,
+                    self.swapchain_extent_,
+                    format,
+                    ash::vk::SampleCountFlags::TYPE_1,
+                ));
+            }
+        }
+
+        for (image_index, image) in self.swapchain_images_.iter().enumerate() {
+            let image_attachments: Vec<attachment::Attachment> = image_formats
+                .iter()
+                .map(|(format, samples)| {
+                    attachment::Attachment::new(
+                        device,
+                        &self.memory_properties_,
+                        self.swapchain_extent_,
+                        *format,
+                        *samples,
+                    )
+                })
+                .collect();
+
+            let views: Vec<ash::vk::ImageView> = image_attachments
+                .iter()
+                .map(|attachment| attachment.image_view_)
+                .collect();
+
+            let mut attachments = vec![self
+                .get_offscreen_target_view(image_index)
+                .unwrap_or(image.view_)];
+            attachments.extend_from_slice(&views[..attachment_formats.len()]);
+            attachments.push(self.depth_buffer_.image_view_);
+            attachments.extend_from_slice(&views[attachment_formats.len()..]);
+
+            self.attachments_.push(image_attachments);
 
             let framebuffer_create_info = ash::vk::FramebufferCreateInfo {
                 s_type: ash::vk::StructureType::FRAMEBUFFER_CREATE_INFO,
@@ -153,6 +275,20 @@ impl Swapchain {
         }
     }
 
+    pub unsafe fn destroy_swapchain_framebuffers(&mut self, device: &ash::Device) {
+        for framebuffer in self.swapchain_framebuffers_.drain(..) {
+            device.destroy_framebuffer(framebuffer, None);
+        }
+
+        for attachment in self.attachments_.drain(..).flatten() {
+            attachment.destroy(device);
+        }
+
+        for target in self.offscreen_targets_.drain(..) {
+            target.destroy(device);
+        }
+    }
+
     pub unsafe fn acquire_next_image(
         &self,
         image_available_sempahore: ash::vk::Semaphore,
@@ -0,0 +1,115 @@
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use crate::render_sequence::render_pass::pipeline::PipelineDescription;
+use crate::vulkan::descriptor;
+use crate::vulkan::render_pass::pipeline::{descriptor_set_layout, Pipeline};
+
+/// A texture bound to a combined image sampler of every pipeline of a renderer declaring it
+#[derive(Clone, Copy, Debug)]
+pub struct TextureBinding {
+    pub set_: u32,
+    pub binding_: u32,
+    pub image_view_: vk::ImageView,
+    pub sampler_: vk::Sampler,
+}
+
+/// Descriptor sets binding textures to the pipelines that sample them. Textures do not change
+/// between frames, so every pipeline shares one set per set index across swapchain images
+pub struct TextureSets {
+    descriptor_pools_: Vec<vk::DescriptorPool>,
+    /// indexed by pipeline, the set index and descriptor set of each set of the pipeline containing
+    /// a bound texture
+    descriptor_sets_: Vec<Vec<(u32, vk::DescriptorSet)>>,
+}
+
+impl TextureSets {
+    /// Allocates and writes descriptor sets for every set of every pipeline declaring a combined
+    /// image sampler at a bound set and binding
+    ///
+    /// # Arguments
+    ///
+    /// * 'device' - the logical device the pipelines were created on
+    ///
+    /// * 'pipelines' - each pipeline together with the description it was built from, such as the
+    ///   pipelines of a render pass's subpasses in subpass order
+    ///
+    /// * 'texture_bindings' - the textures to bind
+    ///
+    pub fn new(
+        device: &ash::Device,
+        pipelines: &[(&PipelineDescription, &Pipeline)],
+        texture_bindings: &[TextureBinding],
+    ) -> TextureSets {
+        let mut descriptor_pools = vec![];
+        let mut descriptor_sets = vec![];
+
+        for (pipeline_description, pipeline) in pipelines.iter() {
+            // The pipeline was built from the same shaders, so its bindings are known to merge
+            let set_bindings =
+                descriptor_set_layout::merge_bindings(&pipeline_description.shaders_)
+                    .expect("Could not merge descriptor bindings");
+
+            let mut pipeline_sets = vec![];
+
+            for (set, bindings) in set_bindings.iter().enumerate() {
+                let writes: Vec<&TextureBinding> = texture_bindings
+                    .iter()
+                    .filter(|texture_binding| {
+                        texture_binding.set_ as usize == set
+                            && bindings.iter().any(|binding| {
+                                binding.binding == texture_binding.binding_
+                                    && binding.descriptor_type
+                                        == vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+                            })
+                    })
+                    .collect();
+
+                if writes.is_empty() {
+                    continue;
+                }
+
+                let descriptor_pool =
+                    descriptor::create_pool(device, std::slice::from_ref(bindings), 1);
+                let descriptor_set = descriptor::allocate_sets(
+                    device,
+                    descriptor_pool,
+                    &[pipeline.descriptor_set_layouts_[set]],
+                )[0];
+
+                for texture_binding in writes {
+                    descriptor::write_image(
+                        device,
+                        descriptor_set,
+                        texture_binding.binding_,
+                        vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        texture_binding.image_view_,
+                        texture_binding.sampler_,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    );
+                }
+
+                pipeline_sets.push((set as u32, descriptor_set));
+                descriptor_pools.push(descriptor_pool);
+            }
+
+            descriptor_sets.push(pipeline_sets);
+        }
+
+        TextureSets {
+            descriptor_pools_: descriptor_pools,
+            descriptor_sets_: descriptor_sets,
+        }
+    }
+
+    /// Returns the set index and descriptor set of each texture set of a pipeline
+    pub fn get_descriptor_sets(&self, pipeline: usize) -> &Vec<(u32, vk::DescriptorSet)> {
+        &self.descriptor_sets_[pipeline]
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        for descriptor_pool in self.descriptor_pools_.iter() {
+            unsafe { device.destroy_descriptor_pool(*descriptor_pool, None) };
+        }
+    }
+}
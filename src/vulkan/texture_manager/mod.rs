@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use ash::Device;
+
+use super::command::CommandDispatch;
+use super::physical_device::PhysicalDevice;
+use crate::geometry::image::texture_data::{TextureData, TextureFormat, ASTC_BLOCK_SIZES};
+use crate::geometry::image::ImageData;
+
+use texture::{Texture, TextureDescription};
+
+pub mod texture;
+
+pub type TextureID = u64;
+
+/// Owns the textures renderers sample, uploading each one on the graphics queue as it is created
+pub struct TextureManager {
+    textures_: HashMap<TextureID, Texture>,
+    curr_id_: u64,
+    command_dispatch_: CommandDispatch,
+    queue_: vk::Queue,
+    upload_fence_: vk::Fence,
+    /// opaque white texture sampled where nothing else is bound, created on first use
+    default_texture_: Option<TextureID>,
+    /// opaque white cube map sampled where no cube map has been given, created on first use
+    default_cube_texture_: Option<TextureID>,
+}
+
+impl TextureManager {
+    pub fn new(device: &Device, physical_device: &PhysicalDevice) -> TextureManager {
+        let queue_family_index = physical_device
+            .queue_family_indices_
+            .graphics_compute_
+            .expect("No graphics and compute queue family");
+
+        let fence_create_info = vk::FenceCreateInfo {
+            s_type: vk::StructureType::FENCE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::FenceCreateFlags::empty(),
+        };
+
+        TextureManager {
+            textures_: HashMap::new(),
+            curr_id_: 0,
+            command_dispatch_: CommandDispatch::new(device, queue_family_index, 1),
+            queue_: unsafe { device.get_device_queue(queue_family_index, 0) },
+            upload_fence_: unsafe {
+                device
+                    .create_fence(&fence_create_info, None)
+                    .expect("Could not create fence")
+            },
+            default_texture_: None,
+            default_cube_texture_: None,
+        }
+    }
+
+    /// Creates a texture from decoded RGBA8 pixels and waits for its upload to complete. A full
+    /// mip chain is generated if the device can blit the format with linear filtering
+    ///
+    /// # Arguments
+    ///
+    /// * 'image' - the pixels of the texture
+    ///
+    /// * 'srgb' - whether the pixels are sRGB encoded colors, which are then converted to linear
+    ///   values when sampled, rather than linear data such as normals
+    ///
+    pub fn create_texture(
+        &mut self,
+        instance: &ash::Instance,
+        device: &Device,
+        physical_device: &PhysicalDevice,
+        image: &ImageData,
+        srgb: bool,
+    ) -> TextureID {
+        let mut description = TextureDescription::new(
+            vk::Extent3D {
+                width: image.width_,
+                height: image.height_,
+                depth: 1,
+            },
+            vulkan_format(TextureFormat::Rgba8, srgb),
+            vk::SamplerAddressMode::REPEAT,
+        );
+
+        if TextureManager::supports_mipmap_generation(
+            instance,
+            physical_device,
+            description.format_,
+        ) {
+            description.mip_levels_ = description.full_mip_chain();
+        }
+
+        self.add_texture(
+            instance,
+            device,
+            physical_device,
+            &description,
+            std::slice::from_ref(&image.pixels_),
+        )
+    }
+
+    /// Creates a texture from the texels of a KTX2 or DDS file with all its mip levels and array
+    /// layers, and waits for its upload to complete. If the device cannot sample the stored
+    /// format, the texels are decoded to RGBA8 instead. Returns an error if they cannot be
+    ///
+    /// # Arguments
+    ///
+    /// * 'texture_data' - the texels of the texture
+    ///
+    /// * 'srgb' - whether the texels are sRGB encoded colors. Textures whose format is sRGB are
+    ///   always sampled as sRGB
+    ///
+    pub fn create_texture_from_data(
+        &mut self,
+        instance: &ash::Instance,
+        device: &Device,
+        physical_device: &PhysicalDevice,
+        texture_data: &TextureData,
+        srgb: bool,
+    ) -> Result<TextureID, String> {
+        let srgb = srgb || texture_data.srgb_;
+        let format = vulkan_format(texture_data.format_, srgb);
+
+        let supported = physical_device
+            .get_supported_format(
+                instance,
+                &[format],
+                vk::ImageTiling::OPTIMAL,
+                vk::FormatFeatureFlags::SAMPLED_IMAGE | vk::FormatFeatureFlags::TRANSFER_DST,
+            )
+            .is_some();
+
+        let decoded;
+        let texture_data = match supported {
+            true => texture_data,
+            false => {
+                decoded = texture_data.decode_rgba8().map_err(|error| {
+                    format!("The device cannot sample the texture and {}", error)
+                })?;
+                &decoded
+            }
+        };
+
+        let mut description = TextureDescription::new(
+            vk::Extent3D {
+                width: texture_data.width_,
+                height: texture_data.height_,
+                depth: 1,
+            },
+            vulkan_format(texture_data.format_, srgb),
+            vk::SamplerAddressMode::REPEAT,
+        );
+        description.array_layers_ = texture_data.layers_;
+        description.mip_levels_ = texture_data.levels_.len() as u32;
+        description.cube_ = texture_data.cube_;
+
+        if texture_data.generate_mipmaps_
+            && TextureManager::supports_mipmap_generation(
+                instance,
+                physical_device,
+                description.format_,
+            )
+        {
+            description.mip_levels_ = description.full_mip_chain();
+        }
+
+        Ok(self.add_texture(
+            instance,
+            device,
+            physical_device,
+            &description,
+            &texture_data.levels_,
+        ))
+    }
+
+    /// Returns a 1x1 opaque white texture, creating it on first use, for samplers no texture has
+    /// been given for
+    pub fn get_default_texture(
+        &mut self,
+        instance: &ash::Instance,
+        device: &Device,
+        physical_device: &PhysicalDevice,
+    ) -> TextureID {
+        if let Some(texture_id) = self.default_texture_ {
+            return texture_id;
+        }
+
+        let texture_id = self.create_texture(
+            instance,
+            device,
+            physical_device,
+            &ImageData::from_rgba(1, 1, vec![255; 4]),
+            false,
+        );
+
+        self.default_texture_ = Some(texture_id);
+        texture_id
+    }
+
+    /// Returns a 1x1 opaque white cube map, creating it on first use, for cube map samplers no
+    /// texture has been given for
+    pub fn get_default_cube_texture(
+        &mut self,
+        instance: &ash::Instance,
+        device: &Device,
+        physical_device: &PhysicalDevice,
+    ) -> TextureID {
+        if let Some(texture_id) = self.default_cube_texture_ {
+            return texture_id;
+        }
+
+        let face = ImageData::from_rgba(1, 1, vec![255; 4]);
+        let texture_data =
+            TextureData::from_cube_faces(&vec![face; 6]).expect("Could not build default cube map");
+
+        let texture_id = self
+            .create_texture_from_data(instance, device, physical_device, &texture_data, false)
+            .expect("Could not create default cube map");
+
+        self.default_cube_texture_ = Some(texture_id);
+        texture_id
+    }
+
+    pub fn get_texture(&self, texture_id: TextureID) -> &Texture {
+        self.textures_.get(&texture_id).expect("No such texture")
+    }
+
+    /// Destroys a texture. The device must no longer be using it
+    pub fn destroy_texture(&mut self, device: &Device, texture_id: TextureID) {
+        self.textures_
+            .remove(&texture_id)
+            .expect("No such texture")
+            .destroy(device);
+    }
+
+    /// Destroys every texture along with the upload fence. The device must be idle
+    pub fn destroy(&mut self, device: &Device) {
+        for texture in self.textures_.values_mut() {
+            texture.destroy(device);
+        }
+
+        unsafe {
+            device.destroy_fence(self.upload_fence_, None);
+        }
+
+        self.textures_.clear();
+    }
+
+    /// Creates a texture from the texels of its first mip levels and uploads it
+    fn add_texture(
+        &mut self,
+        instance: &ash::Instance,
+        device: &Device,
+        physical_device: &PhysicalDevice,
+        description: &TextureDescription,
+        levels: &[Vec<u8>],
+    ) -> TextureID {
+        let mut texture = Texture::new(instance, device, physical_device, description, levels);
+
+        self.upload(device, &mut texture);
+
+        let texture_id = self.curr_id_;
+        self.curr_id_ += 1;
+        self.textures_.insert(texture_id, texture);
+        texture_id
+    }
+
+    /// Returns true if mip levels of the format can be generated by blitting with linear filtering
+    fn supports_mipmap_generation(
+        instance: &ash::Instance,
+        physical_device: &PhysicalDevice,
+        format: vk::Format,
+    ) -> bool {
+        physical_device
+            .get_supported_format(
+                instance,
+                &[format],
+                vk::ImageTiling::OPTIMAL,
+                vk::FormatFeatureFlags::BLIT_SRC
+                    | vk::FormatFeatureFlags::BLIT_DST
+                    | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+            )
+            .is_some()
+    }
+
+    /// Submits the texture's staged upload and frees the staging memory once it has completed
+    fn upload(&self, device: &Device, texture: &mut Texture) {
+        let command_buffer = self.command_dispatch_.command_buffers_[0];
+
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo {
+            s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+            p_next: ptr::null(),
+            flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+            p_inheritance_info: ptr::null(),
+        };
+
+        let submit_info = vk::SubmitInfo {
+            s_type: vk::StructureType::SUBMIT_INFO,
+            p_next: ptr::null(),
+            wait_semaphore_count: 0,
+            p_wait_semaphores: ptr::null(),
+            p_wait_dst_stage_mask: ptr::null(),
+            command_buffer_count: 1,
+            p_command_buffers: &command_buffer,
+            signal_semaphore_count: 0,
+            p_signal_semaphores: ptr::null(),
+        };
+
+        unsafe {
+            device
+                .reset_fences(&[self.upload_fence_])
+                .expect("Could not reset fence");
+
+            device
+                .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+                .expect("Could not begin recording texture upload");
+
+            texture.record_upload(device, command_buffer);
+
+            device
+                .end_command_buffer(command_buffer)
+                .expect("Could not end recording texture upload");
+
+            device
+                .queue_submit(self.queue_, &[submit_info], self.upload_fence_)
+                .expect("Could not submit texture upload");
+
+            device
+                .wait_for_fences(&[self.upload_fence_], true, u64::MAX)
+                .expect("Wait for fences failed");
+        }
+
+        texture.finish_upload(device);
+    }
+}
+
+/// Returns the vulkan format of a texture format, sRGB encoded if requested and the format has an
+/// sRGB variant
+fn vulkan_format(format: TextureFormat, srgb: bool) -> vk::Format {
+    let (linear, srgb_format) = match format {
+        TextureFormat::Rgba8 => (vk::Format::R8G8B8A8_UNORM, vk::Format::R8G8B8A8_SRGB),
+        TextureFormat::Rgba16Float => return vk::Format::R16G16B16A16_SFLOAT,
+        TextureFormat::Bc1Rgb => (
+            vk::Format::BC1_RGB_UNORM_BLOCK,
+            vk::Format::BC1_RGB_SRGB_BLOCK,
+        ),
+        TextureFormat::Bc1Rgba => (
+            vk::Format::BC1_RGBA_UNORM_BLOCK,
+            vk::Format::BC1_RGBA_SRGB_BLOCK,
+        ),
+        TextureFormat::Bc2 => (vk::Format::BC2_UNORM_BLOCK, vk::Format::BC2_SRGB_BLOCK),
+        TextureFormat::Bc3 => (vk::Format::BC3_UNORM_BLOCK, vk::Format::BC3_SRGB_BLOCK),
+        TextureFormat::Bc4 => return vk::Format::BC4_UNORM_BLOCK,
+        TextureFormat::Bc4Snorm => return vk::Format::BC4_SNORM_BLOCK,
+        TextureFormat::Bc5 => return vk::Format::BC5_UNORM_BLOCK,
+        TextureFormat::Bc5Snorm => return vk::Format::BC5_SNORM_BLOCK,
+        TextureFormat::Bc6hUfloat => return vk::Format::BC6H_UFLOAT_BLOCK,
+        TextureFormat::Bc6hSfloat => return vk::Format::BC6H_SFLOAT_BLOCK,
+        TextureFormat::Bc7 => (vk::Format::BC7_UNORM_BLOCK, vk::Format::BC7_SRGB_BLOCK),
+        TextureFormat::Etc2Rgb8 => (
+            vk::Format::ETC2_R8G8B8_UNORM_BLOCK,
+            vk::Format::ETC2_R8G8B8_SRGB_BLOCK,
+        ),
+        TextureFormat::Etc2Rgb8A1 => (
+            vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK,
+            vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK,
+        ),
+        TextureFormat::Etc2Rgba8 => (
+            vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK,
+            vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK,
+        ),
+        TextureFormat::Astc(width, height) => {
+            // The linear and sRGB variants of each block size follow each other in block order
+            let block = ASTC_BLOCK_SIZES
+                .iter()
+                .position(|block| *block == (width, height))
+                .expect("Not an ASTC block size") as i32;
+            let linear = vk::Format::ASTC_4X4_UNORM_BLOCK.as_raw() + block * 2;
+
+            (
+                vk::Format::from_raw(linear),
+                vk::Format::from_raw(linear + 1),
+            )
+        }
+    };
+
+    match srgb {
+        true => srgb_format,
+        false => linear,
+    }
+}
@@ -0,0 +1,483 @@
+use std::os::raw::c_void;
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use crate::vulkan::buffer::Buffer;
+use crate::vulkan::physical_device::PhysicalDevice;
+
+/// The size, format and layout of a texture's image
+#[derive(Clone, Copy, Debug)]
+pub struct TextureDescription {
+    /// the size of the largest mip level in texels. The texture is a 3D image if the depth is
+    /// greater than one
+    pub extent_: vk::Extent3D,
+    pub format_: vk::Format,
+    /// number of array layers, six for each cube map
+    pub array_layers_: u32,
+    pub mip_levels_: u32,
+    /// true if every six layers are the faces of a cube map
+    pub cube_: bool,
+    /// how lookups outside the texture are resolved along every axis
+    pub address_mode_: vk::SamplerAddressMode,
+}
+
+impl TextureDescription {
+    /// Describes a 2D or 3D texture with a single layer and mip level
+    pub fn new(
+        extent: vk::Extent3D,
+        format: vk::Format,
+        address_mode: vk::SamplerAddressMode,
+    ) -> TextureDescription {
+        TextureDescription {
+            extent_: extent,
+            format_: format,
+            array_layers_: 1,
+            mip_levels_: 1,
+            cube_: false,
+            address_mode_: address_mode,
+        }
+    }
+
+    /// Returns the number of mip levels in a full chain down to a single texel
+    pub fn full_mip_chain(&self) -> u32 {
+        let largest = self
+            .extent_
+            .width
+            .max(self.extent_.height)
+            .max(self.extent_.depth);
+
+        32 - largest.leading_zeros()
+    }
+
+    /// Returns the size of a mip level
+    pub fn level_extent(&self, level: u32) -> vk::Extent3D {
+        vk::Extent3D {
+            width: (self.extent_.width >> level).max(1),
+            height: (self.extent_.height >> level).max(1),
+            depth: (self.extent_.depth >> level).max(1),
+        }
+    }
+}
+
+/// A sampled image with its view and sampler. The texels of its first mip levels are kept in a
+/// staging buffer until the upload recorded by record_upload has completed and finish_upload is
+/// called. The upload generates the remaining mip levels by blitting each from the one before
+pub struct Texture {
+    image_: vk::Image,
+    device_memory_: vk::DeviceMemory,
+    pub image_view_: vk::ImageView,
+    pub sampler_: vk::Sampler,
+    pub description_: TextureDescription,
+    /// size of each mip level uploaded from the staging buffer, in the order they are staged
+    staged_level_sizes_: Vec<vk::DeviceSize>,
+    staging_buffer_: Option<Buffer>,
+}
+
+impl Texture {
+    /// Creates the image, view and sampler of a texture and stages the texels of its first mip
+    /// levels. Mip levels beyond those given are generated during the upload, which requires the
+    /// format to support linear blits
+    ///
+    /// # Arguments
+    ///
+    /// * 'description' - the size, format and layout of the texture
+    ///
+    /// * 'levels' - the texels of the first mip levels, largest first. Each holds every layer in
+    ///   order, tightly packed in the texture's format with rows ordered top to bottom
+    ///
+    pub fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &PhysicalDevice,
+        description: &TextureDescription,
+        levels: &[Vec<u8>],
+    ) -> Texture {
+        assert!(
+            !levels.is_empty() && levels.len() as u32 <= description.mip_levels_,
+            "A texture must be given between one and all of its mip levels"
+        );
+
+        let image_type = match description.extent_.depth > 1 {
+            true => vk::ImageType::TYPE_3D,
+            false => vk::ImageType::TYPE_2D,
+        };
+
+        let view_type = match (description.cube_, description.array_layers_) {
+            (true, 6) => vk::ImageViewType::CUBE,
+            (true, _) => vk::ImageViewType::CUBE_ARRAY,
+            (false, 1) if image_type == vk::ImageType::TYPE_3D => vk::ImageViewType::TYPE_3D,
+            (false, 1) => vk::ImageViewType::TYPE_2D,
+            (false, _) => vk::ImageViewType::TYPE_2D_ARRAY,
+        };
+
+        let flags = match description.cube_ {
+            true => vk::ImageCreateFlags::CUBE_COMPATIBLE,
+            false => vk::ImageCreateFlags::empty(),
+        };
+
+        // Generated mip levels are blitted from the level before them
+        let usage = match levels.len() as u32 == description.mip_levels_ {
+            true => vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED,
+            false => {
+                vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::TRANSFER_DST
+                    | vk::ImageUsageFlags::SAMPLED
+            }
+        };
+
+        let image_create_info = vk::ImageCreateInfo {
+            s_type: vk::StructureType::IMAGE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags,
+            image_type,
+            format: description.format_,
+            extent: description.extent_,
+            mip_levels: description.mip_levels_,
+            array_layers: description.array_layers_,
+            samples: vk::SampleCountFlags::TYPE_1,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 0,
+            p_queue_family_indices: ptr::null(),
+            initial_layout: vk::ImageLayout::UNDEFINED,
+        };
+
+        let image = unsafe {
+            device
+                .create_image(&image_create_info, None)
+                .expect("Could not create texture image")
+        };
+
+        let memory_requirements = unsafe { device.get_image_memory_requirements(image) };
+
+        let memory_allocate_info = vk::MemoryAllocateInfo {
+            s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
+            p_next: ptr::null(),
+            allocation_size: memory_requirements.size,
+            memory_type_index: physical_device
+                .get_suitable_memory_type_index(
+                    instance,
+                    memory_requirements.memory_type_bits,
+                    vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                )
+                .expect("Could not find suitable memory type for texture"),
+        };
+
+        let device_memory = unsafe {
+            device
+                .allocate_memory(&memory_allocate_info, None)
+                .expect("Could not allocate memory for texture")
+        };
+
+        unsafe {
+            device
+                .bind_image_memory(image, device_memory, 0)
+                .expect("Could not bind texture memory");
+        }
+
+        let image_view_create_info = vk::ImageViewCreateInfo {
+            s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::ImageViewCreateFlags::empty(),
+            image,
+            view_type,
+            format: description.format_,
+            components: vk::ComponentMapping {
+                r: vk::ComponentSwizzle::IDENTITY,
+                g: vk::ComponentSwizzle::IDENTITY,
+                b: vk::ComponentSwizzle::IDENTITY,
+                a: vk::ComponentSwizzle::IDENTITY,
+            },
+            subresource_range: Texture::subresource_range(description, 0, description.mip_levels_),
+        };
+
+        let image_view = unsafe {
+            device
+                .create_image_view(&image_view_create_info, None)
+                .expect("Could not create texture view")
+        };
+
+        let texels: Vec<u8> = levels.concat();
+        let staging_size = texels.len() as u64;
+
+        let mut staging_buffer = Buffer::new(
+            instance,
+            device,
+            physical_device,
+            staging_size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        );
+
+        unsafe {
+            staging_buffer.map(device, 0, staging_size);
+            staging_buffer.copy_from_data(texels.as_ptr() as *const c_void, staging_size, 0);
+            staging_buffer.unmap(device);
+        }
+
+        Texture {
+            image_: image,
+            device_memory_: device_memory,
+            image_view_: image_view,
+            sampler_: Texture::create_sampler(device, description),
+            description_: *description,
+            staged_level_sizes_: levels.iter().map(|level| level.len() as u64).collect(),
+            staging_buffer_: Some(staging_buffer),
+        }
+    }
+
+    /// Returns true if the staged texels still have to be uploaded with record_upload
+    pub fn has_pending_upload(&self) -> bool {
+        self.staging_buffer_.is_some()
+    }
+
+    /// Records copying the staged texels into the image, generating the remaining mip levels and
+    /// leaving every level ready to be sampled by fragment shaders. Records nothing once the
+    /// texture has been uploaded
+    pub fn record_upload(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        let staging_buffer = match self.staging_buffer_.as_ref() {
+            Some(staging_buffer) => staging_buffer,
+            None => return,
+        };
+
+        let description = &self.description_;
+        let mip_levels = description.mip_levels_;
+        let staged_levels = self.staged_level_sizes_.len() as u32;
+
+        let barrier = |base_level,
+                       level_count,
+                       old_layout,
+                       new_layout,
+                       src_access_mask,
+                       dst_access_mask| {
+            vk::ImageMemoryBarrier {
+                s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+                p_next: ptr::null(),
+                src_access_mask,
+                dst_access_mask,
+                old_layout,
+                new_layout,
+                src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                image: self.image_,
+                subresource_range: Texture::subresource_range(description, base_level, level_count),
+            }
+        };
+
+        let mut buffer_offset = 0;
+        let mut buffer_image_copies = vec![];
+
+        for (level, size) in self.staged_level_sizes_.iter().enumerate() {
+            let level = level as u32;
+
+            buffer_image_copies.push(vk::BufferImageCopy {
+                buffer_offset,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: Texture::subresource_layers(description, level),
+                image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                image_extent: description.level_extent(level),
+            });
+
+            buffer_offset += size;
+        }
+
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    0,
+                    mip_levels,
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::TRANSFER_WRITE,
+                )],
+            );
+
+            device.cmd_copy_buffer_to_image(
+                command_buffer,
+                staging_buffer.buffer_handle_,
+                self.image_,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &buffer_image_copies,
+            );
+
+            // Each generated level is blitted from the one before it, once that level has been
+            // written and moved to the transfer source layout
+            for level in staged_levels..mip_levels {
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[barrier(
+                        level - 1,
+                        1,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                        vk::AccessFlags::TRANSFER_READ,
+                    )],
+                );
+
+                let image_blit = vk::ImageBlit {
+                    src_subresource: Texture::subresource_layers(description, level - 1),
+                    src_offsets: [
+                        vk::Offset3D { x: 0, y: 0, z: 0 },
+                        Texture::extent_to_offset(description.level_extent(level - 1)),
+                    ],
+                    dst_subresource: Texture::subresource_layers(description, level),
+                    dst_offsets: [
+                        vk::Offset3D { x: 0, y: 0, z: 0 },
+                        Texture::extent_to_offset(description.level_extent(level)),
+                    ],
+                };
+
+                device.cmd_blit_image(
+                    command_buffer,
+                    self.image_,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    self.image_,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[image_blit],
+                    vk::Filter::LINEAR,
+                );
+            }
+
+            // Levels blitted from are in the transfer source layout, every other level in the
+            // transfer destination layout
+            let blitted_from = match mip_levels > staged_levels {
+                true => (staged_levels - 1)..(mip_levels - 1),
+                false => 0..0,
+            };
+
+            let mut final_barriers = vec![];
+
+            for level in 0..mip_levels {
+                let (old_layout, src_access_mask) = match blitted_from.contains(&level) {
+                    true => (
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::AccessFlags::TRANSFER_READ,
+                    ),
+                    false => (
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                    ),
+                };
+
+                final_barriers.push(barrier(
+                    level,
+                    1,
+                    old_layout,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    src_access_mask,
+                    vk::AccessFlags::SHADER_READ,
+                ));
+            }
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &final_barriers,
+            );
+        }
+    }
+
+    /// Frees the staging memory once the recorded upload has completed
+    pub fn finish_upload(&mut self, device: &ash::Device) {
+        if let Some(staging_buffer) = self.staging_buffer_.take() {
+            unsafe { staging_buffer.destroy(device) };
+        }
+    }
+
+    pub fn destroy(&mut self, device: &ash::Device) {
+        self.finish_upload(device);
+
+        unsafe {
+            device.destroy_sampler(self.sampler_, None);
+            device.destroy_image_view(self.image_view_, None);
+            device.destroy_image(self.image_, None);
+            device.free_memory(self.device_memory_, None);
+        }
+    }
+
+    fn subresource_range(
+        description: &TextureDescription,
+        base_mip_level: u32,
+        level_count: u32,
+    ) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level,
+            level_count,
+            base_array_layer: 0,
+            layer_count: description.array_layers_,
+        }
+    }
+
+    fn subresource_layers(
+        description: &TextureDescription,
+        mip_level: u32,
+    ) -> vk::ImageSubresourceLayers {
+        vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level,
+            base_array_layer: 0,
+            layer_count: description.array_layers_,
+        }
+    }
+
+    fn extent_to_offset(extent: vk::Extent3D) -> vk::Offset3D {
+        vk::Offset3D {
+            x: extent.width as i32,
+            y: extent.height as i32,
+            z: extent.depth as i32,
+        }
+    }
+
+    /// Creates a trilinear sampler resolving lookups outside the texture with the description's
+    /// address mode
+    fn create_sampler(device: &ash::Device, description: &TextureDescription) -> vk::Sampler {
+        let sampler_create_info = vk::SamplerCreateInfo {
+            s_type: vk::StructureType::SAMPLER_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::SamplerCreateFlags::empty(),
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+            address_mode_u: description.address_mode_,
+            address_mode_v: description.address_mode_,
+            address_mode_w: description.address_mode_,
+            mip_lod_bias: 0.0,
+            anisotropy_enable: vk::FALSE,
+            max_anisotropy: 1.0,
+            compare_enable: vk::FALSE,
+            compare_op: vk::CompareOp::ALWAYS,
+            min_lod: 0.0,
+            max_lod: description.mip_levels_ as f32,
+            border_color: vk::BorderColor::FLOAT_OPAQUE_BLACK,
+            unnormalized_coordinates: vk::FALSE,
+        };
+
+        unsafe {
+            device
+                .create_sampler(&sampler_create_info, None)
+                .expect("Could not create texture sampler")
+        }
+    }
+}
@@ -0,0 +1,3 @@
+vec4 to_color(vec3 color) {
+    return vec4(color, 1.0);
+}